@@ -30,14 +30,20 @@ impl DatabaseManager {
     }
   }
 
-  pub fn has_database(&self, database_name: String) -> bool {
-    self.database.contains_key(&database_name)
+  // 保存到 database_manager 文件中的只有每个 database 的名字，数据在各自的文件中
+  pub fn get_database_names(&self) -> DatabaseManager {
+    DatabaseManager {
+      database: self.database
+        .keys()
+        .map(|database_name| (database_name.to_string(), Database::new(database_name.to_string())))
+        .collect(),
+    }
   }
 
   // 从磁盘读取到内存
   pub fn read<T: DeserializeOwned>(filename: String, new_data: &impl Serialize) -> Result<T> {
     // 先看 filename 在不在，不在就创建这个 file
-    if let Err(_) = File::open(filename.clone()) {
+    if File::open(filename.clone()).is_err() {
       println!("{} creating...", filename);
       DatabaseManager::write_data(
         &filename.to_string(),
@@ -49,7 +55,7 @@ impl DatabaseManager {
     match
       DatabaseManager::read_data(&filename.to_string()) {
         Ok(data) => Ok(data),
-        Err(error) => return Err(error),
+        Err(error) => Err(error),
     }
   }
 
@@ -63,14 +69,14 @@ impl DatabaseManager {
   }
 
   fn write_data(filename: &str, data: &impl Serialize) {
-    let filename = format!("{}", filename);
+    let filename = filename.to_string();
     let bytes: Vec<u8> = serialize(&data).unwrap();
     let mut file = File::create(filename).unwrap();
     file.write_all(&bytes).unwrap();
   }
 
   fn read_data<T: DeserializeOwned>(filename: &str) -> Result<T> {
-      let filename = format!("{}", filename);
+      let filename = filename.to_string();
       let mut file = File::open(filename).unwrap();
       let mut buffer = Vec::<u8>::new();
       file.read_to_end(&mut buffer).unwrap();
@@ -1,18 +1,70 @@
 pub mod database_manager;
+pub mod transaction;
+pub mod session;
+pub mod shared;
+pub mod wal;
 
 use std::collections::{HashMap};
+use std::fs;
 
 use serde::{Deserialize, Serialize};
 
 use crate::table::Table;
 use crate::error::{Result, NollaDBError};
+use crate::storage::pager::{SharedPager, is_paged_file};
+use crate::storage::journal::rollback_journal;
+use crate::storage::database_file::{read_database, save_database, read_wal_generation};
+use crate::sql_query::handle_sql_query;
 
 use database_manager::DatabaseManager;
+use transaction::{UndoRecord, CommitClock};
+use session::Session;
+use wal::{Wal, WalRecord};
+
+// 默认的内存预算，单位是字节
+pub const DEFAULT_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+// 当前会话的设置，不会被保存到 .db 文件中
+#[derive(PartialEq, Debug, Clone)]
+pub struct Settings {
+  // 排序时超过这个大小就把已经排好序的部分写到临时文件中
+  pub memory_budget: usize,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Settings {
+      memory_budget: DEFAULT_MEMORY_BUDGET,
+    }
+  }
+}
 
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct Database {
   pub database_name: String,
   pub tables: HashMap<String, Table>,
+  #[serde(skip)]
+  pub settings: Settings,
+  // 每个会话自己的事务状态，key 是会话 id
+  #[serde(skip)]
+  pub sessions: HashMap<usize, Session>,
+  // 当前执行 SQL 的会话
+  #[serde(skip)]
+  pub session_id: usize,
+  // 提交时间戳的时钟，每次提交加一
+  #[serde(skip)]
+  pub commit_clock: CommitClock,
+  // 每次提交之前先写到 WAL 中，None 的话不写，只有 .save 之后修改才会保存下来
+  #[serde(skip)]
+  pub wal: Option<Wal>,
+  // 分页的 database 文件，表中没有修改过的行查询的时候从这里按需读出来，见 StoredTable
+  // 新建的 database 以及以前整个 bincode 序列化的文件是 None
+  #[serde(skip)]
+  pub pager: Option<SharedPager>,
+  // 提交之后还没有发布的时间戳，None 的话提交的时候马上发布
+  // SharedDatabase 中语句用的 Database 是 Some，修改过的表放回去之后再发布，见 CommitClock
+  #[serde(skip)]
+  pub unpublished_commit_ts: Option<Vec<u64>>,
 }
 
 // use std::ops::{Deref, DerefMut};
@@ -33,6 +85,13 @@ impl Database {
     Database {
       database_name,
       tables: HashMap::new(),
+      settings: Settings::default(),
+      sessions: HashMap::new(),
+      session_id: 0,
+      commit_clock: CommitClock::default(),
+      wal: None,
+      pager: None,
+      unpublished_commit_ts: None,
     }
   }
 
@@ -46,12 +105,10 @@ impl Database {
     let mut database_manager = DatabaseManager::new();
 
     println!("reading {}...", database_name.clone());
-    match Database::read(
-      database_name.clone(),
-      &Database::new(database_name.clone())
-    ) {
+    match Database::read(database_name.clone()) {
       Ok(data) => {
         database = data;
+        database.recover()?;
         println!("reading {} done", database_name);
         // 然后读 database_manager 文件
         match DatabaseManager::read(
@@ -60,12 +117,17 @@ impl Database {
         ) {
           Ok(data) => {
             database_manager = data;
-            if !database_manager.has_database(database.database_name.clone()) {
-              database_manager.database.insert(
-                database.database_name.clone(),
-                database.clone()
-              );
+            // database_manager 文件中只有 database 的名字，数据要从各自的文件中读出来
+            for (name, other_database) in database_manager.database.iter_mut() {
+              if *name != database.database_name && fs::metadata(name).is_ok() {
+                *other_database = Database::read(name.to_string())?;
+                other_database.recover()?;
+              }
             }
+            database_manager.database.insert(
+              database.database_name.clone(),
+              database.clone()
+            );
           },
           Err(error) => return Err(error),
         }
@@ -78,24 +140,31 @@ impl Database {
 
   pub fn end(
     database_name: String,
-    database: &Database,
+    database: &mut Database,
     database_manager_file: String,
     database_manager: &DatabaseManager,
   ) -> Result<()> {
+    // 事务中的修改还没有提交，不能写到文件中
+    if database.sessions.values().any(|session| session.is_in_transaction) {
+      return Err(NollaDBError::General(
+        "Can not save database in a transaction, COMMIT or ROLLBACK first".to_string()
+      ));
+    }
+
     println!("saving {}...", database_name.clone());
     match Database::save(database_name.clone(), database) {
       Ok(_) => {
-        println!("saving {} done", database_name.to_string());
+        println!("saving {} done", database_name);
         // save 完成之后同样要 save database_manager 文件
         match DatabaseManager::save(
           database_manager_file.clone(),
-          database_manager,
+          &database_manager.get_database_names(),
         ) {
           Ok(()) => Ok(()),
-          Err(error) => return Err(error),
+          Err(error) => Err(error),
         }
       }
-      Err(error) => return Err(error),
+      Err(error) => Err(error),
     }
   }
 
@@ -107,7 +176,7 @@ impl Database {
     // 目前先默认在当前目录
     match database_manager.get_database(database_name) {
       Ok(database) => Ok(database),
-      Err(error) => return Err(error)
+      Err(error) => Err(error)
     }
   }
 
@@ -119,38 +188,106 @@ impl Database {
     // 目前先默认在当前目录
     match database_manager.get_database_mut(database_name) {
       Ok(database) => Ok(database),
-      Err(error) => return Err(error)
+      Err(error) => Err(error)
     }
   }
 
-  pub fn read(database_name: String, new_data: &impl Serialize) -> Result<Self> {
-    match DatabaseManager::read(
-      database_name.clone(),
-      new_data,
-    ) {
-      Ok(data) => Ok(data),
-      Err(error) => return Err(error),
+  // database 文件是分页的格式，不存在的话先创建一个空的
+  // 以前的版本把整个 database 用 bincode 序列化到文件中，这样的文件也可以读，保存的时候会转成分页的格式
+  // 上次保存到一半崩溃的话先用回滚日志恢复文件
+  pub fn read(database_name: String) -> Result<Self> {
+    rollback_journal(&database_name)?;
+    let is_empty = fs::metadata(&database_name).map_or(true, |metadata| metadata.len() == 0);
+    if !is_empty && !is_paged_file(&database_name) {
+      return DatabaseManager::read(database_name.clone(), &Database::new(database_name));
+    }
+    if is_empty {
+      println!("{} creating...", database_name);
+      save_database(&database_name, &mut Database::new(database_name.clone()))?;
+      println!("creating {} done", database_name);
     }
+
+    read_database(&database_name)
   }
 
-  pub fn save(database_name: String, data: &Database) -> Result<()> {
-    match DatabaseManager::save(database_name, data) {
-      Ok(()) => Ok(()),
-      Err(error) => return Err(error),
+  // 只有被修改过的页会写回文件
+  // 保存到自己的文件之后 WAL 中的修改都已经在文件中了，也就是一次 checkpoint
+  // 保存到别的文件时自己的文件中还没有这些修改，WAL 要留着
+  // 文件和 WAL 的代数一起写进文件头，之后的提交写到下一代 WAL 中
+  pub fn save(database_name: String, data: &mut Database) -> Result<()> {
+    save_database(&database_name, data)?;
+    match data.wal.as_mut() {
+      Some(wal) if database_name == data.database_name => {
+        wal.clear()?;
+        wal.generation += 1;
+        Ok(())
+      },
+      _ => Ok(()),
     }
   }
 
+  // 打开 database 的 WAL，上次 checkpoint 之后提交的修改都在 WAL 中
+  // 在读出来的 database 上按顺序重做一遍，然后做一次 checkpoint
+  // checkpoint 要么全部写进文件，要么和没有做一样，见 Journal，所以 WAL 总是在它开始时的文件上重做
+  pub fn recover(&mut self) -> Result<()> {
+    let wal_generation = match &self.pager {
+      Some(pager) => pager.lock().get_wal_generation(),
+      None => read_wal_generation(&self.database_name)?,
+    };
+    let wal = Wal::new(&self.database_name, wal_generation + 1);
+    let has_wal = wal.get_size() > 0;
+    self.wal = Some(wal);
+    if has_wal {
+      println!("recovering {}...", self.database_name);
+      let number_of_commits = self.replay_wal()?;
+      Database::save(self.database_name.clone(), self)?;
+      println!("recovering {} done, {} commits replayed", self.database_name, number_of_commits);
+    }
+    Ok(())
+  }
+
+  // 写到一半的最后一次提交不会被读出来，返回重做的提交数
+  // 重做修改表结构的语句时不能再写到 WAL 中，所以先把 WAL 拿出来
+  fn replay_wal(&mut self) -> Result<usize> {
+    let commits = match &self.wal {
+      Some(wal) => wal.read()?,
+      None => return Ok(0),
+    };
+    let number_of_commits = commits.len();
+    let wal = self.wal.take();
+    let result = self.redo_wal_records(commits.into_iter().flatten().collect());
+    self.wal = wal;
+    result.map(|_| number_of_commits)
+  }
+
+  fn redo_wal_records(&mut self, wal_records: Vec<WalRecord>) -> Result<()> {
+    for wal_record in wal_records {
+      match wal_record {
+        WalRecord::Statement(sql_query) => {
+          handle_sql_query(&sql_query, self)?;
+        },
+        WalRecord::Rows { table_name, most_recent_row_id, committed_rows } => {
+          if let Some(table) = self.tables.get_mut(&table_name) {
+            table.most_recent_row_id = table.most_recent_row_id.max(most_recent_row_id);
+            table.redo_committed_rows(&committed_rows)?;
+          }
+        },
+      }
+    }
+    Ok(())
+  }
+
   // 得到指定的 database 里的所有 table name
   pub fn get_all_tables(
     &self,
     database_manager: &DatabaseManager,
     database_name: String
   ) -> Result<Vec<String>> {
-    let database = Database::open(&database_manager, database_name).unwrap();
+    let database = Database::open(database_manager, database_name).unwrap();
     Ok(
       database.tables
-        .iter()
-        .map(|(key, _)| key.to_string())
+        .keys()
+        .map(|key| key.to_string())
         .collect()
     )
   }
@@ -159,7 +296,6 @@ impl Database {
     self.tables.contains_key(&table_name)
   }
 
-  #[allow(dead_code)]
   pub fn get_table(&self, table_name: String) -> Result<&Table> {
     match self.tables.get(&table_name) {
       Some(table) => Ok(table),
@@ -167,12 +303,71 @@ impl Database {
     }
   }
 
+  // 所有的表，key 是表名
+  pub fn get_tables(&self) -> HashMap<&str, &Table> {
+    self.tables
+      .iter()
+      .map(|(table_name, table)| (table_name.as_str(), table))
+      .collect()
+  }
+
+  // 索引名在整个数据库中是唯一的，找到索引所在的表
+  pub fn get_table_name_of_index(&self, index_name: &str) -> Option<String> {
+    self.tables
+      .iter()
+      .find(|(_, table)| table.has_index(index_name))
+      .map(|(table_name, _)| table_name.to_string())
+  }
+
+  // ALTER TABLE RENAME TO
+  // 同时更新 tables 中的 key 以及表自己的 table_name
+  pub fn rename_table(&mut self, old_table_name: String, new_table_name: String) -> Result<()> {
+    if self.has_table(new_table_name.to_string()) {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not rename table, because table '{}' already exists",
+          new_table_name
+        )
+      ));
+    }
+
+    match self.tables.remove(&old_table_name) {
+      Some(mut table) => {
+        table.table_name = new_table_name.to_string();
+        self.tables.insert(new_table_name.to_string(), table);
+        self.session_mut().undo_log.push(UndoRecord::RenameTable(old_table_name, new_table_name));
+        Ok(())
+      },
+      _ => Err(NollaDBError::General(String::from("Table not found"))),
+    }
+  }
+
   pub fn get_table_mut(&mut self, table_name: String) -> Result<&mut Table> {
     match self.tables.get_mut(&table_name) {
       Some(table) => Ok(table),
       _ => Err(NollaDBError::General(String::from("Table not found"))),
     }
   }
+
+  // CREATE TABLE，回滚的时候把表删掉
+  pub fn create_table(&mut self, table: Table) {
+    self.session_mut().undo_log.push(UndoRecord::Table(table.table_name.to_string(), None));
+    self.tables.insert(table.table_name.to_string(), table);
+  }
+
+  // DROP TABLE，回滚的时候把表放回去
+  pub fn drop_table(&mut self, table_name: &str) {
+    if let Some(table) = self.tables.remove(table_name) {
+      self.session_mut().undo_log.push(UndoRecord::Table(table_name.to_string(), Some(Box::new(table))));
+    }
+  }
+
+  // 修改表结构之前先把整张表复制一份，回滚的时候直接替换回去
+  pub fn save_table(&mut self, table_name: &str) {
+    if let Some(table) = self.tables.get(table_name).map(Table::deep_clone) {
+      self.session_mut().undo_log.push(UndoRecord::Table(table_name.to_string(), Some(Box::new(table))));
+    }
+  }
 }
 
 
@@ -185,6 +380,9 @@ mod tests {
   use sqlparser::parser::Parser;
   use sqlparser::dialect::SQLiteDialect;
   use crate::sql_query::query::create::{CreateQuery};
+  use crate::storage::buffer_pool::PAGE_WRITES_BEFORE_CRASH;
+  use crate::storage::database_file::get_table_schema;
+  use crate::table::ScannedRow;
 
   #[rstest]
   #[case("testdb")]
@@ -240,7 +438,7 @@ mod tests {
     let mut database_mut = create_new_database(database_name, query).unwrap();
 
     let table = database.get_table(table_name.to_string()).unwrap();
-    let mut table_mut = database_mut.get_table_mut(table_name.to_string()).unwrap();
+    let table_mut = database_mut.get_table_mut(table_name.to_string()).unwrap();
 
     table_mut.most_recent_row_id += 1;
 
@@ -249,10 +447,208 @@ mod tests {
     assert_eq!(table_mut.most_recent_row_id, expected_most_recent_row_id);
   }
 
+  #[test]
+  fn test_recover_from_wal() {
+    let path = std::env::temp_dir().join(format!("nolladb-recover-{}.db", std::process::id()));
+    let database_name = path.to_str().unwrap().to_string();
+    let database_manager_file = format!("{}.dmf", database_name);
+    let wal = Wal::new(&database_name, 0);
+    for file in [&database_name, &database_manager_file, &wal.path] {
+      let _ = fs::remove_file(file);
+    }
+
+    let (mut database, _) = Database::start(database_name.clone(), database_manager_file.clone()).unwrap();
+    for query in [
+      "CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, score REAL);",
+      "CREATE TABLE other (id INTEGER PRIMARY KEY, note TEXT);",
+      "INSERT INTO test (name, score) VALUES ('a', 1.0), ('b', 2.0), ('c', 3.0);",
+      "INSERT INTO other (note) VALUES ('x');",
+      "BEGIN;",
+      "UPDATE test SET score = 4.0 WHERE name = 'b';",
+      "DELETE FROM test WHERE name = 'c';",
+      "INSERT INTO test (name) VALUES ('d');",
+      "COMMIT;",
+      "ALTER TABLE other RENAME TO renamed;",
+      "CREATE TABLE dropped (id INTEGER PRIMARY KEY);",
+      "DROP TABLE dropped;",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    let expected_tables = get_tables(&database);
+    assert!(wal.get_size() > 0);
+
+    // 没有提交的修改不在 WAL 中，最后一次提交只写了一半
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    handle_sql_query("INSERT INTO test (name) VALUES ('e');", &mut database).unwrap();
+    fs::OpenOptions::new()
+      .append(true)
+      .open(&wal.path)
+      .and_then(|mut file| std::io::Write::write_all(&mut file, &[1, 0, 0, 0, 0]))
+      .unwrap();
+    drop(database);
+
+    // 重做之后做一次 checkpoint，再次打开的时候 WAL 是空的
+    for _ in 0..2 {
+      let (database, database_manager) = Database::start(database_name.clone(), database_manager_file.clone()).unwrap();
+      assert_eq!(get_tables(&database), expected_tables);
+      assert_eq!(database_manager.get_database(database_name.clone()).unwrap().tables.len(), 2);
+      assert_eq!(wal.get_size(), 0);
+    }
+
+    for file in [&database_name, &database_manager_file] {
+      fs::remove_file(file).unwrap();
+    }
+  }
+
+  // checkpoint 写到一半崩溃，文件先用回滚日志恢复到 checkpoint 之前的样子，然后重做 WAL
+  #[test]
+  fn test_recover_from_crash_during_checkpoint() {
+    let path = std::env::temp_dir().join(format!("nolladb-crash-{}.db", std::process::id()));
+    let database_name = path.to_str().unwrap().to_string();
+    let database_manager_file = format!("{}.dmf", database_name);
+    let journal_path = format!("{}-journal", database_name);
+    let wal = Wal::new(&database_name, 0);
+    for file in [&database_name, &database_manager_file, &wal.path, &journal_path] {
+      let _ = fs::remove_file(file);
+    }
+
+    let (mut database, _) = Database::start(database_name.clone(), database_manager_file.clone()).unwrap();
+    let values: Vec<String> = (0..200).map(|i| format!("('{}{}')", "a".repeat(100), i)).collect();
+    for query in [
+      "CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT UNIQUE);".to_string(),
+      format!("INSERT INTO test (name) VALUES {};", values.join(", ")),
+    ] {
+      handle_sql_query(&query, &mut database).unwrap();
+    }
+    Database::save(database_name.clone(), &mut database).unwrap();
+    let bytes = fs::read(&database_name).unwrap();
+
+    for query in [
+      "UPDATE test SET name = 'b' || name WHERE id > 50;",
+      "DELETE FROM test WHERE id <= 20;",
+      "ALTER TABLE test ADD COLUMN score INTEGER;",
+      "INSERT INTO test (name, score) VALUES ('c', 1);",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    let expected_tables = get_tables(&database);
+
+    PAGE_WRITES_BEFORE_CRASH.with(|page_writes| page_writes.set(Some(3)));
+    let result = Database::save(database_name.clone(), &mut database);
+    PAGE_WRITES_BEFORE_CRASH.with(|page_writes| page_writes.set(None));
+    assert!(result.is_err());
+    drop(database);
+    assert!(fs::read(&database_name).unwrap() != bytes);
+    assert!(wal.get_size() > 0);
+
+    rollback_journal(&database_name).unwrap();
+    assert_eq!(fs::read(&database_name).unwrap(), bytes);
+    let (database, _) = Database::start(database_name.clone(), database_manager_file.clone()).unwrap();
+    assert_eq!(get_tables(&database), expected_tables);
+    assert_eq!(wal.get_size(), 0);
+
+    for file in [&database_name, &database_manager_file] {
+      fs::remove_file(file).unwrap();
+    }
+  }
+
+  // 修改表结构的语句在 WAL 中只记录 SQL，之前修改过的行记录这时的值，重做的时候按照顺序执行并重新建立索引
+  #[test]
+  fn test_recover_schema_changes_from_wal() {
+    let path = std::env::temp_dir().join(format!("nolladb-recover-schema-{}.db", std::process::id()));
+    let database_name = path.to_str().unwrap().to_string();
+    let database_manager_file = format!("{}.dmf", database_name);
+    let wal = Wal::new(&database_name, 0);
+    for file in [&database_name, &database_manager_file, &wal.path] {
+      let _ = fs::remove_file(file);
+    }
+
+    let (mut database, _) = Database::start(database_name.clone(), database_manager_file.clone()).unwrap();
+    let values: Vec<String> = (0..200).map(|i| format!("('{}{}')", "a".repeat(100), i % 100)).collect();
+    for query in [
+      "CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT);".to_string(),
+      format!("INSERT INTO test (name) VALUES {};", values.join(", ")),
+    ] {
+      handle_sql_query(&query, &mut database).unwrap();
+    }
+    Database::save(database_name.clone(), &mut database).unwrap();
+
+    // 文件中的行有重复的 name，删掉之后才能建立 UNIQUE 索引
+    for query in [
+      "BEGIN;",
+      "DELETE FROM test WHERE id > 100;",
+      "CREATE UNIQUE INDEX test_name ON test (name);",
+      "ALTER TABLE test ADD COLUMN score INTEGER DEFAULT 1;",
+      "UPDATE test SET score = 2 WHERE id <= 10;",
+      "ALTER TABLE test RENAME COLUMN score TO points;",
+      "ALTER TABLE test RENAME TO renamed;",
+      "INSERT INTO renamed (name, points) VALUES ('b', 3);",
+      "COMMIT;",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    let expected_tables = get_tables(&database);
+    // 没有记录整张表
+    assert!(wal.get_size() < 4096);
+    drop(database);
+
+    let (mut database, _) = Database::start(database_name.clone(), database_manager_file.clone()).unwrap();
+    assert_eq!(get_tables(&database), expected_tables);
+    assert!(handle_sql_query("INSERT INTO renamed (name) VALUES ('b');", &mut database).is_err());
+
+    for file in [&database_name, &database_manager_file] {
+      fs::remove_file(file).unwrap();
+    }
+  }
+
+  // .save 到别的文件之后 WAL 还在，重新打开原来的 database 时修改不会丢
+  #[test]
+  fn test_save_to_other_file_keeps_wal() {
+    let path = std::env::temp_dir().join(format!("nolladb-save-other-{}.db", std::process::id()));
+    let database_name = path.to_str().unwrap().to_string();
+    let other_database_name = format!("{}-other.db", database_name);
+    let database_manager_file = format!("{}.dmf", database_name);
+    let wal = Wal::new(&database_name, 0);
+    for file in [&database_name, &other_database_name, &database_manager_file, &wal.path] {
+      let _ = fs::remove_file(file);
+    }
+
+    let (mut database, database_manager) = Database::start(database_name.clone(), database_manager_file.clone()).unwrap();
+    for query in [
+      "CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT);",
+      "INSERT INTO test (name) VALUES ('a'), ('b');",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    let expected_tables = get_tables(&database);
+    Database::end(other_database_name.clone(), &mut database, database_manager_file.clone(), &database_manager).unwrap();
+    assert!(wal.get_size() > 0);
+    assert_eq!(get_tables(&Database::read(other_database_name.clone()).unwrap()), expected_tables);
+    drop(database);
+
+    let (database, _) = Database::start(database_name.clone(), database_manager_file.clone()).unwrap();
+    assert_eq!(get_tables(&database), expected_tables);
+
+    for file in [&database_name, &other_database_name, &database_manager_file] {
+      fs::remove_file(file).unwrap();
+    }
+  }
+
+  // 文件中的表只有用到的时候才会读出来，所以比较表结构以及读出来的所有行
+  fn get_tables(database: &Database) -> HashMap<String, (Table, Vec<ScannedRow>)> {
+    database.tables
+      .iter()
+      .map(|(table_name, table)| (
+        table_name.to_string(),
+        (get_table_schema(table), table.scan_rows(None, usize::MAX).unwrap()),
+      ))
+      .collect()
+  }
+
   fn create_new_database(database_name: &str, query: &str) -> Result<Database, ()> {
     let mut database = Database::new(database_name.to_string());
     let dialect = SQLiteDialect {};
-    let mut ast = Parser::parse_sql(&dialect, &query).unwrap();
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
     let create_query = CreateQuery::new(&ast.pop().unwrap()).unwrap();
 
     database.tables.insert(
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::collections::HashSet;
+
+use crate::error::{Result, NollaDBError};
+use crate::database::transaction::UndoRecord;
+
+// 事务的隔离级别
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum IsolationLevel {
+  // 事务中的每条语句都读 BEGIN 时的快照，两个事务修改同一行时后修改的那个失败
+  #[default]
+  Snapshot,
+  // 在 SNAPSHOT 的基础上，提交时检查读过的表有没有被别的事务修改并提交
+  // 有的话提交失败，这样可以避免 write skew
+  Serializable,
+}
+
+impl IsolationLevel {
+  pub fn new(isolation_level: &str) -> Result<IsolationLevel> {
+    match isolation_level.to_lowercase().as_ref() {
+      "snapshot" => Ok(IsolationLevel::Snapshot),
+      "serializable" => Ok(IsolationLevel::Serializable),
+      _ => Err(NollaDBError::General(
+        format!("Invalid isolation level '{}'", isolation_level)
+      )),
+    }
+  }
+}
+
+impl fmt::Display for IsolationLevel {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      IsolationLevel::Snapshot => f.write_str("snapshot"),
+      IsolationLevel::Serializable => f.write_str("serializable"),
+    }
+  }
+}
+
+// 会话读数据时用的快照，自己的修改以及在 snapshot_ts 之前提交的修改是可见的
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Snapshot {
+  pub session_id: usize,
+  pub snapshot_ts: u64,
+}
+
+// 一个会话的事务状态，每个会话同时只能有一个事务
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Session {
+  // 还没有提交的修改，执行失败的语句以及 ROLLBACK 用它来撤销
+  // 不在事务中的话每条语句执行完就会被清空
+  pub undo_log: Vec<UndoRecord>,
+  // 是否在 BEGIN 开始的事务中
+  pub is_in_transaction: bool,
+  // 事务中的 savepoint 名字以及创建时 undo log 的长度，后创建的在后面
+  pub savepoints: Vec<(String, usize)>,
+  // 当前事务的隔离级别
+  pub isolation_level: IsolationLevel,
+  // BEGIN 没有指定隔离级别时使用，可以用 PRAGMA isolation_level 修改
+  pub default_isolation_level: IsolationLevel,
+  // 快照的时间戳，只能看到在这之前提交的修改
+  // 事务中是 BEGIN 时的时间戳，不在事务中的话每条语句都会重新取
+  pub snapshot_ts: u64,
+  // SERIALIZABLE 的事务中读过的表
+  pub read_table_names: HashSet<String>,
+}
+
+impl Session {
+  // 是否有还没有提交的表结构修改
+  pub fn has_schema_changes(&self) -> bool {
+    self.undo_log
+      .iter()
+      .any(|undo_record| !matches!(undo_record, UndoRecord::RowChanges(..)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  #[rstest]
+  #[case("snapshot", Ok(IsolationLevel::Snapshot))]
+  #[case("SERIALIZABLE", Ok(IsolationLevel::Serializable))]
+  #[case("read committed", Err(NollaDBError::General("Invalid isolation level 'read committed'".to_string())))]
+  fn test_isolation_level(
+    #[case] isolation_level: &str,
+    #[case] expected: Result<IsolationLevel>,
+  ) {
+    assert_eq!(IsolationLevel::new(isolation_level), expected);
+  }
+}
@@ -0,0 +1,540 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
+
+use crate::database::{Database, Settings};
+use crate::database::session::{Session, Snapshot};
+use crate::database::transaction::UndoRecord;
+use crate::database::wal::WAL_CHECKPOINT_SIZE;
+use crate::error::{Result, NollaDBError};
+use crate::sql_query::{SQLQuery, get_sql_ast, get_table_names_of_statement, handle_sql_query};
+use crate::sql_query::planner::execute_select_query_in_tables;
+use crate::sql_query::query::select::SelectQuery;
+use crate::sql_query::result_set::ResultSet;
+use crate::table::Table;
+
+// 语句用的 Database 中代表其他正在执行的语句的会话，见 Catalog::pinned_snapshots
+const PINNED_SESSION_ID: usize = usize::MAX;
+
+// 一张表，查询只在开始的时候 clone 一下 Arc，之后不拿任何锁
+// 修改它的语句拿着 writer 锁修改自己复制的一份，执行完之后再换掉 table，所以也不会等查询执行完
+#[derive(Debug)]
+struct TableSlot {
+  writer: Mutex<()>,
+  table: Mutex<Arc<Table>>,
+}
+
+impl TableSlot {
+  fn new(table: Table) -> Self {
+    TableSlot {
+      writer: Mutex::new(()),
+      table: Mutex::new(Arc::new(table)),
+    }
+  }
+
+  fn lock_writer(&self) -> MutexGuard<'_, ()> {
+    self.writer.lock().expect("Table lock is poisoned")
+  }
+
+  fn get_table(&self) -> Arc<Table> {
+    self.table.lock().expect("Table lock is poisoned").clone()
+  }
+
+  fn set_table(&self, table: Table) {
+    *self.table.lock().expect("Table lock is poisoned") = Arc::new(table);
+  }
+}
+
+// 可以在多个线程之间共享的 Database，clone 只会复制 Arc 指针
+// 1. schema_lock：修改表结构或者设置，以及需要整个 Database 的操作拿写锁，其他语句拿读锁
+// 2. catalog：会话、提交时钟等表以外的部分，语句开始和结束的时候拿一下，马上就释放
+// 3. 每张表一把 writer 锁：修改表的语句按照表名的顺序拿，所以不会死锁，查询不拿
+// 用到的表不一样的语句可以同时执行，查询和修改同一张表的语句也可以同时执行，事务之间的隔离由 MVCC 负责
+#[derive(Debug, Clone)]
+pub struct SharedDatabase(Arc<SharedState>);
+
+#[derive(Debug)]
+struct SharedState {
+  schema_lock: RwLock<()>,
+  catalog: Mutex<Catalog>,
+}
+
+#[derive(Debug)]
+struct Catalog {
+  // 表以外的部分，tables 是空的
+  database: Database,
+  tables: HashMap<String, Arc<TableSlot>>,
+  // 正在执行语句的会话，这时它的 Session 在语句用的 Database 中
+  running_session_ids: HashSet<usize>,
+  // 正在执行的语句的快照时间戳以及个数
+  // 语句用的 Database 中只有自己的表，回收旧的版本时要用它们代替别的语句的快照
+  pinned_snapshots: BTreeMap<u64, usize>,
+}
+
+impl Catalog {
+  fn pin_snapshot(&mut self, snapshot_ts: u64) {
+    *self.pinned_snapshots.entry(snapshot_ts).or_default() += 1;
+  }
+
+  fn unpin_snapshot(&mut self, snapshot_ts: u64) {
+    if let Some(count) = self.pinned_snapshots.get_mut(&snapshot_ts) {
+      *count -= 1;
+      if *count == 0 {
+        self.pinned_snapshots.remove(&snapshot_ts);
+      }
+    }
+  }
+
+  fn check_session_is_idle(&self, session_id: usize) -> Result<()> {
+    match self.running_session_ids.contains(&session_id) {
+      true => Err(NollaDBError::General(
+        format!("Session {} is executing another statement", session_id)
+      )),
+      false => Ok(()),
+    }
+  }
+
+  fn get_table_slots(&self, table_names: BTreeSet<String>) -> Vec<(String, Arc<TableSlot>)> {
+    table_names
+      .into_iter()
+      .filter_map(|table_name| {
+        self.tables
+          .get(&table_name)
+          .cloned()
+          .map(|table_slot| (table_name, table_slot))
+      })
+      .collect()
+  }
+}
+
+// 查询开始时拿到的表，之后这些表被换掉也没有关系
+struct QueryTables {
+  settings: Settings,
+  snapshot: Snapshot,
+  tables: Vec<(String, Arc<Table>)>,
+}
+
+impl QueryTables {
+  fn execute(&self, select_query: SelectQuery) -> Result<ResultSet> {
+    let tables: HashMap<&str, &Table> = self.tables
+      .iter()
+      .map(|(table_name, table)| (table_name.as_str(), table.as_ref()))
+      .collect();
+    execute_select_query_in_tables(&tables, &self.settings, self.snapshot, select_query)
+  }
+}
+
+impl SharedState {
+  // 拿锁的线程 panic 之后语句可能只执行了一半，没有办法再用了
+  fn lock_catalog(&self) -> MutexGuard<'_, Catalog> {
+    self.catalog.lock().expect("Database lock is poisoned")
+  }
+}
+
+impl SharedDatabase {
+  pub fn new(mut database: Database) -> Self {
+    let tables = split_tables(&mut database);
+    SharedDatabase(Arc::new(SharedState {
+      schema_lock: RwLock::new(()),
+      catalog: Mutex::new(Catalog {
+        database,
+        tables,
+        running_session_ids: HashSet::new(),
+        pinned_snapshots: BTreeMap::new(),
+      }),
+    }))
+  }
+
+  // 拿到整个 Database，会等到别的语句都执行完，拿着的时候别的语句也不能执行
+  pub fn write(&self) -> DatabaseGuard<'_> {
+    let schema_guard = self.0.schema_lock.write().expect("Database lock is poisoned");
+    let mut catalog = self.0.lock_catalog();
+    let mut database = std::mem::replace(&mut catalog.database, Database::new(String::new()));
+    // 别的语句都执行完了，一般只有 TableSlot 中还有这张表，直接拿走，否则复制一份
+    for (table_name, table_slot) in catalog.tables.drain() {
+      let table = table_slot.get_table();
+      drop(table_slot);
+      database.tables.insert(table_name, Arc::try_unwrap(table).unwrap_or_else(|table| table.deep_clone()));
+    }
+
+    DatabaseGuard {
+      shared_state: &self.0,
+      database,
+      _schema_guard: schema_guard,
+    }
+  }
+
+  // 在 session_id 这个会话中执行一条 SQL
+  pub fn execute(&self, session_id: usize, sql_query: &str) -> Result<String> {
+    let message = match get_table_names_of_statement(sql_query) {
+      Some(_) if matches!(SQLQuery::new(sql_query.to_string()), SQLQuery::Select(_)) => {
+        let result_set = self.select(session_id, sql_query)?;
+        let _ = result_set.print_result_set();
+        Some(String::from("SELECT statement done"))
+      },
+      Some(table_names) => self.execute_in_tables(session_id, sql_query, table_names)?,
+      None => None,
+    };
+    let message = match message {
+      Some(message) => message,
+      None => {
+        let mut database = self.write();
+        database.use_session(session_id);
+        handle_sql_query(sql_query, &mut database)?
+      },
+    };
+
+    self.checkpoint_if_needed();
+    Ok(message)
+  }
+
+  // 只读的查询，拿到用到的表现在的样子之后在 session_id 的快照中执行，不会等修改这些表的语句
+  pub fn select(&self, session_id: usize, sql_query: &str) -> Result<ResultSet> {
+    let select_query = SelectQuery::new(&get_sql_ast(sql_query)?)?;
+    let _schema_guard = self.0.schema_lock.read().expect("Database lock is poisoned");
+    let query_tables = self.get_query_tables(session_id, &select_query)?;
+    let result = query_tables.execute(select_query);
+    self.0.lock_catalog().unpin_snapshot(query_tables.snapshot.snapshot_ts);
+    result
+  }
+
+  // 查询用到的表以及快照，快照在 unpin 之前不会被回收
+  fn get_query_tables(&self, session_id: usize, select_query: &SelectQuery) -> Result<QueryTables> {
+    let table_names: BTreeSet<String> = select_query.get_table_names().into_iter().collect();
+    let mut catalog = self.0.lock_catalog();
+    catalog.check_session_is_idle(session_id)?;
+    catalog.database.check_schema_changes(session_id)?;
+    if !catalog.tables.contains_key(&select_query.table_name) {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Table '{}' does not exist",
+          select_query.table_name
+        )
+      ));
+    }
+
+    catalog.database.add_read_table_names(session_id, table_names.iter().cloned().collect());
+    // 表和快照都在拿着 catalog 的时候拿到，发布了的提交一定已经在拿到的表中
+    let mut snapshot = catalog.database.get_session_snapshot(session_id);
+    if !catalog.database.sessions.get(&session_id).is_some_and(|session| session.is_in_transaction) {
+      snapshot.snapshot_ts = catalog.database.commit_clock.get_published();
+    }
+    catalog.pin_snapshot(snapshot.snapshot_ts);
+    let tables = catalog.get_table_slots(table_names)
+      .into_iter()
+      .map(|(table_name, table_slot)| (table_name, table_slot.get_table()))
+      .collect();
+    Ok(QueryTables {
+      settings: catalog.database.settings.clone(),
+      snapshot,
+      tables,
+    })
+  }
+
+  // 修改数据的语句以及 BEGIN、COMMIT、ROLLBACK
+  // 只拿走语句用到的表，以及会话的事务中修改过、读过的表，放到一个只有这些表的 Database 中执行
+  // 会话的事务中有表结构的修改的话返回 None，需要整个 Database
+  fn execute_in_tables(&self, session_id: usize, sql_query: &str, table_names: Vec<String>) -> Result<Option<String>> {
+    let _schema_guard = self.0.schema_lock.read().expect("Database lock is poisoned");
+    let (mut database, table_slots, pinned_snapshot_ts) = {
+      let mut catalog = self.0.lock_catalog();
+      catalog.check_session_is_idle(session_id)?;
+      catalog.database.check_schema_changes(session_id)?;
+      if catalog.database.sessions.get(&session_id).is_some_and(Session::has_schema_changes) {
+        return Ok(None);
+      }
+
+      let session = catalog.database.sessions.remove(&session_id).unwrap_or_default();
+      let mut table_names: BTreeSet<String> = table_names.into_iter().collect();
+      table_names.extend(session.read_table_names.iter().cloned());
+      for undo_record in &session.undo_log {
+        if let UndoRecord::RowChanges(table_name, _) = undo_record {
+          table_names.insert(table_name.to_string());
+        }
+      }
+
+      // 这条语句之后才开始的快照不会早于现在已经发布的时间戳
+      let commit_ts = catalog.database.commit_clock.get_published();
+      let pinned_snapshot_ts = match session.is_in_transaction {
+        true => session.snapshot_ts,
+        false => commit_ts,
+      };
+      catalog.pin_snapshot(pinned_snapshot_ts);
+      catalog.running_session_ids.insert(session_id);
+
+      // 别的会话只需要知道是否在事务中以及快照的时间戳，用来回收旧的版本
+      // 别的语句的快照放在一个一直在事务中的会话中，所以这里也不会做 checkpoint
+      let mut database = Database::new(catalog.database.database_name.to_string());
+      database.unpublished_commit_ts = Some(vec![]);
+      database.settings = catalog.database.settings.clone();
+      database.commit_clock = catalog.database.commit_clock.clone();
+      database.wal = catalog.database.wal.clone();
+      database.sessions = catalog.database.sessions
+        .iter()
+        .map(|(id, session)| (*id, Session {
+          is_in_transaction: session.is_in_transaction,
+          snapshot_ts: session.snapshot_ts,
+          ..Session::default()
+        }))
+        .collect();
+      let min_pinned_snapshot_ts = catalog.pinned_snapshots.keys().next().map_or(commit_ts, |ts| (*ts).min(commit_ts));
+      database.sessions.insert(PINNED_SESSION_ID, Session {
+        is_in_transaction: true,
+        snapshot_ts: min_pinned_snapshot_ts,
+        ..Session::default()
+      });
+      database.sessions.insert(session_id, session);
+      database.session_id = session_id;
+      (database, catalog.get_table_slots(table_names), pinned_snapshot_ts)
+    };
+
+    // 拿到 writer 锁之后表不会再被别的语句换掉，查询可能还在用原来的表，所以复制一份来修改
+    let writer_guards: Vec<MutexGuard<'_, ()>> = table_slots
+      .iter()
+      .map(|(_, table_slot)| table_slot.lock_writer())
+      .collect();
+    for (table_name, table_slot) in &table_slots {
+      database.tables.insert(table_name.to_string(), table_slot.get_table().deep_clone());
+    }
+    let result = handle_sql_query(sql_query, &mut database);
+
+    // 先换掉所有的表再发布这条语句的提交，拿着 catalog 的查询要么看不到这些提交，要么拿到的是新的表
+    let mut catalog = self.0.lock_catalog();
+    for (table_name, table_slot) in &table_slots {
+      if let Some(table) = database.tables.remove(table_name) {
+        table_slot.set_table(table);
+      }
+    }
+    for commit_ts in database.unpublished_commit_ts.take().unwrap_or_default() {
+      catalog.database.commit_clock.publish(commit_ts);
+    }
+    let session = database.sessions.remove(&session_id).unwrap_or_default();
+    catalog.database.sessions.insert(session_id, session);
+    catalog.running_session_ids.remove(&session_id);
+    catalog.unpin_snapshot(pinned_snapshot_ts);
+    drop(catalog);
+    drop(writer_guards);
+    result.map(Some)
+  }
+
+  // 语句用的 Database 中没有全部的表，不能做 checkpoint，要拿到整个 Database 之后再做
+  fn checkpoint_if_needed(&self) {
+    let is_too_large = self.0.lock_catalog().database.wal
+      .as_ref()
+      .is_some_and(|wal| wal.get_size() > WAL_CHECKPOINT_SIZE);
+    if is_too_large {
+      self.write().checkpoint_if_needed();
+    }
+  }
+}
+
+// SharedDatabase::write 拿到的整个 Database，drop 的时候再把表放回各自的 TableSlot 中
+pub struct DatabaseGuard<'a> {
+  shared_state: &'a SharedState,
+  database: Database,
+  _schema_guard: RwLockWriteGuard<'a, ()>,
+}
+
+impl Deref for DatabaseGuard<'_> {
+  type Target = Database;
+
+  fn deref(&self) -> &Database {
+    &self.database
+  }
+}
+
+impl DerefMut for DatabaseGuard<'_> {
+  fn deref_mut(&mut self) -> &mut Database {
+    &mut self.database
+  }
+}
+
+impl Drop for DatabaseGuard<'_> {
+  fn drop(&mut self) {
+    let mut database = std::mem::replace(&mut self.database, Database::new(String::new()));
+    let tables = split_tables(&mut database);
+    let mut catalog = self.shared_state.lock_catalog();
+    catalog.database = database;
+    catalog.tables = tables;
+  }
+}
+
+// 把表从 Database 中拿出来，每张表放到自己的 TableSlot 中
+fn split_tables(database: &mut Database) -> HashMap<String, Arc<TableSlot>> {
+  database.tables
+    .drain()
+    .map(|(table_name, table)| (table_name, Arc::new(TableSlot::new(table))))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::mpsc;
+  use std::thread;
+  use std::time::Duration;
+  use pretty_assertions::{assert_eq};
+  use crate::table::row::value::Value;
+
+  const NUMBER_OF_TABLES: usize = 2;
+  const NUMBER_OF_ACCOUNTS: i32 = 8;
+  const NUMBER_OF_WRITERS: usize = 4;
+  const NUMBER_OF_READERS: usize = 4;
+  const NUMBER_OF_TRANSFERS: i32 = 25;
+
+  #[test]
+  fn test_database_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Database>();
+    assert_send_sync::<SharedDatabase>();
+  }
+
+  // 多个线程同时转账以及查询，写线程分别在不同的表中转账，同一张表的转账有冲突的话回滚之后重试
+  // 查询在任何时候看到的每张表的总额和账户数都不会变，最后每个账户的余额和按顺序执行的结果一样
+  #[test]
+  fn test_concurrent_readers_and_writers() {
+    let shared_database = SharedDatabase::new(Database::new("testdb".to_string()));
+    for table_index in 0..NUMBER_OF_TABLES {
+      shared_database.execute(0, &format!("CREATE TABLE accounts_{} (id INTEGER PRIMARY KEY, balance INTEGER NOT NULL);", table_index)).unwrap();
+      for _ in 0..NUMBER_OF_ACCOUNTS {
+        shared_database.execute(0, &format!("INSERT INTO accounts_{} (balance) VALUES (100);", table_index)).unwrap();
+      }
+    }
+
+    let writers: Vec<_> = (0..NUMBER_OF_WRITERS)
+      .map(|i| {
+        let shared_database = shared_database.clone();
+        thread::spawn(move || {
+          let session_id = i + 1;
+          for (from, to) in get_transfers(i) {
+            while let Err(error) = transfer(&shared_database, session_id, i % NUMBER_OF_TABLES, from, to) {
+              assert!(matches!(error, NollaDBError::SerializationFailure(_)), "{:?}", error);
+              shared_database.execute(session_id, "ROLLBACK;").unwrap();
+              thread::yield_now();
+            }
+          }
+        })
+      })
+      .collect();
+
+    let readers: Vec<_> = (0..NUMBER_OF_READERS)
+      .map(|i| {
+        let shared_database = shared_database.clone();
+        thread::spawn(move || {
+          let session_id = NUMBER_OF_WRITERS + i + 1;
+          for j in 0..NUMBER_OF_TRANSFERS as usize * 2 {
+            let result_set = shared_database.select(
+              session_id,
+              &format!("SELECT COUNT(*), SUM(balance) FROM accounts_{};", (i + j) % NUMBER_OF_TABLES),
+            ).unwrap();
+            assert_eq!(result_set.rows, vec![vec![Value::Integer(NUMBER_OF_ACCOUNTS), Value::Integer(NUMBER_OF_ACCOUNTS * 100)]]);
+            thread::yield_now();
+          }
+        })
+      })
+      .collect();
+
+    for handle in writers.into_iter().chain(readers) {
+      handle.join().unwrap();
+    }
+
+    for table_index in 0..NUMBER_OF_TABLES {
+      let mut balances = vec![100; NUMBER_OF_ACCOUNTS as usize];
+      for i in (table_index..NUMBER_OF_WRITERS).step_by(NUMBER_OF_TABLES) {
+        for (from, to) in get_transfers(i) {
+          balances[from as usize - 1] -= 1;
+          balances[to as usize - 1] += 1;
+        }
+      }
+      let result_set = shared_database.select(0, &format!("SELECT balance FROM accounts_{} ORDER BY id;", table_index)).unwrap();
+      assert_eq!(
+        result_set.rows.into_iter().flatten().collect::<Vec<Value>>(),
+        balances.into_iter().map(Value::Integer).collect::<Vec<Value>>()
+      );
+    }
+  }
+
+  // 查询 a 还没有结束的时候，修改 a 的语句可以执行完，查询的结果还是它开始时的快照
+  // a 被别的语句修改的时候，修改 b 的语句不用等它，修改 a 的语句要等到 writer 锁被释放之后才能执行完
+  #[test]
+  fn test_writers_of_different_tables_make_progress() {
+    let shared_database = SharedDatabase::new(Database::new("testdb".to_string()));
+    shared_database.execute(0, "CREATE TABLE a (id INTEGER PRIMARY KEY, n INTEGER);").unwrap();
+    shared_database.execute(0, "CREATE TABLE b (id INTEGER PRIMARY KEY, n INTEGER);").unwrap();
+    shared_database.execute(0, "INSERT INTO a (n) VALUES (1);").unwrap();
+    shared_database.execute(0, "INSERT INTO b (n) VALUES (1);").unwrap();
+
+    let execute_in_thread = |session_id: usize, sql_query: &'static str| {
+      let shared_database = shared_database.clone();
+      let (sender, receiver) = mpsc::channel();
+      thread::spawn(move || sender.send(shared_database.execute(session_id, sql_query)).unwrap());
+      receiver
+    };
+
+    let select_query = SelectQuery::new(&get_sql_ast("SELECT n FROM a;").unwrap()).unwrap();
+    let reader_of_a = shared_database.get_query_tables(1, &select_query).unwrap();
+    let writer_of_a = execute_in_thread(2, "UPDATE a SET n = 2;");
+    assert!(writer_of_a.recv_timeout(Duration::from_secs(10)).unwrap().is_ok());
+    assert_eq!(reader_of_a.execute(select_query).unwrap().rows, vec![vec![Value::Integer(1)]]);
+    shared_database.0.lock_catalog().unpin_snapshot(reader_of_a.snapshot.snapshot_ts);
+
+    let table_slot = shared_database.0.lock_catalog().tables["a"].clone();
+    let writer_guard = table_slot.lock_writer();
+    let writer_of_b = execute_in_thread(3, "UPDATE b SET n = 2;");
+    assert!(writer_of_b.recv_timeout(Duration::from_secs(10)).unwrap().is_ok());
+    let writer_of_a = execute_in_thread(4, "UPDATE a SET n = 3;");
+    assert!(writer_of_a.recv_timeout(Duration::from_millis(200)).is_err());
+    drop(writer_guard);
+    assert!(writer_of_a.recv_timeout(Duration::from_secs(10)).unwrap().is_ok());
+
+    for (table_name, n) in [("a", 3), ("b", 2)] {
+      let result_set = shared_database.select(0, &format!("SELECT n FROM {};", table_name)).unwrap();
+      assert_eq!(result_set.rows, vec![vec![Value::Integer(n)]]);
+    }
+  }
+
+  // 查询拿到表之后，修改同一张表的语句不用等它执行完，查询的结果还是它开始时的快照
+  #[test]
+  fn test_writer_finishes_while_reader_is_scanning() {
+    let shared_database = SharedDatabase::new(Database::new("testdb".to_string()));
+    shared_database.execute(0, "CREATE TABLE a (id INTEGER PRIMARY KEY, n INTEGER);").unwrap();
+    for _ in 0..3 {
+      shared_database.execute(0, "INSERT INTO a (n) VALUES (1);").unwrap();
+    }
+
+    let select_query = SelectQuery::new(&get_sql_ast("SELECT n FROM a;").unwrap()).unwrap();
+    let query_tables = shared_database.get_query_tables(1, &select_query).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let writer_database = shared_database.clone();
+    thread::spawn(move || sender.send(writer_database.execute(2, "UPDATE a SET n = 2 WHERE id > 1;")).unwrap());
+    assert!(receiver.recv_timeout(Duration::from_secs(10)).unwrap().is_ok());
+
+    let result_set = query_tables.execute(select_query).unwrap();
+    shared_database.0.lock_catalog().unpin_snapshot(query_tables.snapshot.snapshot_ts);
+    assert_eq!(result_set.rows, vec![vec![Value::Integer(1)]; 3]);
+    assert_eq!(
+      shared_database.select(1, "SELECT n FROM a;").unwrap().rows,
+      vec![vec![Value::Integer(1)], vec![Value::Integer(2)], vec![Value::Integer(2)]]
+    );
+  }
+
+  // 第 i 个写线程的所有转账，同一张表的不同线程会转同一个账户，这样才会有冲突
+  fn get_transfers(i: usize) -> Vec<(i32, i32)> {
+    (0..NUMBER_OF_TRANSFERS)
+      .map(|j| {
+        let from = (i as i32 + j) % NUMBER_OF_ACCOUNTS + 1;
+        let to = (i as i32 + j * 3 + 1) % NUMBER_OF_ACCOUNTS + 1;
+        (from, to)
+      })
+      .collect()
+  }
+
+  fn transfer(shared_database: &SharedDatabase, session_id: usize, table_index: usize, from: i32, to: i32) -> Result<()> {
+    shared_database.execute(session_id, "BEGIN;")?;
+    shared_database.execute(session_id, &format!("UPDATE accounts_{} SET balance = balance - 1 WHERE id = {};", table_index, from))?;
+    shared_database.execute(session_id, &format!("UPDATE accounts_{} SET balance = balance + 1 WHERE id = {};", table_index, to))?;
+    shared_database.execute(session_id, "COMMIT;")?;
+    Ok(())
+  }
+}
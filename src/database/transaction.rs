@@ -0,0 +1,474 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::database::Database;
+use crate::database::session::{Session, IsolationLevel, Snapshot};
+use crate::database::wal::{WalRecord, WAL_CHECKPOINT_SIZE};
+use crate::table::{Table, RowChange};
+use crate::error::{Result, NollaDBError};
+
+// undo log 中的一条记录，回滚的时候按照和记录相反的顺序撤销
+// 表中数据的修改只记录被修改的行，表的创建、删除以及结构的修改记录整张表
+#[derive(PartialEq, Debug, Clone)]
+pub enum UndoRecord {
+  // 修改表结构的语句，放在它的其他记录后面，只用来写 WAL，回滚的时候什么都不做
+  Statement(String),
+  // 表在修改之前的样子，None 表示这张表原来不存在
+  Table(String, Option<Box<Table>>),
+  // ALTER TABLE RENAME TO 之前以及之后的表名
+  RenameTable(String, String),
+  // 一条语句对这张表中数据的修改
+  RowChanges(String, Vec<RowChange>),
+}
+
+// 提交时间戳的时钟，值是最近一次提交的时间戳
+// clone 出来的和原来的是同一个时钟，SharedDatabase 中同时执行的语句用它保证每次提交的时间戳都不一样
+// SharedDatabase 中提交之后要等到修改过的表放回去才算发布，别的会话开始的快照只能包括已经发布的提交
+#[derive(Debug, Clone, Default)]
+pub struct CommitClock(Arc<Mutex<ClockState>>);
+
+#[derive(Debug, Default)]
+struct ClockState {
+  commit_ts: u64,
+  // 已经拿到时间戳但是还没有发布的提交
+  unpublished_commit_ts: BTreeSet<u64>,
+}
+
+impl CommitClock {
+  pub fn get(&self) -> u64 {
+    self.lock().commit_ts
+  }
+
+  // 这个时间戳以及之前的提交都已经发布了
+  pub fn get_published(&self) -> u64 {
+    let state = self.lock();
+    state.unpublished_commit_ts
+      .first()
+      .map_or(state.commit_ts, |commit_ts| commit_ts - 1)
+  }
+
+  // 拿到下一次提交的时间戳，发布之前 get_published 不会超过它
+  fn tick(&self) -> u64 {
+    let mut state = self.lock();
+    state.commit_ts += 1;
+    let commit_ts = state.commit_ts;
+    state.unpublished_commit_ts.insert(commit_ts);
+    commit_ts
+  }
+
+  pub fn publish(&self, commit_ts: u64) {
+    self.lock().unpublished_commit_ts.remove(&commit_ts);
+  }
+
+  fn lock(&self) -> MutexGuard<'_, ClockState> {
+    self.0.lock().expect("Commit clock lock is poisoned")
+  }
+}
+
+impl PartialEq for CommitClock {
+  fn eq(&self, other: &Self) -> bool {
+    self.get() == other.get()
+  }
+}
+
+// 多个会话共用同一个 Database，每个会话有自己的事务
+// 修改直接写到表中，同时记录到 undo log 以及每一行的版本链中
+// 读的时候沿着版本链找到自己快照中的值，所以读不会被写阻塞
+impl Database {
+  // 切换执行 SQL 的会话，会话不存在的话会新建一个
+  pub fn use_session(&mut self, session_id: usize) {
+    self.session_id = session_id;
+    self.session_mut();
+  }
+
+  pub fn session_mut(&mut self) -> &mut Session {
+    self.sessions.entry(self.session_id).or_default()
+  }
+
+  pub fn is_in_transaction(&self) -> bool {
+    self.sessions
+      .get(&self.session_id)
+      .is_some_and(|session| session.is_in_transaction)
+  }
+
+  // BEGIN，没有指定隔离级别的话使用会话默认的隔离级别
+  pub fn begin_transaction(&mut self, isolation_level: Option<IsolationLevel>) -> Result<()> {
+    if self.is_in_transaction() {
+      return Err(NollaDBError::General(
+        "Can not begin a transaction within a transaction".to_string()
+      ));
+    }
+    // 事务中的查询在 SharedDatabase 中不拿表的锁，快照里不能有还没有放回去的表中的提交
+    let commit_ts = self.commit_clock.get_published();
+    let session = self.session_mut();
+    session.is_in_transaction = true;
+    session.isolation_level = isolation_level.unwrap_or(session.default_isolation_level);
+    session.snapshot_ts = commit_ts;
+    session.read_table_names.clear();
+    Ok(())
+  }
+
+  // COMMIT，事务中的修改已经写到表中了，给这些修改加上提交的时间戳就可以
+  // SERIALIZABLE 的事务读过的表在快照之后被别的事务修改过的话，回滚整个事务
+  pub fn commit_transaction(&mut self) -> Result<()> {
+    if !self.is_in_transaction() {
+      return Err(NollaDBError::General(
+        "Can not commit, because there is no transaction".to_string()
+      ));
+    }
+
+    let session = self.session_mut();
+    let snapshot_ts = session.snapshot_ts;
+    let is_serializable = session.isolation_level == IsolationLevel::Serializable;
+    let read_table_names = std::mem::take(&mut session.read_table_names);
+    if is_serializable {
+      let mut read_table_names: Vec<&String> = read_table_names.iter().collect();
+      read_table_names.sort();
+      for table_name in read_table_names {
+        if self.tables.get(table_name).is_some_and(|table| table.last_commit_ts > snapshot_ts) {
+          self.rollback_transaction()?;
+          return Err(NollaDBError::SerializationFailure(
+            format!(
+              "table '{}' has been changed by another transaction, the transaction is rolled back",
+              table_name
+            )
+          ));
+        }
+      }
+    }
+
+    self.write_wal()?;
+    let session = self.session_mut();
+    session.savepoints.clear();
+    session.is_in_transaction = false;
+    self.commit_undo_log();
+    self.checkpoint_if_needed();
+    Ok(())
+  }
+
+  // ROLLBACK，撤销事务中所有的修改
+  pub fn rollback_transaction(&mut self) -> Result<()> {
+    if !self.is_in_transaction() {
+      return Err(NollaDBError::General(
+        "Can not rollback, because there is no transaction".to_string()
+      ));
+    }
+    self.rollback_undo_log(0);
+    let session = self.session_mut();
+    session.savepoints.clear();
+    session.read_table_names.clear();
+    session.is_in_transaction = false;
+    self.remove_old_row_versions();
+    Ok(())
+  }
+
+  // SAVEPOINT，记住当前 undo log 的长度
+  // 和 SQL 一样名字可以重复，RELEASE 和 ROLLBACK TO 使用最近创建的那个
+  pub fn create_savepoint(&mut self, savepoint_name: &str) -> Result<()> {
+    if !self.is_in_transaction() {
+      return Err(NollaDBError::General(
+        "Can not create savepoint, because there is no transaction".to_string()
+      ));
+    }
+    let session = self.session_mut();
+    session.savepoints.push((savepoint_name.to_string(), session.undo_log.len()));
+    Ok(())
+  }
+
+  // RELEASE SAVEPOINT，删除这个 savepoint 以及在它之后创建的 savepoint
+  // 修改仍然保留在事务中，COMMIT 之后才真正提交
+  pub fn release_savepoint(&mut self, savepoint_name: &str) -> Result<()> {
+    let i = self.get_savepoint_position(savepoint_name)?;
+    self.session_mut().savepoints.truncate(i);
+    Ok(())
+  }
+
+  // ROLLBACK TO SAVEPOINT，撤销这个 savepoint 之后的修改
+  // 在它之后创建的 savepoint 会被删除，这个 savepoint 本身保留，还可以再次回滚到这里
+  pub fn rollback_to_savepoint(&mut self, savepoint_name: &str) -> Result<()> {
+    let i = self.get_savepoint_position(savepoint_name)?;
+    let (_, number_of_undo_records) = self.session_mut().savepoints[i];
+    self.rollback_undo_log(number_of_undo_records);
+    self.session_mut().savepoints.truncate(i + 1);
+    Ok(())
+  }
+
+  // 一条语句执行之前调用，返回这时 undo log 的长度
+  // 别的会话有还没有提交的表结构修改的话，要等它提交或者回滚之后才能执行
+  pub fn begin_statement(&mut self) -> Result<usize> {
+    self.check_schema_changes(self.session_id)?;
+
+    let commit_ts = self.commit_clock.get();
+    let session = self.session_mut();
+    if !session.is_in_transaction {
+      session.snapshot_ts = commit_ts;
+    }
+    Ok(session.undo_log.len())
+  }
+
+  // 一条语句执行成功之后调用，把每张表中这条语句的修改收集到 undo log 以及版本链中
+  // 有写冲突，或者别的会话在事务中的时候修改了表结构，这条语句就是失败的
+  // 不在事务中的话这条语句就已经提交了
+  pub fn end_statement(&mut self, sql_query: &str, number_of_undo_records: usize) -> Result<()> {
+    self.collect_row_changes()?;
+
+    let session_id = self.session_id;
+    let session = self.session_mut();
+    // COMMIT 和 ROLLBACK 之后 undo log 可能比执行之前短
+    let has_schema_changes = session.undo_log
+      .iter()
+      .skip(number_of_undo_records)
+      .any(|undo_record| !matches!(undo_record, UndoRecord::RowChanges(..)));
+    if has_schema_changes && self.sessions
+      .iter()
+      .any(|(id, session)| *id != session_id && session.is_in_transaction) {
+      return Err(NollaDBError::General(
+        "Can not change the table schema while other sessions are in a transaction".to_string()
+      ));
+    }
+    if has_schema_changes {
+      self.session_mut().undo_log.push(UndoRecord::Statement(sql_query.to_string()));
+    }
+
+    if !self.is_in_transaction() {
+      self.write_wal()?;
+      self.commit_undo_log();
+      self.checkpoint_if_needed();
+    }
+    Ok(())
+  }
+
+  // 一条语句执行失败之后调用，撤销这条语句的所有修改
+  // number_of_undo_records 是这条语句执行之前 undo log 的长度
+  pub fn rollback_statement(&mut self, number_of_undo_records: usize) {
+    // 有写冲突的修改已经在 undo log 中了，这里收集的只是执行到一半的语句的修改
+    let _ = self.collect_row_changes();
+    self.rollback_undo_log(number_of_undo_records);
+    if !self.is_in_transaction() {
+      self.remove_old_row_versions();
+    }
+  }
+
+  // SERIALIZABLE 的事务记录读过的表，提交的时候检查
+  pub fn add_read_table_names(&mut self, session_id: usize, table_names: Vec<String>) {
+    let session = self.sessions.entry(session_id).or_default();
+    if session.is_in_transaction && session.isolation_level == IsolationLevel::Serializable {
+      session.read_table_names.extend(table_names);
+    }
+  }
+
+  // UPDATE 和 DELETE 修改之前检查要修改的行有没有写冲突
+  pub fn check_write_conflicts(&mut self, table_name: &str, row_ids: &[i64]) -> Result<()> {
+    let session_id = self.session_id;
+    let snapshot_ts = self.session_mut().snapshot_ts;
+    match self.tables.get(table_name) {
+      Some(table) => table.check_write_conflicts(session_id, snapshot_ts, row_ids),
+      None => Ok(()),
+    }
+  }
+
+  // 当前会话读数据时用的快照
+  pub fn get_snapshot(&self) -> Snapshot {
+    self.get_session_snapshot(self.session_id)
+  }
+
+  // session_id 读数据时用的快照，只需要 &self，多个线程可以同时拿着读锁调用
+  // 快照只记录时间戳，读每一行的时候再判断哪个版本是可见的，见 Table::get_invisible_rows
+  pub fn get_session_snapshot(&self, session_id: usize) -> Snapshot {
+    // 不在事务中的话能看到所有已经提交的修改
+    let snapshot_ts = self.sessions
+      .get(&session_id)
+      .filter(|session| session.is_in_transaction)
+      .map_or(self.commit_clock.get(), |session| session.snapshot_ts);
+    Snapshot { session_id, snapshot_ts }
+  }
+
+  // 别的会话有还没有提交的表结构修改的话，session_id 不能执行语句
+  pub fn check_schema_changes(&self, session_id: usize) -> Result<()> {
+    match self.sessions
+      .iter()
+      .find(|(id, session)| **id != session_id && session.has_schema_changes()) {
+      Some((id, _)) => Err(NollaDBError::General(
+        format!("Can not execute, because session {} is changing the table schema", id)
+      )),
+      None => Ok(()),
+    }
+  }
+
+  fn get_savepoint_position(&mut self, savepoint_name: &str) -> Result<usize> {
+    self.session_mut()
+      .savepoints
+      .iter()
+      .rposition(|(name, _)| name == savepoint_name)
+      .ok_or_else(|| NollaDBError::General(
+        format!("Savepoint '{}' does not exist", savepoint_name)
+      ))
+  }
+
+  // 把每张表中还没有收集的修改放到当前会话的 undo log 以及版本链中
+  // 所有的修改都会被收集，有写冲突的话最后返回错误
+  fn collect_row_changes(&mut self) -> Result<()> {
+    let session_id = self.session_id;
+    let snapshot_ts = self.session_mut().snapshot_ts;
+    let mut table_names: Vec<String> = self.tables
+      .iter()
+      .filter(|(_, table)| !table.row_changes.is_empty())
+      .map(|(table_name, _)| table_name.to_string())
+      .collect();
+    // 同一条语句中不同表的修改互不影响，排序只是为了让 undo log 的顺序是确定的
+    table_names.sort();
+
+    let mut result = Ok(());
+    for table_name in table_names {
+      let table = self.tables.get_mut(&table_name).unwrap();
+      let row_changes = std::mem::take(&mut table.row_changes);
+      let added = table.add_row_versions(session_id, snapshot_ts, &row_changes);
+      result = result.and(added);
+      self.session_mut().undo_log.push(UndoRecord::RowChanges(table_name, row_changes));
+    }
+    result
+  }
+
+  // 提交当前会话 undo log 中的修改，然后回收不再需要的版本
+  fn commit_undo_log(&mut self) {
+    let session_id = self.session_id;
+    let undo_log = std::mem::take(&mut self.session_mut().undo_log);
+    if !undo_log.is_empty() {
+      let commit_ts = self.commit_clock.tick();
+      match self.unpublished_commit_ts.as_mut() {
+        Some(unpublished_commit_ts) => unpublished_commit_ts.push(commit_ts),
+        None => self.commit_clock.publish(commit_ts),
+      }
+      let mut table_names: HashSet<&String> = HashSet::new();
+      for undo_record in &undo_log {
+        match undo_record {
+          UndoRecord::Table(table_name, _) | UndoRecord::RowChanges(table_name, _) => {
+            table_names.insert(table_name);
+          },
+          UndoRecord::RenameTable(old_table_name, new_table_name) => {
+            table_names.insert(old_table_name);
+            table_names.insert(new_table_name);
+          },
+          UndoRecord::Statement(_) => (),
+        }
+      }
+      for table_name in table_names {
+        if let Some(table) = self.tables.get_mut(table_name) {
+          table.commit_row_versions(session_id, commit_ts);
+        }
+      }
+    }
+    self.remove_old_row_versions();
+  }
+
+  // 提交之前把当前会话 undo log 中的修改写到 WAL 中，WAL 写失败的话就不能提交
+  // 修改表结构的语句只记录 SQL，其他的修改只记录被修改过的行的值，按照 undo log 的顺序排列
+  // 修改表结构之前被修改过的行记录这时的值，放在这条语句前面，重做这条语句的时候表和当时一样
+  // 被修改的行不会同时被别的会话修改，所以表中这些行现在的值就是提交的值
+  fn write_wal(&self) -> Result<()> {
+    let (Some(wal), Some(session)) = (&self.wal, self.sessions.get(&self.session_id)) else {
+      return Ok(());
+    };
+
+    let mut wal_records: Vec<WalRecord> = vec![];
+    // 还没有记下来的被修改过的行，key 是这时的表名
+    let mut row_ids: BTreeMap<&str, BTreeSet<i64>> = BTreeMap::new();
+    for undo_record in &session.undo_log {
+      match undo_record {
+        UndoRecord::RowChanges(table_name, row_changes) => {
+          row_ids.entry(table_name).or_default().extend(row_changes.iter().map(|row_change| {
+            match row_change {
+              RowChange::Insert(row_id, _)
+              | RowChange::Update(row_id, _)
+              | RowChange::Delete(row_id, _) => *row_id,
+            }
+          }));
+        },
+        // 表结构被修改之前的样子，其中是这些行这时的值
+        UndoRecord::Table(table_name, table) => {
+          if let (Some(row_ids), Some(table)) = (row_ids.remove(table_name.as_str()), table) {
+            wal_records.push(get_rows_record(table_name, table, &row_ids)?);
+          }
+        },
+        UndoRecord::RenameTable(old_table_name, new_table_name) => {
+          if let Some(table_row_ids) = row_ids.remove(old_table_name.as_str()) {
+            row_ids.insert(new_table_name, table_row_ids);
+          }
+        },
+        UndoRecord::Statement(sql_query) => {
+          wal_records.push(WalRecord::Statement(sql_query.to_string()));
+        },
+      }
+    }
+    for (table_name, row_ids) in row_ids {
+      if let Some(table) = self.tables.get(table_name) {
+        wal_records.push(get_rows_record(table_name, table, &row_ids)?);
+      }
+    }
+
+    if wal_records.is_empty() {
+      return Ok(());
+    }
+    wal.append(&wal_records)
+  }
+
+  // WAL 太大的话做一次 checkpoint，有会话在事务中的时候表中有还没有提交的修改，不能做
+  // 这时已经提交了，checkpoint 失败的话修改还在 WAL 中，下次提交的时候再做
+  pub fn checkpoint_if_needed(&mut self) {
+    let is_too_large = self.wal.as_ref().is_some_and(|wal| wal.get_size() > WAL_CHECKPOINT_SIZE);
+    if is_too_large && !self.sessions.values().any(|session| session.is_in_transaction) {
+      let _ = Database::save(self.database_name.clone(), self);
+    }
+  }
+
+  // 从后往前撤销当前会话的 undo log，直到只剩下 number_of_undo_records 条
+  fn rollback_undo_log(&mut self, number_of_undo_records: usize) {
+    let undo_log = &mut self.session_mut().undo_log;
+    let undo_records = undo_log.split_off(number_of_undo_records.min(undo_log.len()));
+    for undo_record in undo_records.into_iter().rev() {
+      match undo_record {
+        UndoRecord::Table(table_name, Some(table)) => {
+          self.tables.insert(table_name, *table);
+        },
+        UndoRecord::Table(table_name, None) => {
+          self.tables.remove(&table_name);
+        },
+        UndoRecord::RenameTable(old_table_name, new_table_name) => {
+          if let Some(mut table) = self.tables.remove(&new_table_name) {
+            table.table_name = old_table_name.to_string();
+            self.tables.insert(old_table_name, table);
+          }
+        },
+        UndoRecord::RowChanges(table_name, row_changes) => {
+          if let Some(table) = self.tables.get_mut(&table_name) {
+            table.remove_row_versions(&row_changes);
+            table.undo_row_changes(row_changes);
+          }
+        },
+        UndoRecord::Statement(_) => (),
+      }
+    }
+  }
+
+  // 在事务中的会话里最早的快照之前提交的版本，所有的会话都不会再用到
+  fn remove_old_row_versions(&mut self) {
+    let min_snapshot_ts = self.sessions
+      .values()
+      .filter(|session| session.is_in_transaction)
+      .map(|session| session.snapshot_ts)
+      .min()
+      .unwrap_or(self.commit_clock.get());
+    for table in self.tables.values_mut() {
+      table.remove_old_row_versions(min_snapshot_ts);
+    }
+  }
+}
+
+// table 中这些行现在的值
+fn get_rows_record(table_name: &str, table: &Table, row_ids: &BTreeSet<i64>) -> Result<WalRecord> {
+  Ok(WalRecord::Rows {
+    table_name: table_name.to_string(),
+    most_recent_row_id: table.most_recent_row_id,
+    committed_rows: table.get_committed_rows(row_ids)?,
+  })
+}
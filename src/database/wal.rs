@@ -0,0 +1,160 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::sync::Mutex;
+
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, NollaDBError};
+use crate::table::CommittedRow;
+
+// WAL 超过这个大小之后做一次 checkpoint，单位是字节
+pub const WAL_CHECKPOINT_SIZE: u64 = 4 * 1024 * 1024;
+
+// SharedDatabase 中修改不同表的语句会同时提交，一次只能有一帧在写，不然帧会交错在一起
+static WAL_APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+// 一次提交中的一个修改，重做的时候按照顺序执行
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub enum WalRecord {
+  // 修改表结构的语句，重做的时候再执行一次，索引也会重新建立
+  Statement(String),
+  // 一张表中被修改过的行的值
+  Rows {
+    table_name: String,
+    most_recent_row_id: i64,
+    committed_rows: Vec<CommittedRow>,
+  },
+}
+
+// 预写日志，每次提交的修改在提交之前追加到 DATABASE_NAME.db-wal 中
+// 文件开头是 8 字节的代数，之后每次提交是一帧：4 字节的长度，4 字节的校验和，然后是这次提交的所有 WalRecord
+// 写到一半崩溃的话最后一帧是不完整的，读的时候会被忽略，这次提交就和没有发生一样
+#[derive(PartialEq, Debug, Clone)]
+pub struct Wal {
+  pub path: String,
+  // 每次 checkpoint 之后加一，checkpoint 时和 database 文件一起写进文件头
+  // checkpoint 写完文件之后、清空 WAL 之前崩溃的话，WAL 中的代数不比文件中的大，这些提交不需要重做
+  pub generation: u64,
+}
+
+impl Wal {
+  pub fn new(database_name: &str, generation: u64) -> Self {
+    Wal { path: format!("{}-wal", database_name), generation }
+  }
+
+  // 追加一次提交，写到磁盘上之后才返回
+  pub fn append(&self, wal_records: &[WalRecord]) -> Result<()> {
+    let data = serialize(wal_records)
+      .map_err(|error| NollaDBError::Internal(format!("Can not encode WAL record: {}", error)))?;
+    let mut frame = Vec::with_capacity(data.len() + 16);
+    frame.extend((data.len() as u32).to_le_bytes());
+    frame.extend(get_checksum(&data).to_le_bytes());
+    frame.extend(data);
+
+    let _guard = WAL_APPEND_LOCK.lock().unwrap_or_else(|error| error.into_inner());
+    OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&self.path)
+      .and_then(|mut file| {
+        if file.metadata()?.len() == 0 {
+          frame.splice(0..0, self.generation.to_le_bytes());
+        }
+        file.write_all(&frame)?;
+        file.sync_data()
+      })
+      .map_err(|error| NollaDBError::Internal(format!("Can not write {}: {}", self.path, error)))
+  }
+
+  // 按照提交的顺序读出所有完整的提交
+  // 文件中的代数和 generation 不一样的话是已经 checkpoint 过的 WAL，没有需要重做的提交
+  pub fn read(&self) -> Result<Vec<Vec<WalRecord>>> {
+    let mut bytes = vec![];
+    match File::open(&self.path) {
+      Ok(mut file) => file
+        .read_to_end(&mut bytes)
+        .map_err(|error| NollaDBError::Internal(format!("Can not read {}: {}", self.path, error)))?,
+      Err(_) => return Ok(vec![]),
+    };
+    if bytes.get(..8) != Some(&self.generation.to_le_bytes()[..]) {
+      return Ok(vec![]);
+    }
+
+    let mut commits = vec![];
+    let mut i = 8;
+    while let Some(header) = bytes.get(i..i + 8) {
+      let length = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+      let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+      let data = match bytes.get(i + 8..i + 8 + length) {
+        Some(data) if get_checksum(data) == checksum => data,
+        _ => break,
+      };
+      match deserialize(data) {
+        Ok(wal_records) => commits.push(wal_records),
+        Err(_) => break,
+      }
+      i += 8 + length;
+    }
+    Ok(commits)
+  }
+
+  pub fn get_size(&self) -> u64 {
+    fs::metadata(&self.path).map_or(0, |metadata| metadata.len())
+  }
+
+  // checkpoint 之后 WAL 中的修改都已经在 database 文件中了
+  pub fn clear(&self) -> Result<()> {
+    match fs::remove_file(&self.path) {
+      Ok(()) => Ok(()),
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+      Err(error) => Err(NollaDBError::Internal(format!("Can not remove {}: {}", self.path, error))),
+    }
+  }
+}
+
+// FNV-1a，用来发现写到一半的帧
+pub fn get_checksum(data: &[u8]) -> u32 {
+  data.iter().fold(0x811c9dc5, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x01000193))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::{assert_eq};
+  use crate::table::row::value::Value;
+
+  #[test]
+  fn test_wal() {
+    let path = std::env::temp_dir().join(format!("nolladb-wal-{}.db", std::process::id()));
+    let wal = Wal::new(path.to_str().unwrap(), 1);
+    wal.clear().unwrap();
+    assert_eq!(wal.read(), Ok(vec![]));
+
+    let commits = vec![
+      vec![WalRecord::Statement("DROP TABLE a;".to_string())],
+      vec![WalRecord::Rows {
+        table_name: "b".to_string(),
+        most_recent_row_id: 2,
+        committed_rows: vec![(1, None), (2, Some(vec![Value::Text("x".to_string()), Value::Null]))],
+      }],
+    ];
+    for wal_records in &commits {
+      wal.append(wal_records).unwrap();
+    }
+    assert_eq!(wal.read(), Ok(commits.clone()));
+
+    // 崩溃时最后一帧只写了一半
+    let size = wal.get_size();
+    wal.append(&commits[1]).unwrap();
+    let file = OpenOptions::new().write(true).open(&wal.path).unwrap();
+    file.set_len(size + 10).unwrap();
+    assert_eq!(wal.read(), Ok(commits));
+
+    // 已经 checkpoint 过的 WAL
+    assert_eq!(Wal::new(path.to_str().unwrap(), 2).read(), Ok(vec![]));
+
+    wal.clear().unwrap();
+    assert_eq!(wal.get_size(), 0);
+  }
+}
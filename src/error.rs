@@ -15,6 +15,12 @@ pub enum NollaDBError {
   SQLParseError(#[from] ParserError),
   #[error("To be Implemented error: {0}")]
   ToBeImplemented(String),
+  // 分别是 table name 和 column name
+  #[error("NOT NULL constraint failed: {0}.{1}")]
+  NotNullConstraint(String, String),
+  // 并发的事务之间有冲突，需要回滚之后重试
+  #[error("Serialization failure: {0}")]
+  SerializationFailure(String),
 }
 
 pub type Result<T> = result::Result<T, NollaDBError>;
@@ -34,7 +40,7 @@ mod tests {
   #[case("test error")]
   fn test_nolladb_error(#[case] input: &str) {
     let expected = NollaDBError::General(input.to_string());
-    let result = nolladb_error(&input);
+    let result = nolladb_error(input);
 
     assert_eq!(result, expected);
   }
@@ -83,4 +89,19 @@ mod tests {
 
       assert_eq!(result, expected);
   }
+
+  #[rstest]
+  #[case("test", "name")]
+  fn test_nolladb_not_null_constraint_error(
+    #[case] table_name: &str,
+    #[case] column_name: &str,
+  ) {
+      let expected = format!("NOT NULL constraint failed: {}.{}", table_name, column_name);
+      let result = format!(
+        "{}",
+        NollaDBError::NotNullConstraint(table_name.to_string(), column_name.to_string())
+      );
+
+      assert_eq!(result, expected);
+  }
 }
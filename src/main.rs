@@ -9,6 +9,7 @@ mod sql_query;
 mod read_eval_print_loop;
 mod table;
 mod database;
+mod storage;
 
 use std::{env, process};
 
@@ -18,15 +19,16 @@ use env_logger::Env;
 
 use intro_message::intro_message;
 use meta_command::{MetaCommand, handle_meta_command};
-use sql_query::handle_sql_query;
 use read_eval_print_loop::{
   RealEvalPrintLoopHelper,
   CommandType,
   get_config,
   get_command_type,
 };
-use database::Database;
+use database::{Database, DEFAULT_MEMORY_BUDGET};
 use database::database_manager::DatabaseManager;
+use database::shared::SharedDatabase;
+use sql_query::query::pragma::parse_memory_budget;
 
 fn main() -> rustyline::Result<()> {
 
@@ -37,13 +39,23 @@ fn main() -> rustyline::Result<()> {
 
   // 创建 database
   let args: Vec<String> = env::args().collect();
-  // cargo run
-  let args_right_number = 2;
-  if args.len() != args_right_number {
-    println!("Usage: {} DATABASE_NAME.db", "cargo run");
-    process::exit(1)
-  }
-  let database_name = &args[args_right_number - 1];
+  // cargo run DATABASE_NAME.db [--memory-budget SIZE]
+  let usage = "Usage: cargo run DATABASE_NAME.db [--memory-budget SIZE]";
+  let memory_budget = match args.len() {
+    2 => DEFAULT_MEMORY_BUDGET,
+    4 if args[2] == "--memory-budget" => match parse_memory_budget(&args[3]) {
+      Ok(memory_budget) => memory_budget,
+      Err(error) => {
+        println!("{}", error);
+        process::exit(1)
+      },
+    },
+    _ => {
+      println!("{}", usage);
+      process::exit(1)
+    },
+  };
+  let database_name = &args[1];
   if !database_name.ends_with(".db") {
     println!("Database name should end with '.db'");
     process::exit(1)
@@ -64,6 +76,9 @@ fn main() -> rustyline::Result<()> {
       process::exit(1)
     }
   }
+  database.settings.memory_budget = memory_budget;
+  // 用 SharedDatabase 包起来之后可以交给别的线程使用
+  let shared_database = SharedDatabase::new(database);
 
   // 创建 repl helper
   let repl_helper = RealEvalPrintLoopHelper::default();
@@ -85,7 +100,7 @@ fn main() -> rustyline::Result<()> {
   intro_message();
 
   loop {
-    let print = format!("nolladb>");
+    let print = "nolladb>".to_string();
     repl
      .helper_mut()
      .expect("No helper found")
@@ -94,26 +109,32 @@ fn main() -> rustyline::Result<()> {
     let readline = repl.readline(&print);
     match readline {
       Ok(command) => {
-        if command.split_whitespace().collect::<Vec<&str>>().len() == 0 { continue; }
+        if command.split_whitespace().collect::<Vec<&str>>().is_empty() { continue; }
 
         repl.add_history_entry(command.as_str());
         let command_type = get_command_type(&command.trim().to_owned());
         match command_type {
           CommandType::MetaCommand(cmd) => {
-            match handle_meta_command(
+            // 写锁在这条语句结束的时候就释放了，下面处理 response 的时候还要再拿锁
+            let result = handle_meta_command(
               cmd,
               &mut repl,
-              &mut database,
+              &mut shared_database.write(),
               &mut database_manager
-            ) {
+            );
+            match result {
               Ok(response) => {
                 match response {
                   MetaCommand::Open(new_database_name) => {
                     match Database::open_mut(&mut database_manager, new_database_name) {
                       Ok(new_database) => {
                         println!("Opening {}...", new_database.database_name);
+                        let mut database = shared_database.write();
                         // 这里 clone 去掉引用，拿到的就是引用指向的数据内容
-                        database = new_database.clone();
+                        // 当前会话的设置不跟着 database 走
+                        let settings = database.settings.clone();
+                        *database = new_database.clone();
+                        database.settings = settings;
                         println!("Opening {} done", database.database_name);
                       },
                       Err(error) => eprintln!("An error occurred: {:?}", error),
@@ -122,7 +143,10 @@ fn main() -> rustyline::Result<()> {
                   MetaCommand::Read(database_name) => {
                     match Database::start(database_name.clone(), database_manager_file.clone()) {
                       Ok((new_database, new_database_manager)) => {
-                        database = new_database;
+                        let mut database = shared_database.write();
+                        let settings = database.settings.clone();
+                        *database = new_database;
+                        database.settings = settings;
                         database_manager = new_database_manager;
                       },
                       Err(error) => {
@@ -131,13 +155,20 @@ fn main() -> rustyline::Result<()> {
                     }
                   },
                   MetaCommand::Save(database_name) => {
+                    let mut database = shared_database.write();
                     match Database::end(
                       database_name.clone(),
-                      &database,
+                      &mut database,
                       database_manager_file.clone(),
                       &database_manager
                     ) {
-                      Ok(()) => (),
+                      // 保存到自己的文件之后表中的行改成从文件中读，database_manager 中的也要跟着更新
+                      Ok(()) => {
+                        database_manager.database.insert(
+                          database.database_name.clone(),
+                          database.clone()
+                        );
+                      },
                       Err(error) => eprintln!("An error occurred: {:?}", error),
                     }
                   },
@@ -148,10 +179,12 @@ fn main() -> rustyline::Result<()> {
             }
           },
           CommandType::SQLQuery(_) => {
-            match handle_sql_query(&command, &mut database) {
+            let session_id = shared_database.write().session_id;
+            match shared_database.execute(session_id, &command) {
               Ok(response) => {
                 println!("{}", response);
                 // SQL statement 执行成功后要更新 database 以及 database_manager
+                let database = shared_database.write();
                 database_manager.database.insert(
                   database.database_name.clone(),
                   database.clone()
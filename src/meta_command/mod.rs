@@ -16,13 +16,14 @@ pub enum MetaCommand {
   Read(String),
   Save(String),
   Ast(String),
+  Session(String),
   Unknown,
 }
 
 impl MetaCommand {
   pub fn new(command: String) -> MetaCommand {
     let args: Vec<&str> = command.split_whitespace().collect();
-    if args.len() == 0 {
+    if args.is_empty() {
       return MetaCommand::Unknown;
     }
     // to_owned 将 &str 转变成 String
@@ -37,6 +38,7 @@ impl MetaCommand {
       ".read" => MetaCommand::Read(command),
       ".save" => MetaCommand::Save(command),
       ".ast" => MetaCommand::Ast(command),
+      ".session" => MetaCommand::Session(command),
       _ => MetaCommand::Unknown,
     }
   }
@@ -54,6 +56,7 @@ impl fmt::Display for MetaCommand {
       MetaCommand::Read(_) => f.write_str(".read"),
       MetaCommand::Save(_) => f.write_str(".save"),
       MetaCommand::Ast(_) => f.write_str(".ast"),
+      MetaCommand::Session(_) => f.write_str(".session"),
       MetaCommand::Unknown => f.write_str("Unknown command"),
     }
   }
@@ -67,7 +70,7 @@ fn handle_exit_or_quit_meta_command(
 }
 
 fn get_str_after_meta_command(
-  ref args: String,
+  args: String,
   error_message: &str,
 ) -> Result<String> {
   let mut args_vec = args.split_whitespace().collect::<Vec<&str>>();
@@ -89,22 +92,22 @@ pub fn handle_meta_command(
     MetaCommand::Quit => handle_exit_or_quit_meta_command(repl_helper),
     MetaCommand::Help => {
       println!(
-        "{}{}{}{}{}{}{}{}{}",
-        "Special commands:\n",
-        ".help            - Display help message\n",
-        "---------------------------------------\n",
-        ".ast  <QUERY>    - Show the abstract syntax tree for QUERY\n",
-        ".exit            - Quits this application\n",
-        ".open <FILENAME> - Close existing database and reopen FILENAME\n",
-        ".read <FILENAME> - Read input from FILENAME\n",
-        ".save <FILENAME> - Write in-memory database into FILENAME\n",
-        ".tables          - List names of tables\n",
+        "Special commands:\n\
+         .help            - Display help message\n\
+         ---------------------------------------\n\
+         .ast  <QUERY>    - Show the abstract syntax tree for QUERY\n\
+         .exit            - Quits this application\n\
+         .open <FILENAME> - Close existing database and reopen FILENAME\n\
+         .read <FILENAME> - Read input from FILENAME\n\
+         .save <FILENAME> - Write in-memory database into FILENAME\n\
+         .session <ID>    - Switch to session ID, each session has its own transaction\n\
+         .tables          - List names of tables\n",
       );
       Ok(command)
     },
     MetaCommand::Tables => {
       let table_names = database.get_all_tables(
-        &database_manager,
+        database_manager,
         database.database_name.clone()
       ).unwrap();
 
@@ -119,7 +122,7 @@ pub fn handle_meta_command(
         ".open <FILENAME>: FILENAME should not be empty",
       ) {
         Ok(args) => Ok(MetaCommand::Open(args)),
-        Err(error) => return Err(error),
+        Err(error) => Err(error),
       }
     },
     MetaCommand::Read(args) => {
@@ -128,7 +131,7 @@ pub fn handle_meta_command(
         ".read <FILENAME>: FILENAME should not be empty",
       ) {
         Ok(args) => Ok(MetaCommand::Read(args)),
-        Err(error) => return Err(error),
+        Err(error) => Err(error),
       }
     },
     MetaCommand::Save(args) => {
@@ -137,7 +140,7 @@ pub fn handle_meta_command(
         ".save <FILENAME>: FILENAME should not be empty",
       ) {
         Ok(args) => Ok(MetaCommand::Save(args)),
-        Err(error) => return Err(error),
+        Err(error) => Err(error),
       }
     },
     MetaCommand::Ast(ref args) => {
@@ -155,9 +158,25 @@ pub fn handle_meta_command(
       }
       Ok(command)
     },
-    MetaCommand::Unknown => Err(NollaDBError::UnknownCommand(format!(
-      "Unknown command or invalid arguments. Enter '.help'"
-    ))),
+    MetaCommand::Session(ref args) => {
+      let session_id = get_str_after_meta_command(
+        args.to_string(),
+        ".session <ID>: ID should not be empty",
+      )?;
+      match session_id.parse::<usize>() {
+        Ok(session_id) => {
+          database.use_session(session_id);
+          println!("Using session {}", session_id);
+          Ok(command)
+        },
+        Err(_) => Err(NollaDBError::UnknownCommand(
+          format!(".session <ID>: ID should be a number, got '{}'", session_id)
+        )),
+      }
+    },
+    MetaCommand::Unknown => Err(NollaDBError::UnknownCommand(
+      "Unknown command or invalid arguments. Enter '.help'".to_string()
+    )),
   }
 }
 
@@ -185,6 +204,19 @@ mod test {
     assert_eq!(result.is_ok(), true);
   }
 
+  #[rstest]
+  #[case(".session 1", true)]
+  #[case(".session", false)]
+  #[case(".session abc", false)]
+  fn test_session_meta_command(
+    #[case] command: &str,
+    #[case] expected: bool,
+  ) {
+    let input = MetaCommand::Session(command.to_string());
+    let result = gen_result(input);
+    assert_eq!(result.is_ok(), expected);
+  }
+
   #[rstest]
   #[case(MetaCommand::Unknown)]
   fn test_unknown_meta_command(#[case] input: MetaCommand) {
@@ -210,6 +242,7 @@ mod test {
   #[case(MetaCommand::Read(".read test.db".to_string()), ".read")]
   #[case(MetaCommand::Save(".save test.db".to_string()), ".save")]
   #[case(MetaCommand::Ast(".ast SELECT * from test;".to_string()), ".ast")]
+  #[case(MetaCommand::Session(".session 1".to_string()), ".session")]
   fn test_display_meta_command_2(
     #[case] input: MetaCommand,
     #[case] expected: &str,
@@ -230,14 +263,11 @@ mod test {
     let mut repl = init_repl().unwrap();
     let mut database = Database::new("test".to_string());
     let mut database_manager = DatabaseManager::new();
-    match handle_meta_command(
+    handle_meta_command(
       input,
       &mut repl,
       &mut database,
       &mut database_manager
-    ) {
-      Ok(result) => Ok(result),
-      Err(error) => return Err(error),
-    }
+    )
   }
 }
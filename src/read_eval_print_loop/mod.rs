@@ -146,7 +146,9 @@ mod tests {
 
   #[rstest]
   #[case(".help", CommandType::MetaCommand(MetaCommand::Help))]
+  #[case("EXPLAIN SELECT * FROM test;", CommandType::SQLQuery(SQLQuery::Explain("EXPLAIN SELECT * FROM test;".to_string())))]
   #[case("SELECT * FROM test;", CommandType::SQLQuery(SQLQuery::Select("SELECT * FROM test;".to_string())))]
+  #[case("BEGIN;", CommandType::SQLQuery(SQLQuery::Transaction("BEGIN;".to_string())))]
   fn test_get_command_type(
     #[case] input: &str,
     #[case] expected: CommandType,
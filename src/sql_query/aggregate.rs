@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::cmp::Ordering;
+
+use sqlparser::ast::{
+  Expr,
+  Function,
+  FunctionArg,
+  BinaryOperator,
+};
+
+use crate::error::{Result, NollaDBError};
+use crate::sql_query::expression::{
+  RowValues,
+  evaluate_expression,
+  evaluate_binary_operation,
+  visit_expression,
+};
+use crate::table::column::data_type::DataType;
+use crate::table::row::value::Value;
+
+// 目前支持的聚合函数
+const AGGREGATE_FUNCTION_NAMES: [&str; 5] = ["COUNT", "SUM", "AVG", "MIN", "MAX"];
+
+pub fn is_aggregate_function(function: &Function) -> bool {
+  AGGREGATE_FUNCTION_NAMES.contains(&get_function_name(function).as_str())
+}
+
+pub fn has_aggregate_function(expr: &Expr) -> bool {
+  let mut result = false;
+  let _ = visit_expression(expr, &mut |expr| {
+    if let Expr::Function(function) = expr {
+      result = result || is_aggregate_function(function);
+    }
+    Ok(!result)
+  });
+  result
+}
+
+// 找到表达式中所有的聚合函数，相同的聚合函数只会求值一次
+// 聚合函数里面不能再嵌套聚合函数
+pub fn get_aggregate_functions(expr: &Expr, functions: &mut Vec<Function>) -> Result<()> {
+  visit_expression(expr, &mut |expr| {
+    match expr {
+      Expr::Function(function) if is_aggregate_function(function) => {
+        if function.args.iter().any(|arg| match arg {
+          FunctionArg::Named { arg, .. } => has_aggregate_function(arg),
+          FunctionArg::Unnamed(arg) => has_aggregate_function(arg),
+        }) {
+          return Err(NollaDBError::General(
+            format!("Aggregate function '{}' can not be nested", expr)
+          ));
+        }
+        if !functions.contains(function) {
+          functions.push(function.clone());
+        }
+        Ok(false)
+      },
+      _ => Ok(true),
+    }
+  })
+}
+
+// 不在聚合函数中的 column 必须出现在 GROUP BY 中
+pub fn check_group_by_columns(expr: &Expr, group_by: &[Expr]) -> Result<()> {
+  visit_expression(expr, &mut |expr| {
+    if group_by.contains(expr) { return Ok(false); }
+    match expr {
+      Expr::Function(function) if is_aggregate_function(function) => Ok(false),
+      Expr::Identifier(_) | Expr::CompoundIdentifier(_) => Err(NollaDBError::General(
+        format!(
+          "Column '{}' must appear in the GROUP BY clause or be used in an aggregate function",
+          expr
+        )
+      )),
+      _ => Ok(true),
+    }
+  })
+}
+
+// 检查带有聚合函数或者 GROUP BY 的 SELECT，并且找到其中所有的聚合函数
+// 1. 不在聚合函数中的 column 必须出现在 GROUP BY 中
+// 2. 聚合函数不能嵌套，也不能出现在 GROUP BY 中
+// 之后按照 GROUP BY 分组求出聚合函数的值，再用 HAVING 过滤分组，最后对每一组求出 projection 的值
+pub fn get_select_aggregate_functions(
+  column_exprs: &[Expr],
+  group_by: &[Expr],
+  having: &Option<Expr>,
+) -> Result<Vec<Function>> {
+  let mut functions: Vec<Function> = vec![];
+  for expr in column_exprs.iter().chain(having.iter()) {
+    check_group_by_columns(expr, group_by)?;
+    get_aggregate_functions(expr, &mut functions)?;
+  }
+  for expr in group_by {
+    if has_aggregate_function(expr) {
+      return Err(NollaDBError::General(
+        format!("Aggregate function is not allowed in GROUP BY: '{}'", expr)
+      ));
+    }
+  }
+
+  Ok(functions)
+}
+
+// 按照 GROUP BY 对行进行分组，并且对每一组求出所有聚合函数的值
+// 每一组返回一行 RowValues，包含这一组第一行的数据以及聚合函数的值
+// 没有 GROUP BY 的话所有的行都是一组，即使没有任何行也会返回一组
+pub fn aggregate_rows(
+  rows: &[RowValues],
+  group_by: &[Expr],
+  functions: &[Function],
+) -> Result<Vec<RowValues>> {
+  let mut groups: Vec<Vec<&RowValues>> = vec![];
+
+  if group_by.is_empty() {
+    groups.push(rows.iter().collect());
+  } else {
+    // Value 中有 f32 不能直接 hash，这里用 Debug 的输出作为 key
+    // 这样 GROUP BY 的值是 NULL 的行也会被分到同一组
+    let mut group_indexes: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+      let group_key = group_by
+        .iter()
+        .map(|expr| evaluate_expression(expr, row))
+        .collect::<Result<Vec<Value>>>()?;
+
+      match group_indexes.get(&format!("{:?}", group_key)) {
+        Some(i) => groups[*i].push(row),
+        None => {
+          group_indexes.insert(format!("{:?}", group_key), groups.len());
+          groups.push(vec![row]);
+        },
+      }
+    }
+  }
+
+  let mut group_rows: Vec<RowValues> = vec![];
+  for group in &groups {
+    let mut group_row = group.first().map_or(RowValues::new(), |row| (*row).clone());
+    for function in functions {
+      group_row.insert(function.to_string(), evaluate_aggregate_function(function, group)?);
+    }
+    group_rows.push(group_row);
+  }
+
+  Ok(group_rows)
+}
+
+// COUNT(*) 统计所有的行
+// 其他的聚合函数都会跳过 NULL，没有任何值的话除了 COUNT 以外结果都是 NULL
+pub fn evaluate_aggregate_function(function: &Function, rows: &[&RowValues]) -> Result<Value> {
+  let function_name = get_function_name(function);
+  let arg = match function.args.as_slice() {
+    [FunctionArg::Unnamed(arg)] => arg,
+    _ => return Err(NollaDBError::General(
+      format!("Aggregate function '{}' expects exactly one argument", function)
+    )),
+  };
+
+  if let Expr::Wildcard = arg {
+    if function_name != "COUNT" || function.distinct {
+      return Err(NollaDBError::General(
+        format!("Invalid use of '*' in aggregate function '{}'", function)
+      ));
+    }
+    return Ok(Value::Integer(rows.len() as i32));
+  }
+
+  let mut values: Vec<Value> = vec![];
+  for row in rows {
+    let value = evaluate_expression(arg, row)?;
+    if value.is_null() { continue; }
+    if function.distinct && values.contains(&value) { continue; }
+    values.push(value);
+  }
+
+  match function_name.as_str() {
+    "COUNT" => Ok(Value::Integer(values.len() as i32)),
+    "SUM" => sum_values(&function_name, &values),
+    "AVG" => {
+      if values.is_empty() { return Ok(Value::Null); }
+      let sum = sum_values(&function_name, &values)?.cast(&DataType::Real)?;
+      evaluate_binary_operation(&sum, &BinaryOperator::Divide, &Value::Integer(values.len() as i32))
+    },
+    "MIN" => get_extreme_value(&values, Ordering::Less),
+    _ => get_extreme_value(&values, Ordering::Greater),
+  }
+}
+
+fn get_function_name(function: &Function) -> String {
+  function.name.to_string().to_uppercase()
+}
+
+// Integer 求和的结果还是 Integer，只要有 Real 结果就是 Real
+fn sum_values(function_name: &str, values: &[Value]) -> Result<Value> {
+  if values.is_empty() { return Ok(Value::Null); }
+
+  let mut sum = Value::Integer(0);
+  for value in values {
+    match value {
+      Value::Integer(_) | Value::Real(_) => {
+        sum = evaluate_binary_operation(&sum, &BinaryOperator::Plus, value)?;
+      },
+      _ => return Err(NollaDBError::General(
+        format!(
+          "Can not apply {} on {} value '{}'",
+          function_name,
+          value.get_data_type(),
+          value
+        )
+      )),
+    }
+  }
+
+  Ok(sum)
+}
+
+// MIN 找 Ordering::Less 的值，MAX 找 Ordering::Greater 的值
+fn get_extreme_value(values: &[Value], ordering: Ordering) -> Result<Value> {
+  let mut result = Value::Null;
+  for value in values {
+    if result.is_null() || value.compare(&result)? == Some(ordering) {
+      result = value.clone();
+    }
+  }
+
+  Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+  use crate::sql_query::expression::is_row_matched;
+  use crate::sql_query::query::select::{SelectQuery, Projection};
+
+  #[rstest]
+  #[case(
+    "SELECT COUNT(*), COUNT(score), SUM(score), AVG(score), MIN(name), MAX(score) FROM test;",
+    vec![vec![
+      Value::Integer(4),
+      Value::Integer(3),
+      Value::Integer(9),
+      Value::Real(3.0),
+      Value::Text("a".to_string()),
+      Value::Integer(5),
+    ]],
+  )]
+  #[case(
+    "SELECT name, COUNT(*), SUM(score) FROM test GROUP BY name;",
+    vec![
+      vec![Value::Text("a".to_string()), Value::Integer(2), Value::Integer(4)],
+      vec![Value::Text("b".to_string()), Value::Integer(2), Value::Integer(5)],
+    ],
+  )]
+  #[case(
+    "SELECT name, SUM(score) * 2 FROM test GROUP BY name HAVING COUNT(score) > 1;",
+    vec![
+      vec![Value::Text("a".to_string()), Value::Integer(8)],
+    ],
+  )]
+  #[case(
+    "SELECT COUNT(DISTINCT name), AVG(score + 0.5) FROM test WHERE score IS NULL;",
+    vec![vec![Value::Integer(1), Value::Null]],
+  )]
+  #[case(
+    "SELECT score, COUNT(*) FROM test GROUP BY score HAVING score IS NULL;",
+    vec![vec![Value::Null, Value::Integer(1)]],
+  )]
+  fn test_select_aggregate_rows(
+    #[case] query: &str,
+    #[case] expected: Vec<Vec<Value>>,
+  ) {
+    let select_query = parse_select_query(query);
+    let rows = create_rows()
+      .into_iter()
+      .filter(|row| match &select_query.selection {
+        Some(selection) => is_row_matched(selection, row).unwrap(),
+        None => true,
+      })
+      .collect::<Vec<RowValues>>();
+
+    assert_eq!(
+      select_aggregate_rows(
+        &rows,
+        &get_column_exprs(&select_query),
+        &select_query.group_by,
+        &select_query.having,
+      ),
+      Ok(expected)
+    );
+  }
+
+  #[rstest]
+  #[case("SELECT name, COUNT(*) FROM test;")]
+  #[case("SELECT SUM(COUNT(*)) FROM test;")]
+  #[case("SELECT SUM(name) FROM test;")]
+  #[case("SELECT SUM(*) FROM test;")]
+  #[case("SELECT COUNT(*) FROM test GROUP BY name HAVING score > 1;")]
+  fn test_select_aggregate_rows_error(
+    #[case] query: &str,
+  ) {
+    let select_query = parse_select_query(query);
+
+    assert!(
+      select_aggregate_rows(
+        &create_rows(),
+        &get_column_exprs(&select_query),
+        &select_query.group_by,
+        &select_query.having,
+      ).is_err()
+    );
+  }
+
+  // 和执行 SELECT 时一样，先分组求出聚合函数的值，再用 HAVING 过滤，最后求出每一列的值
+  fn select_aggregate_rows(
+    rows: &[RowValues],
+    column_exprs: &[Expr],
+    group_by: &[Expr],
+    having: &Option<Expr>,
+  ) -> Result<Vec<Vec<Value>>> {
+    let functions = get_select_aggregate_functions(column_exprs, group_by, having)?;
+    let mut result: Vec<Vec<Value>> = vec![];
+    for group_row in aggregate_rows(rows, group_by, &functions)? {
+      if let Some(having) = having {
+        if !is_row_matched(having, &group_row)? { continue; }
+      }
+      result.push(
+        column_exprs
+          .iter()
+          .map(|expr| evaluate_expression(expr, &group_row))
+          .collect::<Result<Vec<Value>>>()?
+      );
+    }
+    Ok(result)
+  }
+
+  fn create_rows() -> Vec<RowValues> {
+    vec![
+      ("a", Value::Integer(1)),
+      ("b", Value::Integer(5)),
+      ("a", Value::Integer(3)),
+      ("b", Value::Null),
+    ]
+      .into_iter()
+      .map(|(name, score)| {
+        let mut row = RowValues::new();
+        row.insert("name".to_string(), Value::Text(name.to_string()));
+        row.insert("score".to_string(), score);
+        row
+      })
+      .collect()
+  }
+
+  fn get_column_exprs(select_query: &SelectQuery) -> Vec<Expr> {
+    select_query.projection
+      .iter()
+      .map(|projection| match projection {
+        Projection::Expr(expr, _) => expr.clone(),
+        _ => panic!("Unexpected wildcard"),
+      })
+      .collect()
+  }
+
+  fn parse_select_query(query: &str) -> SelectQuery {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    SelectQuery::new(&ast.pop().unwrap()).unwrap()
+  }
+}
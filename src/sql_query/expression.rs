@@ -0,0 +1,463 @@
+use std::collections::HashMap;
+use std::cmp::Ordering;
+
+use sqlparser::ast::{
+  Expr,
+  BinaryOperator,
+  UnaryOperator,
+  FunctionArg,
+  Value as SQLValue,
+};
+
+use crate::error::{Result, NollaDBError};
+use crate::table::row::value::Value;
+use crate::sql_query::aggregate::is_aggregate_function;
+
+// 对表达式求值时用到的一行数据
+// key 是 column name，value 是这一行在这个 column 上的值
+pub type RowValues = HashMap<String, Value>;
+
+// 对 WHERE 这种谓词求值，只有结果为 true 的行才算匹配
+// 结果为 false 或者 Null 的行都不匹配
+pub fn is_row_matched(expr: &Expr, row: &RowValues) -> Result<bool> {
+  match evaluate_expression(expr, row)? {
+    Value::Bool(value) => Ok(value),
+    Value::Null => Ok(false),
+    value => Err(NollaDBError::General(
+      format!(
+        "Expected a boolean expression, but '{}' is evaluated to {} value '{}'",
+        expr,
+        value.get_data_type(),
+        value
+      )
+    )),
+  }
+}
+
+pub fn evaluate_expression(expr: &Expr, row: &RowValues) -> Result<Value> {
+  match expr {
+    Expr::Identifier(ident) => get_column_value(&ident.value, row),
+    // 类似于 test.id 这种带表名的 column
+    // 先按照完整的名字找，找不到再只按照 column name 找
+    Expr::CompoundIdentifier(idents) => {
+      let qualified_column_name = idents
+        .iter()
+        .map(|ident| ident.value.to_string())
+        .collect::<Vec<String>>()
+        .join(".");
+      match row.get(&qualified_column_name) {
+        Some(value) => Ok(value.clone()),
+        None => match idents.last() {
+          Some(ident) => get_column_value(&ident.value, row),
+          None => Err(NollaDBError::Internal(
+            format!("Invalid column name '{}'", expr)
+          )),
+        },
+      }
+    },
+    Expr::Value(value) => parse_sql_value(value),
+    Expr::Nested(expr) => evaluate_expression(expr, row),
+    Expr::IsNull(expr) => Ok(Value::Bool(evaluate_expression(expr, row)?.is_null())),
+    Expr::IsNotNull(expr) => Ok(Value::Bool(!evaluate_expression(expr, row)?.is_null())),
+    Expr::UnaryOp { op, expr } => {
+      let value = evaluate_expression(expr, row)?;
+      evaluate_unary_operation(op, value)
+    },
+    Expr::BinaryOp { left, op, right } => {
+      match op {
+        BinaryOperator::And | BinaryOperator::Or => {
+          evaluate_logical_operation(left, op, right, row)
+        },
+        _ => {
+          let left_value = evaluate_expression(left, row)?;
+          let right_value = evaluate_expression(right, row)?;
+          evaluate_binary_operation(&left_value, op, &right_value)
+        },
+      }
+    },
+    // expr [NOT] BETWEEN low AND high
+    Expr::Between { expr, negated, low, high } => {
+      let value = evaluate_expression(expr, row)?;
+      let low_value = evaluate_expression(low, row)?;
+      let high_value = evaluate_expression(high, row)?;
+      let result = and_values(
+        &evaluate_binary_operation(&value, &BinaryOperator::GtEq, &low_value)?,
+        &evaluate_binary_operation(&value, &BinaryOperator::LtEq, &high_value)?,
+      )?;
+      match negated {
+        true => not_value(&result),
+        false => Ok(result),
+      }
+    },
+    // expr [NOT] IN (a, b, c)
+    Expr::InList { expr, list, negated } => {
+      let value = evaluate_expression(expr, row)?;
+      let mut result = Value::Bool(false);
+      for item in list {
+        let item_value = evaluate_expression(item, row)?;
+        result = or_values(
+          &result,
+          &evaluate_binary_operation(&value, &BinaryOperator::Eq, &item_value)?,
+        )?;
+        if result == Value::Bool(true) { break; }
+      }
+      match negated {
+        true => not_value(&result),
+        false => Ok(result),
+      }
+    },
+    // 聚合函数的值在分组的时候就已经求出来了
+    // 以函数本身的字符串作为 key 存放在这一组的 RowValues 中
+    Expr::Function(function) if is_aggregate_function(function) => {
+      match row.get(&expr.to_string()) {
+        Some(value) => Ok(value.clone()),
+        None => Err(NollaDBError::General(
+          format!("Aggregate function '{}' is not allowed here", expr)
+        )),
+      }
+    },
+    _ => Err(NollaDBError::ToBeImplemented(
+      format!("Expression '{}' will be implemented soon", expr)
+    )),
+  }
+}
+
+// 深度优先遍历表达式
+// visitor 返回 false 的话就不再遍历这个表达式的子表达式
+pub fn visit_expression<F>(expr: &Expr, visitor: &mut F) -> Result<()>
+where
+  F: FnMut(&Expr) -> Result<bool>,
+{
+  if !visitor(expr)? { return Ok(()); }
+
+  match expr {
+    Expr::Nested(expr) |
+    Expr::IsNull(expr) |
+    Expr::IsNotNull(expr) |
+    Expr::UnaryOp { expr, .. } => visit_expression(expr, visitor),
+    Expr::BinaryOp { left, right, .. } => {
+      visit_expression(left, visitor)?;
+      visit_expression(right, visitor)
+    },
+    Expr::Between { expr, low, high, .. } => {
+      visit_expression(expr, visitor)?;
+      visit_expression(low, visitor)?;
+      visit_expression(high, visitor)
+    },
+    Expr::InList { expr, list, .. } => {
+      visit_expression(expr, visitor)?;
+      for item in list {
+        visit_expression(item, visitor)?;
+      }
+      Ok(())
+    },
+    Expr::Function(function) => {
+      for arg in &function.args {
+        match arg {
+          FunctionArg::Named { arg, .. } => visit_expression(arg, visitor)?,
+          FunctionArg::Unnamed(arg) => visit_expression(arg, visitor)?,
+        }
+      }
+      Ok(())
+    },
+    _ => Ok(()),
+  }
+}
+
+// 把 sqlparser 解析出来的字面量转换成 Value
+// 数字能转成 Integer 就转成 Integer，否则转成 Real
+pub fn parse_sql_value(value: &SQLValue) -> Result<Value> {
+  match value {
+    SQLValue::Number(n, _) => {
+      if let Ok(integer) = n.parse::<i32>() {
+        return Ok(Value::Integer(integer));
+      }
+      match n.parse::<f32>() {
+        Ok(real) => Ok(Value::Real(real)),
+        Err(_) => Err(NollaDBError::General(format!("Invalid number '{}'", n))),
+      }
+    },
+    SQLValue::SingleQuotedString(sqs) => Ok(Value::Text(sqs.to_string())),
+    SQLValue::DoubleQuotedString(dqs) => Ok(Value::Text(dqs.to_string())),
+    SQLValue::Boolean(b) => Ok(Value::Bool(*b)),
+    SQLValue::Null => Ok(Value::Null),
+    _ => Err(NollaDBError::ToBeImplemented(
+      format!("Value '{}' will be implemented soon", value)
+    )),
+  }
+}
+
+fn get_column_value(column_name: &str, row: &RowValues) -> Result<Value> {
+  match row.get(column_name) {
+    Some(value) => Ok(value.clone()),
+    None => Err(NollaDBError::Internal(
+      format!("Column '{}' does not exist", column_name)
+    )),
+  }
+}
+
+fn evaluate_unary_operation(op: &UnaryOperator, value: Value) -> Result<Value> {
+  match (op, &value) {
+    (_, Value::Null) => Ok(Value::Null),
+    (UnaryOperator::Not, _) => not_value(&value),
+    (UnaryOperator::Plus, Value::Integer(_)) |
+    (UnaryOperator::Plus, Value::Real(_)) => Ok(value),
+    (UnaryOperator::Minus, Value::Integer(v)) => match v.checked_neg() {
+      Some(v) => Ok(Value::Integer(v)),
+      None => Err(NollaDBError::General(format!("Integer overflow on '-{}'", v))),
+    },
+    (UnaryOperator::Minus, Value::Real(v)) => Ok(Value::Real(-v)),
+    _ => Err(NollaDBError::General(
+      format!(
+        "Can not apply '{}' on {} value '{}'",
+        op,
+        value.get_data_type(),
+        value
+      )
+    )),
+  }
+}
+
+// AND 和 OR 需要短路求值，并且遵循 SQL 的三值逻辑
+fn evaluate_logical_operation(
+  left: &Expr,
+  op: &BinaryOperator,
+  right: &Expr,
+  row: &RowValues,
+) -> Result<Value> {
+  let left_value = evaluate_expression(left, row)?;
+  match (op, &left_value) {
+    (BinaryOperator::And, Value::Bool(false)) => Ok(Value::Bool(false)),
+    (BinaryOperator::Or, Value::Bool(true)) => Ok(Value::Bool(true)),
+    (BinaryOperator::And, _) => and_values(&left_value, &evaluate_expression(right, row)?),
+    _ => or_values(&left_value, &evaluate_expression(right, row)?),
+  }
+}
+
+pub fn evaluate_binary_operation(
+  left: &Value,
+  op: &BinaryOperator,
+  right: &Value,
+) -> Result<Value> {
+  match op {
+    BinaryOperator::Eq |
+    BinaryOperator::NotEq |
+    BinaryOperator::Gt |
+    BinaryOperator::Lt |
+    BinaryOperator::GtEq |
+    BinaryOperator::LtEq => {
+      let ordering = match left.compare(right)? {
+        Some(ordering) => ordering,
+        None => return Ok(Value::Null),
+      };
+      Ok(Value::Bool(match op {
+        BinaryOperator::Eq => ordering == Ordering::Equal,
+        BinaryOperator::NotEq => ordering != Ordering::Equal,
+        BinaryOperator::Gt => ordering == Ordering::Greater,
+        BinaryOperator::Lt => ordering == Ordering::Less,
+        BinaryOperator::GtEq => ordering != Ordering::Less,
+        _ => ordering != Ordering::Greater,
+      }))
+    },
+    BinaryOperator::And => and_values(left, right),
+    BinaryOperator::Or => or_values(left, right),
+    BinaryOperator::Plus |
+    BinaryOperator::Minus |
+    BinaryOperator::Multiply |
+    BinaryOperator::Divide |
+    BinaryOperator::Modulo => evaluate_arithmetic_operation(left, op, right),
+    BinaryOperator::StringConcat => match (left, right) {
+      (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+      _ => Ok(Value::Text(format!("{}{}", left, right))),
+    },
+    _ => Err(NollaDBError::ToBeImplemented(
+      format!("Operator '{}' will be implemented soon", op)
+    )),
+  }
+}
+
+// Integer 和 Integer 运算结果还是 Integer
+// 只要有一边是 Real，结果就是 Real
+// 和 SQLite 一样，除以 0 的结果是 Null
+fn evaluate_arithmetic_operation(
+  left: &Value,
+  op: &BinaryOperator,
+  right: &Value,
+) -> Result<Value> {
+  match (left, right) {
+    (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+    (Value::Integer(a), Value::Integer(b)) => {
+      let result = match op {
+        BinaryOperator::Plus => a.checked_add(*b),
+        BinaryOperator::Minus => a.checked_sub(*b),
+        BinaryOperator::Multiply => a.checked_mul(*b),
+        BinaryOperator::Divide if *b == 0 => return Ok(Value::Null),
+        BinaryOperator::Divide => a.checked_div(*b),
+        BinaryOperator::Modulo if *b == 0 => return Ok(Value::Null),
+        _ => a.checked_rem(*b),
+      };
+      match result {
+        Some(result) => Ok(Value::Integer(result)),
+        None => Err(NollaDBError::General(
+          format!("Integer overflow on '{} {} {}'", a, op, b)
+        )),
+      }
+    },
+    (Value::Integer(_), Value::Real(_)) |
+    (Value::Real(_), Value::Integer(_)) |
+    (Value::Real(_), Value::Real(_)) => {
+      let a = get_real_value(left);
+      let b = get_real_value(right);
+      let result = match op {
+        BinaryOperator::Plus => a + b,
+        BinaryOperator::Minus => a - b,
+        BinaryOperator::Multiply => a * b,
+        BinaryOperator::Divide if b == 0.0 => return Ok(Value::Null),
+        BinaryOperator::Divide => a / b,
+        BinaryOperator::Modulo if b == 0.0 => return Ok(Value::Null),
+        _ => a % b,
+      };
+      Ok(Value::Real(result as f32))
+    },
+    _ => Err(NollaDBError::General(
+      format!(
+        "Can not apply '{}' on {} value '{}' and {} value '{}'",
+        op,
+        left.get_data_type(),
+        left,
+        right.get_data_type(),
+        right
+      )
+    )),
+  }
+}
+
+fn get_real_value(value: &Value) -> f64 {
+  match value {
+    Value::Integer(v) => f64::from(*v),
+    Value::Real(v) => f64::from(*v),
+    _ => 0.0,
+  }
+}
+
+fn get_bool_value(value: &Value) -> Result<Option<bool>> {
+  match value {
+    Value::Bool(b) => Ok(Some(*b)),
+    Value::Null => Ok(None),
+    _ => Err(NollaDBError::General(
+      format!(
+        "Expected a boolean value, but found {} value '{}'",
+        value.get_data_type(),
+        value
+      )
+    )),
+  }
+}
+
+fn not_value(value: &Value) -> Result<Value> {
+  match get_bool_value(value)? {
+    Some(b) => Ok(Value::Bool(!b)),
+    None => Ok(Value::Null),
+  }
+}
+
+fn and_values(left: &Value, right: &Value) -> Result<Value> {
+  match (get_bool_value(left)?, get_bool_value(right)?) {
+    (Some(false), _) | (_, Some(false)) => Ok(Value::Bool(false)),
+    (Some(true), Some(true)) => Ok(Value::Bool(true)),
+    _ => Ok(Value::Null),
+  }
+}
+
+fn or_values(left: &Value, right: &Value) -> Result<Value> {
+  match (get_bool_value(left)?, get_bool_value(right)?) {
+    (Some(true), _) | (_, Some(true)) => Ok(Value::Bool(true)),
+    (Some(false), Some(false)) => Ok(Value::Bool(false)),
+    _ => Ok(Value::Null),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+  use sqlparser::ast::{Statement, SetExpr};
+
+  #[rstest]
+  #[case("id = 1", Value::Bool(true))]
+  #[case("id <> 1", Value::Bool(false))]
+  #[case("id < 2 AND score >= 1.5", Value::Bool(true))]
+  #[case("id > 1 OR name = 'xxx'", Value::Bool(true))]
+  #[case("NOT active", Value::Bool(false))]
+  #[case("id + 1 = 2.0", Value::Bool(true))]
+  #[case("id * 3 - 1", Value::Integer(2))]
+  #[case("id / 0", Value::Null)]
+  #[case("score * 2", Value::Real(3.0))]
+  #[case("name > 'aaa'", Value::Bool(true))]
+  #[case("email IS NULL", Value::Bool(true))]
+  #[case("email IS NOT NULL", Value::Bool(false))]
+  #[case("email = 'xxx'", Value::Null)]
+  #[case("email = 'xxx' OR id = 1", Value::Bool(true))]
+  #[case("email = 'xxx' AND id = 1", Value::Null)]
+  #[case("email = 'xxx' AND id = 2", Value::Bool(false))]
+  #[case("id BETWEEN 0 AND 1", Value::Bool(true))]
+  #[case("id NOT IN (2, 3)", Value::Bool(true))]
+  #[case("(id = 1) = active", Value::Bool(true))]
+  fn test_evaluate_expression(
+    #[case] expression: &str,
+    #[case] expected: Value,
+  ) {
+    let expr = parse_expression(expression);
+    assert_eq!(evaluate_expression(&expr, &create_row()), Ok(expected));
+  }
+
+  #[rstest]
+  #[case("name = 1")]
+  #[case("id AND active")]
+  #[case("not_exist = 1")]
+  #[case("name + 1")]
+  fn test_evaluate_expression_error(
+    #[case] expression: &str,
+  ) {
+    let expr = parse_expression(expression);
+    assert!(evaluate_expression(&expr, &create_row()).is_err());
+  }
+
+  #[rstest]
+  #[case("id = 1", true)]
+  #[case("id = 2", false)]
+  #[case("email = 'xxx'", false)]
+  fn test_is_row_matched(
+    #[case] expression: &str,
+    #[case] expected: bool,
+  ) {
+    let expr = parse_expression(expression);
+    assert_eq!(is_row_matched(&expr, &create_row()), Ok(expected));
+  }
+
+  fn create_row() -> RowValues {
+    let mut row = RowValues::new();
+    row.insert("id".to_string(), Value::Integer(1));
+    row.insert("name".to_string(), Value::Text("xxx".to_string()));
+    row.insert("email".to_string(), Value::Null);
+    row.insert("active".to_string(), Value::Bool(true));
+    row.insert("score".to_string(), Value::Real(1.5));
+    row
+  }
+
+  fn parse_expression(expression: &str) -> Expr {
+    let dialect = SQLiteDialect {};
+    let query = format!("SELECT * FROM test WHERE {};", expression);
+    let mut ast = Parser::parse_sql(&dialect, &query).unwrap();
+    match ast.pop().unwrap() {
+      Statement::Query(query) => match query.body {
+        SetExpr::Select(select) => select.selection.unwrap(),
+        _ => panic!("Expected a SELECT statement"),
+      },
+      _ => panic!("Expected a SELECT statement"),
+    }
+  }
+}
@@ -0,0 +1,208 @@
+use std::cmp::Ordering;
+use std::mem::size_of;
+
+use sqlparser::ast::OrderByExpr;
+
+use crate::error::Result;
+use crate::table::row::value::Value;
+use crate::sql_query::order_by::{sort_rows, compare_rows};
+use crate::sql_query::spill_file::SpillFile;
+
+// 估算一行数据占用的内存大小，单位是字节
+pub fn get_row_size(row: &[Value]) -> usize {
+  size_of::<Vec<Value>>() + row
+    .iter()
+    .map(|value| size_of::<Value>() + match value {
+      Value::Text(text) => text.capacity(),
+      _ => 0,
+    })
+    .sum::<usize>()
+}
+
+// 外部归并排序
+// 1. 行先放在内存中，超过内存预算的话就排好序写到一个临时文件中，称为一个 run
+// 2. 所有的行都放进来之后，对每个 run 以及内存中剩下的行做多路归并
+// 先放进来的行所在的 run 在前面，归并时值相同的行取前面的 run，所以排序是稳定的
+pub struct ExternalSorter {
+  order_by: Vec<OrderByExpr>,
+  column_indexes: Vec<usize>,
+  memory_budget: usize,
+  rows: Vec<Vec<Value>>,
+  rows_size: usize,
+  runs: Vec<SpillFile>,
+}
+
+impl ExternalSorter {
+  pub fn new(order_by: &[OrderByExpr], column_indexes: &[usize], memory_budget: usize) -> Self {
+    ExternalSorter {
+      order_by: order_by.to_vec(),
+      column_indexes: column_indexes.to_vec(),
+      memory_budget,
+      rows: vec![],
+      rows_size: 0,
+      runs: vec![],
+    }
+  }
+
+  pub fn push(&mut self, row: Vec<Value>) -> Result<()> {
+    self.rows_size += get_row_size(&row);
+    self.rows.push(row);
+    if self.rows_size > self.memory_budget {
+      self.spill()?;
+    }
+    Ok(())
+  }
+
+  // 内存中剩下的行排好序之后作为最后一个 run，归并在读出的时候才做
+  pub fn finish(mut self) -> Result<SortedRows> {
+    sort_rows(&mut self.rows, &self.order_by, &self.column_indexes)?;
+    let mut in_memory_rows = std::mem::take(&mut self.rows).into_iter();
+    let mut heads: Vec<Option<Vec<Value>>> = vec![];
+    for run in self.runs.iter_mut() {
+      heads.push(run.read()?);
+    }
+    heads.push(in_memory_rows.next());
+
+    Ok(SortedRows {
+      order_by: self.order_by,
+      column_indexes: self.column_indexes,
+      runs: self.runs,
+      in_memory_rows,
+      heads,
+    })
+  }
+
+  fn spill(&mut self) -> Result<()> {
+    sort_rows(&mut self.rows, &self.order_by, &self.column_indexes)?;
+    let mut run = SpillFile::new("sort")?;
+    for row in self.rows.drain(..) {
+      run.write(&row)?;
+    }
+    self.runs.push(run);
+    self.rows_size = 0;
+    Ok(())
+  }
+}
+
+// 排好序的行，每次调用 next 从所有的 run 中归并出下一行
+// 内存中只有每个 run 当前最小的一行，以及最后一个 run 中没有写到文件中的行
+pub struct SortedRows {
+  order_by: Vec<OrderByExpr>,
+  column_indexes: Vec<usize>,
+  runs: Vec<SpillFile>,
+  in_memory_rows: std::vec::IntoIter<Vec<Value>>,
+  // heads[i] 是第 i 个 run 当前最小的一行，最后一个是内存中的 run
+  heads: Vec<Option<Vec<Value>>>,
+}
+
+impl SortedRows {
+  pub fn next(&mut self) -> Result<Option<Vec<Value>>> {
+    let mut min_index: Option<usize> = None;
+    for (i, head) in self.heads.iter().enumerate() {
+      let head = match head {
+        Some(head) => head,
+        None => continue,
+      };
+      let is_smaller = match min_index {
+        Some(min_index) => compare_rows(
+          head,
+          self.heads[min_index].as_ref().unwrap(),
+          &self.order_by,
+          &self.column_indexes,
+        )? == Ordering::Less,
+        None => true,
+      };
+      if is_smaller {
+        min_index = Some(i);
+      }
+    }
+
+    let min_index = match min_index {
+      Some(min_index) => min_index,
+      None => return Ok(None),
+    };
+    let next_row = match self.runs.get_mut(min_index) {
+      Some(run) => run.read()?,
+      None => self.in_memory_rows.next(),
+    };
+    Ok(std::mem::replace(&mut self.heads[min_index], next_row))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+  use sqlparser::ast::Statement;
+  use crate::sql_query::order_by::resolve_order_by;
+
+  #[rstest]
+  #[case("ORDER BY 1", usize::MAX, false)]
+  #[case("ORDER BY 1", 0, true)]
+  #[case("ORDER BY 1 DESC, 2", 500, true)]
+  #[case("ORDER BY 2 NULLS LAST, 1", 1000, true)]
+  fn test_external_sorter(
+    #[case] order_by: &str,
+    #[case] memory_budget: usize,
+    #[case] expected_spilled: bool,
+  ) {
+    // 第一列有重复的值，第二列有 NULL，用来检查排序是稳定的
+    let rows = (0..100)
+      .map(|i| vec![
+        Value::Integer((i * 37) % 11),
+        match i % 7 {
+          0 => Value::Null,
+          _ => Value::Text(format!("{:03}", (i * 13) % 100)),
+        },
+        Value::Integer(i),
+      ])
+      .collect::<Vec<Vec<Value>>>();
+    let order_by = parse_order_by(order_by);
+    let column_names = vec!["a".to_string(), "b".to_string(), "i".to_string()];
+    let column_indexes = resolve_order_by(&order_by, &column_names, &mut vec![]).unwrap();
+
+    let mut expected = rows.clone();
+    sort_rows(&mut expected, &order_by, &column_indexes).unwrap();
+
+    let mut sorter = ExternalSorter::new(&order_by, &column_indexes, memory_budget);
+    for row in rows {
+      sorter.push(row).unwrap();
+    }
+    assert_eq!(!sorter.runs.is_empty(), expected_spilled);
+    let number_of_runs = sorter.runs.len();
+    let mut sorted_rows = sorter.finish().unwrap();
+    // 归并之前内存中只有每个 run 的第一行
+    assert_eq!(sorted_rows.heads.len(), number_of_runs + 1);
+    assert_eq!(get_sorted_rows(&mut sorted_rows), Ok(expected));
+  }
+
+  #[rstest]
+  fn test_external_sorter_error() {
+    let order_by = parse_order_by("ORDER BY 1");
+    let mut sorter = ExternalSorter::new(&order_by, &[0], 0);
+    sorter.push(vec![Value::Integer(1)]).unwrap();
+
+    assert!(sorter.push(vec![Value::Text("a".to_string())]).is_ok());
+    assert!(sorter.finish().and_then(|mut sorted_rows| get_sorted_rows(&mut sorted_rows)).is_err());
+  }
+
+  fn get_sorted_rows(sorted_rows: &mut SortedRows) -> Result<Vec<Vec<Value>>> {
+    let mut rows = vec![];
+    while let Some(row) = sorted_rows.next()? {
+      rows.push(row);
+    }
+    Ok(rows)
+  }
+
+  fn parse_order_by(order_by: &str) -> Vec<OrderByExpr> {
+    let dialect = SQLiteDialect {};
+    let query = format!("SELECT * FROM test {};", order_by);
+    match Parser::parse_sql(&dialect, &query).unwrap().pop().unwrap() {
+      Statement::Query(query) => query.order_by,
+      _ => panic!("Expected a SELECT statement"),
+    }
+  }
+}
@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+
+use sqlparser::ast::{
+  Expr,
+  Ident,
+  BinaryOperator,
+};
+
+use crate::error::{Result, NollaDBError};
+use crate::database::Database;
+use crate::database::session::Snapshot;
+use crate::table::{Table, InvisibleRows, ScannedRow};
+use crate::table::column::Column;
+use crate::table::row::value::Value;
+use crate::sql_query::expression::{
+  RowValues,
+  visit_expression,
+  is_row_matched,
+};
+use crate::sql_query::query::select::{
+  Projection,
+  JoinQuery,
+  JoinType,
+  JoinCondition,
+};
+
+// 参与 JOIN 的一张表，alias 没有设置的话就是表名
+// 表中的数据都要通过这里读，快照中看不到最新版本的行用 invisible_rows 中的值
+pub struct JoinTable<'a> {
+  pub alias: String,
+  pub table: &'a Table,
+  pub invisible_rows: InvisibleRows,
+}
+
+impl<'a> JoinTable<'a> {
+  // 快照中所有的 row id
+  pub fn get_row_ids(&self) -> Result<Vec<i64>> {
+    Ok(self.merge_row_ids(self.table.get_row_ids()?))
+  }
+
+  // 按照 row id 从小到大的顺序读出快照中 row id 大于 after 的一批行
+  // 返回这批行以及下一批从哪里开始，表中已经没有更多的行时是 None
+  // 快照中看不到最新版本的行在这一批的范围中的话，换成快照中的值
+  pub fn scan_rows(&self, after: Option<i64>, limit: usize) -> Result<(Vec<ScannedRow>, Option<i64>)> {
+    let rows = self.table.scan_rows(after, limit)?;
+    let next = match rows.len() < limit {
+      true => None,
+      false => rows.last().map(|(row_id, _)| *row_id),
+    };
+    if self.invisible_rows.is_empty() { return Ok((rows, next)); }
+
+    let is_in_batch = |row_id: &i64| {
+      after.is_none_or(|after| *row_id > after) && next.is_none_or(|next| *row_id <= next)
+    };
+    let mut rows = rows
+      .into_iter()
+      .filter(|(row_id, _)| !self.invisible_rows.contains_key(row_id))
+      .chain(
+        self.invisible_rows
+          .iter()
+          .filter(|(row_id, _)| is_in_batch(row_id))
+          .filter_map(|(row_id, row_values)| row_values.clone().map(|row_values| (*row_id, row_values)))
+      )
+      .collect::<Vec<ScannedRow>>();
+    rows.sort_unstable_by_key(|(row_id, _)| *row_id);
+    Ok((rows, next))
+  }
+
+  // 把从表或者索引中找到的 row id 换成快照中的 row id
+  // 去掉快照中看不到最新版本的行，再加上这些行中在快照中存在的行，按照 row id 从小到大排列
+  // 加上的行不一定满足查找的条件，之后还要用快照中的值再检查一遍
+  pub fn merge_row_ids(&self, row_ids: Vec<i64>) -> Vec<i64> {
+    if self.invisible_rows.is_empty() { return row_ids; }
+    let mut row_ids = row_ids
+      .into_iter()
+      .filter(|row_id| !self.invisible_rows.contains_key(row_id))
+      .chain(
+        self.invisible_rows
+          .iter()
+          .filter(|(_, row_values)| row_values.is_some())
+          .map(|(row_id, _)| *row_id)
+      )
+      .collect::<Vec<i64>>();
+    row_ids.sort_unstable();
+    row_ids
+  }
+
+  // 拿到 row id 对应的一整行数据，用于表达式求值
+  pub fn get_row(&self, row_id: &i64) -> Result<RowValues> {
+    match self.invisible_rows.get(row_id) {
+      Some(Some(row_values)) => Ok(
+        self.table.table_columns
+          .iter()
+          .map(|table_column| table_column.column_name.to_string())
+          .zip(row_values.iter().cloned())
+          .collect()
+      ),
+      _ => self.table.get_row(row_id),
+    }
+  }
+
+  // 找到满足 WHERE 条件的所有 row id
+  pub fn select_row_ids(&self, selection: &Option<Expr>) -> Result<Vec<i64>> {
+    if self.invisible_rows.is_empty() {
+      return self.table.select_row_ids(selection);
+    }
+    self.filter_row_ids(self.table.get_row_ids()?, selection)
+  }
+
+  // 从表或者索引中找到的 row_ids 中找到满足 WHERE 条件的 row id
+  pub fn filter_row_ids(&self, row_ids: Vec<i64>, selection: &Option<Expr>) -> Result<Vec<i64>> {
+    if self.invisible_rows.is_empty() {
+      return self.table.filter_row_ids(row_ids, selection);
+    }
+    let row_ids = self.merge_row_ids(row_ids);
+    let expr = match selection {
+      Some(expr) => expr,
+      None => return Ok(row_ids),
+    };
+
+    let mut matched_row_ids: Vec<i64> = vec![];
+    for row_id in row_ids {
+      if is_row_matched(expr, &self.get_row(&row_id)?)? {
+        matched_row_ids.push(row_id);
+      }
+    }
+    Ok(matched_row_ids)
+  }
+
+  // 按照 column_names 的顺序，把 row_ids 中每一行对应的值取出来
+  pub fn select_rows(&self, row_ids: &[i64], column_names: &[String]) -> Result<Vec<Vec<Value>>> {
+    let mut rows = self.table.select_rows(row_ids, column_names)?;
+    if self.invisible_rows.is_empty() {
+      return Ok(rows);
+    }
+
+    let column_indexes = column_names
+      .iter()
+      .map(|column_name| {
+        self.table.table_columns
+          .iter()
+          .position(|table_column| &table_column.column_name == column_name)
+      })
+      .collect::<Vec<Option<usize>>>();
+    for (row, row_id) in rows.iter_mut().zip(row_ids) {
+      if let Some(Some(row_values)) = self.invisible_rows.get(row_id) {
+        for (value, column_index) in row.iter_mut().zip(&column_indexes) {
+          if let Some(column_index) = column_index {
+            *value = row_values[*column_index].clone();
+          }
+        }
+      }
+    }
+    Ok(rows)
+  }
+}
+
+// 每个 JOIN 的连接方式
+pub enum JoinStrategy<'a> {
+  // 用左边的表达式的值在右边表的 column 的索引中查找
+  IndexLookup(Expr, &'a Column),
+  // 每一项是左边用来查找的表达式以及右边用来建立 hash 表的表达式
+  HashJoin(Vec<(Expr, Expr)>),
+  NestedLoop,
+}
+
+// FROM 以及 JOIN 后面所有的表
+// JOIN 之后的每一行 RowValues 中
+// 1. 每一个 column 都有一个 "alias.column" 的 key
+// 2. 只在一张表中出现的 column 还有一个不带表名的 key
+// 3. USING 中的 column 不带表名的 key 是两边合并之后的值
+// 第 i 个 JOIN 的右边是 tables[i + 1]
+pub struct JoinScope<'a> {
+  pub tables: Vec<JoinTable<'a>>,
+  // 每个 JOIN 对应的条件，USING 会被转换成 ON
+  pub conditions: Vec<Option<Expr>>,
+  pub join_types: Vec<JoinType>,
+  pub using_column_names: Vec<Vec<String>>,
+}
+
+impl<'a> JoinScope<'a> {
+  pub fn new(
+    database: &'a Database,
+    snapshot: Snapshot,
+    table_name: &str,
+    table_alias: &Option<String>,
+    joins: &[JoinQuery],
+  ) -> Result<Self> {
+    JoinScope::from_tables(&database.get_tables(), snapshot, table_name, table_alias, joins)
+  }
+
+  // 只在 tables 中找 FROM 以及 JOIN 后面的表
+  pub fn from_tables(
+    tables: &HashMap<&str, &'a Table>,
+    snapshot: Snapshot,
+    table_name: &str,
+    table_alias: &Option<String>,
+    joins: &[JoinQuery],
+  ) -> Result<Self> {
+    let mut scope = JoinScope {
+      tables: vec![],
+      conditions: vec![],
+      join_types: vec![],
+      using_column_names: vec![],
+    };
+
+    let table_references = std::iter::once((table_name.to_string(), table_alias.clone()))
+      .chain(joins.iter().map(|join| (join.table.table_name.to_string(), join.table.alias.clone())));
+    for (table_name, alias) in table_references {
+      let table = *tables.get(table_name.as_str()).ok_or_else(|| {
+        NollaDBError::Internal(format!("Table '{}' does not exist", table_name))
+      })?;
+      let alias = alias.unwrap_or(table_name);
+      if scope.get_table_index(&alias).is_some() {
+        return Err(NollaDBError::General(
+          format!("Not unique table or alias: '{}'", alias)
+        ));
+      }
+      let invisible_rows = table.get_invisible_rows(snapshot.session_id, snapshot.snapshot_ts)?;
+      scope.tables.push(JoinTable { alias, table, invisible_rows });
+    }
+
+    for (i, join) in joins.iter().enumerate() {
+      let right_index = i + 1;
+      let (condition, using_column_names) = match &join.condition {
+        JoinCondition::On(expr) => (Some(expr.clone()), vec![]),
+        JoinCondition::Using(column_names) => (
+          Some(scope.get_using_condition(right_index, column_names)?),
+          column_names.clone(),
+        ),
+        JoinCondition::None => (None, vec![]),
+      };
+      if join.join_type == JoinType::Cross && condition.is_some() {
+        return Err(NollaDBError::General(
+          "CROSS JOIN can not have an ON or USING condition".to_string()
+        ));
+      }
+
+      scope.conditions.push(condition);
+      scope.join_types.push(join.join_type.clone());
+      scope.using_column_names.push(using_column_names);
+    }
+
+    // ON 中的 column 也要检查
+    for condition in scope.conditions.iter().flatten() {
+      scope.check_column_names(condition)?;
+    }
+
+    Ok(scope)
+  }
+
+  // 把 * 以及 test.* 展开成对应表中所有的 column
+  pub fn expand_projection(&self, projection: Vec<Projection>) -> Result<(Vec<String>, Vec<Expr>)> {
+    let mut column_names: Vec<String> = vec![];
+    let mut column_exprs: Vec<Expr> = vec![];
+    for projection_item in projection {
+      let table_indexes = match &projection_item {
+        Projection::Wildcard => (0..self.tables.len()).collect(),
+        Projection::QualifiedWildcard(alias) => match self.get_table_index(alias) {
+          Some(i) => vec![i],
+          None => return Err(NollaDBError::General(format!("No such table: '{}'", alias))),
+        },
+        Projection::Expr(expr, column_name) => {
+          column_names.push(column_name.to_string());
+          column_exprs.push(expr.clone());
+          continue;
+        },
+      };
+
+      for i in table_indexes {
+        for table_column in &self.tables[i].table.table_columns {
+          column_names.push(table_column.column_name.to_string());
+          column_exprs.push(Expr::CompoundIdentifier(vec![
+            Ident::new(&self.tables[i].alias),
+            Ident::new(&table_column.column_name),
+          ]));
+        }
+      }
+    }
+
+    Ok((column_names, column_exprs))
+  }
+
+  // 检查表达式中的 column 是否存在
+  // 不带表名的 column 如果在多张表中都存在，并且不是 USING 中的 column 就是有歧义的
+  pub fn check_column_names(&self, expr: &Expr) -> Result<()> {
+    visit_expression(expr, &mut |expr| {
+      match expr {
+        Expr::Identifier(ident) => {
+          let table_indexes = self.get_table_indexes_of_column(&ident.value);
+          if table_indexes.is_empty() {
+            return Err(NollaDBError::Internal(
+              format!("Can not select, because column '{}' does not exist", ident.value)
+            ));
+          }
+          if table_indexes.len() > 1 && !self.is_using_column(&ident.value) {
+            return Err(NollaDBError::General(
+              format!("Ambiguous column name: '{}'", ident.value)
+            ));
+          }
+        },
+        Expr::CompoundIdentifier(idents) => {
+          let (alias, column_name) = match idents.as_slice() {
+            [alias, column_name] => (&alias.value, &column_name.value),
+            _ => return Err(NollaDBError::General(format!("Invalid column name '{}'", expr))),
+          };
+          match self.get_table_index(alias) {
+            Some(i) if self.tables[i].table.has_column(column_name.to_string()) => (),
+            Some(_) => return Err(NollaDBError::Internal(
+              format!("Can not select, because column '{}' does not exist", expr)
+            )),
+            None => return Err(NollaDBError::General(format!("No such table: '{}'", alias))),
+          }
+        },
+        _ => (),
+      }
+      Ok(true)
+    })
+  }
+
+  pub fn get_table_index(&self, alias: &str) -> Option<usize> {
+    self.tables.iter().position(|join_table| join_table.alias == alias)
+  }
+
+  fn get_table_indexes_of_column(&self, column_name: &str) -> Vec<usize> {
+    (0..self.tables.len())
+      .filter(|i| self.tables[*i].table.has_column(column_name.to_string()))
+      .collect()
+  }
+
+  fn is_using_column(&self, column_name: &str) -> bool {
+    self.using_column_names
+      .iter()
+      .any(|column_names| column_names.iter().any(|name| name == column_name))
+  }
+
+  // a JOIN b USING (x, y) 相当于 a JOIN b ON a.x = b.x AND a.y = b.y
+  // 左边的 column 取左边第一张有这个 column 的表
+  fn get_using_condition(&self, right_index: usize, column_names: &[String]) -> Result<Expr> {
+    let mut condition: Option<Expr> = None;
+    for column_name in column_names {
+      let left_index = (0..right_index)
+        .find(|i| self.tables[*i].table.has_column(column_name.to_string()));
+      let left_index = match left_index {
+        Some(i) if self.tables[right_index].table.has_column(column_name.to_string()) => i,
+        _ => return Err(NollaDBError::General(
+          format!(
+            "Can not join using column '{}', because it is not present in both tables",
+            column_name
+          )
+        )),
+      };
+
+      let equality = Expr::BinaryOp {
+        left: Box::new(self.get_column_expr(left_index, column_name)),
+        op: BinaryOperator::Eq,
+        right: Box::new(self.get_column_expr(right_index, column_name)),
+      };
+      condition = Some(match condition {
+        Some(left) => Expr::BinaryOp {
+          left: Box::new(left),
+          op: BinaryOperator::And,
+          right: Box::new(equality),
+        },
+        None => equality,
+      });
+    }
+
+    condition.ok_or_else(|| NollaDBError::General("USING expects at least one column".to_string()))
+  }
+
+  fn get_column_expr(&self, table_index: usize, column_name: &str) -> Expr {
+    Expr::CompoundIdentifier(vec![
+      Ident::new(&self.tables[table_index].alias),
+      Ident::new(column_name),
+    ])
+  }
+
+  // 选择右边的表的连接方式
+  // 1. ON 中有 "左边的表达式 = 右边表的 column"，并且这个 column 有完整的索引，就在索引中查找
+  // 2. ON 中有 "左边的表达式 = 右边表的表达式" 这种等值条件，就用 hash join
+  // 3. 否则就是 nested loop join，对左边的每一行遍历右边整张表
+  // 不管用哪种方式，找到的行最后都还要再检查一遍完整的 ON 条件
+  pub fn get_join_strategy(&self, right_index: usize) -> JoinStrategy<'a> {
+    let condition = match &self.conditions[right_index - 1] {
+      Some(condition) => condition,
+      None => return JoinStrategy::NestedLoop,
+    };
+    if let Some((probe_expr, column)) = self.get_index_lookup(right_index, condition) {
+      return JoinStrategy::IndexLookup(probe_expr, column);
+    }
+
+    let mut keys: Vec<(Expr, Expr)> = vec![];
+    for conjunct in get_conjuncts(condition) {
+      if let Expr::BinaryOp { left, op: BinaryOperator::Eq, right } = conjunct {
+        for (probe_expr, build_expr) in [(left, right), (right, left)] {
+          if self.references_only_table(right_index, build_expr) &&
+             !self.references_table(right_index, probe_expr) {
+            keys.push(((**probe_expr).clone(), (**build_expr).clone()));
+            break;
+          }
+        }
+      }
+    }
+    match keys.is_empty() {
+      true => JoinStrategy::NestedLoop,
+      false => JoinStrategy::HashJoin(keys),
+    }
+  }
+
+  // 找到 ON 中 AND 连接的 "probe = 右边表的 column" 这种条件
+  // 要求 probe 中没有用到右边表的 column，并且右边表的 column 有完整的索引
+  fn get_index_lookup(&self, right_index: usize, condition: &Expr) -> Option<(Expr, &'a Column)> {
+    get_conjuncts(condition)
+      .into_iter()
+      .find_map(|conjunct| match conjunct {
+        Expr::BinaryOp { left, op: BinaryOperator::Eq, right } => {
+          [(left, right), (right, left)]
+            .into_iter()
+            .find_map(|(probe_expr, key_expr)| {
+              let column = self.get_indexed_column(right_index, key_expr)?;
+              match self.references_table(right_index, probe_expr) {
+                true => None,
+                false => Some(((**probe_expr).clone(), column)),
+              }
+            })
+        },
+        _ => None,
+      })
+  }
+
+  // 表达式是某张表的一个 column，并且这个 column 的索引可以用
+  pub fn get_indexed_column(&self, table_index: usize, expr: &Expr) -> Option<&'a Column> {
+    let column = self.get_table_column(table_index, expr)?;
+    match self.tables[table_index].table.has_usable_index(column) {
+      true => Some(column),
+      false => None,
+    }
+  }
+
+  // 表达式是某张表的一个 column
+  pub fn get_table_column(&self, table_index: usize, expr: &Expr) -> Option<&'a Column> {
+    let column_name = match expr {
+      Expr::Identifier(ident) => match self.get_table_indexes_of_column(&ident.value).as_slice() {
+        [i] if *i == table_index => &ident.value,
+        _ => return None,
+      },
+      Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+        [alias, column_name] if self.get_table_index(&alias.value) == Some(table_index) => &column_name.value,
+        _ => return None,
+      },
+      _ => return None,
+    };
+
+    self.tables[table_index].table.get_column(column_name.to_string()).ok()
+  }
+
+  // 表达式中是否用到了某张表的 column
+  fn references_table(&self, table_index: usize, expr: &Expr) -> bool {
+    let mut result = false;
+    let _ = visit_expression(expr, &mut |expr| {
+      match expr {
+        Expr::Identifier(ident) => {
+          result = result || self.get_table_indexes_of_column(&ident.value).contains(&table_index);
+        },
+        Expr::CompoundIdentifier(idents) => {
+          result = result || idents
+            .first()
+            .is_some_and(|alias| self.get_table_index(&alias.value) == Some(table_index));
+        },
+        _ => (),
+      }
+      Ok(!result)
+    });
+    result
+  }
+
+  // 表达式中用到了 column，并且所有的 column 都是这张表的
+  fn references_only_table(&self, table_index: usize, expr: &Expr) -> bool {
+    let mut has_column = false;
+    let mut result = true;
+    let _ = visit_expression(expr, &mut |expr| {
+      match expr {
+        Expr::Identifier(ident) => {
+          has_column = true;
+          result = result && self.get_table_indexes_of_column(&ident.value) == vec![table_index];
+        },
+        Expr::CompoundIdentifier(idents) => {
+          has_column = true;
+          result = result && idents
+            .first()
+            .is_some_and(|alias| self.get_table_index(&alias.value) == Some(table_index));
+        },
+        _ => (),
+      }
+      Ok(result)
+    });
+    has_column && result
+  }
+
+  // 拿到一张表中 row id 对应的一行数据
+  pub fn get_row(&self, table_index: usize, row_id: &i64) -> Result<RowValues> {
+    Ok(self.to_join_row(table_index, self.tables[table_index].get_row(row_id)?))
+  }
+
+  // LEFT JOIN 和 RIGHT JOIN 中没有匹配的一边都是 NULL
+  pub fn get_null_row(&self, table_index: usize) -> RowValues {
+    let row = self.tables[table_index].table.table_columns
+      .iter()
+      .map(|table_column| (table_column.column_name.to_string(), Value::Null))
+      .collect();
+    self.to_join_row(table_index, row)
+  }
+
+  // 把一张表中的一行数据转换成 JOIN 中用到的 key
+  pub fn to_join_row(&self, table_index: usize, row: RowValues) -> RowValues {
+    let alias = &self.tables[table_index].alias;
+    let mut join_row = RowValues::new();
+    for (column_name, value) in row {
+      // 只有一张表的话不需要带表名的 key，test.id 找不到时会按照 id 去找
+      if self.tables.len() > 1 {
+        join_row.insert(format!("{}.{}", alias, column_name), value.clone());
+      }
+      if self.get_table_indexes_of_column(&column_name).len() == 1 {
+        join_row.insert(column_name, value);
+      }
+    }
+    join_row
+  }
+
+  // USING 中的 column 不带表名的时候取两边不是 NULL 的那个值
+  pub fn merge_rows(&self, right_index: usize, left_row: &RowValues, right_row: &RowValues) -> RowValues {
+    let mut row = left_row.clone();
+    row.extend(right_row.iter().map(|(key, value)| (key.to_string(), value.clone())));
+    for column_name in &self.using_column_names[right_index - 1] {
+      let right_value = right_row
+        .get(&format!("{}.{}", self.tables[right_index].alias, column_name))
+        .cloned()
+        .unwrap_or(Value::Null);
+      // 前面的 USING 已经合并过的话直接用不带表名的值，否则取左边第一张有这个 column 的表
+      let left_value = left_row.get(column_name).or_else(|| {
+        (0..right_index)
+          .find(|i| self.tables[*i].table.has_column(column_name.to_string()))
+          .and_then(|i| left_row.get(&format!("{}.{}", self.tables[i].alias, column_name)))
+      });
+      let value = match left_value {
+        Some(left_value) if !left_value.is_null() => left_value.clone(),
+        _ => right_value,
+      };
+      row.insert(column_name.to_string(), value);
+    }
+    row
+  }
+}
+
+// 把 AND 连接的条件拆开
+pub fn get_conjuncts(expr: &Expr) -> Vec<&Expr> {
+  match expr {
+    Expr::Nested(expr) => get_conjuncts(expr),
+    Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+      let mut conjuncts = get_conjuncts(left);
+      conjuncts.extend(get_conjuncts(right));
+      conjuncts
+    },
+    _ => vec![expr],
+  }
+}
+
+// hash join 中 hash 表的 key
+// Integer 和 Real 都转换成 f64，这样 1 和 1.0 的 key 是一样的
+// 有 NULL 的话和任何值都不相等，返回 None
+pub fn get_hash_key(values: &[Value]) -> Option<String> {
+  let mut hash_key = String::new();
+  for value in values {
+    let key = match value {
+      Value::Null => return None,
+      Value::Integer(v) => format!("{:?}", *v as f64),
+      Value::Real(v) => format!("{:?}", *v as f64),
+      value => format!("{:?}", value),
+    };
+    hash_key.push_str(&key);
+    hash_key.push('\u{0}');
+  }
+  Some(hash_key)
+}
+
+// 在索引中查找 value 对应的所有 row id
+// NULL 和任何值都不相等，所以没有匹配的行
+// value 的类型和 column 的类型不一样的话不能用索引，返回 None 表示需要遍历整张表
+pub fn lookup_index(table: &Table, column: &Column, value: &Value) -> Result<Option<Vec<i64>>> {
+  if value.is_null() { return Ok(Some(vec![])); }
+  if value.get_data_type() != column.column_datatype { return Ok(None); }
+  Ok(Some(table.get_row_ids_of_value(column, value)?))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use crate::sql_query::{get_sql_ast, handle_sql_query};
+  use crate::sql_query::query::select::SelectQuery;
+
+  #[rstest]
+  #[case("SELECT * FROM orders o JOIN users u ON o.user_id = u.id;", Some("id"))]
+  #[case("SELECT * FROM orders o LEFT JOIN users u ON u.name = 'a' AND (u.email = o.email);", Some("email"))]
+  #[case("SELECT * FROM users u JOIN orders o ON u.id = o.user_id;", None)]
+  #[case("SELECT * FROM orders o JOIN users u ON u.id = u.id + o.user_id;", None)]
+  #[case("SELECT * FROM orders o JOIN users u USING (id);", Some("id"))]
+  fn test_get_index_lookup(
+    #[case] query: &str,
+    #[case] expected: Option<&str>,
+  ) {
+    let mut database = Database::new("testdb".to_string());
+    for query in [
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT UNIQUE);",
+      "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, email TEXT);",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+
+    let select_query = SelectQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+    let scope = JoinScope::new(
+      &database,
+      database.get_snapshot(),
+      &select_query.table_name,
+      &select_query.table_alias,
+      &select_query.joins,
+    ).unwrap();
+    let condition = scope.conditions[0].as_ref().unwrap();
+
+    assert_eq!(
+      scope
+        .get_index_lookup(1, condition)
+        .map(|(_, column)| column.column_name.as_str()),
+      expected
+    );
+  }
+
+  #[rstest]
+  #[case("SELECT * FROM orders o JOIN users u ON o.user_id = u.id;", "IndexLookup")]
+  #[case("SELECT * FROM users u JOIN orders o ON u.id = o.user_id AND o.email = u.email;", "HashJoin(2)")]
+  #[case("SELECT * FROM users u JOIN orders o ON u.id + 1 = o.user_id - 1;", "HashJoin(1)")]
+  #[case("SELECT * FROM users u JOIN orders o ON u.id < o.user_id;", "NestedLoop")]
+  #[case("SELECT * FROM users u CROSS JOIN orders o;", "NestedLoop")]
+  fn test_get_join_strategy(
+    #[case] query: &str,
+    #[case] expected: &str,
+  ) {
+    let mut database = Database::new("testdb".to_string());
+    for query in [
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT UNIQUE);",
+      "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, email TEXT);",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+
+    let select_query = SelectQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+    let scope = JoinScope::new(
+      &database,
+      database.get_snapshot(),
+      &select_query.table_name,
+      &select_query.table_alias,
+      &select_query.joins,
+    ).unwrap();
+
+    assert_eq!(
+      match scope.get_join_strategy(1) {
+        JoinStrategy::IndexLookup(..) => "IndexLookup".to_string(),
+        JoinStrategy::HashJoin(keys) => format!("HashJoin({})", keys.len()),
+        JoinStrategy::NestedLoop => "NestedLoop".to_string(),
+      },
+      expected
+    );
+  }
+}
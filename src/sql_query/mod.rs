@@ -1,15 +1,34 @@
 pub mod query;
+pub mod result_set;
+pub mod expression;
+pub mod aggregate;
+pub mod order_by;
+pub mod join;
+pub mod external_sort;
+pub mod spill_file;
+pub mod planner;
 
 use sqlparser::parser::{Parser, ParserError};
 use sqlparser::dialect::SQLiteDialect;
-use sqlparser::ast::Statement;
+use sqlparser::ast::{Statement, ObjectType, TransactionMode, TransactionIsolationLevel};
 
 use crate::error::{Result, NollaDBError};
 use crate::database::Database;
+use crate::database::session::IsolationLevel;
 use crate::table::{Table};
 
 use query::create::{CreateQuery};
+use query::create_index::{CreateIndexQuery};
 use query::insert::{InsertQuery};
+use query::select::SelectQuery;
+use query::update::{UpdateQuery};
+use query::delete::{DeleteQuery};
+use query::drop::{DropQuery};
+use query::alter::{AlterQuery, AlterOperation};
+use query::pragma::{PragmaQuery, is_pragma_query, parse_memory_budget};
+use query::savepoint::{SavepointQuery, is_savepoint_query};
+use query::explain::ExplainQuery;
+use planner::{execute_select_query, select_row_ids, explain_statement};
 
 #[derive(Debug, PartialEq)]
 pub enum SQLQuery {
@@ -18,22 +37,33 @@ pub enum SQLQuery {
   Insert(String),
   Update(String),
   Delete(String),
+  Drop(String),
+  Alter(String),
+  Pragma(String),
+  Explain(String),
+  Transaction(String),
   Unknown(String),
 }
 
 impl SQLQuery {
   pub fn new(command: String) -> SQLQuery {
     let args: Vec<&str> = command.split_whitespace().collect();
-    if args.len() == 0 {
+    if args.is_empty() {
       return SQLQuery::Unknown(command);
     }
-    let first_cmd = args[0].to_owned();
+    // COMMIT; 这种只有一个单词的语句，分号会和单词连在一起
+    let first_cmd = args[0].trim_end_matches(';').to_owned();
     match first_cmd.to_lowercase().as_ref() {
       "create" => SQLQuery::CreateTable(command),
       "select" => SQLQuery::Select(command),
       "insert" => SQLQuery::Insert(command),
       "update" => SQLQuery::Update(command),
       "delete" => SQLQuery::Delete(command),
+      "drop" => SQLQuery::Drop(command),
+      "alter" => SQLQuery::Alter(command),
+      "pragma" => SQLQuery::Pragma(command),
+      "explain" => SQLQuery::Explain(command),
+      "begin" | "start" | "commit" | "rollback" | "savepoint" | "release" => SQLQuery::Transaction(command),
       _ => SQLQuery::Unknown(command),
     }
   }
@@ -42,14 +72,14 @@ impl SQLQuery {
 pub fn get_sql_ast(sql_query: &str) -> Result<Statement> {
   let dialect = SQLiteDialect {};
   let mut ast =
-    Parser::parse_sql(&dialect, &sql_query)
+    Parser::parse_sql(&dialect, sql_query)
       .map_err(NollaDBError::from)?;
 
-  if ast.len() == 0 {
+  if ast.is_empty() {
     return Err(
       NollaDBError::SQLParseError(
         ParserError::ParserError(
-          format!("Expected a correct SQL query statement")
+          "Expected a correct SQL query statement".to_string()
         )
       )
     );
@@ -73,6 +103,58 @@ pub fn get_sql_ast(sql_query: &str) -> Result<Statement> {
 }
 
 pub fn handle_sql_query(sql_query: &str, database: &mut Database) -> Result<String> {
+  // sqlparser 不能解析 PRAGMA，需要单独处理
+  if is_pragma_query(sql_query) {
+    return handle_pragma_query(&PragmaQuery::new(sql_query)?, database);
+  }
+  // SAVEPOINT 也一样
+  if is_savepoint_query(sql_query) {
+    return handle_savepoint_query(&SavepointQuery::new(sql_query)?, database);
+  }
+
+  // 每条语句都是原子的，执行失败的话撤销这条语句已经做的修改
+  // 在事务中的话之前的语句不受影响
+  let number_of_undo_records = database.begin_statement()?;
+  let result = execute_sql_query(sql_query, database)
+    .and_then(|message| database.end_statement(sql_query, number_of_undo_records).map(|_| message));
+  if result.is_err() {
+    database.rollback_statement(number_of_undo_records);
+  }
+
+  result
+}
+
+// 一条语句会读或者写的表，SharedDatabase 执行的时候只需要锁住这些表
+// 会修改表结构或者设置的语句，以及 SAVEPOINT 返回 None，需要锁住整个数据库
+// 解析失败的话也返回 None，执行的时候会返回解析的错误
+pub fn get_table_names_of_statement(sql_query: &str) -> Option<Vec<String>> {
+  if is_pragma_query(sql_query) || is_savepoint_query(sql_query) {
+    return None;
+  }
+  get_table_names(&get_sql_ast(sql_query).ok()?)
+}
+
+fn get_table_names(statement: &Statement) -> Option<Vec<String>> {
+  match statement {
+    Statement::Query(_) => Some(SelectQuery::new(statement).ok()?.get_table_names()),
+    Statement::Insert { .. } => {
+      let insert_query = InsertQuery::new(statement).ok()?;
+      let mut table_names = vec![insert_query.table_name];
+      if let Some(select_query) = insert_query.select_query {
+        table_names.extend(select_query.get_table_names());
+      }
+      Some(table_names)
+    },
+    Statement::Update { .. } => Some(vec![UpdateQuery::new(statement).ok()?.table_name]),
+    Statement::Delete { .. } => Some(vec![DeleteQuery::new(statement).ok()?.table_name]),
+    Statement::Explain { .. } => get_table_names(&ExplainQuery::new(statement).ok()?.statement),
+    // 事务中修改过以及读过的表由 SharedDatabase 从会话中拿到
+    Statement::StartTransaction { .. } | Statement::Commit { .. } | Statement::Rollback { .. } => Some(vec![]),
+    _ => None,
+  }
+}
+
+fn execute_sql_query(sql_query: &str, database: &mut Database) -> Result<String> {
   let message: String;
   match get_sql_ast(sql_query) {
     Ok(statement) => {
@@ -85,7 +167,14 @@ pub fn handle_sql_query(sql_query: &str, database: &mut Database) -> Result<Stri
               let table_name = create_query.table_name.clone();
 
               // 检查表是否已经被创建
+              // 如果是 CREATE TABLE IF NOT EXISTS 就直接跳过
               if database.has_table(table_name.to_string()) {
+                if create_query.if_not_exists {
+                  return Ok(format!(
+                    "CREATE TABLE statement done, table '{}' already exists",
+                    table_name
+                  ));
+                }
                 return Err(NollaDBError::Internal(
                   format!(
                     "Can not create table, because table '{}' already exists",
@@ -94,20 +183,246 @@ pub fn handle_sql_query(sql_query: &str, database: &mut Database) -> Result<Stri
                 ));
               }
 
+              // 约束建立的索引和其他索引一样，名字在整个数据库中是唯一的
+              for table_constraint in &create_query.table_constraints {
+                if database.get_table_name_of_index(&table_constraint.constraint_name).is_some() {
+                  return Err(NollaDBError::Internal(
+                    format!(
+                      "Can not create table, because index '{}' already exists",
+                      table_constraint.constraint_name
+                    )
+                  ));
+                }
+              }
+
               // 创建表
               let table = Table::new(create_query);
               // 打印表 schema
               let _ = table.print_column_of_schema();
               // 把表插入到数据库中
-              database.tables.insert(table_name.to_string(), table);
+              database.create_table(table);
 
               message = String::from("CREATE TABLE statement done");
             },
             Err(error) => return Err(error),
           }
         },
+        Statement::CreateIndex {
+          ..
+        } => {
+          match CreateIndexQuery::new(&statement) {
+            Ok(create_index_query) => {
+              let CreateIndexQuery {
+                index_name,
+                table_name,
+                column_names,
+                is_unique,
+                if_not_exists,
+              } = create_index_query;
+
+              // 检查表是否已经被创建
+              if !database.has_table(table_name.to_string()) {
+                return Err(NollaDBError::Internal(
+                  format!(
+                    "Table '{}' does not exist",
+                    table_name
+                  )
+                ));
+              }
+
+              // 索引名在整个数据库中是唯一的
+              // 如果是 CREATE INDEX IF NOT EXISTS 就直接跳过
+              if database.get_table_name_of_index(&index_name).is_some() {
+                if if_not_exists {
+                  return Ok(format!(
+                    "CREATE INDEX statement done, index '{}' already exists",
+                    index_name
+                  ));
+                }
+                return Err(NollaDBError::Internal(
+                  format!(
+                    "Can not create index, because index '{}' already exists",
+                    index_name
+                  )
+                ));
+              }
+
+              // 用已经存在的行建立索引，并打印表 schema
+              // 多个 column 的话建立多列索引
+              database.save_table(&table_name);
+              let table = database.get_table_mut(table_name.to_string()).unwrap();
+              match column_names.as_slice() {
+                [column_name] => table.create_index(&index_name, column_name, is_unique)?,
+                _ => table.create_composite_index(&index_name, &column_names, is_unique)?,
+              }
+              let _ = table.print_column_of_schema();
+
+              message = String::from("CREATE INDEX statement done");
+            },
+            Err(error) => return Err(error),
+          }
+        },
+        Statement::Drop {
+          ..
+        } => {
+          match DropQuery::new(&statement) {
+            Ok(drop_query) => {
+              let DropQuery {
+                object_type,
+                if_exists,
+                names,
+              } = drop_query;
+
+              match object_type {
+                ObjectType::Table => {
+                  // 先检查所有的表是否存在，再统一删除
+                  // 避免删除到一半失败
+                  // 如果是 DROP TABLE IF EXISTS 就跳过不存在的表
+                  for table_name in &names {
+                    if !database.has_table(table_name.to_string()) && !if_exists {
+                      return Err(NollaDBError::Internal(
+                        format!(
+                          "Can not drop table, because table '{}' does not exist",
+                          table_name
+                        )
+                      ));
+                    }
+                  }
+
+                  // 把表从数据库中删除
+                  for table_name in &names {
+                    database.drop_table(table_name);
+                  }
+
+                  message = String::from("DROP TABLE statement done");
+                },
+                ObjectType::Index => {
+                  // 和 DROP TABLE 一样先检查所有的索引是否存在，再统一删除
+                  // 约束建立的索引不能删除
+                  for index_name in &names {
+                    match database.get_table_name_of_index(index_name) {
+                      None if !if_exists => return Err(NollaDBError::Internal(
+                        format!(
+                          "Can not drop index, because index '{}' does not exist",
+                          index_name
+                        )
+                      )),
+                      None => (),
+                      Some(table_name) => {
+                        let table = database.get_table(table_name).unwrap();
+                        if table.composite_indexes
+                            .get(index_name)
+                            .is_some_and(|composite_index| composite_index.is_constraint) {
+                          return Err(NollaDBError::Internal(
+                            format!(
+                              "Can not drop index '{}', because it is used by a PRIMARY KEY or UNIQUE constraint",
+                              index_name
+                            )
+                          ));
+                        }
+                      },
+                    }
+                  }
+
+                  for index_name in &names {
+                    if let Some(table_name) = database.get_table_name_of_index(index_name) {
+                      database.save_table(&table_name);
+                      let table = database.get_table_mut(table_name).unwrap();
+                      table.drop_index(index_name)?;
+                      let _ = table.print_column_of_schema();
+                    }
+                  }
+
+                  message = String::from("DROP INDEX statement done");
+                },
+                _ => return Err(NollaDBError::ToBeImplemented(
+                  format!("DROP {} will be implemented soon", object_type)
+                )),
+              }
+            },
+            Err(error) => return Err(error),
+          }
+        },
+        Statement::AlterTable {
+          ..
+        } => {
+          match AlterQuery::new(&statement) {
+            Ok(alter_query) => {
+              let AlterQuery {
+                table_name,
+                operation,
+              } = alter_query;
+
+              // 检查表是否已经被创建
+              if !database.has_table(table_name.to_string()) {
+                return Err(NollaDBError::Internal(
+                  format!(
+                    "Table '{}' does not exist",
+                    table_name
+                  )
+                ));
+              }
+
+              // 除了改表名之外都会修改表结构
+              if !matches!(operation, AlterOperation::RenameTable(_)) {
+                database.save_table(&table_name);
+              }
+
+              match operation {
+                AlterOperation::RenameTable(new_table_name) => {
+                  database.rename_table(table_name, new_table_name)?;
+                },
+                AlterOperation::AddColumn(schema_of_sql_column) => {
+                  let table = database.get_table_mut(table_name.to_string()).unwrap();
+                  table.add_column(&schema_of_sql_column)?;
+                  let _ = table.print_column_of_schema();
+                },
+                AlterOperation::DropColumn { column_name, if_exists } => {
+                  let table = database.get_table_mut(table_name.to_string()).unwrap();
+                  // 如果是 DROP COLUMN IF EXISTS 就跳过不存在的 column
+                  if !if_exists || table.has_column(column_name.to_string()) {
+                    table.drop_column(&column_name)?;
+                  }
+                  let _ = table.print_column_of_schema();
+                },
+                AlterOperation::RenameColumn { old_column_name, new_column_name } => {
+                  let table = database.get_table_mut(table_name.to_string()).unwrap();
+                  table.rename_column(&old_column_name, &new_column_name)?;
+                  let _ = table.print_column_of_schema();
+                },
+              }
+
+              message = String::from("ALTER TABLE statement done");
+            },
+            Err(error) => return Err(error),
+          }
+        },
         Statement::Query(_) => {
-          message = String::from("SELECT statement done");
+          match SelectQuery::new(&statement) {
+            Ok(select_query) => {
+              let table_name = select_query.table_name.clone();
+
+              // 检查表是否已经被创建
+              if !database.has_table(table_name.to_string()) {
+                return Err(NollaDBError::Internal(
+                  format!(
+                    "Table '{}' does not exist",
+                    table_name
+                  )
+                ));
+              }
+
+              // 在当前会话的快照中执行查询
+              database.add_read_table_names(database.session_id, select_query.get_table_names());
+              let result_set = execute_select_query(database, database.get_snapshot(), select_query)?;
+
+              // 打印查询结果
+              let _ = result_set.print_result_set();
+
+              message = String::from("SELECT statement done");
+            },
+            Err(error) => return Err(error),
+          }
         },
         Statement::Insert {
           ..
@@ -117,7 +432,8 @@ pub fn handle_sql_query(sql_query: &str, database: &mut Database) -> Result<Stri
               let InsertQuery {
                 table_name,
                 table_column_names,
-                table_column_values,
+                mut table_column_values,
+                select_query,
               } = insert_query;
 
               // 检查表是否已经被创建
@@ -130,21 +446,27 @@ pub fn handle_sql_query(sql_query: &str, database: &mut Database) -> Result<Stri
                 ));
               }
 
+              // INSERT INTO ... SELECT 先执行 SELECT，结果中的每一行就是要插入的一行
+              if let Some(select_query) = select_query {
+                database.add_read_table_names(database.session_id, select_query.get_table_names());
+                table_column_values = execute_select_query(database, database.get_snapshot(), select_query)?.rows;
+              }
+
               // 在对应表中执行插入操作
               let table = database.get_table_mut(table_name.to_string()).unwrap();
               // 检查要插入的 column name 是否在表中存在
               if !table_column_names
                 .iter()
                 .all(|column_name| table.has_column(column_name.to_string())) {
-                return Err(NollaDBError::Internal(format!(
-                  "Can not insert, because some of the columns do not exist"
-                )));
+                return Err(NollaDBError::Internal(
+                  "Can not insert, because some of the columns do not exist".to_string()
+                ));
               }
 
               // TODO: 这里有一种情况是 SQL 里面没有指定列名，那么就按照顺序写入
 
-              for table_column_value in table_column_values {
-                // 1. 检查要插入的 column value 的个数是否和 column name 一致
+              // 检查要插入的 column value 的个数是否和 column name 一致
+              for table_column_value in &table_column_values {
                 let v_len = table_column_value.len();
                 let n_len = table_column_names.len();
                 if v_len != n_len {
@@ -156,22 +478,12 @@ pub fn handle_sql_query(sql_query: &str, database: &mut Database) -> Result<Stri
                     )
                   ));
                 }
-
-                // 2. 检查唯一约束
-                if let Err(error) =
-                  table.check_unique_constraint(&table_column_names, &table_column_value) {
-                  return Err(NollaDBError::Internal(
-                    format!(
-                      "Unique key constraint violation: {}",
-                      error
-                    )
-                  ));
-                }
-
-                // 3. 以上 2 点检查完毕，说明没有唯一约束，可以插入
-                table.insert_row(&table_column_names, &table_column_value);
               }
 
+              // 检查唯一约束以及 NOT NULL 约束并插入
+              // 任意一行失败的话整条语句都不会生效
+              table.insert_rows(&table_column_names, &table_column_values)?;
+
               // 打印插入完成后的表数据
               let _ = table.print_table_data();
 
@@ -183,14 +495,140 @@ pub fn handle_sql_query(sql_query: &str, database: &mut Database) -> Result<Stri
         Statement::Update {
           ..
         } => {
-          // TODO: 在表中更新
-          message = String::from("UPDATE statement done");
+          match UpdateQuery::new(&statement) {
+            Ok(update_query) => {
+              let UpdateQuery {
+                table_name,
+                assignments,
+                selection,
+              } = update_query;
+
+              // 检查表是否已经被创建
+              if !database.has_table(table_name.to_string()) {
+                return Err(NollaDBError::Internal(
+                  format!(
+                    "Table '{}' does not exist",
+                    table_name
+                  )
+                ));
+              }
+
+              // 先在快照中找到满足 WHERE 条件的行，检查没有写冲突之后再对这些行执行更新
+              database.add_read_table_names(database.session_id, vec![table_name.to_string()]);
+              let row_ids = select_row_ids(database, database.get_snapshot(), &table_name, selection)?;
+              database.check_write_conflicts(&table_name, &row_ids)?;
+              let table = database.get_table_mut(table_name.to_string()).unwrap();
+              let number_of_updated_rows = table.update_rows(&row_ids, &assignments)?;
+
+              // 打印更新完成后的表数据
+              let _ = table.print_table_data();
+
+              message = format!(
+                "UPDATE statement done, {} rows updated",
+                number_of_updated_rows
+              );
+            },
+            Err(error) => return Err(error),
+          }
         },
         Statement::Delete {
           ..
         } => {
-          // TODO: 在表中删除
-          message = String::from("DELETE statement done");
+          match DeleteQuery::new(&statement) {
+            Ok(delete_query) => {
+              let DeleteQuery {
+                table_name,
+                selection,
+              } = delete_query;
+
+              // 检查表是否已经被创建
+              if !database.has_table(table_name.to_string()) {
+                return Err(NollaDBError::Internal(
+                  format!(
+                    "Table '{}' does not exist",
+                    table_name
+                  )
+                ));
+              }
+
+              // 先在快照中找到满足 WHERE 条件的行，检查没有写冲突之后再把这些行删除
+              database.add_read_table_names(database.session_id, vec![table_name.to_string()]);
+              let row_ids = select_row_ids(database, database.get_snapshot(), &table_name, selection)?;
+              database.check_write_conflicts(&table_name, &row_ids)?;
+              let table = database.get_table_mut(table_name.to_string()).unwrap();
+              let number_of_deleted_rows = table.delete_rows(&row_ids)?;
+
+              // 打印删除完成后的表数据
+              let _ = table.print_table_data();
+
+              message = format!(
+                "DELETE statement done, {} rows deleted",
+                number_of_deleted_rows
+              );
+            },
+            Err(error) => return Err(error),
+          }
+        },
+        Statement::Explain {
+          ..
+        } => {
+          match ExplainQuery::new(&statement) {
+            Ok(explain_query) => {
+              let result_set = explain_statement(database, database.get_snapshot(), explain_query)?;
+
+              // 打印执行计划
+              let _ = result_set.print_result_set();
+
+              message = String::from("EXPLAIN statement done");
+            },
+            Err(error) => return Err(error),
+          }
+        },
+        Statement::StartTransaction {
+          modes,
+        } => {
+          // REPEATABLE READ 和 SNAPSHOT 一样，都是整个事务读同一个快照
+          let mut isolation_level = None;
+          for mode in modes {
+            match mode {
+              TransactionMode::IsolationLevel(TransactionIsolationLevel::RepeatableRead) => {
+                isolation_level = Some(IsolationLevel::Snapshot);
+              },
+              TransactionMode::IsolationLevel(TransactionIsolationLevel::Serializable) => {
+                isolation_level = Some(IsolationLevel::Serializable);
+              },
+              _ => return Err(NollaDBError::ToBeImplemented(
+                format!("Transaction mode {} will be implemented soon", mode)
+              )),
+            }
+          }
+          database.begin_transaction(isolation_level)?;
+
+          message = String::from("BEGIN statement done");
+        },
+        Statement::Commit {
+          chain,
+        } => {
+          if chain {
+            return Err(NollaDBError::ToBeImplemented(
+              "COMMIT AND CHAIN will be implemented soon".to_string()
+            ));
+          }
+          database.commit_transaction()?;
+
+          message = String::from("COMMIT statement done");
+        },
+        Statement::Rollback {
+          chain,
+        } => {
+          if chain {
+            return Err(NollaDBError::ToBeImplemented(
+              "ROLLBACK AND CHAIN will be implemented soon".to_string()
+            ));
+          }
+          database.rollback_transaction()?;
+
+          message = String::from("ROLLBACK statement done");
         },
         _ => {
           return Err(
@@ -208,31 +646,53 @@ pub fn handle_sql_query(sql_query: &str, database: &mut Database) -> Result<Stri
   Ok(message)
 }
 
+// PRAGMA 用来查看或者修改当前会话的设置
+fn handle_pragma_query(pragma_query: &PragmaQuery, database: &mut Database) -> Result<String> {
+  match pragma_query.name.as_str() {
+    "memory_budget" => {
+      if let Some(value) = &pragma_query.value {
+        database.settings.memory_budget = parse_memory_budget(value)?;
+      }
+      Ok(format!("memory_budget = {}", database.settings.memory_budget))
+    },
+    // 当前会话中 BEGIN 默认的隔离级别
+    "isolation_level" => {
+      if let Some(value) = &pragma_query.value {
+        database.session_mut().default_isolation_level = IsolationLevel::new(value)?;
+      }
+      Ok(format!("isolation_level = {}", database.session_mut().default_isolation_level))
+    },
+    name => Err(NollaDBError::General(format!("Unknown PRAGMA '{}'", name))),
+  }
+}
+
+fn handle_savepoint_query(savepoint_query: &SavepointQuery, database: &mut Database) -> Result<String> {
+  match savepoint_query {
+    SavepointQuery::Savepoint(savepoint_name) => {
+      database.create_savepoint(savepoint_name)?;
+      Ok(String::from("SAVEPOINT statement done"))
+    },
+    SavepointQuery::Release(savepoint_name) => {
+      database.release_savepoint(savepoint_name)?;
+      Ok(String::from("RELEASE statement done"))
+    },
+    SavepointQuery::RollbackTo(savepoint_name) => {
+      database.rollback_to_savepoint(savepoint_name)?;
+      Ok(String::from("ROLLBACK TO statement done"))
+    },
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use std::result::Result;
+  use std::collections::HashMap;
+  use crate::database::DEFAULT_MEMORY_BUDGET;
+  use crate::table::row::value::Value;
   use rstest::rstest;
   use pretty_assertions::{assert_eq};
 
-  #[rstest]
-  #[case("SELECT * FROM test;", "SELECT statement done")]
-  #[case("DELETE FROM test WHERE id=1;", "DELETE statement done")]
-  #[case("UPDATE test SET name='xxx' WHERE id=1;", "UPDATE statement done")]
-  fn test_handle_query_statement_sql(
-    #[case] input: &str,
-    #[case] expected: &str,
-  ) {
-    let mut database = Database::new("testdb".to_string());
-    let _ = match handle_sql_query(input, &mut database) {
-      Ok(response) => assert_eq!(response, expected),
-      Err(error) => {
-        eprintln!("Error: {}", error);
-        assert!(false)
-      }
-    };
-  }
-
   #[rstest]
   #[case(
     "testdb",
@@ -251,13 +711,24 @@ mod tests {
     "INSERT INTO test (name) Values ('xxx');",
     "INSERT statement done",
   )]
+  #[case(
+    "testdb",
+    "CREATE TABLE test (
+      id INTEGER PRIMARY KEY,
+      name TEXT,
+      age INTEGER,
+      score REAL
+    );",
+    "INSERT INTO test (name, age) Values (NULL, NULL);",
+    "INSERT statement done",
+  )]
   fn test_handle_insert_sql(
     #[case] database_name: &str,
     #[case] query: &str,
     #[case] insert_query: &str,
     #[case] expected: &str,
   ) {
-    let _ = match
+    match
       insert_table_into_database_and_insert_data_into_table(
         database_name,
         query,
@@ -265,20 +736,1067 @@ mod tests {
       ) {
         Ok(response) => assert_eq!(response, expected),
         Err(error) => {
-          eprintln!("Error: {}", error);
-          assert!(false)
+          panic!("Error: {}", error)
         },
     };
   }
 
-  fn insert_table_into_database_and_insert_data_into_table(
+  #[rstest]
+  #[case(
+    "INSERT INTO test (email) Values ('a@x.com');",
+    NollaDBError::NotNullConstraint("test".to_string(), "name".to_string()),
+  )]
+  #[case(
+    "INSERT INTO test (name, email) Values ('a', 'a@x.com'), (NULL, 'b@x.com');",
+    NollaDBError::NotNullConstraint("test".to_string(), "name".to_string()),
+  )]
+  #[case(
+    "INSERT INTO test (id, name) Values (NULL, 'a'), (2, NULL);",
+    NollaDBError::NotNullConstraint("test".to_string(), "name".to_string()),
+  )]
+  fn test_handle_insert_sql_error(
+    #[case] insert_query: &str,
+    #[case] expected: NollaDBError,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        email TEXT
+      );",
+    );
+
+    assert_eq!(handle_sql_query(insert_query, &mut database), Err(expected));
+
+    // 失败的 INSERT 不会改动表中的数据
+    let table = database.get_table("test".to_string()).unwrap();
+    assert_eq!(table.get_row_ids().unwrap(), Vec::<i64>::new());
+    assert_eq!(table.most_recent_row_id, 0);
+    assert_eq!(
+      handle_sql_query("INSERT INTO test (name) Values ('c');", &mut database),
+      Ok("INSERT statement done".to_string())
+    );
+    assert_eq!(database.get_table("test".to_string()).unwrap().get_row_ids().unwrap(), vec![1]);
+  }
+
+  #[rstest]
+  #[case(
+    "INSERT INTO archive (name, item) SELECT u.name, o.item FROM users u JOIN orders o ON u.id = o.user_id ORDER BY o.id DESC;",
+    Ok(vec![vec!["1", "a", "pear"], vec!["2", "b", "kiwi"], vec!["3", "a", "apple"]]),
+  )]
+  #[case(
+    "INSERT INTO archive (name) SELECT name FROM users WHERE id > 5;",
+    Ok(vec![]),
+  )]
+  #[case("INSERT INTO archive (name, item) SELECT name FROM users;", Err(()))]
+  #[case("INSERT INTO archive (name) SELECT not_exist FROM users;", Err(()))]
+  fn test_handle_insert_select_sql(
+    #[case] insert_query: &str,
+    #[case] expected: Result<Vec<Vec<&str>>, ()>,
+  ) {
+    let mut database = create_join_database();
+    handle_sql_query(
+      "CREATE TABLE archive (id INTEGER PRIMARY KEY, name TEXT, item TEXT);",
+      &mut database,
+    ).unwrap();
+
+    let result = handle_sql_query(insert_query, &mut database).map_err(|_| ());
+    let select_query = SelectQuery::new(&get_sql_ast("SELECT * FROM archive;").unwrap()).unwrap();
+    let rows = execute_select_query(&database, database.get_snapshot(), select_query).unwrap().rows
+      .iter()
+      .map(|row| row.iter().map(|value| value.to_string()).collect::<Vec<String>>())
+      .collect::<Vec<Vec<String>>>();
+
+    match expected {
+      Ok(expected) => {
+        assert_eq!(result, Ok("INSERT statement done".to_string()));
+        assert_eq!(rows, expected);
+      },
+      // 失败的话不会插入任何一行
+      Err(_) => {
+        assert!(result.is_err());
+        assert_eq!(rows, Vec::<Vec<String>>::new());
+      },
+    }
+  }
+
+  #[rstest]
+  #[case("SELECT * FROM test;", "SELECT statement done")]
+  #[case("SELECT name, id FROM test;", "SELECT statement done")]
+  #[case("SELECT test.id FROM test;", "SELECT statement done")]
+  #[case("SELECT id FROM test WHERE name = 'xxx' AND id > 0;", "SELECT statement done")]
+  #[case("SELECT id FROM test WHERE name IS NOT NULL;", "SELECT statement done")]
+  #[case("SELECT id * 2 AS double_id, name || '!' FROM test;", "SELECT statement done")]
+  #[case("SELECT COUNT(*), MAX(id) FROM test;", "SELECT statement done")]
+  #[case("SELECT name, COUNT(id) AS total FROM test GROUP BY name HAVING COUNT(id) > 0;", "SELECT statement done")]
+  fn test_handle_select_sql(
+    #[case] select_query: &str,
+    #[case] expected: &str,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT
+      );",
+    );
+    handle_sql_query("INSERT INTO test (name) Values ('xxx');", &mut database).unwrap();
+
+    match handle_sql_query(select_query, &mut database) {
+      Ok(response) => assert_eq!(response, expected),
+      Err(error) => {
+        panic!("Error: {}", error)
+      },
+    };
+  }
+
+  #[rstest]
+  #[case(
+    "SELECT name FROM test ORDER BY id DESC;",
+    vec![vec!["c"], vec!["NULL"], vec!["b"], vec!["a"]],
+  )]
+  #[case(
+    "SELECT id FROM test ORDER BY email NULLS LAST;",
+    vec![vec!["4"], vec!["2"], vec!["1"], vec!["3"]],
+  )]
+  #[case(
+    "SELECT id, score FROM test ORDER BY score DESC, 1 LIMIT 2 OFFSET 1;",
+    vec![vec!["2", "1.5"], vec!["4", "1.5"]],
+  )]
+  #[case(
+    "SELECT id AS score FROM test WHERE id > 1 ORDER BY score;",
+    vec![vec!["2"], vec!["3"], vec!["4"]],
+  )]
+  #[case(
+    "SELECT score, COUNT(*) FROM test GROUP BY score ORDER BY COUNT(*) DESC, score LIMIT 2;",
+    vec![vec!["1.5", "2"], vec!["0.5", "1"]],
+  )]
+  #[case(
+    "SELECT id FROM test WHERE id >= 2 AND id < 4;",
+    vec![vec!["2"], vec!["3"]],
+  )]
+  #[case(
+    "SELECT id FROM test WHERE email IN ('a@x.com', 'b@x.com', 'z@x.com') ORDER BY id DESC;",
+    vec![vec!["4"], vec!["2"]],
+  )]
+  fn test_select_from_table(
+    #[case] select_query: &str,
+    #[case] expected: Vec<Vec<&str>>,
+    // 内存预算是 0 的话每一行都会写到临时文件中
+    #[values(DEFAULT_MEMORY_BUDGET, 0)] memory_budget: usize,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT,
+        email TEXT UNIQUE,
+        score REAL
+      );",
+    );
+    handle_sql_query(
+      "INSERT INTO test (name, email, score) Values
+        ('a', 'c@x.com', 0.5),
+        ('b', 'b@x.com', 1.5),
+        (NULL, NULL, 2.5),
+        ('c', 'a@x.com', 1.5);",
+      &mut database,
+    ).unwrap();
+
+    database.settings.memory_budget = memory_budget;
+
+    let select_query = SelectQuery::new(&get_sql_ast(select_query).unwrap()).unwrap();
+    let result_set = execute_select_query(&database, database.get_snapshot(), select_query).unwrap();
+
+    assert_eq!(
+      result_set.rows
+        .iter()
+        .map(|row| row.iter().map(|value| value.to_string()).collect::<Vec<String>>())
+        .collect::<Vec<Vec<String>>>(),
+      expected
+    );
+  }
+
+  #[rstest]
+  #[case(
+    "SELECT u.name, o.item FROM users u JOIN orders o ON u.id = o.user_id ORDER BY o.id;",
+    vec![vec!["a", "apple"], vec!["b", "kiwi"], vec!["a", "pear"]],
+  )]
+  #[case(
+    "SELECT name, item FROM orders o JOIN users u ON o.user_id = u.id WHERE item <> 'pear';",
+    vec![vec!["a", "apple"], vec!["b", "kiwi"]],
+  )]
+  #[case(
+    "SELECT users.name, orders.item FROM users LEFT JOIN orders ON users.id = orders.user_id ORDER BY users.id, orders.id;",
+    vec![vec!["a", "apple"], vec!["a", "pear"], vec!["b", "kiwi"], vec!["c", "NULL"]],
+  )]
+  #[case(
+    "SELECT u.name, o.item FROM users u RIGHT JOIN orders o ON u.id = o.user_id ORDER BY o.id;",
+    vec![vec!["a", "apple"], vec!["b", "kiwi"], vec!["a", "pear"], vec!["NULL", "plum"]],
+  )]
+  #[case(
+    "SELECT COUNT(*) FROM users CROSS JOIN orders;",
+    vec![vec!["12"]],
+  )]
+  #[case(
+    "SELECT u.name, COUNT(o.id) FROM users u LEFT JOIN orders o ON o.user_id = u.id GROUP BY u.name ORDER BY 1;",
+    vec![vec!["a", "2"], vec!["b", "1"], vec!["c", "0"]],
+  )]
+  #[case(
+    "SELECT id, name, tag FROM users JOIN tags USING (id) ORDER BY id;",
+    vec![vec!["1", "a", "x"], vec!["3", "c", "z"]],
+  )]
+  #[case(
+    "SELECT o.* FROM users u JOIN orders o ON u.name = 'b' AND u.id = o.user_id;",
+    vec![vec!["2", "2", "kiwi"]],
+  )]
+  fn test_select_from_joined_tables(
+    #[case] select_query: &str,
+    #[case] expected: Vec<Vec<&str>>,
+    #[values(DEFAULT_MEMORY_BUDGET, 0)] memory_budget: usize,
+  ) {
+    let mut database = create_join_database();
+    database.settings.memory_budget = memory_budget;
+
+    let select_query = SelectQuery::new(&get_sql_ast(select_query).unwrap()).unwrap();
+    let result_set = execute_select_query(&database, database.get_snapshot(), select_query).unwrap();
+
+    assert_eq!(
+      result_set.rows
+        .iter()
+        .map(|row| row.iter().map(|value| value.to_string()).collect::<Vec<String>>())
+        .collect::<Vec<Vec<String>>>(),
+      expected
+    );
+  }
+
+  #[rstest]
+  #[case("SELECT id FROM users JOIN orders ON users.id = orders.user_id;")]
+  #[case("SELECT * FROM users JOIN not_exist ON users.id = not_exist.id;")]
+  #[case("SELECT * FROM users u JOIN orders u ON u.id = u.user_id;")]
+  #[case("SELECT * FROM users u JOIN orders o ON users.id = o.user_id;")]
+  #[case("SELECT o.not_exist FROM users u JOIN orders o ON u.id = o.user_id;")]
+  #[case("SELECT x.* FROM users u JOIN orders o ON u.id = o.user_id;")]
+  #[case("SELECT * FROM users JOIN orders USING (item);")]
+  fn test_select_from_joined_tables_error(
+    #[case] select_query: &str,
+  ) {
+    let mut database = create_join_database();
+
+    assert!(handle_sql_query(select_query, &mut database).is_err());
+  }
+
+  #[rstest]
+  #[case("SELECT a.id, b.id FROM a JOIN b ON a.k = b.k ORDER BY a.id, b.id;")]
+  #[case("SELECT a.id, b.id FROM a LEFT JOIN b ON a.k = b.k AND b.id > 10 ORDER BY a.id, b.id;")]
+  #[case("SELECT a.id, b.id FROM a RIGHT JOIN b ON b.k = a.k ORDER BY b.id, a.id;")]
+  #[case("SELECT COUNT(*) FROM a JOIN b ON a.k = b.k AND a.name = b.name;")]
+  fn test_select_from_joined_tables_with_partitioned_hash_join(
+    #[case] select_query: &str,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE a (id INTEGER PRIMARY KEY, k INTEGER, name TEXT);",
+    );
+    handle_sql_query("CREATE TABLE b (id INTEGER PRIMARY KEY, k INTEGER, name TEXT);", &mut database).unwrap();
+    for i in 0..100 {
+      let k = match i % 9 {
+        0 => "NULL".to_string(),
+        _ => (i % 13).to_string(),
+      };
+      handle_sql_query(&format!("INSERT INTO a (k, name) Values ({}, 'n{}');", k, i % 3), &mut database).unwrap();
+      handle_sql_query(&format!("INSERT INTO b (k, name) Values ({}, 'n{}');", (i * 7) % 17, i % 2), &mut database).unwrap();
+    }
+
+    let expected = execute_select_query(
+      &database,
+      database.get_snapshot(),
+      SelectQuery::new(&get_sql_ast(select_query).unwrap()).unwrap(),
+    ).unwrap();
+
+    // hash 表超过内存预算的时候分区写到临时文件中，结果是一样的
+    database.settings.memory_budget = 512;
+    let explain_query = ExplainQuery::new(
+      &get_sql_ast(&format!("EXPLAIN {}", select_query)).unwrap()
+    ).unwrap();
+    let result_set = explain_statement(&database, database.get_snapshot(), explain_query).unwrap();
+    assert!(result_set.rows.iter().any(|row| row[0].to_string().contains("Hash Join")));
+    assert!(result_set.rows.iter().any(|row| row[0].to_string().contains("partitions")));
+    assert_eq!(
+      execute_select_query(&database, database.get_snapshot(), SelectQuery::new(&get_sql_ast(select_query).unwrap()).unwrap()),
+      Ok(expected)
+    );
+  }
+
+  #[rstest]
+  #[case("PRAGMA memory_budget;",Ok("memory_budget = 67108864".to_string()), DEFAULT_MEMORY_BUDGET)]
+  #[case("PRAGMA memory_budget = 1024;", Ok("memory_budget = 1024".to_string()), 1024)]
+  #[case(
+    "PRAGMA memory_budget = abc;",
+    Err(NollaDBError::General("Invalid memory budget 'ABC'".to_string())),
+    DEFAULT_MEMORY_BUDGET,
+  )]
+  #[case(
+    "PRAGMA not_exist;",
+    Err(NollaDBError::General("Unknown PRAGMA 'not_exist'".to_string())),
+    DEFAULT_MEMORY_BUDGET,
+  )]
+  fn test_handle_pragma_sql(
+    #[case] pragma_query: &str,
+    #[case] expected: Result<String, NollaDBError>,
+    #[case] expected_memory_budget: usize,
+  ) {
+    let mut database = Database::new("testdb".to_string());
+
+    assert_eq!(handle_sql_query(pragma_query, &mut database), expected);
+    assert_eq!(database.settings.memory_budget, expected_memory_budget);
+  }
+
+  #[rstest]
+  #[case("SELECT * FROM not_exist;")]
+  #[case("SELECT not_exist FROM test;")]
+  #[case("SELECT * FROM test WHERE name = 1;")]
+  #[case("SELECT SUM(not_exist) FROM test;")]
+  #[case("SELECT id, COUNT(*) FROM test;")]
+  #[case("SELECT * FROM test WHERE COUNT(*) > 1;")]
+  #[case("SELECT * FROM test ORDER BY not_exist;")]
+  #[case("SELECT id FROM test ORDER BY 2;")]
+  fn test_handle_select_sql_error(
+    #[case] select_query: &str,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT
+      );",
+    );
+    handle_sql_query("INSERT INTO test (name) Values ('xxx');", &mut database).unwrap();
+
+    assert!(handle_sql_query(select_query, &mut database).is_err());
+  }
+
+  #[rstest]
+  #[case("UPDATE test SET name = 'yyy' WHERE id = 1;", "UPDATE statement done, 1 rows updated")]
+  #[case("UPDATE test SET score = score * 2;", "UPDATE statement done, 2 rows updated")]
+  #[case("UPDATE test SET name = 'zzz' WHERE id > 5;", "UPDATE statement done, 0 rows updated")]
+  fn test_handle_update_sql(
+    #[case] update_query: &str,
+    #[case] expected: &str,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT,
+        score REAL
+      );",
+    );
+    handle_sql_query(
+      "INSERT INTO test (name, score) Values ('xxx', 1.0), ('yyy', 2.0);",
+      &mut database
+    ).unwrap();
+
+    match handle_sql_query(update_query, &mut database) {
+      Ok(response) => assert_eq!(response, expected),
+      Err(error) => {
+        panic!("Error: {}", error)
+      },
+    };
+  }
+
+  #[rstest]
+  #[case("UPDATE not_exist SET name = 'xxx';")]
+  #[case("UPDATE test SET not_exist = 'xxx';")]
+  #[case("UPDATE test SET id = 3 WHERE id = 1;")]
+  #[case("UPDATE test SET name = 1;")]
+  fn test_handle_update_sql_error(
+    #[case] update_query: &str,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT
+      );",
+    );
+    handle_sql_query("INSERT INTO test (name) Values ('xxx');", &mut database).unwrap();
+
+    assert!(handle_sql_query(update_query, &mut database).is_err());
+  }
+
+  #[rstest]
+  #[case("DELETE FROM test WHERE id = 1;", "DELETE statement done, 1 rows deleted")]
+  #[case("DELETE FROM test WHERE name = 'zzz';", "DELETE statement done, 0 rows deleted")]
+  #[case("DELETE FROM test;", "DELETE statement done, 2 rows deleted")]
+  fn test_handle_delete_sql(
+    #[case] delete_query: &str,
+    #[case] expected: &str,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT
+      );",
+    );
+    handle_sql_query(
+      "INSERT INTO test (name) Values ('xxx'), ('yyy');",
+      &mut database
+    ).unwrap();
+
+    match handle_sql_query(delete_query, &mut database) {
+      Ok(response) => assert_eq!(response, expected),
+      Err(error) => {
+        panic!("Error: {}", error)
+      },
+    };
+  }
+
+  #[rstest]
+  #[case("CREATE UNIQUE INDEX idx_email ON test (email);", Ok("CREATE INDEX statement done"), vec!["idx_email"])]
+  #[case("CREATE INDEX idx_name ON test (name);", Ok("CREATE INDEX statement done"), vec!["idx_name"])]
+  #[case(
+    "CREATE INDEX IF NOT EXISTS idx_id ON test (email);",
+    Ok("CREATE INDEX statement done, index 'idx_id' already exists"),
+    vec![],
+  )]
+  #[case("CREATE INDEX idx_id ON test (email);", Err(()), vec![])]
+  #[case("CREATE UNIQUE INDEX idx_name ON test (name);", Err(()), vec![])]
+  #[case("CREATE INDEX idx_name ON not_exist (name);", Err(()), vec![])]
+  #[case("CREATE INDEX idx_name_email ON test (name, email);", Ok("CREATE INDEX statement done"), vec!["idx_name_email"])]
+  #[case("CREATE UNIQUE INDEX idx_name_email ON test (name, email);", Ok("CREATE INDEX statement done"), vec!["idx_name_email"])]
+  #[case("CREATE UNIQUE INDEX idx_name_id ON test (name, name);", Err(()), vec![])]
+  #[case("CREATE INDEX idx_id ON test (name, email);", Err(()), vec![])]
+  #[case("DROP INDEX idx_id;", Ok("DROP INDEX statement done"), vec![])]
+  #[case("DROP INDEX IF EXISTS not_exist, idx_id;", Ok("DROP INDEX statement done"), vec![])]
+  #[case("DROP INDEX not_exist;", Err(()), vec![])]
+  fn test_handle_index_sql(
+    #[case] index_query: &str,
+    #[case] expected: Result<&str, ()>,
+    #[case] expected_index_names: Vec<&str>,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT,
+        email TEXT
+      );",
+    );
+    handle_sql_query("INSERT INTO test (name, email) Values ('a', 'a@x.com'), ('a', 'b@x.com');", &mut database).unwrap();
+    handle_sql_query("CREATE INDEX idx_id ON test (id);", &mut database).unwrap();
+
+    assert_eq!(
+      handle_sql_query(index_query, &mut database)
+        .as_deref()
+        .map_err(|_| ()),
+      expected
+    );
+    let table = database.get_table("test".to_string()).unwrap();
+    let mut index_names = table.indexes
+      .keys()
+      .chain(table.composite_indexes.keys())
+      .filter(|index_name| *index_name != "idx_id")
+      .map(|index_name| index_name.as_str())
+      .collect::<Vec<&str>>();
+    index_names.sort();
+    assert_eq!(index_names, expected_index_names);
+  }
+
+  #[rstest]
+  fn test_select_with_created_index() {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        email TEXT
+      );",
+    );
+    handle_sql_query("INSERT INTO test (email) Values ('b@x.com'), ('a@x.com');", &mut database).unwrap();
+    handle_sql_query("CREATE UNIQUE INDEX idx_email ON test (email);", &mut database).unwrap();
+    handle_sql_query("INSERT INTO test (email) Values ('c@x.com');", &mut database).unwrap();
+
+    // 建立 UNIQUE 索引之后，WHERE 和 ORDER BY 都会用到这个索引
+    let explain_query = ExplainQuery::new(
+      &get_sql_ast("EXPLAIN SELECT id FROM test WHERE email >= 'b@x.com' ORDER BY email;").unwrap()
+    ).unwrap();
+    let result_set = explain_statement(&database, database.get_snapshot(), explain_query).unwrap();
+    assert!(result_set.rows[1][0].to_string().starts_with("  -> Index Range Scan on test using index on email"));
+    assert!(result_set.rows[1][0].to_string().contains("order by index on email ASC"));
+
+    let select_query = SelectQuery::new(
+      &get_sql_ast("SELECT id FROM test WHERE email >= 'b@x.com' ORDER BY email;").unwrap()
+    ).unwrap();
+    assert_eq!(
+      execute_select_query(&database, database.get_snapshot(), select_query).unwrap().rows,
+      vec![vec![Value::Integer(1)], vec![Value::Integer(3)]]
+    );
+  }
+
+  #[rstest]
+  fn test_handle_table_constraint_sql() {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE user_groups (
+        user_id INTEGER,
+        group_id INTEGER,
+        PRIMARY KEY (user_id, group_id)
+      );",
+    );
+    handle_sql_query("INSERT INTO user_groups (user_id, group_id) Values (1, 1), (1, 2), (2, 1);", &mut database).unwrap();
+    assert!(handle_sql_query("INSERT INTO user_groups (user_id, group_id) Values (3, 3), (1, 2);", &mut database).is_err());
+    assert!(handle_sql_query("UPDATE user_groups SET group_id = 1 WHERE user_id = 1;", &mut database).is_err());
+    assert!(handle_sql_query("DROP INDEX user_groups_pkey;", &mut database).is_err());
+    assert!(handle_sql_query("CREATE TABLE test (a INTEGER, b INTEGER, CONSTRAINT user_groups_pkey UNIQUE (a, b));", &mut database).is_err());
+
+    // 多列索引的前缀可以用来查找
+    let explain_query = ExplainQuery::new(
+      &get_sql_ast("EXPLAIN SELECT group_id FROM user_groups WHERE user_id = 1 AND group_id = 2;").unwrap()
+    ).unwrap();
+    let result_set = explain_statement(&database, database.get_snapshot(), explain_query).unwrap();
+    assert_eq!(
+      result_set.rows[1][0].to_string(),
+      "  -> Index Lookup on user_groups using index user_groups_pkey on (user_id, group_id), \
+      index condition: user_id = 1 AND group_id = 2, filter: user_id = 1 AND group_id = 2"
+    );
+
+    let select_query = SelectQuery::new(
+      &get_sql_ast("SELECT group_id FROM user_groups WHERE user_id = 1 ORDER BY group_id DESC;").unwrap()
+    ).unwrap();
+    assert_eq!(
+      execute_select_query(&database, database.get_snapshot(), select_query).unwrap().rows,
+      vec![vec![Value::Integer(2)], vec![Value::Integer(1)]]
+    );
+  }
+
+  #[rstest]
+  #[case("SELECT id FROM orders WHERE status = 'open';", vec![1, 3, 4])]
+  #[case("SELECT id FROM orders WHERE user_id IN (2, 3);", vec![2, 3, 5])]
+  #[case("SELECT id FROM orders WHERE user_id > 1 ORDER BY user_id DESC;", vec![3, 5, 2])]
+  #[case("SELECT id FROM orders ORDER BY status DESC;", vec![1, 3, 4, 2, 5])]
+  fn test_select_with_non_unique_index(
+    #[case] query: &str,
+    #[case] expected_ids: Vec<i32>,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE orders (
+        id INTEGER PRIMARY KEY,
+        user_id INTEGER,
+        status TEXT
+      );",
+    );
+    for query in [
+      "INSERT INTO orders (user_id, status) Values (1, 'open'), (2, 'closed'), (3, 'open'), (1, 'open'), (3, 'closed'), (1, 'draft');",
+      "CREATE INDEX idx_user_id ON orders (user_id);",
+      "CREATE INDEX idx_status ON orders (status);",
+      // 删除和修改一行之后，value 相同的其他行还在索引中
+      "DELETE FROM orders WHERE id = 6;",
+      "UPDATE orders SET user_id = 1 WHERE id = 2;",
+      "UPDATE orders SET user_id = 2 WHERE id = 2;",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+
+    let explain_query = ExplainQuery::new(
+      &get_sql_ast(&format!("EXPLAIN {}", query)).unwrap()
+    ).unwrap();
+    let result_set = explain_statement(&database, database.get_snapshot(), explain_query).unwrap();
+    assert!(result_set.rows.iter().any(|row| row[0].to_string().contains("using index on")));
+
+    let select_query = SelectQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+    assert_eq!(
+      execute_select_query(&database, database.get_snapshot(), select_query).unwrap().rows,
+      expected_ids.into_iter().map(|id| vec![Value::Integer(id)]).collect::<Vec<Vec<Value>>>()
+    );
+  }
+
+  #[rstest]
+  #[case(
+    "CREATE TABLE test (id INTEGER PRIMARY KEY);",
+    Err(()),
+  )]
+  #[case(
+    "CREATE TABLE IF NOT EXISTS test (id INTEGER PRIMARY KEY);",
+    Ok("CREATE TABLE statement done, table 'test' already exists"),
+  )]
+  #[case(
+    "CREATE TABLE IF NOT EXISTS test2 (id INTEGER PRIMARY KEY);",
+    Ok("CREATE TABLE statement done"),
+  )]
+  fn test_handle_create_sql(
+    #[case] create_query: &str,
+    #[case] expected: Result<&str, ()>,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT
+      );",
+    );
+    handle_sql_query("INSERT INTO test (name) Values ('xxx');", &mut database).unwrap();
+
+    assert_eq!(
+      handle_sql_query(create_query, &mut database)
+        .as_deref()
+        .map_err(|_| ()),
+      expected
+    );
+    // 已经存在的表不会被覆盖
+    assert_eq!(database.get_table("test".to_string()).unwrap().get_row_ids().unwrap(), vec![1]);
+  }
+
+  #[rstest]
+  #[case("DROP TABLE test;", Ok("DROP TABLE statement done"), false)]
+  #[case("DROP TABLE IF EXISTS test;", Ok("DROP TABLE statement done"), false)]
+  #[case("DROP TABLE IF EXISTS not_exist;", Ok("DROP TABLE statement done"), true)]
+  #[case("DROP TABLE IF EXISTS not_exist, test;", Ok("DROP TABLE statement done"), false)]
+  #[case("DROP TABLE not_exist;", Err(()), true)]
+  #[case("DROP TABLE test, not_exist;", Err(()), true)]
+  fn test_handle_drop_sql(
+    #[case] drop_query: &str,
+    #[case] expected: Result<&str, ()>,
+    #[case] expected_has_table: bool,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY
+      );",
+    );
+
+    assert_eq!(
+      handle_sql_query(drop_query, &mut database)
+        .as_deref()
+        .map_err(|_| ()),
+      expected
+    );
+    assert_eq!(database.has_table("test".to_string()), expected_has_table);
+  }
+
+  #[rstest]
+  #[case("ALTER TABLE test ADD COLUMN score REAL DEFAULT 0;", "test", "SELECT id, score FROM test;")]
+  #[case("ALTER TABLE test DROP COLUMN name;", "test", "SELECT id FROM test;")]
+  #[case("ALTER TABLE test DROP COLUMN IF EXISTS not_exist;", "test", "SELECT * FROM test;")]
+  #[case("ALTER TABLE test RENAME COLUMN name TO user_name;", "test", "SELECT user_name FROM test;")]
+  #[case("ALTER TABLE test RENAME TO test2;", "test2", "SELECT * FROM test2 WHERE id = 1;")]
+  fn test_handle_alter_sql(
+    #[case] alter_query: &str,
+    #[case] expected_table_name: &str,
+    #[case] select_query: &str,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT
+      );",
+    );
+    handle_sql_query("INSERT INTO test (name) Values ('xxx');", &mut database).unwrap();
+
+    assert_eq!(
+      handle_sql_query(alter_query, &mut database),
+      Ok("ALTER TABLE statement done".to_string())
+    );
+    assert!(database.has_table(expected_table_name.to_string()));
+    assert_eq!(
+      database.get_table(expected_table_name.to_string()).unwrap().table_name,
+      expected_table_name
+    );
+    assert!(handle_sql_query(select_query, &mut database).is_ok());
+  }
+
+  #[rstest]
+  #[case("ALTER TABLE not_exist ADD COLUMN score REAL;")]
+  #[case("ALTER TABLE test DROP COLUMN not_exist;")]
+  #[case("ALTER TABLE test RENAME TO test;")]
+  fn test_handle_alter_sql_error(
+    #[case] alter_query: &str,
+  ) {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT
+      );",
+    );
+
+    assert!(handle_sql_query(alter_query, &mut database).is_err());
+  }
+
+  #[rstest]
+  #[case(vec![
+    "INSERT INTO test (name, email) Values ('c', 'c@x.com'), ('d', 'd@x.com');",
+    "UPDATE test SET name = 'x' WHERE id < 3;",
+    "DELETE FROM test WHERE id = 1;",
+  ])]
+  #[case(vec![
+    "UPDATE test SET email = 'a@x.com' WHERE id = 2;",
+    "INSERT INTO test (id, name) Values (3, 'c'), (3, 'd');",
+    "DELETE FROM test;",
+  ])]
+  #[case(vec![
+    "CREATE TABLE other (id INTEGER PRIMARY KEY, tag TEXT);",
+    "INSERT INTO other (tag) Values ('x');",
+    "DROP TABLE test;",
+  ])]
+  #[case(vec![
+    "INSERT INTO test (name) Values ('c');",
+    "ALTER TABLE test ADD COLUMN score REAL DEFAULT 1.5;",
+    "ALTER TABLE test RENAME COLUMN name TO nickname;",
+    "CREATE INDEX idx_name_email ON test (nickname, email);",
+    "UPDATE test SET score = 2.5 WHERE id = 1;",
+    "ALTER TABLE test DROP COLUMN email;",
+  ])]
+  #[case(vec![
+    "DELETE FROM test WHERE id = 2;",
+    "ALTER TABLE test RENAME TO renamed;",
+    "INSERT INTO renamed (name) Values ('c');",
+  ])]
+  fn test_handle_transaction_rollback(
+    #[case] queries: Vec<&str>,
+  ) {
+    let mut database = create_transaction_database();
+    let tables: HashMap<String, Table> = database.tables
+      .iter()
+      .map(|(table_name, table)| (table_name.to_string(), table.deep_clone()))
+      .collect();
+
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    for query in queries {
+      let _ = handle_sql_query(query, &mut database);
+    }
+    handle_sql_query("ROLLBACK;", &mut database).unwrap();
+
+    // 表中的数据、索引以及 most_recent_row_id 都和 BEGIN 之前一样
+    assert_eq!(database.tables, tables);
+    assert!(database.session_mut().undo_log.is_empty());
+    assert!(!database.is_in_transaction());
+  }
+
+  #[rstest]
+  fn test_handle_transaction_commit() {
+    let mut database = create_transaction_database();
+
+    handle_sql_query("BEGIN TRANSACTION;", &mut database).unwrap();
+    assert!(handle_sql_query("BEGIN;", &mut database).is_err());
+    handle_sql_query("INSERT INTO test (name) Values ('c');", &mut database).unwrap();
+    // 失败的语句只撤销自己的修改，之前的语句不受影响
+    assert!(handle_sql_query("INSERT INTO test (name, email) Values ('d', 'd@x.com'), ('e', 'a@x.com');", &mut database).is_err());
+    handle_sql_query("UPDATE test SET name = 'x' WHERE id = 1;", &mut database).unwrap();
+    assert!(!database.session_mut().undo_log.is_empty());
+    handle_sql_query("COMMIT;", &mut database).unwrap();
+
+    assert!(database.session_mut().undo_log.is_empty());
+    assert!(handle_sql_query("COMMIT;", &mut database).is_err());
+    assert!(handle_sql_query("ROLLBACK;", &mut database).is_err());
+
+    let select_query = SelectQuery::new(
+      &get_sql_ast("SELECT name FROM test;").unwrap()
+    ).unwrap();
+    assert_eq!(
+      execute_select_query(&database, database.get_snapshot(), select_query).unwrap().rows,
+      vec![
+        vec![Value::Text("x".to_string())],
+        vec![Value::Text("b".to_string())],
+        vec![Value::Text("c".to_string())],
+      ]
+    );
+    assert_eq!(database.get_table("test".to_string()).unwrap().most_recent_row_id, 3);
+  }
+
+  #[rstest]
+  fn test_handle_sql_statement_atomicity() {
+    let mut database = create_transaction_database();
+    let tables: HashMap<String, Table> = database.tables
+      .iter()
+      .map(|(table_name, table)| (table_name.to_string(), table.deep_clone()))
+      .collect();
+
+    // 不在事务中的时候，失败的语句同样不会留下任何修改
+    assert!(handle_sql_query("INSERT INTO test (name, email) Values ('c', 'c@x.com'), ('d', 'b@x.com');", &mut database).is_err());
+    assert!(handle_sql_query("UPDATE test SET email = 'x@x.com';", &mut database).is_err());
+    assert!(handle_sql_query("ALTER TABLE test DROP COLUMN id;", &mut database).is_err());
+    assert_eq!(database.tables, tables);
+
+    // 执行成功的语句会直接提交
+    handle_sql_query("DELETE FROM test WHERE id = 1;", &mut database).unwrap();
+    assert!(database.session_mut().undo_log.is_empty());
+    assert_eq!(database.get_table("test".to_string()).unwrap().get_row_ids().unwrap(), vec![2]);
+  }
+
+  #[rstest]
+  fn test_handle_savepoint_sql() {
+    let mut database = create_transaction_database();
+    let get_names = |database: &Database| {
+      let select_query = SelectQuery::new(
+        &get_sql_ast("SELECT name FROM test;").unwrap()
+      ).unwrap();
+      execute_select_query(database, database.get_snapshot(), select_query)
+        .unwrap()
+        .rows
+        .into_iter()
+        .map(|row| row[0].to_string())
+        .collect::<Vec<String>>()
+    };
+
+    assert!(handle_sql_query("SAVEPOINT sp1;", &mut database).is_err());
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    handle_sql_query("INSERT INTO test (name) Values ('c');", &mut database).unwrap();
+    handle_sql_query("SAVEPOINT sp1;", &mut database).unwrap();
+    let tables: HashMap<String, Table> = database.tables
+      .iter()
+      .map(|(table_name, table)| (table_name.to_string(), table.deep_clone()))
+      .collect();
+    handle_sql_query("UPDATE test SET name = 'x' WHERE id = 1;", &mut database).unwrap();
+    handle_sql_query("SAVEPOINT sp2;", &mut database).unwrap();
+    handle_sql_query("CREATE INDEX idx_name ON test (name);", &mut database).unwrap();
+    handle_sql_query("INSERT INTO test (name) Values ('d');", &mut database).unwrap();
+    assert_eq!(get_names(&database), vec!["x", "b", "c", "d"]);
+
+    // 回滚到 sp2 之后 sp2 还在，可以再回滚一次
+    handle_sql_query("ROLLBACK TO sp2;", &mut database).unwrap();
+    assert_eq!(get_names(&database), vec!["x", "b", "c"]);
+    handle_sql_query("DELETE FROM test;", &mut database).unwrap();
+    handle_sql_query("ROLLBACK TO SAVEPOINT sp2;", &mut database).unwrap();
+    assert_eq!(get_names(&database), vec!["x", "b", "c"]);
+
+    // 回滚到 sp1 会删除之后创建的 sp2
+    handle_sql_query("ROLLBACK TRANSACTION TO SAVEPOINT SP1;", &mut database).unwrap();
+    assert_eq!(database.tables, tables);
+    assert!(handle_sql_query("RELEASE sp2;", &mut database).is_err());
+
+    // RELEASE 之后修改仍然保留在事务中
+    handle_sql_query("SAVEPOINT sp3;", &mut database).unwrap();
+    handle_sql_query("DELETE FROM test WHERE id = 2;", &mut database).unwrap();
+    handle_sql_query("RELEASE SAVEPOINT sp3;", &mut database).unwrap();
+    assert!(handle_sql_query("ROLLBACK TO sp3;", &mut database).is_err());
+    assert_eq!(get_names(&database), vec!["a", "c"]);
+    handle_sql_query("ROLLBACK TO sp1;", &mut database).unwrap();
+    assert_eq!(get_names(&database), vec!["a", "b", "c"]);
+
+    handle_sql_query("COMMIT;", &mut database).unwrap();
+    assert!(database.session_mut().savepoints.is_empty());
+    assert!(handle_sql_query("ROLLBACK TO sp1;", &mut database).is_err());
+    assert_eq!(get_names(&database), vec!["a", "b", "c"]);
+  }
+
+  #[rstest]
+  fn test_handle_snapshot_isolation() {
+    let mut database = create_transaction_database();
+
+    // 会话 1 的事务一直读 BEGIN 时的快照
+    database.use_session(1);
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["a", "b"]);
+
+    // 会话 2 的修改不管有没有提交，会话 1 都看不到
+    database.use_session(2);
+    handle_sql_query("UPDATE test SET name = 'x' WHERE id = 1;", &mut database).unwrap();
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    handle_sql_query("DELETE FROM test WHERE id = 2;", &mut database).unwrap();
+    handle_sql_query("INSERT INTO test (name) Values ('c');", &mut database).unwrap();
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["x", "c"]);
+
+    database.use_session(1);
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["a", "b"]);
+    assert_eq!(get_column_values(&database, "SELECT name FROM test WHERE email = 'b@x.com';"), vec!["b"]);
+    // 自己的修改是可见的
+    handle_sql_query("INSERT INTO test (name) Values ('d');", &mut database).unwrap();
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["a", "b", "d"]);
+
+    // 不在事务中的会话读到的是已经提交的数据
+    database.use_session(3);
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["x", "b"]);
+
+    database.use_session(2);
+    handle_sql_query("COMMIT;", &mut database).unwrap();
+    database.use_session(1);
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["a", "b", "d"]);
+    handle_sql_query("COMMIT;", &mut database).unwrap();
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["x", "c", "d"]);
+
+    // 没有事务之后旧的版本都被回收了
+    assert!(database.get_table("test".to_string()).unwrap().row_versions.is_empty());
+  }
+
+  #[rstest]
+  #[case("SELECT name FROM test WHERE email = 'b@x.com';", vec!["b"])]
+  #[case("SELECT name FROM test WHERE email IN ('a@x.com', 'x@x.com');", vec!["a"])]
+  #[case("SELECT name FROM test WHERE id >= 1 ORDER BY id DESC;", vec!["b", "a"])]
+  #[case("SELECT name FROM test ORDER BY email DESC;", vec!["b", "a"])]
+  #[case("SELECT t1.name FROM test t1 JOIN test t2 ON t1.email = t2.email ORDER BY t2.id;", vec!["a", "b"])]
+  #[case("SELECT COUNT(*) FROM test t1 LEFT JOIN test t2 ON t1.name = t2.name;", vec!["2"])]
+  fn test_handle_snapshot_isolation_with_indexes(
+    #[case] select_query: &str,
+    #[case] expected: Vec<&str>,
+  ) {
+    let mut database = create_transaction_database();
+
+    database.use_session(1);
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+
+    // 会话 2 还没有提交的修改已经写到了表和索引中，会话 1 读的时候要用快照中的值
+    database.use_session(2);
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    handle_sql_query("UPDATE test SET name = 'x', email = 'x@x.com' WHERE id = 2;", &mut database).unwrap();
+    handle_sql_query("DELETE FROM test WHERE id = 1;", &mut database).unwrap();
+    handle_sql_query("INSERT INTO test (name, email) Values ('c', 'a@x.com');", &mut database).unwrap();
+
+    database.use_session(1);
+    assert_eq!(get_column_values(&database, select_query), expected);
+  }
+
+  #[rstest]
+  fn test_handle_write_conflict() {
+    let mut database = create_transaction_database();
+
+    database.use_session(1);
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    handle_sql_query("UPDATE test SET name = 'x' WHERE id = 1;", &mut database).unwrap();
+
+    // 先修改的事务还没有提交，后修改的失败，并且不会等待
+    database.use_session(2);
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    assert!(matches!(
+      handle_sql_query("UPDATE test SET name = 'y';", &mut database),
+      Err(NollaDBError::SerializationFailure(_))
+    ));
+    assert!(matches!(
+      handle_sql_query("DELETE FROM test WHERE id = 1;", &mut database),
+      Err(NollaDBError::SerializationFailure(_))
+    ));
+    handle_sql_query("UPDATE test SET name = 'y' WHERE id = 2;", &mut database).unwrap();
+    // 在事务中的时候不能修改表结构，别的会话也不能
+    assert!(handle_sql_query("ALTER TABLE test ADD COLUMN score REAL;", &mut database).is_err());
+
+    database.use_session(1);
+    handle_sql_query("COMMIT;", &mut database).unwrap();
+
+    // 先修改的事务已经提交了，但是在会话 2 的快照之后，还是冲突
+    database.use_session(2);
+    assert!(matches!(
+      handle_sql_query("UPDATE test SET name = 'z' WHERE id = 1;", &mut database),
+      Err(NollaDBError::SerializationFailure(_))
+    ));
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["a", "y"]);
+    handle_sql_query("COMMIT;", &mut database).unwrap();
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["x", "y"]);
+
+    // 回滚的事务不会和之后的事务冲突
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    handle_sql_query("DELETE FROM test WHERE id = 1;", &mut database).unwrap();
+    handle_sql_query("ROLLBACK;", &mut database).unwrap();
+    database.use_session(1);
+    handle_sql_query("UPDATE test SET name = 'a' WHERE id = 1;", &mut database).unwrap();
+    handle_sql_query("ALTER TABLE test ADD COLUMN score REAL;", &mut database).unwrap();
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), vec!["a", "y"]);
+  }
+
+  #[rstest]
+  #[case("BEGIN ISOLATION LEVEL SERIALIZABLE;", true)]
+  #[case("BEGIN ISOLATION LEVEL REPEATABLE READ;", false)]
+  #[case("BEGIN;", false)]
+  fn test_handle_serializable_isolation(
+    #[case] begin_query: &str,
+    #[case] is_serializable: bool,
+  ) {
+    let mut database = create_transaction_database();
+
+    // 两个事务都先检查表中的行数，再各自插入一行，串行执行的话第二个事务能看到第一个插入的行
+    for session_id in [1, 2] {
+      database.use_session(session_id);
+      handle_sql_query(begin_query, &mut database).unwrap();
+      handle_sql_query("SELECT COUNT(*) FROM test;", &mut database).unwrap();
+      assert_eq!(get_column_values(&database, "SELECT COUNT(*) FROM test;"), vec!["2"]);
+      handle_sql_query(&format!("INSERT INTO test (name) Values ('s{}');", session_id), &mut database).unwrap();
+    }
+
+    database.use_session(1);
+    handle_sql_query("COMMIT;", &mut database).unwrap();
+    database.use_session(2);
+    let result = handle_sql_query("COMMIT;", &mut database);
+    // SERIALIZABLE 的话后提交的事务读过的表被修改了，整个事务回滚
+    assert_eq!(matches!(result, Err(NollaDBError::SerializationFailure(_))), is_serializable);
+    assert!(!database.is_in_transaction());
+
+    let expected = match is_serializable {
+      true => vec!["a", "b", "s1"],
+      false => vec!["a", "b", "s1", "s2"],
+    };
+    assert_eq!(get_column_values(&database, "SELECT name FROM test;"), expected);
+  }
+
+  #[rstest]
+  fn test_handle_isolation_level_pragma() {
+    let mut database = create_transaction_database();
+
+    assert_eq!(handle_sql_query("PRAGMA isolation_level;", &mut database), Ok("isolation_level = snapshot".to_string()));
+    assert_eq!(
+      handle_sql_query("PRAGMA isolation_level = serializable;", &mut database),
+      Ok("isolation_level = serializable".to_string())
+    );
+    assert!(handle_sql_query("PRAGMA isolation_level = read_committed;", &mut database).is_err());
+    assert!(handle_sql_query("BEGIN ISOLATION LEVEL READ COMMITTED;", &mut database).is_err());
+    handle_sql_query("BEGIN;", &mut database).unwrap();
+    assert_eq!(database.session_mut().isolation_level, IsolationLevel::Serializable);
+
+    // 每个会话的设置是独立的
+    database.use_session(1);
+    assert_eq!(handle_sql_query("PRAGMA isolation_level;", &mut database), Ok("isolation_level = snapshot".to_string()));
+  }
+
+  fn get_column_values(database: &Database, query: &str) -> Vec<String> {
+    let select_query = SelectQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+    execute_select_query(database, database.get_snapshot(), select_query)
+      .unwrap()
+      .rows
+      .into_iter()
+      .map(|row| row[0].to_string())
+      .collect()
+  }
+
+  fn create_transaction_database() -> Database {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        email TEXT UNIQUE
+      );",
+    );
+    handle_sql_query("INSERT INTO test (name, email) Values ('a', 'a@x.com'), ('b', 'b@x.com');", &mut database).unwrap();
+    database
+  }
+
+  // users 和 orders 通过 orders.user_id 关联，tags 和 users 共用 id
+  fn create_join_database() -> Database {
+    let mut database = insert_table_into_database(
+      "testdb",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+    );
+    for query in [
+      "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, item TEXT);",
+      "CREATE TABLE tags (id INTEGER PRIMARY KEY, tag TEXT);",
+      "INSERT INTO users (name) Values ('a'), ('b'), ('c');",
+      "INSERT INTO orders (user_id, item) Values (1, 'apple'), (2, 'kiwi'), (1, 'pear'), (NULL, 'plum');",
+      "INSERT INTO tags (id, tag) Values (1, 'x'), (3, 'z'), (5, 'w');",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    database
+  }
+
+  fn insert_table_into_database(
     database_name: &str,
     query: &str,
-    insert_query: &str,
-  ) -> Result<String, NollaDBError> {
+  ) -> Database {
     let mut database = Database::new(database_name.to_string());
     let dialect = SQLiteDialect {};
-    let mut ast = Parser::parse_sql(&dialect, &query).unwrap();
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
     let create_query = CreateQuery::new(&ast.pop().unwrap()).unwrap();
 
     database.tables.insert(
@@ -286,9 +1804,16 @@ mod tests {
       Table::new(create_query),
     );
 
-    match handle_sql_query(&insert_query, &mut database) {
-      Ok(response) => Ok(response),
-      Err(error) => Err(error),
-    }
+    database
+  }
+
+  fn insert_table_into_database_and_insert_data_into_table(
+    database_name: &str,
+    query: &str,
+    insert_query: &str,
+  ) -> Result<String, NollaDBError> {
+    let mut database = insert_table_into_database(database_name, query);
+
+    handle_sql_query(insert_query, &mut database)
   }
 }
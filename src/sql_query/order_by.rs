@@ -0,0 +1,268 @@
+use std::cmp::Ordering;
+
+use sqlparser::ast::{
+  Expr,
+  OrderByExpr,
+  Value as SQLValue,
+};
+
+use crate::error::{Result, NollaDBError};
+use crate::sql_query::expression::{RowValues, evaluate_expression};
+use crate::table::row::value::Value;
+use crate::table::column::index::RealKey;
+
+// 把 ORDER BY 中的每一项转换成结果集中的第几列
+// 1. ORDER BY 1 这种数字表示结果集中的第几列
+// 2. ORDER BY 别名，或者和 SELECT 中某一项相同的表达式，就是那一列
+// 3. 其他的表达式会被加到 column_exprs 的后面一起求值，排序之后再去掉
+pub fn resolve_order_by(
+  order_by: &[OrderByExpr],
+  column_names: &[String],
+  column_exprs: &mut Vec<Expr>,
+) -> Result<Vec<usize>> {
+  let mut column_indexes: Vec<usize> = vec![];
+
+  for order_by_expr in order_by {
+    let expr = &order_by_expr.expr;
+    let column_index = match expr {
+      Expr::Value(SQLValue::Number(n, _)) => match n.parse::<usize>() {
+        Ok(i) if i >= 1 && i <= column_names.len() => i - 1,
+        _ => return Err(NollaDBError::General(
+          format!(
+            "ORDER BY term '{}' out of range, should be between 1 and {}",
+            n,
+            column_names.len()
+          )
+        )),
+      },
+      _ => {
+        let alias_index = match expr {
+          Expr::Identifier(ident) => column_names
+            .iter()
+            .position(|column_name| *column_name == ident.value),
+          _ => None,
+        };
+        match alias_index.or_else(|| column_exprs.iter().position(|column_expr| column_expr == expr)) {
+          Some(i) => i,
+          None => {
+            column_exprs.push(expr.clone());
+            column_exprs.len() - 1
+          },
+        }
+      },
+    };
+
+    column_indexes.push(column_index);
+  }
+
+  Ok(column_indexes)
+}
+
+// 和 SQLite 一样，默认 NULL 是最小的
+// 也就是 ASC 的时候 NULL 在最前面，DESC 的时候 NULL 在最后面
+pub fn is_nulls_first(order_by_expr: &OrderByExpr) -> bool {
+  order_by_expr.nulls_first.unwrap_or_else(|| is_asc(order_by_expr))
+}
+
+pub fn is_asc(order_by_expr: &OrderByExpr) -> bool {
+  order_by_expr.asc.unwrap_or(true)
+}
+
+// 按照 ORDER BY 对结果集排序，column_indexes 是 resolve_order_by 的结果
+// 排序是稳定的，ORDER BY 的值相同的行保持原来的顺序
+pub fn sort_rows(
+  rows: &mut [Vec<Value>],
+  order_by: &[OrderByExpr],
+  column_indexes: &[usize],
+) -> Result<()> {
+  let mut sort_error: Option<NollaDBError> = None;
+
+  rows.sort_by(|a, b| {
+    match compare_rows(a, b, order_by, column_indexes) {
+      Ok(ordering) => ordering,
+      Err(error) => {
+        sort_error.get_or_insert(error);
+        Ordering::Equal
+      },
+    }
+  });
+
+  match sort_error {
+    Some(error) => Err(error),
+    None => Ok(()),
+  }
+}
+
+// 按照 ORDER BY 中的每一项依次比较两行
+pub fn compare_rows(
+  a: &[Value],
+  b: &[Value],
+  order_by: &[OrderByExpr],
+  column_indexes: &[usize],
+) -> Result<Ordering> {
+  for (order_by_expr, column_index) in order_by.iter().zip(column_indexes) {
+    match compare_sort_values(&a[*column_index], &b[*column_index], order_by_expr)? {
+      Ordering::Equal => continue,
+      ordering => return Ok(ordering),
+    }
+  }
+  Ok(Ordering::Equal)
+}
+
+fn compare_sort_values(a: &Value, b: &Value, order_by_expr: &OrderByExpr) -> Result<Ordering> {
+  let nulls_first = is_nulls_first(order_by_expr);
+  match (a.is_null(), b.is_null()) {
+    (true, true) => Ok(Ordering::Equal),
+    (true, false) if nulls_first => Ok(Ordering::Less),
+    (true, false) => Ok(Ordering::Greater),
+    (false, true) if nulls_first => Ok(Ordering::Greater),
+    (false, true) => Ok(Ordering::Less),
+    _ => {
+      let ordering = match (a, b) {
+        // 和 Real 的索引一样，NaN 排在所有的数后面
+        (Value::Real(a), Value::Real(b)) => RealKey::new(*a).cmp(&RealKey::new(*b)),
+        _ => a.compare(b)?.unwrap_or(Ordering::Equal),
+      };
+      match is_asc(order_by_expr) {
+        true => Ok(ordering),
+        false => Ok(ordering.reverse()),
+      }
+    },
+  }
+}
+
+// 求出 LIMIT 和 OFFSET 的值，先跳过 OFFSET 行，再最多保留 LIMIT 行
+// 没有 LIMIT 或者 LIMIT ALL 表示没有限制
+// sqlparser 只会解析出数字，这里和 SQLite 一样，LIMIT 是负数也表示没有限制，OFFSET 是负数当作 0
+pub fn get_limit_and_offset(
+  limit: &Option<Expr>,
+  offset: &Option<Expr>,
+) -> Result<(usize, usize)> {
+  let offset = match offset {
+    Some(expr) => get_integer_value("OFFSET", expr)?.max(0) as usize,
+    None => 0,
+  };
+  let limit = match limit {
+    Some(expr) => match get_integer_value("LIMIT", expr)? {
+      limit if limit < 0 => usize::MAX,
+      limit => limit as usize,
+    },
+    None => usize::MAX,
+  };
+
+  Ok((limit, offset))
+}
+
+fn get_integer_value(clause: &str, expr: &Expr) -> Result<i32> {
+  match evaluate_expression(expr, &RowValues::new())? {
+    Value::Integer(value) => Ok(value),
+    value => Err(NollaDBError::General(
+      format!(
+        "{} expects an integer, but found {} value '{}'",
+        clause,
+        value.get_data_type(),
+        value
+      )
+    )),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+  use sqlparser::ast::{Statement, Ident};
+
+  #[rstest]
+  #[case("ORDER BY id", vec![0], 2)]
+  #[case("ORDER BY 2 DESC, total", vec![1, 1], 2)]
+  #[case("ORDER BY score + 1, id", vec![2, 0], 3)]
+  fn test_resolve_order_by(
+    #[case] order_by: &str,
+    #[case] expected: Vec<usize>,
+    #[case] expected_column_exprs_len: usize,
+  ) {
+    let column_names = vec!["id".to_string(), "total".to_string()];
+    let mut column_exprs = vec![
+      Expr::Identifier(Ident::new("id")),
+      Expr::Identifier(Ident::new("score")),
+    ];
+
+    assert_eq!(
+      resolve_order_by(&parse_order_by(order_by), &column_names, &mut column_exprs),
+      Ok(expected)
+    );
+    assert_eq!(column_exprs.len(), expected_column_exprs_len);
+  }
+
+  #[rstest]
+  #[case("ORDER BY 0")]
+  #[case("ORDER BY 3")]
+  fn test_resolve_order_by_error(#[case] order_by: &str) {
+    let column_names = vec!["id".to_string(), "total".to_string()];
+    let mut column_exprs = vec![];
+
+    assert!(resolve_order_by(&parse_order_by(order_by), &column_names, &mut column_exprs).is_err());
+  }
+
+  #[rstest]
+  #[case("ORDER BY 1", vec![Value::Null, Value::Integer(1), Value::Integer(2), Value::Integer(3)])]
+  #[case("ORDER BY 1 DESC", vec![Value::Integer(3), Value::Integer(2), Value::Integer(1), Value::Null])]
+  #[case("ORDER BY 1 NULLS LAST", vec![Value::Integer(1), Value::Integer(2), Value::Integer(3), Value::Null])]
+  #[case("ORDER BY 1 DESC NULLS FIRST", vec![Value::Null, Value::Integer(3), Value::Integer(2), Value::Integer(1)])]
+  #[case("ORDER BY 2, 1 DESC", vec![Value::Integer(3), Value::Integer(1), Value::Integer(2), Value::Null])]
+  fn test_sort_rows(
+    #[case] order_by: &str,
+    #[case] expected: Vec<Value>,
+  ) {
+    let mut rows = vec![
+      vec![Value::Integer(2), Value::Text("b".to_string())],
+      vec![Value::Null, Value::Text("b".to_string())],
+      vec![Value::Integer(3), Value::Text("a".to_string())],
+      vec![Value::Integer(1), Value::Text("a".to_string())],
+    ];
+    let order_by = parse_order_by(order_by);
+    let column_names = vec!["id".to_string(), "name".to_string()];
+    let column_indexes = resolve_order_by(&order_by, &column_names, &mut vec![]).unwrap();
+
+    sort_rows(&mut rows, &order_by, &column_indexes).unwrap();
+    assert_eq!(rows.into_iter().map(|row| row[0].clone()).collect::<Vec<Value>>(), expected);
+  }
+
+  #[rstest]
+  #[case("LIMIT 2", vec![1, 2])]
+  #[case("LIMIT 2 OFFSET 3", vec![4])]
+  #[case("LIMIT ALL OFFSET 1", vec![2, 3, 4])]
+  #[case("LIMIT 10", vec![1, 2, 3, 4])]
+  #[case("LIMIT 0", vec![])]
+  fn test_get_limit_and_offset(
+    #[case] limit_and_offset: &str,
+    #[case] expected: Vec<i32>,
+  ) {
+    let rows = (1..=4).map(|i| vec![Value::Integer(i)]).collect::<Vec<Vec<Value>>>();
+    let dialect = SQLiteDialect {};
+    let query = format!("SELECT * FROM test {};", limit_and_offset);
+    let (limit, offset) = match Parser::parse_sql(&dialect, &query).unwrap().pop().unwrap() {
+      Statement::Query(query) => (query.limit, query.offset.map(|offset| offset.value)),
+      _ => panic!("Expected a SELECT statement"),
+    };
+
+    let (limit, offset) = get_limit_and_offset(&limit, &offset).unwrap();
+    assert_eq!(
+      rows.into_iter().skip(offset).take(limit).collect::<Vec<Vec<Value>>>(),
+      expected.into_iter().map(|i| vec![Value::Integer(i)]).collect::<Vec<Vec<Value>>>()
+    );
+  }
+
+  fn parse_order_by(order_by: &str) -> Vec<OrderByExpr> {
+    let dialect = SQLiteDialect {};
+    let query = format!("SELECT * FROM test {};", order_by);
+    match Parser::parse_sql(&dialect, &query).unwrap().pop().unwrap() {
+      Statement::Query(query) => query.order_by,
+      _ => panic!("Expected a SELECT statement"),
+    }
+  }
+}
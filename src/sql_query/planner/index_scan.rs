@@ -0,0 +1,425 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Bound;
+
+use sqlparser::ast::{Expr, BinaryOperator, UnaryOperator};
+
+use crate::error::Result;
+use crate::table::Table;
+use crate::table::column::Column;
+use crate::table::column::data_type::DataType;
+use crate::table::row::value::Value;
+use crate::sql_query::expression::{RowValues, evaluate_expression};
+use crate::sql_query::join::{JoinScope, get_conjuncts};
+
+// 在索引中查找的条件
+#[derive(Debug, PartialEq, Clone)]
+pub enum IndexCondition {
+  // column = value 以及 column IN (value, ...)
+  Lookup(Vec<Value>),
+  // column > value 以及 column BETWEEN low AND high 等，同一个 column 上的多个范围取交集
+  Range(Bound<Value>, Bound<Value>),
+  // 多列索引中前几列的等值条件，比如索引 (a, b, c) 上的 a = 1 AND b = 2
+  Prefix {
+    index_name: String,
+    column_names: Vec<String>,
+    values: Vec<Value>,
+  },
+}
+
+// 用 column 的索引找到可能满足 WHERE 条件的行，不需要遍历整张表
+// 找到的行最后还要再检查一遍完整的 WHERE 条件
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexScan {
+  // 多列索引的话是索引中的第一列
+  pub column_name: String,
+  pub condition: IndexCondition,
+}
+
+impl IndexScan {
+  // 在索引中找到满足条件的 row id，按照 row id 从小到大排列，和 Seq Scan 的顺序一样
+  pub fn get_row_ids(&self, table: &Table) -> Result<Vec<i64>> {
+    let table_column = table.get_column(self.column_name.to_string())?;
+    let mut row_ids = match &self.condition {
+      IndexCondition::Lookup(values) => {
+        let mut row_ids = vec![];
+        for value in values {
+          row_ids.extend(table.get_row_ids_of_value(table_column, value)?);
+        }
+        row_ids
+      },
+      IndexCondition::Range(lower, upper) => table
+        .get_row_ids_in_range(table_column, lower.as_ref(), upper.as_ref())?
+        .unwrap_or_default(),
+      IndexCondition::Prefix { index_name, values, .. } => table.get_row_ids_with_prefix(index_name, values)?,
+    };
+    row_ids.sort_unstable();
+    row_ids.dedup();
+    Ok(row_ids)
+  }
+
+  pub fn get_name(&self) -> &'static str {
+    match self.condition {
+      IndexCondition::Lookup(_) | IndexCondition::Prefix { .. } => "Index Lookup",
+      IndexCondition::Range(..) => "Index Range Scan",
+    }
+  }
+
+  // EXPLAIN 中显示用到的是哪个索引
+  pub fn get_index_label(&self) -> String {
+    match &self.condition {
+      IndexCondition::Prefix { index_name, column_names, .. } => {
+        format!("index {} on ({})", index_name, column_names.join(", "))
+      },
+      _ => format!("index on {}", self.column_name),
+    }
+  }
+}
+
+impl fmt::Display for IndexScan {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.condition {
+      IndexCondition::Lookup(values) => match values.as_slice() {
+        [value] => write!(f, "{} = {}", self.column_name, format_value(value)),
+        values => write!(
+          f,
+          "{} IN ({})",
+          self.column_name,
+          values.iter().map(format_value).collect::<Vec<String>>().join(", "),
+        ),
+      },
+      IndexCondition::Range(lower, upper) => {
+        let mut conditions: Vec<String> = vec![];
+        match lower {
+          Bound::Included(value) => conditions.push(format!("{} >= {}", self.column_name, format_value(value))),
+          Bound::Excluded(value) => conditions.push(format!("{} > {}", self.column_name, format_value(value))),
+          Bound::Unbounded => (),
+        }
+        match upper {
+          Bound::Included(value) => conditions.push(format!("{} <= {}", self.column_name, format_value(value))),
+          Bound::Excluded(value) => conditions.push(format!("{} < {}", self.column_name, format_value(value))),
+          Bound::Unbounded => (),
+        }
+        f.write_str(&conditions.join(" AND "))
+      },
+      IndexCondition::Prefix { column_names, values, .. } => {
+        let conditions = column_names
+          .iter()
+          .zip(values)
+          .map(|(column_name, value)| format!("{} = {}", column_name, format_value(value)))
+          .collect::<Vec<String>>();
+        f.write_str(&conditions.join(" AND "))
+      },
+    }
+  }
+}
+
+fn format_value(value: &Value) -> String {
+  match value {
+    Value::Text(text) => format!("'{}'", text),
+    value => value.to_string(),
+  }
+}
+
+// 从 WHERE 条件中找到可以用索引的部分
+// 1. 多列索引的前两列以上都有 column = value 的话用多列索引查找
+// 2. AND 连接的条件中有 column = value 或者 column IN (...) 的话直接在单列索引中查找
+// 3. 多列索引的第一列有 column = value 的话用多列索引查找
+// 4. 否则把同一个 column 上的 >、>=、<、<= 以及 BETWEEN 合并成一个范围
+// OR 以及 NOT 这种条件不会用到索引
+pub fn get_index_scan(scope: &JoinScope, table_index: usize, predicate: &Expr) -> Option<IndexScan> {
+  let conjuncts = get_conjuncts(predicate);
+  let prefix = get_prefix_index_scan(scope, table_index, &conjuncts);
+  if let Some(IndexScan { condition: IndexCondition::Prefix { values, .. }, .. }) = &prefix {
+    if values.len() >= 2 { return prefix; }
+  }
+
+  let mut range: Option<IndexScan> = None;
+  for conjunct in conjuncts {
+    let index_scan = match get_index_condition(scope, table_index, conjunct) {
+      Some(index_scan) => index_scan,
+      None => continue,
+    };
+
+    match (index_scan.condition, &mut range) {
+      (IndexCondition::Lookup(values), _) => return Some(IndexScan {
+        column_name: index_scan.column_name,
+        condition: IndexCondition::Lookup(values),
+      }),
+      (IndexCondition::Range(lower, upper), None) => range = Some(IndexScan {
+        column_name: index_scan.column_name,
+        condition: IndexCondition::Range(lower, upper),
+      }),
+      (IndexCondition::Range(lower, upper), Some(range)) => {
+        if range.column_name != index_scan.column_name { continue; }
+        if let IndexCondition::Range(range_lower, range_upper) = &mut range.condition {
+          *range_lower = get_tighter_bound(range_lower.clone(), lower, Ordering::Greater);
+          *range_upper = get_tighter_bound(range_upper.clone(), upper, Ordering::Less);
+        }
+      },
+      (IndexCondition::Prefix { .. }, _) => (),
+    }
+  }
+  prefix.or(range)
+}
+
+// 找到 column = value 的条件可以匹配的前缀最长的多列索引
+fn get_prefix_index_scan(scope: &JoinScope, table_index: usize, conjuncts: &[&Expr]) -> Option<IndexScan> {
+  let table = scope.tables[table_index].table;
+  if table.composite_indexes.is_empty() { return None; }
+
+  let mut equalities: Vec<(&str, Value)> = vec![];
+  for conjunct in conjuncts {
+    if let Expr::BinaryOp { left, op: BinaryOperator::Eq, right } = conjunct {
+      let equality = [(left, right), (right, left)]
+        .into_iter()
+        .find_map(|(column_expr, value_expr)| {
+          let column = scope.get_table_column(table_index, column_expr)?;
+          match get_constant_value(column, value_expr)? {
+            Value::Null => None,
+            value => Some((column.column_name.as_str(), value)),
+          }
+        });
+      equalities.extend(equality);
+    }
+  }
+
+  // 索引名排序之后再找，这样前缀一样长的时候每次选到的都是同一个索引
+  let mut index_names = table.composite_indexes.keys().collect::<Vec<&String>>();
+  index_names.sort();
+  let mut prefix: Option<IndexScan> = None;
+  let mut prefix_len = 0;
+  for index_name in index_names {
+    let column_names = &table.composite_indexes[index_name].column_names;
+    let values = column_names
+      .iter()
+      .map_while(|column_name| {
+        equalities
+          .iter()
+          .find(|(equality_column_name, _)| equality_column_name == column_name)
+          .map(|(_, value)| value.clone())
+      })
+      .collect::<Vec<Value>>();
+    if values.len() <= prefix_len { continue; }
+
+    prefix_len = values.len();
+    prefix = Some(IndexScan {
+      column_name: column_names[0].to_string(),
+      condition: IndexCondition::Prefix {
+        index_name: index_name.to_string(),
+        column_names: column_names.clone(),
+        values,
+      },
+    });
+  }
+  prefix
+}
+
+fn get_index_condition(scope: &JoinScope, table_index: usize, expr: &Expr) -> Option<IndexScan> {
+  let (column, condition) = match expr {
+    Expr::BinaryOp { left, op, right } => {
+      // value op column 的话把操作符反过来
+      let (column, op, value_expr) = match scope.get_indexed_column(table_index, left) {
+        Some(column) => (column, op.clone(), right),
+        None => (scope.get_indexed_column(table_index, right)?, flip_operator(op)?, left),
+      };
+      let value = match get_constant_value(column, value_expr)? {
+        Value::Null => return Some(get_empty_index_scan(column)),
+        value => value,
+      };
+      let condition = match op {
+        BinaryOperator::Eq => IndexCondition::Lookup(vec![value]),
+        BinaryOperator::Gt => IndexCondition::Range(Bound::Excluded(value), Bound::Unbounded),
+        BinaryOperator::GtEq => IndexCondition::Range(Bound::Included(value), Bound::Unbounded),
+        BinaryOperator::Lt => IndexCondition::Range(Bound::Unbounded, Bound::Excluded(value)),
+        BinaryOperator::LtEq => IndexCondition::Range(Bound::Unbounded, Bound::Included(value)),
+        _ => return None,
+      };
+      (column, condition)
+    },
+    Expr::Between { expr, negated: false, low, high } => {
+      let column = scope.get_indexed_column(table_index, expr)?;
+      match (get_constant_value(column, low)?, get_constant_value(column, high)?) {
+        (Value::Null, _) | (_, Value::Null) => return Some(get_empty_index_scan(column)),
+        (low, high) => (column, IndexCondition::Range(Bound::Included(low), Bound::Included(high))),
+      }
+    },
+    Expr::InList { expr, list, negated: false } => {
+      let column = scope.get_indexed_column(table_index, expr)?;
+      let mut values: Vec<Value> = vec![];
+      for item in list {
+        // NULL 和任何值都不相等
+        match get_constant_value(column, item)? {
+          Value::Null => (),
+          value => values.push(value),
+        }
+      }
+      (column, IndexCondition::Lookup(values))
+    },
+    // WHERE flag 以及 WHERE NOT flag
+    Expr::Identifier(_) | Expr::CompoundIdentifier(_) => {
+      (get_bool_column(scope, table_index, expr)?, IndexCondition::Lookup(vec![Value::Bool(true)]))
+    },
+    Expr::UnaryOp { op: UnaryOperator::Not, expr } => {
+      (get_bool_column(scope, table_index, expr)?, IndexCondition::Lookup(vec![Value::Bool(false)]))
+    },
+    _ => return None,
+  };
+
+  Some(IndexScan {
+    column_name: column.column_name.to_string(),
+    condition,
+  })
+}
+
+// 和 NULL 比较的结果永远不是 true，所以一行都找不到
+fn get_empty_index_scan(column: &Column) -> IndexScan {
+  IndexScan {
+    column_name: column.column_name.to_string(),
+    condition: IndexCondition::Lookup(vec![]),
+  }
+}
+
+fn get_bool_column<'a>(scope: &'a JoinScope, table_index: usize, expr: &Expr) -> Option<&'a Column> {
+  let column = scope.get_indexed_column(table_index, expr)?;
+  match column.column_datatype {
+    DataType::Bool => Some(column),
+    _ => None,
+  }
+}
+
+fn flip_operator(op: &BinaryOperator) -> Option<BinaryOperator> {
+  match op {
+    BinaryOperator::Eq => Some(BinaryOperator::Eq),
+    BinaryOperator::Gt => Some(BinaryOperator::Lt),
+    BinaryOperator::GtEq => Some(BinaryOperator::LtEq),
+    BinaryOperator::Lt => Some(BinaryOperator::Gt),
+    BinaryOperator::LtEq => Some(BinaryOperator::GtEq),
+    _ => None,
+  }
+}
+
+// 不用任何 column 就能求出值的表达式，并且类型和 column 一样
+// 类型不一样的话，比如 INTEGER 的 column 和 1.5 比较，就不用索引
+// REAL 的 column 和 INTEGER 比较时，INTEGER 可以精确地转成 REAL 的话也可以用索引
+fn get_constant_value(column: &Column, expr: &Expr) -> Option<Value> {
+  match evaluate_expression(expr, &RowValues::new()).ok()? {
+    Value::Null => Some(Value::Null),
+    Value::Integer(value) if column.column_datatype == DataType::Real => {
+      match value as f32 as i64 == i64::from(value) {
+        true => Some(Value::Real(value as f32)),
+        false => None,
+      }
+    },
+    value if value.get_data_type() == column.column_datatype => Some(value),
+    _ => None,
+  }
+}
+
+// 下界取较大的，上界取较小的，值一样的话 Excluded 更严格
+fn get_tighter_bound(a: Bound<Value>, b: Bound<Value>, tighter: Ordering) -> Bound<Value> {
+  let ordering = match (&a, &b) {
+    (Bound::Unbounded, _) => return b,
+    (_, Bound::Unbounded) => return a,
+    (
+      Bound::Included(x) | Bound::Excluded(x),
+      Bound::Included(y) | Bound::Excluded(y),
+    ) => x.compare(y).ok().flatten().unwrap_or(Ordering::Equal),
+  };
+  match ordering {
+    Ordering::Equal if matches!(a, Bound::Excluded(_)) => a,
+    Ordering::Equal => b,
+    ordering if ordering == tighter => a,
+    _ => b,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use crate::database::Database;
+  use crate::sql_query::{get_sql_ast, handle_sql_query};
+  use crate::sql_query::query::select::SelectQuery;
+
+  #[rstest]
+  #[case("id = 2", Some("id = 2"), vec![2])]
+  #[case("3 > id", Some("id < 3"), vec![1, 2])]
+  #[case("id > 1 AND id <= 3 AND id >= 2", Some("id >= 2 AND id <= 3"), vec![2, 3])]
+  #[case("id BETWEEN 2 AND 10 AND name <> 'b'", Some("id >= 2 AND id <= 10"), vec![2, 3, 4])]
+  #[case("id > 1 AND email IN ('d@x.com', NULL, 'a@x.com')", Some("email IN ('d@x.com', 'a@x.com')"), vec![1, 4])]
+  #[case("test.email = NULL", Some("email IN ()"), vec![])]
+  #[case("id > 3 AND id < 2", Some("id > 3 AND id < 2"), vec![])]
+  #[case("id = 1 OR id = 2", None, vec![])]
+  #[case("name = 'a'", None, vec![])]
+  #[case("id = 1.5", None, vec![])]
+  #[case("id NOT BETWEEN 1 AND 2", None, vec![])]
+  #[case("id = id", None, vec![])]
+  #[case("is_active", Some("is_active = true"), vec![1, 3])]
+  #[case("NOT test.is_active AND name <> 'b'", Some("is_active = false"), vec![2, 4])]
+  #[case("score > 2", Some("score > 2"), vec![2, 4])]
+  #[case("score BETWEEN 1.5 AND 2.5", Some("score >= 1.5 AND score <= 2.5"), vec![1, 2])]
+  #[case("score = 16777217", None, vec![])]
+  #[case("NOT name = 'a'", None, vec![])]
+  fn test_get_index_scan(
+    #[case] selection: &str,
+    #[case] expected: Option<&str>,
+    #[case] expected_row_ids: Vec<i64>,
+  ) {
+    let mut database = Database::new("testdb".to_string());
+    for query in [
+      "CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT, email TEXT UNIQUE, is_active BOOLEAN, score REAL UNIQUE);",
+      "INSERT INTO test (name, email, is_active, score) Values
+        ('a', 'a@x.com', true, 1.5), ('b', 'b@x.com', false, 2.5), ('c', 'c@x.com', true, NULL), ('d', 'd@x.com', false, 4);",
+      "CREATE INDEX idx_is_active ON test (is_active);",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    let select_query = SelectQuery::new(
+      &get_sql_ast(&format!("SELECT * FROM test WHERE {};", selection)).unwrap()
+    ).unwrap();
+    let scope = JoinScope::new(&database, database.get_snapshot(), "test", &None, &[]).unwrap();
+
+    let index_scan = get_index_scan(&scope, 0, &select_query.selection.unwrap());
+    assert_eq!(index_scan.as_ref().map(|index_scan| index_scan.to_string()), expected.map(String::from));
+    if let Some(index_scan) = index_scan {
+      let table = database.get_table("test".to_string()).unwrap();
+      assert_eq!(index_scan.get_row_ids(table), Ok(expected_row_ids));
+    }
+  }
+
+  #[rstest]
+  #[case("user_id = 1 AND group_id = 20", Some("user_id = 1 AND group_id = 20"), vec![2])]
+  #[case("20 = group_id AND link.user_id = 1 AND role = 'admin'", Some("user_id = 1 AND group_id = 20"), vec![2])]
+  #[case("user_id = 1", Some("user_id = 1"), vec![1, 2])]
+  #[case("user_id = 2 AND group_id > 10", Some("user_id = 2"), vec![3])]
+  #[case("group_id = 10", None, vec![])]
+  #[case("user_id > 1", None, vec![])]
+  #[case("user_id = NULL AND group_id = 10", None, vec![])]
+  fn test_get_prefix_index_scan(
+    #[case] selection: &str,
+    #[case] expected: Option<&str>,
+    #[case] expected_row_ids: Vec<i64>,
+  ) {
+    let mut database = Database::new("testdb".to_string());
+    for query in [
+      "CREATE TABLE link (user_id INTEGER, group_id INTEGER, role TEXT, PRIMARY KEY (user_id, group_id));",
+      "INSERT INTO link (user_id, group_id, role) Values (1, 10, 'member'), (1, 20, 'admin'), (2, 10, 'member');",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    let select_query = SelectQuery::new(
+      &get_sql_ast(&format!("SELECT * FROM link WHERE {};", selection)).unwrap()
+    ).unwrap();
+    let scope = JoinScope::new(&database, database.get_snapshot(), "link", &None, &[]).unwrap();
+
+    let index_scan = get_index_scan(&scope, 0, &select_query.selection.unwrap());
+    assert_eq!(index_scan.as_ref().map(|index_scan| index_scan.to_string()), expected.map(String::from));
+    if let Some(index_scan) = index_scan {
+      assert_eq!(index_scan.get_index_label(), "index link_pkey on (user_id, group_id)");
+      let table = database.get_table("link".to_string()).unwrap();
+      assert_eq!(index_scan.get_row_ids(table), Ok(expected_row_ids));
+    }
+  }
+}
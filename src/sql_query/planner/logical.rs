@@ -0,0 +1,264 @@
+use sqlparser::ast::{
+  Expr,
+  Function,
+  OrderByExpr,
+};
+
+use crate::error::Result;
+use crate::sql_query::aggregate::{has_aggregate_function, get_select_aggregate_functions};
+use crate::sql_query::join::JoinScope;
+use crate::sql_query::order_by::resolve_order_by;
+use crate::sql_query::query::select::{SelectQuery, JoinType};
+
+// 逻辑计划，描述要做什么，不关心具体怎么做
+// 每个节点的输入都是它的子节点的输出
+#[derive(Debug, PartialEq, Clone)]
+pub enum LogicalPlan {
+  // 读出一张表的所有行，alias 没有设置的话就是表名
+  Scan {
+    table_name: String,
+    alias: String,
+  },
+  // 只保留 predicate 为 true 的行
+  Filter {
+    input: Box<LogicalPlan>,
+    predicate: Expr,
+  },
+  // 对每一行求出要输出的每一列的值
+  // exprs 可能比 column_names 多，多出来的是只在 ORDER BY 中用到的表达式
+  Project {
+    input: Box<LogicalPlan>,
+    column_names: Vec<String>,
+    exprs: Vec<Expr>,
+  },
+  // right 是 JoinScope 中的第 right_index 张表
+  Join {
+    left: Box<LogicalPlan>,
+    right: Box<LogicalPlan>,
+    right_index: usize,
+    join_type: JoinType,
+    condition: Option<Expr>,
+  },
+  // 按照 group_by 分组，并且求出每一组所有聚合函数的值
+  Aggregate {
+    input: Box<LogicalPlan>,
+    group_by: Vec<Expr>,
+    functions: Vec<Function>,
+  },
+  // column_indexes 是 ORDER BY 中的每一项在 Project 的输出中是第几列
+  Sort {
+    input: Box<LogicalPlan>,
+    order_by: Vec<OrderByExpr>,
+    column_indexes: Vec<usize>,
+  },
+  Limit {
+    input: Box<LogicalPlan>,
+    limit: Option<Expr>,
+    offset: Option<Expr>,
+  },
+}
+
+impl LogicalPlan {
+  // 把 SELECT 转换成逻辑计划，同时检查用到的 column 是否存在
+  // Scan -> Join -> Filter(WHERE) -> Aggregate -> Filter(HAVING) -> Project -> Sort -> Limit
+  pub fn from_select_query(select_query: SelectQuery, scope: &JoinScope) -> Result<LogicalPlan> {
+    let SelectQuery {
+      projection,
+      selection,
+      group_by,
+      having,
+      order_by,
+      limit,
+      offset,
+      ..
+    } = select_query;
+
+    let (column_names, mut exprs) = scope.expand_projection(projection)?;
+    let column_indexes = resolve_order_by(&order_by, &column_names, &mut exprs)?;
+    for expr in exprs.iter().chain(&group_by).chain(having.iter()).chain(selection.iter()) {
+      scope.check_column_names(expr)?;
+    }
+
+    let mut plan = LogicalPlan::from_tables(scope);
+    if let Some(predicate) = selection {
+      plan = LogicalPlan::Filter {
+        input: Box::new(plan),
+        predicate,
+      };
+    }
+
+    let is_aggregate_query =
+      !group_by.is_empty() ||
+      having.is_some() ||
+      exprs.iter().any(has_aggregate_function);
+    if is_aggregate_query {
+      let functions = get_select_aggregate_functions(&exprs, &group_by, &having)?;
+      plan = LogicalPlan::Aggregate {
+        input: Box::new(plan),
+        group_by,
+        functions,
+      };
+      if let Some(predicate) = having {
+        plan = LogicalPlan::Filter {
+          input: Box::new(plan),
+          predicate,
+        };
+      }
+    }
+
+    plan = LogicalPlan::Project {
+      input: Box::new(plan),
+      column_names,
+      exprs,
+    };
+    if !order_by.is_empty() {
+      plan = LogicalPlan::Sort {
+        input: Box::new(plan),
+        order_by,
+        column_indexes,
+      };
+    }
+    if limit.is_some() || offset.is_some() {
+      plan = LogicalPlan::Limit {
+        input: Box::new(plan),
+        limit,
+        offset,
+      };
+    }
+
+    Ok(plan)
+  }
+
+  // 找到满足 WHERE 条件的行，UPDATE 和 DELETE 用到
+  pub fn from_selection(selection: Option<Expr>, scope: &JoinScope) -> Result<LogicalPlan> {
+    let plan = LogicalPlan::from_tables(scope);
+    match selection {
+      Some(predicate) => {
+        scope.check_column_names(&predicate)?;
+        Ok(LogicalPlan::Filter {
+          input: Box::new(plan),
+          predicate,
+        })
+      },
+      None => Ok(plan),
+    }
+  }
+
+  // FROM 以及 JOIN 后面的表从左到右依次 JOIN
+  fn from_tables(scope: &JoinScope) -> LogicalPlan {
+    let mut plan = LogicalPlan::Scan {
+      table_name: scope.tables[0].table.table_name.to_string(),
+      alias: scope.tables[0].alias.to_string(),
+    };
+    for right_index in 1..scope.tables.len() {
+      plan = LogicalPlan::Join {
+        left: Box::new(plan),
+        right: Box::new(LogicalPlan::Scan {
+          table_name: scope.tables[right_index].table.table_name.to_string(),
+          alias: scope.tables[right_index].alias.to_string(),
+        }),
+        right_index,
+        join_type: scope.join_types[right_index - 1].clone(),
+        condition: scope.conditions[right_index - 1].clone(),
+      };
+    }
+    plan
+  }
+
+  // 输出的列名，只有 Project 以及它上面的节点才有
+  pub fn get_column_names(&self) -> Vec<String> {
+    match self {
+      LogicalPlan::Project { column_names, .. } => column_names.clone(),
+      LogicalPlan::Sort { input, .. } |
+      LogicalPlan::Limit { input, .. } => input.get_column_names(),
+      _ => vec![],
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use crate::database::Database;
+  use crate::sql_query::{get_sql_ast, handle_sql_query};
+
+  #[rstest]
+  #[case("SELECT * FROM users;", "Project(Scan(users))")]
+  #[case(
+    "SELECT name FROM users WHERE id > 1 ORDER BY id LIMIT 1;",
+    "Limit(Sort(Project(Filter(Scan(users)))))",
+  )]
+  #[case(
+    "SELECT name, COUNT(*) FROM users GROUP BY name HAVING COUNT(*) > 1;",
+    "Project(Filter(Aggregate(Scan(users))))",
+  )]
+  #[case(
+    "SELECT u.name FROM users u JOIN orders o ON u.id = o.user_id CROSS JOIN users x;",
+    "Project(Join(Join(Scan(u), Scan(o)), Scan(x)))",
+  )]
+  fn test_logical_plan_from_select_query(
+    #[case] query: &str,
+    #[case] expected: &str,
+  ) {
+    let database = create_database();
+    let select_query = SelectQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+    let scope = JoinScope::new(
+      &database,
+      database.get_snapshot(),
+      &select_query.table_name,
+      &select_query.table_alias,
+      &select_query.joins,
+    ).unwrap();
+
+    assert_eq!(
+      format_plan(&LogicalPlan::from_select_query(select_query, &scope).unwrap()),
+      expected
+    );
+  }
+
+  #[rstest]
+  #[case("SELECT not_exist FROM users;")]
+  #[case("SELECT name FROM users WHERE not_exist = 1;")]
+  #[case("SELECT name, COUNT(*) FROM users;")]
+  #[case("SELECT id FROM users ORDER BY 2;")]
+  fn test_logical_plan_from_select_query_error(#[case] query: &str) {
+    let database = create_database();
+    let select_query = SelectQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+    let scope = JoinScope::new(
+      &database,
+      database.get_snapshot(),
+      &select_query.table_name,
+      &select_query.table_alias,
+      &select_query.joins,
+    ).unwrap();
+
+    assert!(LogicalPlan::from_select_query(select_query, &scope).is_err());
+  }
+
+  fn format_plan(plan: &LogicalPlan) -> String {
+    match plan {
+      LogicalPlan::Scan { alias, .. } => format!("Scan({})", alias),
+      LogicalPlan::Filter { input, .. } => format!("Filter({})", format_plan(input)),
+      LogicalPlan::Project { input, .. } => format!("Project({})", format_plan(input)),
+      LogicalPlan::Join { left, right, .. } => {
+        format!("Join({}, {})", format_plan(left), format_plan(right))
+      },
+      LogicalPlan::Aggregate { input, .. } => format!("Aggregate({})", format_plan(input)),
+      LogicalPlan::Sort { input, .. } => format!("Sort({})", format_plan(input)),
+      LogicalPlan::Limit { input, .. } => format!("Limit({})", format_plan(input)),
+    }
+  }
+
+  fn create_database() -> Database {
+    let mut database = Database::new("testdb".to_string());
+    for query in [
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+      "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER);",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    database
+  }
+}
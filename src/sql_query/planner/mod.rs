@@ -0,0 +1,271 @@
+pub mod logical;
+pub mod physical;
+pub mod index_scan;
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use sqlparser::ast::{Statement, Expr};
+
+use crate::error::{Result, NollaDBError};
+use crate::database::{Database, Settings};
+use crate::database::session::Snapshot;
+use crate::table::Table;
+use crate::table::row::value::Value;
+use crate::sql_query::join::JoinScope;
+use crate::sql_query::query::select::SelectQuery;
+use crate::sql_query::query::update::UpdateQuery;
+use crate::sql_query::query::delete::DeleteQuery;
+use crate::sql_query::query::explain::ExplainQuery;
+use crate::sql_query::result_set::ResultSet;
+
+use logical::LogicalPlan;
+use physical::{create_physical_plan, explain_operator};
+
+// 执行 SELECT，返回结果集
+// SelectQuery -> 逻辑计划 -> 物理算子，再从最上面的算子中一行一行地拉取数据
+// 读到的是 snapshot 中的数据
+pub fn execute_select_query(database: &Database, snapshot: Snapshot, select_query: SelectQuery) -> Result<ResultSet> {
+  execute_select_query_in_tables(&database.get_tables(), &database.settings, snapshot, select_query)
+}
+
+// 只在 tables 中找查询用到的表，SharedDatabase 只需要锁住这些表
+pub fn execute_select_query_in_tables(
+  tables: &HashMap<&str, &Table>,
+  settings: &Settings,
+  snapshot: Snapshot,
+  select_query: SelectQuery,
+) -> Result<ResultSet> {
+  let scope = JoinScope::from_tables(
+    tables,
+    snapshot,
+    &select_query.table_name,
+    &select_query.table_alias,
+    &select_query.joins,
+  )?;
+  let plan = LogicalPlan::from_select_query(select_query, &scope)?;
+  let column_names = plan.get_column_names();
+
+  let mut operator = create_physical_plan(&plan, &scope, settings, false)?;
+  let mut rows = vec![];
+  while let Some(mut tuple) = operator.next()? {
+    // 去掉只在 ORDER BY 中用到的列
+    tuple.values.truncate(column_names.len());
+    rows.push(tuple.values);
+  }
+
+  Ok(ResultSet::new(column_names, rows))
+}
+
+// 找到一张表中满足 WHERE 条件的所有 row id，UPDATE 和 DELETE 用到
+pub fn select_row_ids(
+  database: &Database,
+  snapshot: Snapshot,
+  table_name: &str,
+  selection: Option<Expr>,
+) -> Result<Vec<i64>> {
+  let scope = JoinScope::new(database, snapshot, table_name, &None, &[])?;
+  let plan = LogicalPlan::from_selection(selection, &scope)?;
+
+  let mut operator = create_physical_plan(&plan, &scope, &database.settings, false)?;
+  let mut row_ids = vec![];
+  while let Some(tuple) = operator.next()? {
+    row_ids.extend(tuple.row_id);
+  }
+
+  Ok(row_ids)
+}
+
+// 显示语句的执行计划，每一行是一个算子
+// UPDATE 和 DELETE 显示的是找到要修改的行的执行计划
+// EXPLAIN ANALYZE 会真正执行 SELECT，并且显示每个算子输出的行数以及用时
+pub fn explain_statement(database: &Database, snapshot: Snapshot, explain_query: ExplainQuery) -> Result<ResultSet> {
+  let ExplainQuery { analyze, statement } = explain_query;
+  if analyze && !matches!(statement, Statement::Query(_)) {
+    return Err(NollaDBError::ToBeImplemented(
+      "EXPLAIN ANALYZE only supports SELECT now".to_string()
+    ));
+  }
+
+  let (table_name, select_query, selection) = match &statement {
+    Statement::Query(_) => {
+      let select_query = SelectQuery::new(&statement)?;
+      (select_query.table_name.to_string(), Some(select_query), None)
+    },
+    Statement::Update { .. } => {
+      let update_query = UpdateQuery::new(&statement)?;
+      (update_query.table_name, None, update_query.selection)
+    },
+    Statement::Delete { .. } => {
+      let delete_query = DeleteQuery::new(&statement)?;
+      (delete_query.table_name, None, delete_query.selection)
+    },
+    _ => return Err(NollaDBError::ToBeImplemented(
+      "EXPLAIN for this kind of SQL statement will be implemented soon".to_string()
+    )),
+  };
+
+  let scope = match &select_query {
+    Some(select_query) => JoinScope::new(
+      database,
+      snapshot,
+      &select_query.table_name,
+      &select_query.table_alias,
+      &select_query.joins,
+    )?,
+    None => JoinScope::new(database, snapshot, &table_name, &None, &[])?,
+  };
+  let plan = match select_query {
+    Some(select_query) => LogicalPlan::from_select_query(select_query, &scope)?,
+    None => LogicalPlan::from_selection(selection, &scope)?,
+  };
+
+  let start = Instant::now();
+  let mut operator = create_physical_plan(&plan, &scope, &database.settings, analyze)?;
+  if analyze {
+    while operator.next()?.is_some() {}
+  }
+
+  let mut lines = vec![];
+  explain_operator(&*operator, 0, &mut lines);
+  if analyze {
+    lines.push(format!("Execution time: {:.3}ms", start.elapsed().as_secs_f64() * 1000.0));
+  }
+
+  Ok(ResultSet::new(
+    vec!["QUERY PLAN".to_string()],
+    lines.into_iter().map(|line| vec![Value::Text(line)]).collect(),
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use crate::sql_query::{get_sql_ast, handle_sql_query};
+
+  #[rstest]
+  #[case(
+    "EXPLAIN SELECT name FROM users ORDER BY id DESC LIMIT 1;",
+    vec![
+      "Limit: 1, offset: 0",
+      "  -> Project: name, id",
+      "    -> Index Scan on users using index on id DESC",
+    ],
+  )]
+  #[case(
+    "EXPLAIN SELECT name FROM users WHERE id > 1 ORDER BY id DESC;",
+    vec![
+      "Project: name, id",
+      "  -> Index Range Scan on users using index on id, index condition: id > 1, order by index on id DESC, filter: id > 1",
+    ],
+  )]
+  #[case(
+    "EXPLAIN SELECT * FROM users WHERE name = 'a' AND id IN (1, 3);",
+    vec![
+      "Project: users.id, users.name",
+      "  -> Index Lookup on users using index on id, index condition: id IN (1, 3), filter: name = 'a' AND id IN (1, 3)",
+    ],
+  )]
+  #[case(
+    "EXPLAIN SELECT o.item FROM orders o JOIN users u ON u.id = o.user_id ORDER BY o.item;",
+    vec![
+      "Sort: o.item",
+      "  -> Project: o.item",
+      "    -> Index Nested Loop Join (INNER) with users AS u using index on id, condition: u.id = o.user_id",
+      "      -> Seq Scan on orders AS o",
+    ],
+  )]
+  #[case(
+    "EXPLAIN SELECT u.name, COUNT(*) FROM users u LEFT JOIN orders o ON o.user_id = u.id GROUP BY u.name;",
+    vec![
+      "Project: u.name, COUNT(*)",
+      "  -> Aggregate: COUNT(*), group by: u.name",
+      "    -> Hash Join (LEFT) with orders AS o, condition: o.user_id = u.id",
+      "      -> Seq Scan on users AS u",
+    ],
+  )]
+  #[case(
+    "EXPLAIN DELETE FROM orders WHERE item = 'kiwi';",
+    vec!["Seq Scan on orders, filter: item = 'kiwi'"],
+  )]
+  fn test_explain_statement(
+    #[case] query: &str,
+    #[case] expected: Vec<&str>,
+  ) {
+    let database = create_database();
+    let explain_query = ExplainQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+
+    assert_eq!(get_lines(explain_statement(&database, database.get_snapshot(), explain_query).unwrap()), expected);
+  }
+
+  #[rstest]
+  #[case(
+    "EXPLAIN ANALYZE SELECT name FROM users WHERE name <> 'b' LIMIT 1;",
+    vec![
+      "Limit: 1, offset: 0 (rows=1",
+      "  -> Project: name (rows=1",
+      "    -> Seq Scan on users, filter: name <> 'b' (rows=1",
+      "Execution time",
+    ],
+  )]
+  #[case(
+    "EXPLAIN ANALYZE SELECT u.name, o.item FROM users u CROSS JOIN orders o;",
+    vec![
+      "Project: u.name, o.item (rows=6",
+      "  -> Nested Loop Join (CROSS) with orders AS o (rows=6",
+      "    -> Seq Scan on users AS u (rows=3",
+      "Execution time",
+    ],
+  )]
+  fn test_explain_analyze_statement(
+    #[case] query: &str,
+    #[case] expected: Vec<&str>,
+  ) {
+    let database = create_database();
+    let explain_query = ExplainQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+
+    // 用时每次都不一样，只比较前面的部分
+    let lines = get_lines(explain_statement(&database, database.get_snapshot(), explain_query).unwrap())
+      .into_iter()
+      .map(|line| match line.starts_with("Execution time") {
+        true => "Execution time".to_string(),
+        false => line.split(", time=").next().unwrap().to_string(),
+      })
+      .collect::<Vec<String>>();
+    assert_eq!(lines, expected);
+  }
+
+  #[rstest]
+  #[case("EXPLAIN SELECT * FROM not_exist;")]
+  #[case("EXPLAIN SELECT not_exist FROM users;")]
+  #[case("EXPLAIN ANALYZE DELETE FROM users;")]
+  #[case("EXPLAIN CREATE TABLE test (id INTEGER);")]
+  fn test_explain_statement_error(#[case] query: &str) {
+    let database = create_database();
+    let explain_query = ExplainQuery::new(&get_sql_ast(query).unwrap()).unwrap();
+
+    assert!(explain_statement(&database, database.get_snapshot(), explain_query).is_err());
+  }
+
+  fn get_lines(result_set: ResultSet) -> Vec<String> {
+    result_set.rows
+      .iter()
+      .map(|row| row[0].to_string())
+      .collect()
+  }
+
+  fn create_database() -> Database {
+    let mut database = Database::new("testdb".to_string());
+    for query in [
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+      "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, item TEXT);",
+      "INSERT INTO users (name) Values ('a'), ('b'), ('c');",
+      "INSERT INTO orders (user_id, item) Values (1, 'apple'), (2, 'kiwi');",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    database
+  }
+}
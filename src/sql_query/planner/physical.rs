@@ -0,0 +1,1005 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+
+use sqlparser::ast::{
+  Expr,
+  Function,
+  OrderByExpr,
+};
+
+use crate::error::{Result, NollaDBError};
+use crate::database::Settings;
+use crate::table::row::value::Value;
+use crate::sql_query::aggregate::aggregate_rows;
+use crate::sql_query::expression::{RowValues, evaluate_expression, is_row_matched};
+use crate::sql_query::external_sort::{ExternalSorter, SortedRows};
+use crate::sql_query::spill_file::SpillFile;
+use crate::sql_query::join::{JoinScope, JoinTable, JoinStrategy, get_hash_key, lookup_index};
+use crate::sql_query::order_by::{get_limit_and_offset, is_asc, is_nulls_first};
+use crate::sql_query::query::select::JoinType;
+use crate::sql_query::planner::logical::LogicalPlan;
+use crate::sql_query::planner::index_scan::{IndexScan, get_index_scan};
+
+// Scan 每次从表中取出的行数
+const SCAN_BATCH_SIZE: usize = 1024;
+
+// hash join 最多分成多少个分区，每个分区左右两边各有一个临时文件
+const MAX_NUMBER_OF_PARTITIONS: usize = 64;
+
+// 算子之间传递的一行数据
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Tuple {
+  // 直接来自 Scan 的话是这一行的 row id，UPDATE 和 DELETE 用它找到要修改的行
+  pub row_id: Option<i64>,
+  // 按照 column name 对表达式求值时用到的数据
+  pub row: RowValues,
+  // Project 之后按照输出顺序排列的每一列的值
+  pub values: Vec<Value>,
+}
+
+// Volcano 模型中的物理算子
+// 每次调用 next 从子算子中拉取数据，返回下一行，没有数据了就返回 None
+pub trait Operator {
+  fn next(&mut self) -> Result<Option<Tuple>>;
+  // EXPLAIN 中显示的这个算子的描述
+  fn describe(&self) -> String;
+  // EXPLAIN 中显示在这个算子下一层的子算子
+  fn children(&self) -> Vec<&dyn Operator>;
+  // EXPLAIN ANALYZE 中统计的输出行数以及用时，只有 AnalyzeOperator 才有
+  fn get_statistics(&self) -> Option<(usize, Duration)> {
+    None
+  }
+}
+
+// 把逻辑计划转换成物理算子
+// 1. 直接在 Scan 上面的 Filter 会被下推到 Scan 中
+// 2. 只按照一个有完整索引的 column 排序的话，Scan 直接按照索引的顺序读出 row id，不需要 Sort
+// 3. Join 的连接方式见 JoinScope::get_join_strategy
+// 4. analyze 为 true 的话，每个算子都会被 AnalyzeOperator 包起来统计行数以及用时
+pub fn create_physical_plan<'a>(
+  plan: &LogicalPlan,
+  scope: &'a JoinScope<'a>,
+  settings: &Settings,
+  analyze: bool,
+) -> Result<Box<dyn Operator + 'a>> {
+  let operator = create_operator(plan, scope, settings, analyze)?;
+  Ok(analyze_operator(operator, analyze))
+}
+
+fn analyze_operator<'a>(operator: Box<dyn Operator + 'a>, analyze: bool) -> Box<dyn Operator + 'a> {
+  match analyze {
+    true => Box::new(AnalyzeOperator {
+      input: operator,
+      number_of_rows: 0,
+      elapsed: Duration::ZERO,
+    }),
+    false => operator,
+  }
+}
+
+fn create_operator<'a>(
+  plan: &LogicalPlan,
+  scope: &'a JoinScope<'a>,
+  settings: &Settings,
+  analyze: bool,
+) -> Result<Box<dyn Operator + 'a>> {
+  match plan {
+    LogicalPlan::Scan { alias, .. } => Ok(Box::new(ScanOperator::new(scope, alias, None, None)?)),
+    LogicalPlan::Filter { input, predicate } => match &**input {
+      LogicalPlan::Scan { alias, .. } => {
+        Ok(Box::new(ScanOperator::new(scope, alias, Some(predicate.clone()), None)?))
+      },
+      input => Ok(Box::new(FilterOperator {
+        input: create_physical_plan(input, scope, settings, analyze)?,
+        predicate: predicate.clone(),
+      })),
+    },
+    LogicalPlan::Project { input, exprs, .. } => Ok(Box::new(ProjectOperator {
+      input: create_physical_plan(input, scope, settings, analyze)?,
+      exprs: exprs.clone(),
+    })),
+    LogicalPlan::Join { left, right_index, join_type, condition, .. } => {
+      Ok(Box::new(JoinOperator::new(
+        create_physical_plan(left, scope, settings, analyze)?,
+        scope,
+        *right_index,
+        join_type.clone(),
+        condition.clone(),
+        settings.memory_budget,
+      )?))
+    },
+    LogicalPlan::Aggregate { input, group_by, functions } => Ok(Box::new(AggregateOperator {
+      input: create_physical_plan(input, scope, settings, analyze)?,
+      group_by: group_by.clone(),
+      functions: functions.clone(),
+      output: None,
+    })),
+    LogicalPlan::Sort { input, order_by, column_indexes } => {
+      if let Some(operator) = create_index_ordered_plan(input, order_by, column_indexes, scope, analyze)? {
+        return Ok(operator);
+      }
+      Ok(Box::new(SortOperator {
+        input: create_physical_plan(input, scope, settings, analyze)?,
+        order_by: order_by.clone(),
+        column_indexes: column_indexes.clone(),
+        memory_budget: settings.memory_budget,
+        output: None,
+      }))
+    },
+    LogicalPlan::Limit { input, limit, offset } => {
+      let (limit, offset) = get_limit_and_offset(limit, offset)?;
+      Ok(Box::new(LimitOperator {
+        input: create_physical_plan(input, scope, settings, analyze)?,
+        limit,
+        offset,
+        number_of_skipped_rows: 0,
+        number_of_returned_rows: 0,
+      }))
+    },
+  }
+}
+
+// Sort(Project(Scan)) 或者 Sort(Project(Filter(Scan)))
+// 并且只按照一个有完整索引的 column 排序的话，直接按照索引的顺序 Scan
+fn create_index_ordered_plan<'a>(
+  input: &LogicalPlan,
+  order_by: &[OrderByExpr],
+  column_indexes: &[usize],
+  scope: &'a JoinScope<'a>,
+  analyze: bool,
+) -> Result<Option<Box<dyn Operator + 'a>>> {
+  let (project_input, exprs) = match input {
+    LogicalPlan::Project { input, exprs, .. } => (input, exprs),
+    _ => return Ok(None),
+  };
+  let (alias, predicate) = match &**project_input {
+    LogicalPlan::Scan { alias, .. } => (alias, None),
+    LogicalPlan::Filter { input, predicate } => match &**input {
+      LogicalPlan::Scan { alias, .. } => (alias, Some(predicate.clone())),
+      _ => return Ok(None),
+    },
+    _ => return Ok(None),
+  };
+  let (order_by_expr, column_index) = match (order_by, column_indexes) {
+    ([order_by_expr], [column_index]) => (order_by_expr, column_index),
+    _ => return Ok(None),
+  };
+  let column_name = match &exprs[*column_index] {
+    Expr::Identifier(ident) => ident.value.to_string(),
+    Expr::CompoundIdentifier(idents) => match idents.last() {
+      Some(ident) => ident.value.to_string(),
+      None => return Ok(None),
+    },
+    _ => return Ok(None),
+  };
+
+  // 快照中有看不到最新版本的行的话，索引中的顺序和快照中的值对不上
+  let table_index = get_table_index(scope, alias)?;
+  if !scope.tables[table_index].invisible_rows.is_empty() {
+    return Ok(None);
+  }
+  let table = scope.tables[table_index].table;
+  let table_column = match table.get_column(column_name.to_string()) {
+    Ok(table_column) => table_column,
+    Err(_) => return Ok(None),
+  };
+  if !table.has_usable_index(table_column) {
+    return Ok(None);
+  }
+
+  let scan = ScanOperator::new(
+    scope,
+    alias,
+    predicate,
+    Some((column_name, is_asc(order_by_expr), is_nulls_first(order_by_expr))),
+  )?;
+  let project = ProjectOperator {
+    input: analyze_operator(Box::new(scan), analyze),
+    exprs: exprs.clone(),
+  };
+  Ok(Some(analyze_operator(Box::new(project), analyze)))
+}
+
+fn get_table_index(scope: &JoinScope, alias: &str) -> Result<usize> {
+  scope.get_table_index(alias).ok_or_else(|| {
+    NollaDBError::Internal(format!("Table '{}' is not in the query", alias))
+  })
+}
+
+// 表名和 alias 不一样的话显示成 "orders AS o"
+fn get_table_label(scope: &JoinScope, table_index: usize) -> String {
+  let JoinTable { alias, table, .. } = &scope.tables[table_index];
+  match alias == &table.table_name {
+    true => alias.to_string(),
+    false => format!("{} AS {}", table.table_name, alias),
+  }
+}
+
+fn get_join_type_name(join_type: &JoinType) -> &'static str {
+  match join_type {
+    JoinType::Inner => "INNER",
+    JoinType::Left => "LEFT",
+    JoinType::Right => "RIGHT",
+    JoinType::Cross => "CROSS",
+  }
+}
+
+fn join_exprs<T: std::fmt::Display>(exprs: &[T]) -> String {
+  exprs
+    .iter()
+    .map(|expr| expr.to_string())
+    .collect::<Vec<String>>()
+    .join(", ")
+}
+
+// 把算子树转换成 EXPLAIN 输出的每一行，子算子比父算子多缩进一层
+pub fn explain_operator(operator: &dyn Operator, depth: usize, lines: &mut Vec<String>) {
+  let mut line = match depth {
+    0 => operator.describe(),
+    _ => format!("{}-> {}", "  ".repeat(depth), operator.describe()),
+  };
+  if let Some((number_of_rows, elapsed)) = operator.get_statistics() {
+    line.push_str(&format!(
+      " (rows={}, time={:.3}ms)",
+      number_of_rows,
+      elapsed.as_secs_f64() * 1000.0,
+    ));
+  }
+  lines.push(line);
+
+  for child in operator.children() {
+    explain_operator(child, depth + 1, lines);
+  }
+}
+
+// 读出一张表的行，第一次调用 next 的时候才开始读
+// predicate 是下推到 Scan 中的 WHERE 条件，其中能用索引的部分放在 index_scan 中
+// order 是按照索引排序的 column name、是否是 ASC 以及 NULL 是否在最前面
+// 不用索引的时候按照 row id 的顺序一批一批地读，文件中的行只有读到这一批的时候才会从文件中读出来
+pub struct ScanOperator<'a> {
+  scope: &'a JoinScope<'a>,
+  table_index: usize,
+  predicate: Option<Expr>,
+  index_scan: Option<IndexScan>,
+  order: Option<(String, bool, bool)>,
+  position: ScanPosition,
+  buffer: VecDeque<Tuple>,
+}
+
+// ScanOperator 读到了哪里
+enum ScanPosition {
+  NotStarted,
+  // 用索引找到的 row id 中还没有读的部分
+  RowIds(std::vec::IntoIter<i64>),
+  // 按照 row id 的顺序读，下一批从大于这个 row id 的行开始
+  After(Option<i64>),
+  Finished,
+}
+
+impl<'a> ScanOperator<'a> {
+  pub fn new(
+    scope: &'a JoinScope<'a>,
+    alias: &str,
+    predicate: Option<Expr>,
+    order: Option<(String, bool, bool)>,
+  ) -> Result<Self> {
+    let table_index = get_table_index(scope, alias)?;
+    let index_scan = predicate
+      .as_ref()
+      .and_then(|predicate| get_index_scan(scope, table_index, predicate));
+    Ok(ScanOperator {
+      scope,
+      table_index,
+      predicate,
+      index_scan,
+      order,
+      position: ScanPosition::NotStarted,
+      buffer: VecDeque::new(),
+    })
+  }
+
+  fn get_row_ids(&self) -> Result<Vec<i64>> {
+    let join_table = &self.scope.tables[self.table_index];
+    let row_ids = match &self.index_scan {
+      Some(index_scan) => join_table.filter_row_ids(index_scan.get_row_ids(join_table.table)?, &self.predicate)?,
+      None => join_table.select_row_ids(&self.predicate)?,
+    };
+    match &self.order {
+      Some((column_name, asc, nulls_first)) => {
+        match join_table.table.sort_row_ids_by_index(&row_ids, column_name, *asc, *nulls_first)? {
+          Some(sorted_row_ids) => Ok(sorted_row_ids),
+          None => Err(NollaDBError::Internal(
+            format!("Column '{}' does not have a usable index", column_name)
+          )),
+        }
+      },
+      None => Ok(row_ids),
+    }
+  }
+
+  // 用索引找到的 row id 按列一次取出一批行
+  fn read_rows(&mut self, row_ids: Vec<i64>) -> Result<()> {
+    let join_table = &self.scope.tables[self.table_index];
+    let column_names = join_table.table.table_columns
+      .iter()
+      .map(|table_column| table_column.column_name.to_string())
+      .collect::<Vec<String>>();
+    for (row_id, values) in row_ids.iter().zip(join_table.select_rows(&row_ids, &column_names)?) {
+      let row = column_names.iter().cloned().zip(values).collect::<RowValues>();
+      self.buffer.push_back(Tuple {
+        row_id: Some(*row_id),
+        row: self.scope.to_join_row(self.table_index, row),
+        values: vec![],
+      });
+    }
+    Ok(())
+  }
+
+  // 按照 row id 的顺序读出一批行，留下满足 predicate 的行，返回下一批从哪里开始
+  fn scan_rows(&mut self, after: Option<i64>) -> Result<Option<i64>> {
+    let join_table = &self.scope.tables[self.table_index];
+    let (rows, next) = join_table.scan_rows(after, SCAN_BATCH_SIZE)?;
+    for (row_id, values) in rows {
+      let row = join_table.table.table_columns
+        .iter()
+        .map(|table_column| table_column.column_name.to_string())
+        .zip(values)
+        .collect::<RowValues>();
+      if let Some(predicate) = &self.predicate {
+        if !is_row_matched(predicate, &row)? { continue; }
+      }
+      self.buffer.push_back(Tuple {
+        row_id: Some(row_id),
+        row: self.scope.to_join_row(self.table_index, row),
+        values: vec![],
+      });
+    }
+    Ok(next)
+  }
+}
+
+impl<'a> Operator for ScanOperator<'a> {
+  fn next(&mut self) -> Result<Option<Tuple>> {
+    if let ScanPosition::NotStarted = self.position {
+      self.position = match (&self.index_scan, &self.order) {
+        (None, None) => ScanPosition::After(None),
+        _ => ScanPosition::RowIds(self.get_row_ids()?.into_iter()),
+      };
+    }
+
+    // 一批中的行可能都不满足 predicate，直到读出一行或者读完为止
+    while self.buffer.is_empty() {
+      match std::mem::replace(&mut self.position, ScanPosition::Finished) {
+        ScanPosition::RowIds(mut row_ids) => {
+          let batch = row_ids.by_ref().take(SCAN_BATCH_SIZE).collect::<Vec<i64>>();
+          if batch.is_empty() { break; }
+          self.position = ScanPosition::RowIds(row_ids);
+          self.read_rows(batch)?;
+        },
+        ScanPosition::After(after) => {
+          if let Some(next) = self.scan_rows(after)? {
+            self.position = ScanPosition::After(Some(next));
+          }
+        },
+        ScanPosition::NotStarted | ScanPosition::Finished => break,
+      }
+    }
+
+    Ok(self.buffer.pop_front())
+  }
+
+  fn describe(&self) -> String {
+    let table_label = get_table_label(self.scope, self.table_index);
+    let mut description = match (&self.index_scan, &self.order) {
+      (Some(index_scan), _) => format!(
+        "{} on {} using {}",
+        index_scan.get_name(),
+        table_label,
+        index_scan.get_index_label(),
+      ),
+      (None, Some((column_name, asc, _))) => format!(
+        "Index Scan on {} using index on {} {}",
+        table_label,
+        column_name,
+        if *asc { "ASC" } else { "DESC" },
+      ),
+      (None, None) => format!("Seq Scan on {}", table_label),
+    };
+    if let Some(index_scan) = &self.index_scan {
+      description.push_str(&format!(", index condition: {}", index_scan));
+      if let Some((column_name, asc, _)) = &self.order {
+        description.push_str(&format!(
+          ", order by index on {} {}",
+          column_name,
+          if *asc { "ASC" } else { "DESC" },
+        ));
+      }
+    }
+    if let Some(predicate) = &self.predicate {
+      description.push_str(&format!(", filter: {}", predicate));
+    }
+    description
+  }
+
+  fn children(&self) -> Vec<&dyn Operator> {
+    vec![]
+  }
+}
+
+pub struct FilterOperator<'a> {
+  input: Box<dyn Operator + 'a>,
+  predicate: Expr,
+}
+
+impl<'a> Operator for FilterOperator<'a> {
+  fn next(&mut self) -> Result<Option<Tuple>> {
+    while let Some(tuple) = self.input.next()? {
+      if is_row_matched(&self.predicate, &tuple.row)? {
+        return Ok(Some(tuple));
+      }
+    }
+    Ok(None)
+  }
+
+  fn describe(&self) -> String {
+    format!("Filter: {}", self.predicate)
+  }
+
+  fn children(&self) -> Vec<&dyn Operator> {
+    vec![&*self.input]
+  }
+}
+
+pub struct ProjectOperator<'a> {
+  input: Box<dyn Operator + 'a>,
+  exprs: Vec<Expr>,
+}
+
+impl<'a> Operator for ProjectOperator<'a> {
+  fn next(&mut self) -> Result<Option<Tuple>> {
+    match self.input.next()? {
+      Some(mut tuple) => {
+        tuple.values = self.exprs
+          .iter()
+          .map(|expr| evaluate_expression(expr, &tuple.row))
+          .collect::<Result<Vec<Value>>>()?;
+        Ok(Some(tuple))
+      },
+      None => Ok(None),
+    }
+  }
+
+  fn describe(&self) -> String {
+    format!("Project: {}", join_exprs(&self.exprs))
+  }
+
+  fn children(&self) -> Vec<&dyn Operator> {
+    vec![&*self.input]
+  }
+}
+
+// 对左边的每一行，找到右边表中满足 ON 条件的行
+// LEFT JOIN 中左边没有匹配的行，右边都是 NULL
+// RIGHT JOIN 中右边没有匹配的行，在左边所有的行都处理完之后输出，左边都是 NULL
+// 右边的行在用到的时候才从表中取，只有 nested loop join 会在内存预算以内缓存右边的行
+pub struct JoinOperator<'a> {
+  left: Box<dyn Operator + 'a>,
+  scope: &'a JoinScope<'a>,
+  right_index: usize,
+  join_type: JoinType,
+  condition: Option<Expr>,
+  join_strategy: JoinStrategy<'a>,
+  right_row_ids: Vec<i64>,
+  // nested loop join 中缓存的右边表的行，没有缓存的行每次都从表中取
+  right_rows: HashMap<i64, RowValues>,
+  // hash join 中右边的表建立的 hash 表，只存放 row id，key 相同的 row id 按照原来的顺序存放
+  // 分区的话这里只是当前分区的 hash 表
+  hash_table: HashMap<String, Vec<i64>>,
+  // hash 表超过内存预算的时候按照 hash key 分区
+  partitions: Option<HashPartitions>,
+  matched_right_row_ids: HashSet<i64>,
+  output: VecDeque<Tuple>,
+  is_left_finished: bool,
+}
+
+// 估算 JOIN 中一行数据占用的内存大小，单位是字节
+fn get_row_values_size(row: &RowValues) -> usize {
+  row
+    .iter()
+    .map(|(key, value)| size_of::<String>() + key.capacity() + size_of::<Value>() + match value {
+      Value::Text(text) => text.capacity(),
+      _ => 0,
+    })
+    .sum()
+}
+
+// grace hash join
+// 右边表的 (hash key, row id) 以及左边的行都按照 hash key 写到各自分区的临时文件中
+// 左边的行都写完之后，每次只把一个分区的右边读到 hash 表中，再用这个分区左边的行去匹配
+// key 一样的行一定在同一个分区中，hash key 分布不均匀的话一个分区的 hash 表还是可能超过内存预算
+struct HashPartitions {
+  right: Vec<SpillFile>,
+  left: Vec<SpillFile>,
+  // 正在匹配的分区，None 表示左边的行还没有分完
+  current: Option<usize>,
+}
+
+impl HashPartitions {
+  fn new(number_of_partitions: usize) -> Result<Self> {
+    Ok(HashPartitions {
+      right: (0..number_of_partitions)
+        .map(|_| SpillFile::new("join"))
+        .collect::<Result<Vec<SpillFile>>>()?,
+      left: (0..number_of_partitions)
+        .map(|_| SpillFile::new("join"))
+        .collect::<Result<Vec<SpillFile>>>()?,
+      current: None,
+    })
+  }
+
+  fn get_partition_index(&self, hash_key: &str) -> usize {
+    let mut hasher = DefaultHasher::new();
+    hash_key.hash(&mut hasher);
+    (hasher.finish() % self.right.len() as u64) as usize
+  }
+}
+
+impl<'a> JoinOperator<'a> {
+  pub fn new(
+    left: Box<dyn Operator + 'a>,
+    scope: &'a JoinScope<'a>,
+    right_index: usize,
+    join_type: JoinType,
+    condition: Option<Expr>,
+    memory_budget: usize,
+  ) -> Result<Self> {
+    let right_row_ids = scope.tables[right_index].get_row_ids()?;
+    let join_strategy = scope.get_join_strategy(right_index);
+
+    let mut right_rows: HashMap<i64, RowValues> = HashMap::new();
+    if let JoinStrategy::NestedLoop = join_strategy {
+      let mut right_rows_size = 0;
+      for row_id in &right_row_ids {
+        let right_row = scope.get_row(right_index, row_id)?;
+        right_rows_size += get_row_values_size(&right_row);
+        if right_rows_size > memory_budget { break; }
+        right_rows.insert(*row_id, right_row);
+      }
+    }
+
+    let mut hash_table: HashMap<String, Vec<i64>> = HashMap::new();
+    let mut partitions: Option<HashPartitions> = None;
+    if let JoinStrategy::HashJoin(keys) = &join_strategy {
+      let mut hash_table_size = 0;
+      for (i, row_id) in right_row_ids.iter().enumerate() {
+        let right_row = scope.get_row(right_index, row_id)?;
+        let values = keys
+          .iter()
+          .map(|(_, build_expr)| evaluate_expression(build_expr, &right_row))
+          .collect::<Result<Vec<Value>>>()?;
+        let hash_key = match get_hash_key(&values) {
+          Some(hash_key) => hash_key,
+          None => continue,
+        };
+
+        if let Some(partitions) = partitions.as_mut() {
+          let partition_index = partitions.get_partition_index(&hash_key);
+          partitions.right[partition_index].write(&(hash_key, *row_id))?;
+          continue;
+        }
+
+        hash_table_size += size_of::<i64>();
+        if !hash_table.contains_key(&hash_key) {
+          hash_table_size += size_of::<String>() + size_of::<Vec<i64>>() + hash_key.capacity();
+        }
+        hash_table.entry(hash_key).or_default().push(*row_id);
+
+        // 超过内存预算的时候按照已经处理的行估算整个 hash 表的大小，决定分区的个数
+        // 已经在 hash 表中的 row id 也写到分区中
+        if hash_table_size > memory_budget {
+          let estimated_size = hash_table_size / (i + 1) * right_row_ids.len();
+          let number_of_partitions = (estimated_size / memory_budget.max(1) + 1)
+            .clamp(2, MAX_NUMBER_OF_PARTITIONS);
+          let mut new_partitions = HashPartitions::new(number_of_partitions)?;
+          for (hash_key, row_ids) in hash_table.drain() {
+            let partition_index = new_partitions.get_partition_index(&hash_key);
+            for row_id in row_ids {
+              new_partitions.right[partition_index].write(&(hash_key.clone(), row_id))?;
+            }
+          }
+          partitions = Some(new_partitions);
+        }
+      }
+    }
+
+    Ok(JoinOperator {
+      left,
+      scope,
+      right_index,
+      join_type,
+      condition,
+      join_strategy,
+      right_row_ids,
+      right_rows,
+      hash_table,
+      partitions,
+      matched_right_row_ids: HashSet::new(),
+      output: VecDeque::new(),
+      is_left_finished: false,
+    })
+  }
+
+  // hash join 中左边的行用来在 hash 表中查找的 key
+  fn get_probe_hash_key(&self, left_row: &RowValues) -> Result<Option<String>> {
+    match &self.join_strategy {
+      JoinStrategy::HashJoin(keys) => {
+        let values = keys
+          .iter()
+          .map(|(probe_expr, _)| evaluate_expression(probe_expr, left_row))
+          .collect::<Result<Vec<Value>>>()?;
+        Ok(get_hash_key(&values))
+      },
+      _ => Ok(None),
+    }
+  }
+
+  fn get_candidate_row_ids(&self, left_row: &RowValues) -> Result<Vec<i64>> {
+    match &self.join_strategy {
+      JoinStrategy::IndexLookup(probe_expr, column) => {
+        let right_table = self.scope.tables[self.right_index].table;
+        match lookup_index(right_table, column, &evaluate_expression(probe_expr, left_row)?)? {
+          Some(row_ids) => Ok(self.scope.tables[self.right_index].merge_row_ids(row_ids)),
+          None => Ok(self.right_row_ids.clone()),
+        }
+      },
+      JoinStrategy::HashJoin(_) => Ok(
+        self.get_probe_hash_key(left_row)?
+          .and_then(|hash_key| self.hash_table.get(&hash_key).cloned())
+          .unwrap_or_default()
+      ),
+      JoinStrategy::NestedLoop => Ok(self.right_row_ids.clone()),
+    }
+  }
+
+  fn join_left_row(&mut self, left_row: &RowValues) -> Result<()> {
+    let mut is_left_row_matched = false;
+    for right_row_id in self.get_candidate_row_ids(left_row)? {
+      let fetched_right_row: RowValues;
+      let right_row = match self.right_rows.get(&right_row_id) {
+        Some(right_row) => right_row,
+        None => {
+          fetched_right_row = self.scope.get_row(self.right_index, &right_row_id)?;
+          &fetched_right_row
+        },
+      };
+      let row = self.scope.merge_rows(self.right_index, left_row, right_row);
+      let is_matched = match &self.condition {
+        Some(condition) => is_row_matched(condition, &row)?,
+        None => true,
+      };
+      if !is_matched { continue; }
+
+      is_left_row_matched = true;
+      self.matched_right_row_ids.insert(right_row_id);
+      self.output.push_back(Tuple { row, ..Tuple::default() });
+    }
+
+    if !is_left_row_matched && self.join_type == JoinType::Left {
+      let null_right_row = self.scope.get_null_row(self.right_index);
+      self.output.push_back(Tuple {
+        row: self.scope.merge_rows(self.right_index, left_row, &null_right_row),
+        ..Tuple::default()
+      });
+    }
+    Ok(())
+  }
+
+  // 分区的话左边的行先写到对应的分区中，key 有 NULL 的行不会匹配任何行，直接处理
+  fn partition_left_row(&mut self, left_row: RowValues) -> Result<()> {
+    let hash_key = self.get_probe_hash_key(&left_row)?;
+    match (self.partitions.as_mut(), hash_key) {
+      (Some(partitions), Some(hash_key)) => {
+        let partition_index = partitions.get_partition_index(&hash_key);
+        partitions.left[partition_index].write(&left_row)
+      },
+      _ => self.join_left_row(&left_row),
+    }
+  }
+
+  // 从当前分区中读出左边的下一行，当前分区读完了就把下一个分区的右边读到 hash 表中
+  // 所有的分区都读完了返回 None
+  fn read_partitioned_left_row(&mut self) -> Result<Option<RowValues>> {
+    let partitions = match self.partitions.as_mut() {
+      Some(partitions) => partitions,
+      None => return Ok(None),
+    };
+    loop {
+      if let Some(current) = partitions.current {
+        if let Some(left_row) = partitions.left[current].read::<RowValues>()? {
+          return Ok(Some(left_row));
+        }
+      }
+
+      let next = partitions.current.map_or(0, |current| current + 1);
+      self.hash_table.clear();
+      if next == partitions.right.len() {
+        return Ok(None);
+      }
+      while let Some((hash_key, row_id)) = partitions.right[next].read::<(String, i64)>()? {
+        self.hash_table.entry(hash_key).or_default().push(row_id);
+      }
+      partitions.current = Some(next);
+    }
+  }
+
+  fn join_unmatched_right_rows(&mut self) -> Result<()> {
+    let mut null_left_row = RowValues::new();
+    for i in 0..self.right_index {
+      null_left_row.extend(self.scope.get_null_row(i));
+    }
+    for right_row_id in &self.right_row_ids {
+      if self.matched_right_row_ids.contains(right_row_id) { continue; }
+      let right_row = self.scope.get_row(self.right_index, right_row_id)?;
+      self.output.push_back(Tuple {
+        row: self.scope.merge_rows(self.right_index, &null_left_row, &right_row),
+        ..Tuple::default()
+      });
+    }
+    Ok(())
+  }
+}
+
+impl<'a> Operator for JoinOperator<'a> {
+  fn next(&mut self) -> Result<Option<Tuple>> {
+    loop {
+      if let Some(tuple) = self.output.pop_front() {
+        return Ok(Some(tuple));
+      }
+      if self.is_left_finished {
+        return Ok(None);
+      }
+
+      match self.left.next()? {
+        Some(left_tuple) => self.partition_left_row(left_tuple.row)?,
+        None => {
+          // 分区的话等到左边的行都分完了才开始逐个分区匹配
+          // 每个分区的匹配结果都放在 output 中，output 空了再读下一行
+          if let Some(left_row) = self.read_partitioned_left_row()? {
+            self.join_left_row(&left_row)?;
+            continue;
+          }
+          self.is_left_finished = true;
+          if self.join_type == JoinType::Right {
+            self.join_unmatched_right_rows()?;
+          }
+        },
+      }
+    }
+  }
+
+  fn describe(&self) -> String {
+    let mut description = format!(
+      "{} ({}) with {}",
+      match self.join_strategy {
+        JoinStrategy::IndexLookup(..) => "Index Nested Loop Join",
+        JoinStrategy::HashJoin(_) => "Hash Join",
+        JoinStrategy::NestedLoop => "Nested Loop Join",
+      },
+      get_join_type_name(&self.join_type),
+      get_table_label(self.scope, self.right_index),
+    );
+    if let JoinStrategy::IndexLookup(_, column) = &self.join_strategy {
+      description.push_str(&format!(" using index on {}", column.column_name));
+    }
+    if let Some(partitions) = &self.partitions {
+      description.push_str(&format!(" in {} partitions", partitions.right.len()));
+    }
+    if let Some(condition) = &self.condition {
+      description.push_str(&format!(", condition: {}", condition));
+    }
+    description
+  }
+
+  fn children(&self) -> Vec<&dyn Operator> {
+    vec![&*self.left]
+  }
+}
+
+// 第一次调用 next 的时候读出所有的行并分组，之后每次返回一组
+pub struct AggregateOperator<'a> {
+  input: Box<dyn Operator + 'a>,
+  group_by: Vec<Expr>,
+  functions: Vec<Function>,
+  output: Option<std::vec::IntoIter<RowValues>>,
+}
+
+impl<'a> Operator for AggregateOperator<'a> {
+  fn next(&mut self) -> Result<Option<Tuple>> {
+    if self.output.is_none() {
+      let mut rows: Vec<RowValues> = vec![];
+      while let Some(tuple) = self.input.next()? {
+        rows.push(tuple.row);
+      }
+      self.output = Some(aggregate_rows(&rows, &self.group_by, &self.functions)?.into_iter());
+    }
+
+    Ok(self.output.as_mut().unwrap().next().map(|row| Tuple { row, ..Tuple::default() }))
+  }
+
+  fn describe(&self) -> String {
+    let mut description = format!("Aggregate: {}", join_exprs(&self.functions));
+    if !self.group_by.is_empty() {
+      description.push_str(&format!(", group by: {}", join_exprs(&self.group_by)));
+    }
+    description
+  }
+
+  fn children(&self) -> Vec<&dyn Operator> {
+    vec![&*self.input]
+  }
+}
+
+// 第一次调用 next 的时候读出所有的行并排序，超过内存预算的话会写到临时文件中
+// 之后每次调用 next 只从临时文件中归并出下一行，不会把所有的行再放回内存中
+pub struct SortOperator<'a> {
+  input: Box<dyn Operator + 'a>,
+  order_by: Vec<OrderByExpr>,
+  column_indexes: Vec<usize>,
+  memory_budget: usize,
+  output: Option<SortedRows>,
+}
+
+impl<'a> Operator for SortOperator<'a> {
+  fn next(&mut self) -> Result<Option<Tuple>> {
+    if self.output.is_none() {
+      let mut sorter = ExternalSorter::new(&self.order_by, &self.column_indexes, self.memory_budget);
+      while let Some(tuple) = self.input.next()? {
+        sorter.push(tuple.values)?;
+      }
+      self.output = Some(sorter.finish()?);
+    }
+
+    Ok(self.output.as_mut().unwrap().next()?.map(|values| Tuple { values, ..Tuple::default() }))
+  }
+
+  fn describe(&self) -> String {
+    format!("Sort: {}", join_exprs(&self.order_by))
+  }
+
+  fn children(&self) -> Vec<&dyn Operator> {
+    vec![&*self.input]
+  }
+}
+
+// 先跳过 offset 行，再最多输出 limit 行，输出够了就不再从子算子中拉取数据
+pub struct LimitOperator<'a> {
+  input: Box<dyn Operator + 'a>,
+  limit: usize,
+  offset: usize,
+  number_of_skipped_rows: usize,
+  number_of_returned_rows: usize,
+}
+
+impl<'a> Operator for LimitOperator<'a> {
+  fn next(&mut self) -> Result<Option<Tuple>> {
+    while self.number_of_skipped_rows < self.offset {
+      if self.input.next()?.is_none() { return Ok(None); }
+      self.number_of_skipped_rows += 1;
+    }
+    if self.number_of_returned_rows >= self.limit { return Ok(None); }
+
+    self.number_of_returned_rows += 1;
+    self.input.next()
+  }
+
+  fn describe(&self) -> String {
+    match self.limit {
+      usize::MAX => format!("Limit: offset: {}", self.offset),
+      limit => format!("Limit: {}, offset: {}", limit, self.offset),
+    }
+  }
+
+  fn children(&self) -> Vec<&dyn Operator> {
+    vec![&*self.input]
+  }
+}
+
+// EXPLAIN ANALYZE 用来统计一个算子输出的行数以及用时
+// 用时包括了子算子的用时
+pub struct AnalyzeOperator<'a> {
+  input: Box<dyn Operator + 'a>,
+  number_of_rows: usize,
+  elapsed: Duration,
+}
+
+impl<'a> Operator for AnalyzeOperator<'a> {
+  fn next(&mut self) -> Result<Option<Tuple>> {
+    let start = Instant::now();
+    let tuple = self.input.next()?;
+    self.elapsed += start.elapsed();
+    if tuple.is_some() {
+      self.number_of_rows += 1;
+    }
+    Ok(tuple)
+  }
+
+  fn describe(&self) -> String {
+    self.input.describe()
+  }
+
+  fn children(&self) -> Vec<&dyn Operator> {
+    self.input.children()
+  }
+
+  fn get_statistics(&self) -> Option<(usize, Duration)> {
+    Some((self.number_of_rows, self.elapsed))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  // 按顺序输出 0..number_of_rows，并记录被拉取了多少次
+  struct CountOperator {
+    number_of_rows: i32,
+    number_of_pulls: std::rc::Rc<std::cell::Cell<i32>>,
+  }
+
+  impl Operator for CountOperator {
+    fn next(&mut self) -> Result<Option<Tuple>> {
+      let i = self.number_of_pulls.get();
+      self.number_of_pulls.set(i + 1);
+      match i < self.number_of_rows {
+        true => Ok(Some(Tuple { values: vec![Value::Integer(i)], ..Tuple::default() })),
+        false => Ok(None),
+      }
+    }
+
+    fn describe(&self) -> String {
+      "Count".to_string()
+    }
+
+    fn children(&self) -> Vec<&dyn Operator> {
+      vec![]
+    }
+  }
+
+  #[rstest]
+  #[case(10, 3, 2, vec![2, 3, 4], 5)]
+  #[case(10, 0, 2, vec![], 2)]
+  #[case(3, 5, 1, vec![1, 2], 4)]
+  #[case(3, 1, 5, vec![], 4)]
+  fn test_limit_operator(
+    #[case] number_of_rows: i32,
+    #[case] limit: usize,
+    #[case] offset: usize,
+    #[case] expected: Vec<i32>,
+    #[case] expected_number_of_pulls: i32,
+  ) {
+    let number_of_pulls = std::rc::Rc::new(std::cell::Cell::new(0));
+    let mut operator = LimitOperator {
+      input: Box::new(CountOperator {
+        number_of_rows,
+        number_of_pulls: number_of_pulls.clone(),
+      }),
+      limit,
+      offset,
+      number_of_skipped_rows: 0,
+      number_of_returned_rows: 0,
+    };
+
+    let mut values = vec![];
+    while let Some(tuple) = operator.next().unwrap() {
+      values.extend(tuple.values);
+    }
+    assert_eq!(values, expected.into_iter().map(Value::Integer).collect::<Vec<Value>>());
+    // 输出够了就不再从子算子中拉取数据
+    assert_eq!(number_of_pulls.get(), expected_number_of_pulls);
+  }
+}
@@ -0,0 +1,135 @@
+use sqlparser::ast::{
+  Statement,
+  AlterTableOperation,
+};
+
+use crate::error::{Result, NollaDBError};
+use crate::sql_query::query::create::{
+  SchemaOfSQLColumn,
+  get_schema_of_sql_column,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum AlterOperation {
+  // ADD [COLUMN] <column_def>
+  AddColumn(SchemaOfSQLColumn),
+  // DROP [COLUMN] [IF EXISTS] <column_name>
+  DropColumn {
+    column_name: String,
+    if_exists: bool,
+  },
+  // RENAME [COLUMN] <old_column_name> TO <new_column_name>
+  RenameColumn {
+    old_column_name: String,
+    new_column_name: String,
+  },
+  // RENAME TO <table_name>
+  RenameTable(String),
+}
+
+#[derive(Debug)]
+pub struct AlterQuery {
+  pub table_name: String,
+  pub operation: AlterOperation,
+}
+
+impl AlterQuery {
+  pub fn new(statement: &Statement) -> Result<AlterQuery> {
+    match statement {
+      Statement::AlterTable {
+        name,
+        operation,
+      } => {
+        let table_name = name.to_string();
+        let operation = match operation {
+          AlterTableOperation::AddColumn { column_def } => AlterOperation::AddColumn(
+            get_schema_of_sql_column(column_def, &[], &table_name)?
+          ),
+          AlterTableOperation::DropColumn { column_name, if_exists, .. } => {
+            AlterOperation::DropColumn {
+              column_name: column_name.to_string(),
+              if_exists: *if_exists,
+            }
+          },
+          AlterTableOperation::RenameColumn { old_column_name, new_column_name } => {
+            AlterOperation::RenameColumn {
+              old_column_name: old_column_name.to_string(),
+              new_column_name: new_column_name.to_string(),
+            }
+          },
+          AlterTableOperation::RenameTable { table_name } => {
+            AlterOperation::RenameTable(table_name.to_string())
+          },
+          _ => return Err(NollaDBError::ToBeImplemented(
+            format!("ALTER TABLE {} will be implemented soon", operation)
+          )),
+        };
+
+        Ok(AlterQuery {
+          table_name,
+          operation,
+        })
+      },
+      _ => Err(NollaDBError::Internal("Parsing ALTER SQL query error".to_string())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+  use crate::table::row::value::Value;
+
+  #[rstest]
+  #[case(
+    "ALTER TABLE test ADD COLUMN score REAL DEFAULT 1;",
+    AlterOperation::AddColumn(SchemaOfSQLColumn {
+      column_name: "score".to_string(),
+      column_datatype: "Real".to_string(),
+      is_primary_key: false,
+      is_unique_constraint: false,
+      is_not_null_constraint: false,
+      default_value: Some(Value::Real(1.0)),
+    }),
+  )]
+  #[case(
+    "ALTER TABLE test DROP COLUMN IF EXISTS score;",
+    AlterOperation::DropColumn {
+      column_name: "score".to_string(),
+      if_exists: true,
+    },
+  )]
+  #[case(
+    "ALTER TABLE test RENAME COLUMN score TO points;",
+    AlterOperation::RenameColumn {
+      old_column_name: "score".to_string(),
+      new_column_name: "points".to_string(),
+    },
+  )]
+  #[case(
+    "ALTER TABLE test RENAME TO test2;",
+    AlterOperation::RenameTable("test2".to_string()),
+  )]
+  fn test_alter_query(
+    #[case] query: &str,
+    #[case] expected_operation: AlterOperation,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let statement = ast.pop().unwrap();
+
+    match AlterQuery::new(&statement) {
+      Ok(alter_query) => {
+        assert_eq!(alter_query.table_name, "test");
+        assert_eq!(alter_query.operation, expected_operation);
+      },
+      Err(error) => {
+        panic!("Error: {}", error)
+      },
+    }
+  }
+}
@@ -1,5 +1,8 @@
-use sqlparser::ast::{Statement, DataType, ColumnOption};
+use sqlparser::ast::{Statement, DataType, ColumnOption, ColumnDef, TableConstraint};
 use crate::error::{Result, NollaDBError};
+use crate::sql_query::expression::{RowValues, evaluate_expression};
+use crate::table::column::data_type::DataType as ColumnDataType;
+use crate::table::row::value::Value;
 
 #[derive(Debug, PartialEq)]
 // TODO: 待优化
@@ -9,12 +12,28 @@ pub struct SchemaOfSQLColumn {
   pub is_primary_key: bool,
   pub is_unique_constraint: bool,
   pub is_not_null_constraint: bool,
+  // DEFAULT 值，没有设置的话就是 None
+  pub default_value: Option<Value>,
+}
+
+// 表级别的 PRIMARY KEY (a, b) 或者 UNIQUE (a, b) 约束
+// 只有一列的约束会直接放到这一列的 SchemaOfSQLColumn 中
+#[derive(Debug, PartialEq)]
+pub struct SchemaOfSQLConstraint {
+  // CONSTRAINT 后面的名字，没有的话是 表名_pkey 或者 表名_列名_key
+  // 同时也是这个约束建立的索引的名字
+  pub constraint_name: String,
+  pub column_names: Vec<String>,
+  pub is_primary_key: bool,
 }
 
 #[derive(Debug)]
 pub struct CreateQuery {
   pub table_name: String,
   pub table_metadata_columns: Vec<SchemaOfSQLColumn>,
+  pub table_constraints: Vec<SchemaOfSQLConstraint>,
+  // CREATE TABLE IF NOT EXISTS
+  pub if_not_exists: bool,
 }
 
 impl CreateQuery {
@@ -22,12 +41,16 @@ impl CreateQuery {
     #[allow(unused_assignments)]
     let mut option_table_name: Option<String> = None;
     let mut table_metadata_columns: Vec<SchemaOfSQLColumn> = vec![];
+    let mut table_constraints: Vec<SchemaOfSQLConstraint> = vec![];
+    #[allow(unused_assignments)]
+    let mut option_if_not_exists: bool = false;
 
     match statement {
       Statement::CreateTable {
         name,
         columns,
         constraints,
+        if_not_exists,
         // with_options,
         // external,
         // file_format,
@@ -35,6 +58,7 @@ impl CreateQuery {
         ..
       } => {
         option_table_name = Some(name.to_string());
+        option_if_not_exists = *if_not_exists;
 
         // 处理 columns
         for column in columns {
@@ -51,76 +75,20 @@ impl CreateQuery {
             );
           }
 
-          let column_datatype = match &column.data_type {
-            DataType::SmallInt(_) => "Integer", // bytes
-            DataType::Int(_) => "Integer", // bytes
-            DataType::BigInt(_) => "Integer", // bytes
-            DataType::Text => "Text",
-            DataType::Varchar(_) => "Text", // bytes
-            DataType::Boolean => "Bool",
-            DataType::Real => "Real",
-            DataType::Float(_) => "Real", // precision
-            DataType::Double => "Real",
-            DataType::Decimal(_, _) => "Real", // precision
-            _ => {
-              eprintln!("not matched on custom type");
-              "Invalid"
-            }
-          };
-
-          let mut is_primary_key: bool = false;
-          let mut is_unique_constraint: bool = false;
-          let mut is_not_null_constraint: bool = false;
-
-          for column_option in &column.options {
-            match column_option.option {
-              ColumnOption::Unique {
-                is_primary
-              } => {
-                // 只有 Integer 和 Text 类型可以作为 PRIMARY KEY 和 Unique 约束
-                if column_datatype == "Bool" ||
-                   column_datatype == "Real" ||
-                   !is_primary { continue; }
-
-                // 这里还要检查创建表时，表里面是否已经有 PRIMARY KEY
-                if table_metadata_columns
-                    .iter()
-                    .any(|table_metadata_column| table_metadata_column.is_primary_key == true) {
-                  return Err(
-                    NollaDBError::Internal(
-                      format!("Table '{}' has more than one PRIMARY KEY", &name)
-                    )
-                  );
-                }
-
-                is_primary_key = is_primary;
-                is_unique_constraint = true;
-                // 而只有是 PRIMARY KEY 的情况下，才可以是 NOT NULL 约束
-                is_not_null_constraint = true;
-
-              },
-              ColumnOption::NotNull => {
-                is_not_null_constraint = true;
-              },
-              _ => (),
-            };
-          }
-
-          // 组装 table_metadata_columns
-          table_metadata_columns.push(SchemaOfSQLColumn {
-            column_name,
-            column_datatype: column_datatype.to_string(),
-            is_primary_key,
-            is_unique_constraint,
-            is_not_null_constraint,
-          });
+          table_metadata_columns.push(
+            get_schema_of_sql_column(column, &table_metadata_columns, &name.to_string())?
+          );
         }
 
-        // TODO: 处理 constraints
+        // 处理 constraints
         for constraint in constraints {
-          println!("{:?}", constraint);
+          add_table_constraint(
+            constraint,
+            &mut table_metadata_columns,
+            &mut table_constraints,
+            &name.to_string(),
+          )?;
         }
-
       },
       _ => return Err(NollaDBError::Internal("Parsing CREATE SQL query error".to_string())),
     }
@@ -129,10 +97,182 @@ impl CreateQuery {
       Some(table_name) => Ok(CreateQuery {
           table_name,
           table_metadata_columns,
+          table_constraints,
+          if_not_exists: option_if_not_exists,
         }),
-      _ => return Err(NollaDBError::Internal("Parsing CREATE SQL query error".to_string())),
+      _ => Err(NollaDBError::Internal("Parsing CREATE SQL query error".to_string())),
+    }
+  }
+}
+
+// 解析 CREATE TABLE 中表级别的约束
+// 1. 只有一列的 UNIQUE，以及只有一列并且是 Integer 或者 Text 的 PRIMARY KEY，和写在 column 后面的约束一样
+// 2. 其他的 PRIMARY KEY 和 UNIQUE 会建立一个多列索引，PRIMARY KEY 中的每一列都有 NOT NULL 约束
+fn add_table_constraint(
+  constraint: &TableConstraint,
+  table_metadata_columns: &mut [SchemaOfSQLColumn],
+  table_constraints: &mut Vec<SchemaOfSQLConstraint>,
+  table_name: &str,
+) -> Result<()> {
+  let (name, columns, is_primary_key) = match constraint {
+    TableConstraint::Unique { name, columns, is_primary } => (name, columns, *is_primary),
+    _ => return Err(NollaDBError::ToBeImplemented(
+      format!("Constraint '{}' will be implemented soon", constraint)
+    )),
+  };
+
+  let mut column_names: Vec<String> = vec![];
+  for column in columns {
+    let column_name = column.value.to_string();
+    if !table_metadata_columns
+        .iter()
+        .any(|table_metadata_column| table_metadata_column.column_name == column_name) {
+      return Err(NollaDBError::Internal(
+        format!("Column '{}' in constraint '{}' does not exist", column_name, constraint)
+      ));
+    }
+    if column_names.contains(&column_name) {
+      return Err(NollaDBError::Internal(
+        format!("Duplicate column name '{}' in constraint '{}'", column_name, constraint)
+      ));
+    }
+    column_names.push(column_name);
+  }
+
+  if is_primary_key && (
+    table_metadata_columns.iter().any(|table_metadata_column| table_metadata_column.is_primary_key) ||
+    table_constraints.iter().any(|table_constraint| table_constraint.is_primary_key)
+  ) {
+    return Err(NollaDBError::Internal(
+      format!("Table '{}' has more than one PRIMARY KEY", table_name)
+    ));
+  }
+
+  if let [column_name] = column_names.as_slice() {
+    let table_metadata_column = table_metadata_columns
+      .iter_mut()
+      .find(|table_metadata_column| table_metadata_column.column_name == *column_name)
+      .unwrap();
+    let is_row_id_type = table_metadata_column.column_datatype == "Integer" ||
+      table_metadata_column.column_datatype == "Text";
+    if !is_primary_key || is_row_id_type {
+      table_metadata_column.is_unique_constraint = true;
+      table_metadata_column.is_primary_key |= is_primary_key;
+      table_metadata_column.is_not_null_constraint |= is_primary_key;
+      return Ok(());
+    }
+  }
+
+  let constraint_name = match name {
+    Some(name) => name.value.to_string(),
+    None if is_primary_key => format!("{}_pkey", table_name),
+    None => format!("{}_{}_key", table_name, column_names.join("_")),
+  };
+  if table_constraints
+      .iter()
+      .any(|table_constraint| table_constraint.constraint_name == constraint_name) {
+    return Err(NollaDBError::Internal(
+      format!("Duplicate constraint name '{}' in table '{}'", constraint_name, table_name)
+    ));
+  }
+
+  if is_primary_key {
+    for table_metadata_column in table_metadata_columns.iter_mut() {
+      if column_names.contains(&table_metadata_column.column_name) {
+        table_metadata_column.is_not_null_constraint = true;
+      }
+    }
+  }
+  table_constraints.push(SchemaOfSQLConstraint {
+    constraint_name,
+    column_names,
+    is_primary_key,
+  });
+
+  Ok(())
+}
+
+// 解析 CREATE TABLE 或者 ALTER TABLE ADD COLUMN 中的一个 column
+// table_metadata_columns 是这张表中已经存在的 column，用来检查 PRIMARY KEY 是否重复
+pub fn get_schema_of_sql_column(
+  column: &ColumnDef,
+  table_metadata_columns: &[SchemaOfSQLColumn],
+  table_name: &str,
+) -> Result<SchemaOfSQLColumn> {
+  let column_name = column.name.to_string();
+
+  let column_datatype = match &column.data_type {
+    DataType::SmallInt(_) => "Integer", // bytes
+    DataType::Int(_) => "Integer", // bytes
+    DataType::BigInt(_) => "Integer", // bytes
+    DataType::Text => "Text",
+    DataType::Varchar(_) => "Text", // bytes
+    DataType::Boolean => "Bool",
+    DataType::Real => "Real",
+    DataType::Float(_) => "Real", // precision
+    DataType::Double => "Real",
+    DataType::Decimal(_, _) => "Real", // precision
+    _ => {
+      eprintln!("not matched on custom type");
+      "Invalid"
     }
+  };
+
+  let mut is_primary_key: bool = false;
+  let mut is_unique_constraint: bool = false;
+  let mut is_not_null_constraint: bool = false;
+  let mut default_value: Option<Value> = None;
+
+  for column_option in &column.options {
+    match &column_option.option {
+      ColumnOption::Unique {
+        is_primary
+      } => {
+        // 只有 Integer 和 Text 类型可以作为 PRIMARY KEY，所有类型都可以有 UNIQUE 约束
+        if *is_primary &&
+           (column_datatype == "Bool" || column_datatype == "Real") { continue; }
+
+        is_unique_constraint = true;
+
+        // 只是 UNIQUE 约束的话到这里就可以了
+        if !is_primary { continue; }
+
+        // 这里还要检查创建表时，表里面是否已经有 PRIMARY KEY
+        if table_metadata_columns
+            .iter()
+            .any(|table_metadata_column| table_metadata_column.is_primary_key) {
+          return Err(
+            NollaDBError::Internal(
+              format!("Table '{}' has more than one PRIMARY KEY", table_name)
+            )
+          );
+        }
+
+        is_primary_key = *is_primary;
+        // 而只有是 PRIMARY KEY 的情况下，才可以是 NOT NULL 约束
+        is_not_null_constraint = true;
+
+      },
+      ColumnOption::NotNull => {
+        is_not_null_constraint = true;
+      },
+      // DEFAULT 只能是常量表达式，求值之后转换成 column 的类型
+      ColumnOption::Default(expr) => {
+        let value = evaluate_expression(expr, &RowValues::new())?;
+        default_value = Some(value.cast(&ColumnDataType::new(column_datatype.to_string()))?);
+      },
+      _ => (),
+    };
   }
+
+  Ok(SchemaOfSQLColumn {
+    column_name,
+    column_datatype: column_datatype.to_string(),
+    is_primary_key,
+    is_unique_constraint,
+    is_not_null_constraint,
+    default_value,
+  })
 }
 
 #[cfg(test)]
@@ -152,26 +292,122 @@ mod tests {
       email TEXT NOT NULL UNIQUE,
     );",
     "test",
+    false,
+  )]
+  #[case(
+    "CREATE TABLE IF NOT EXISTS test (
+      id INTEGER PRIMARY KEY
+    );",
+    "test",
+    true,
   )]
   fn test_create_table(
     #[case] query: &str,
     #[case] expected: &str,
+    #[case] expected_if_not_exists: bool,
   ) {
     let dialect = SQLiteDialect {};
-    let mut ast = Parser::parse_sql(&dialect, &query).unwrap();
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
     let statement = ast.pop().unwrap();
 
-    let _ = match statement {
-      Statement::CreateTable {..} => {
-        match CreateQuery::new(&statement) {
-          Ok(create_query) => assert_eq!(create_query.table_name, expected),
-          Err(error) => {
-            eprintln!("Error: {}", error);
-            assert!(false)
-          },
-        }
-      },
-      _ => (),
+    if let Statement::CreateTable {..} = statement {
+      match CreateQuery::new(&statement) {
+        Ok(create_query) => {
+          assert_eq!(create_query.table_name, expected);
+          assert_eq!(create_query.if_not_exists, expected_if_not_exists);
+        },
+        Err(error) => {
+          panic!("Error: {}", error)
+        },
+      }
     };
   }
+
+  #[rstest]
+  fn test_create_table_with_bool_and_real_constraints() {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(
+      &dialect,
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        is_active BOOLEAN UNIQUE,
+        score REAL PRIMARY KEY
+      );",
+    ).unwrap();
+    let create_query = CreateQuery::new(&ast.pop().unwrap()).unwrap();
+
+    // Bool 和 Real 可以有 UNIQUE 约束，但是不能作为 PRIMARY KEY
+    let constraints = create_query.table_metadata_columns
+      .iter()
+      .map(|column| (column.column_name.as_str(), column.is_primary_key, column.is_unique_constraint))
+      .collect::<Vec<(&str, bool, bool)>>();
+    assert_eq!(constraints, vec![("id", true, true), ("is_active", false, true), ("score", false, false)]);
+  }
+
+  #[rstest]
+  #[case(
+    "CREATE TABLE link (user_id INTEGER, group_id INTEGER, PRIMARY KEY (user_id, group_id));",
+    vec![("link_pkey", vec!["user_id", "group_id"], true)],
+    vec![("user_id", false, false, true), ("group_id", false, false, true)],
+  )]
+  #[case(
+    "CREATE TABLE link (id INTEGER, user_id INTEGER, group_id INTEGER, CONSTRAINT uq_link UNIQUE (user_id, group_id), PRIMARY KEY (id));",
+    vec![("uq_link", vec!["user_id", "group_id"], false)],
+    vec![("id", true, true, true), ("user_id", false, false, false), ("group_id", false, false, false)],
+  )]
+  #[case(
+    "CREATE TABLE test (id INTEGER PRIMARY KEY, score REAL, name TEXT, UNIQUE (name), UNIQUE (name, score));",
+    vec![("test_name_score_key", vec!["name", "score"], false)],
+    vec![("id", true, true, true), ("score", false, false, false), ("name", false, true, false)],
+  )]
+  #[case(
+    "CREATE TABLE test (score REAL, PRIMARY KEY (score));",
+    vec![("test_pkey", vec!["score"], true)],
+    vec![("score", false, false, true)],
+  )]
+  fn test_create_table_with_table_constraints(
+    #[case] query: &str,
+    #[case] expected_constraints: Vec<(&str, Vec<&str>, bool)>,
+    #[case] expected_columns: Vec<(&str, bool, bool, bool)>,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let create_query = CreateQuery::new(&ast.pop().unwrap()).unwrap();
+
+    // 多列的约束会建立多列索引，只有一列的约束和写在 column 后面的一样
+    let constraints = create_query.table_constraints
+      .iter()
+      .map(|constraint| (
+        constraint.constraint_name.as_str(),
+        constraint.column_names.iter().map(|column_name| column_name.as_str()).collect(),
+        constraint.is_primary_key,
+      ))
+      .collect::<Vec<(&str, Vec<&str>, bool)>>();
+    assert_eq!(constraints, expected_constraints);
+
+    let columns = create_query.table_metadata_columns
+      .iter()
+      .map(|column| (
+        column.column_name.as_str(),
+        column.is_primary_key,
+        column.is_unique_constraint,
+        column.is_not_null_constraint,
+      ))
+      .collect::<Vec<(&str, bool, bool, bool)>>();
+    assert_eq!(columns, expected_columns);
+  }
+
+  #[rstest]
+  #[case("CREATE TABLE test (id INTEGER PRIMARY KEY, a INTEGER, PRIMARY KEY (id, a));")]
+  #[case("CREATE TABLE test (a INTEGER, b INTEGER, PRIMARY KEY (a), PRIMARY KEY (b));")]
+  #[case("CREATE TABLE test (a INTEGER, b INTEGER, UNIQUE (a, not_exist));")]
+  #[case("CREATE TABLE test (a INTEGER, b INTEGER, UNIQUE (a, a));")]
+  #[case("CREATE TABLE test (a INTEGER, b INTEGER, UNIQUE (a, b), UNIQUE (a, b));")]
+  #[case("CREATE TABLE test (a INTEGER, b INTEGER, CHECK (a > b));")]
+  fn test_create_table_with_table_constraints_error(#[case] query: &str) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+
+    assert!(CreateQuery::new(&ast.pop().unwrap()).is_err());
+  }
 }
@@ -0,0 +1,99 @@
+use sqlparser::ast::{
+  Statement,
+  Expr,
+  OrderByExpr,
+};
+
+use crate::error::{Result, NollaDBError};
+
+#[derive(Debug)]
+pub struct CreateIndexQuery {
+  pub index_name: String,
+  pub table_name: String,
+  // 建立索引的 column，可以有多个
+  pub column_names: Vec<String>,
+  pub is_unique: bool,
+  pub if_not_exists: bool,
+}
+
+impl CreateIndexQuery {
+  pub fn new(statement: &Statement) -> Result<CreateIndexQuery> {
+    match statement {
+      Statement::CreateIndex {
+        name,
+        table_name,
+        columns,
+        unique,
+        if_not_exists,
+      } => {
+        let mut column_names: Vec<String> = vec![];
+        for column in columns {
+          match column {
+            // 索引中的值都是按照从小到大的顺序存放的，不支持 DESC
+            OrderByExpr { expr: Expr::Identifier(ident), asc: None | Some(true), .. } => {
+              column_names.push(ident.value.to_string());
+            },
+            _ => return Err(NollaDBError::ToBeImplemented(
+              format!("Index on '{}' will be implemented soon", column)
+            )),
+          }
+        }
+
+        Ok(CreateIndexQuery {
+          index_name: name.to_string(),
+          table_name: table_name.to_string(),
+          column_names,
+          is_unique: *unique,
+          if_not_exists: *if_not_exists,
+        })
+      },
+      _ => Err(NollaDBError::Internal("Parsing CREATE INDEX SQL query error".to_string())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+
+  #[rstest]
+  #[case("CREATE INDEX idx_name ON test (name);", "idx_name", vec!["name"], false, false)]
+  #[case("CREATE UNIQUE INDEX IF NOT EXISTS idx_email ON test (email ASC);", "idx_email", vec!["email"], true, true)]
+  #[case("CREATE INDEX idx_name_email ON test (name, email);", "idx_name_email", vec!["name", "email"], false, false)]
+  fn test_create_index_query(
+    #[case] query: &str,
+    #[case] expected_index_name: &str,
+    #[case] expected_column_names: Vec<&str>,
+    #[case] expected_is_unique: bool,
+    #[case] expected_if_not_exists: bool,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let statement = ast.pop().unwrap();
+
+    match CreateIndexQuery::new(&statement) {
+      Ok(create_index_query) => {
+        assert_eq!(create_index_query.index_name, expected_index_name);
+        assert_eq!(create_index_query.table_name, "test");
+        assert_eq!(create_index_query.column_names, expected_column_names);
+        assert_eq!(create_index_query.is_unique, expected_is_unique);
+        assert_eq!(create_index_query.if_not_exists, expected_if_not_exists);
+      },
+      Err(error) => panic!("Error: {}", error),
+    }
+  }
+
+  #[rstest]
+  #[case("CREATE INDEX idx_name ON test (name DESC);")]
+  #[case("CREATE INDEX idx_name ON test (LOWER(name));")]
+  fn test_create_index_query_error(#[case] query: &str) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+
+    assert!(CreateIndexQuery::new(&ast.pop().unwrap()).is_err());
+  }
+}
@@ -0,0 +1,60 @@
+use sqlparser::ast::{
+  Statement,
+  Expr,
+};
+
+use crate::error::{Result, NollaDBError};
+
+#[derive(Debug)]
+pub struct DeleteQuery {
+  pub table_name: String,
+  // WHERE 条件，没有的话就是删除表中所有的行
+  pub selection: Option<Expr>,
+}
+
+impl DeleteQuery {
+  pub fn new(statement: &Statement) -> Result<DeleteQuery> {
+    match statement {
+      Statement::Delete {
+        table_name,
+        selection,
+      } => Ok(DeleteQuery {
+        table_name: table_name.to_string(),
+        selection: selection.clone(),
+      }),
+      _ => Err(NollaDBError::Internal("Parsing DELETE SQL query error".to_string())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+
+  #[rstest]
+  #[case("DELETE FROM test WHERE id = 1;", "test", true)]
+  #[case("DELETE FROM test;", "test", false)]
+  fn test_delete_query(
+    #[case] query: &str,
+    #[case] expected_table_name: &str,
+    #[case] expected_has_selection: bool,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let statement = ast.pop().unwrap();
+
+    match DeleteQuery::new(&statement) {
+      Ok(delete_query) => {
+        assert_eq!(delete_query.table_name, expected_table_name);
+        assert_eq!(delete_query.selection.is_some(), expected_has_selection);
+      },
+      Err(error) => {
+        panic!("Error: {}", error)
+      },
+    }
+  }
+}
@@ -0,0 +1,70 @@
+use sqlparser::ast::{
+  Statement,
+  ObjectType,
+};
+
+use crate::error::{Result, NollaDBError};
+
+#[derive(Debug)]
+pub struct DropQuery {
+  // 要删除的对象类型，比如 TABLE
+  pub object_type: ObjectType,
+  pub if_exists: bool,
+  pub names: Vec<String>,
+}
+
+impl DropQuery {
+  pub fn new(statement: &Statement) -> Result<DropQuery> {
+    match statement {
+      Statement::Drop {
+        object_type,
+        if_exists,
+        names,
+        ..
+      } => Ok(DropQuery {
+        object_type: object_type.clone(),
+        if_exists: *if_exists,
+        names: names
+          .iter()
+          .map(|name| name.to_string())
+          .collect(),
+      }),
+      _ => Err(NollaDBError::Internal("Parsing DROP SQL query error".to_string())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+
+  #[rstest]
+  #[case("DROP TABLE test;", ObjectType::Table, false, vec!["test"])]
+  #[case("DROP TABLE IF EXISTS test, test2;", ObjectType::Table, true, vec!["test", "test2"])]
+  #[case("DROP INDEX IF EXISTS idx_name;", ObjectType::Index, true, vec!["idx_name"])]
+  fn test_drop_query(
+    #[case] query: &str,
+    #[case] expected_object_type: ObjectType,
+    #[case] expected_if_exists: bool,
+    #[case] expected_names: Vec<&str>,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let statement = ast.pop().unwrap();
+
+    match DropQuery::new(&statement) {
+      Ok(drop_query) => {
+        assert_eq!(drop_query.object_type, expected_object_type);
+        assert_eq!(drop_query.if_exists, expected_if_exists);
+        assert_eq!(drop_query.names, expected_names);
+      },
+      Err(error) => {
+        panic!("Error: {}", error)
+      },
+    }
+  }
+}
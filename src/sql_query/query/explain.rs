@@ -0,0 +1,69 @@
+use sqlparser::ast::Statement;
+
+use crate::error::{Result, NollaDBError};
+
+#[derive(Debug)]
+pub struct ExplainQuery {
+  // EXPLAIN ANALYZE 会真正执行查询，并且统计每个算子输出的行数以及用时
+  pub analyze: bool,
+  // 要查看执行计划的语句
+  pub statement: Statement,
+}
+
+impl ExplainQuery {
+  pub fn new(statement: &Statement) -> Result<ExplainQuery> {
+    match statement {
+      Statement::Explain {
+        describe_alias: false,
+        analyze,
+        statement,
+        ..
+      } => Ok(ExplainQuery {
+        analyze: *analyze,
+        statement: *statement.clone(),
+      }),
+      _ => Err(NollaDBError::Internal("Parsing EXPLAIN SQL query error".to_string())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+
+  #[rstest]
+  #[case("EXPLAIN SELECT * FROM test;", false, "SELECT * FROM test")]
+  #[case("EXPLAIN ANALYZE SELECT id FROM test WHERE id = 1;", true, "SELECT id FROM test WHERE id = 1")]
+  #[case("EXPLAIN DELETE FROM test;", false, "DELETE FROM test")]
+  fn test_explain_query(
+    #[case] query: &str,
+    #[case] expected_analyze: bool,
+    #[case] expected_statement: &str,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let statement = ast.pop().unwrap();
+
+    match ExplainQuery::new(&statement) {
+      Ok(explain_query) => {
+        assert_eq!(explain_query.analyze, expected_analyze);
+        assert_eq!(explain_query.statement.to_string(), expected_statement);
+      },
+      Err(error) => panic!("Error: {}", error),
+    }
+  }
+
+  #[rstest]
+  #[case("DESCRIBE SELECT * FROM test;")]
+  #[case("SELECT * FROM test;")]
+  fn test_explain_query_error(#[case] query: &str) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+
+    assert!(ExplainQuery::new(&ast.pop().unwrap()).is_err());
+  }
+}
@@ -3,17 +3,20 @@ use sqlparser::ast::{
   Query,
   SetExpr,
   Values,
-  Expr,
-  Value,
 };
 
 use crate::error::{Result, NollaDBError};
+use crate::sql_query::expression::{RowValues, evaluate_expression};
+use crate::table::row::value::Value;
+use crate::sql_query::query::select::SelectQuery;
 
 #[derive(Debug)]
 pub struct InsertQuery {
   pub table_name: String,
   pub table_column_names: Vec<String>,
-  pub table_column_values: Vec<Vec<String>>,
+  pub table_column_values: Vec<Vec<Value>>,
+  // INSERT INTO ... SELECT 的话，要插入的值是 SELECT 的结果
+  pub select_query: Option<SelectQuery>,
 }
 
 impl InsertQuery {
@@ -21,7 +24,8 @@ impl InsertQuery {
     #[allow(unused_assignments)]
     let mut option_table_name: Option<String> = None;
     let mut table_column_names: Vec<String> = vec![];
-    let mut table_column_values: Vec<Vec<String>> = vec![];
+    let mut table_column_values: Vec<Vec<Value>> = vec![];
+    let mut select_query: Option<SelectQuery> = None;
 
     match statement {
       Statement::Insert {
@@ -36,48 +40,39 @@ impl InsertQuery {
         }
 
         // &Query
-        match &**source {
-          Query {
-            body,
-            // order_by,
-            // limit,
-            // offset,
-            // fetch,
-            ..
-          } => {
-            // 解析类似于
-            //-- Values stored as TEXT, INTEGER, INTEGER, REAL, TEXT.
-            // INSERT INTO t1 VALUES('500.0', '500.0', '500.0', '500.0', '500.0');
-            // 的语句
-            // body 里面是解析之后的 INSERT 之后的 ast
-            // 把里面对应的表达式抽出来然后一个一个转成字符串
-            if let SetExpr::Values(values) = body {
-              #[allow(irrefutable_let_patterns)]
-              if let Values(expressions) = values {
-                for expression in expressions {
-                  let mut table_column_value: Vec<String> = vec![];
-                  for expr in expression {
-                    match expr {
-                      Expr::Value(v) => match v {
-                        Value::Number(n, _) => table_column_value.push(n.to_string()),
-                        Value::Boolean(b) => match *b {
-                          true => table_column_value.push("true".to_string()),
-                          false => table_column_value.push("false".to_string()),
-                        },
-                        Value::SingleQuotedString(sqs) => table_column_value.push(sqs.to_string()),
-                        Value::Null => table_column_value.push("Null".to_string()),
-                        _ => {},
-                      },
-                      Expr::Identifier(i) => table_column_value.push(i.to_string()),
-                      _ => {},
-                    }
-                  }
+        let Query {
+          body,
+          // order_by,
+          // limit,
+          // offset,
+          // fetch,
+          ..
+        } = &**source;
+        // 解析类似于
+        //-- Values stored as TEXT, INTEGER, INTEGER, REAL, TEXT.
+        // INSERT INTO t1 VALUES('500.0', '500.0', '500.0', '500.0', '500.0');
+        // 的语句
+        // body 里面是解析之后的 INSERT 之后的 ast
+        // 把里面对应的表达式抽出来然后一个一个求值
+        // VALUES 中只能是常量表达式，NULL 会被解析成 Value::Null
+        match body {
+          SetExpr::Values(Values(expressions)) => {
+            for expression in expressions {
+              let mut table_column_value: Vec<Value> = vec![];
+              for expr in expression {
+                table_column_value.push(evaluate_expression(expr, &RowValues::new())?);
+              }
 
-                  table_column_values.push(table_column_value);
-                }
-              };
-            };
-          }
+              table_column_values.push(table_column_value);
+            }
+          },
+          // INSERT INTO t1 (a, b) SELECT c, d FROM t2;
+          SetExpr::Select(_) => {
+            select_query = Some(SelectQuery::new(&Statement::Query(source.clone()))?);
+          },
+          _ => return Err(NollaDBError::ToBeImplemented(
+            "INSERT with this kind of source will be implemented soon".to_string()
+          )),
         }
       },
       _ => return Err(NollaDBError::Internal("Parsing INSERT SQL query error".to_string())),
@@ -88,8 +83,9 @@ impl InsertQuery {
         table_name,
         table_column_names,
         table_column_values,
+        select_query,
       }),
-      _ => return Err(NollaDBError::Internal("Parsing INSERT SQL query error".to_string())),
+      _ => Err(NollaDBError::Internal("Parsing INSERT SQL query error".to_string())),
     }
   }
 }
@@ -0,0 +1,128 @@
+use crate::error::{Result, NollaDBError};
+
+// sqlparser 0.13 不能解析 PRAGMA，这里直接解析 SQL 字符串
+// 支持 PRAGMA name; 以及 PRAGMA name = value; 和 PRAGMA name(value);
+#[derive(Debug, PartialEq)]
+pub struct PragmaQuery {
+  pub name: String,
+  // 没有 value 的话就是查询当前的值
+  pub value: Option<String>,
+}
+
+impl PragmaQuery {
+  pub fn new(sql_query: &str) -> Result<PragmaQuery> {
+    let sql_query = sql_query.trim().trim_end_matches(';').trim();
+    let pragma = match get_pragma_body(sql_query) {
+      Some(pragma) => pragma,
+      None => return Err(NollaDBError::Internal("Parsing PRAGMA SQL query error".to_string())),
+    };
+
+    let (name, value) = match (pragma.find('='), pragma.find('(')) {
+      (Some(i), _) => (&pragma[..i], Some(pragma[i + 1..].trim())),
+      (None, Some(i)) if pragma.ends_with(')') => (&pragma[..i], Some(pragma[i + 1..pragma.len() - 1].trim())),
+      (None, None) => (pragma, None),
+      _ => return Err(NollaDBError::Internal("Parsing PRAGMA SQL query error".to_string())),
+    };
+
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+      return Err(NollaDBError::Internal("Parsing PRAGMA SQL query error".to_string()));
+    }
+    if value.is_some_and(|value| value.is_empty()) {
+      return Err(NollaDBError::Internal(format!("PRAGMA {} expects a value", name)));
+    }
+
+    Ok(PragmaQuery {
+      name,
+      value: value.map(|value| value.trim_matches('\'').to_string()),
+    })
+  }
+}
+
+pub fn is_pragma_query(sql_query: &str) -> bool {
+  get_pragma_body(sql_query.trim()).is_some()
+}
+
+fn get_pragma_body(sql_query: &str) -> Option<&str> {
+  let keyword = "PRAGMA";
+  match sql_query.get(..keyword.len()) {
+    Some(prefix) if prefix.eq_ignore_ascii_case(keyword) => {
+      let body = &sql_query[keyword.len()..];
+      match body.starts_with(char::is_whitespace) {
+        true => Some(body.trim()),
+        false => None,
+      }
+    },
+    _ => None,
+  }
+}
+
+// 内存预算的单位是字节，也可以带上 KB / MB / GB 后缀
+pub fn parse_memory_budget(value: &str) -> Result<usize> {
+  let value = value.trim().to_uppercase();
+  let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+    Some(i) => (&value[..i], value[i..].trim()),
+    None => (value.as_str(), ""),
+  };
+  let unit = match unit {
+    "" | "B" => 1,
+    "KB" => 1024,
+    "MB" => 1024 * 1024,
+    "GB" => 1024 * 1024 * 1024,
+    _ => return Err(NollaDBError::General(format!("Invalid memory budget '{}'", value))),
+  };
+
+  number
+    .parse::<usize>()
+    .ok()
+    .and_then(|number| number.checked_mul(unit))
+    .ok_or_else(|| NollaDBError::General(format!("Invalid memory budget '{}'", value)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  #[rstest]
+  #[case("PRAGMA memory_budget;", "memory_budget", None)]
+  #[case("pragma Memory_Budget = 1024;", "memory_budget", Some("1024"))]
+  #[case("PRAGMA memory_budget('16MB')", "memory_budget", Some("16MB"))]
+  fn test_pragma_query(
+    #[case] query: &str,
+    #[case] expected_name: &str,
+    #[case] expected_value: Option<&str>,
+  ) {
+    assert!(is_pragma_query(query));
+    assert_eq!(
+      PragmaQuery::new(query),
+      Ok(PragmaQuery {
+        name: expected_name.to_string(),
+        value: expected_value.map(|value| value.to_string()),
+      })
+    );
+  }
+
+  #[rstest]
+  #[case("PRAGMA;")]
+  #[case("PRAGMA memory_budget =;")]
+  #[case("PRAGMA memory budget;")]
+  #[case("PRAGMAmemory_budget;")]
+  fn test_pragma_query_error(#[case] query: &str) {
+    assert!(PragmaQuery::new(query).is_err());
+  }
+
+  #[rstest]
+  #[case("1024", Ok(1024))]
+  #[case("4kb", Ok(4 * 1024))]
+  #[case("16 MB", Ok(16 * 1024 * 1024))]
+  #[case("-1", Err(NollaDBError::General("Invalid memory budget '-1'".to_string())))]
+  #[case("1TB", Err(NollaDBError::General("Invalid memory budget '1TB'".to_string())))]
+  fn test_parse_memory_budget(
+    #[case] value: &str,
+    #[case] expected: Result<usize>,
+  ) {
+    assert_eq!(parse_memory_budget(value), expected);
+  }
+}
@@ -0,0 +1,95 @@
+use crate::error::{Result, NollaDBError};
+
+// sqlparser 0.13 不能解析 SAVEPOINT，这里和 PRAGMA 一样直接解析 SQL 字符串
+// 支持 SAVEPOINT name; RELEASE [SAVEPOINT] name; 以及 ROLLBACK [TRANSACTION] TO [SAVEPOINT] name;
+// savepoint 的名字不区分大小写，统一转成小写
+#[derive(Debug, PartialEq)]
+pub enum SavepointQuery {
+  Savepoint(String),
+  Release(String),
+  RollbackTo(String),
+}
+
+impl SavepointQuery {
+  pub fn new(sql_query: &str) -> Result<SavepointQuery> {
+    let words = get_words(sql_query);
+    let words: Vec<&str> = words.iter().map(|word| word.as_str()).collect();
+
+    let savepoint_query = match words.as_slice() {
+      ["savepoint", name] => SavepointQuery::Savepoint(name.to_string()),
+      ["release", name] | ["release", "savepoint", name] => SavepointQuery::Release(name.to_string()),
+      ["rollback", rest @ ..] => {
+        let rest = match rest {
+          ["transaction" | "work", rest @ ..] => rest,
+          _ => rest,
+        };
+        match rest {
+          ["to", name] | ["to", "savepoint", name] => SavepointQuery::RollbackTo(name.to_string()),
+          _ => return Err(NollaDBError::Internal("Parsing ROLLBACK TO SQL query error".to_string())),
+        }
+      },
+      _ => return Err(NollaDBError::Internal("Parsing SAVEPOINT SQL query error".to_string())),
+    };
+
+    Ok(savepoint_query)
+  }
+}
+
+// ROLLBACK 后面没有 TO 的话是回滚整个事务，交给 sqlparser 解析
+pub fn is_savepoint_query(sql_query: &str) -> bool {
+  let words = get_words(sql_query);
+  match words.first().map(|word| word.as_str()) {
+    Some("savepoint") | Some("release") => true,
+    Some("rollback") => words.iter().skip(1).take(2).any(|word| word == "to"),
+    _ => false,
+  }
+}
+
+fn get_words(sql_query: &str) -> Vec<String> {
+  sql_query
+    .trim()
+    .trim_end_matches(';')
+    .split_whitespace()
+    .map(|word| word.to_lowercase())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  #[rstest]
+  #[case("SAVEPOINT sp1;", SavepointQuery::Savepoint("sp1".to_string()))]
+  #[case("release SP1", SavepointQuery::Release("sp1".to_string()))]
+  #[case("RELEASE SAVEPOINT sp1;", SavepointQuery::Release("sp1".to_string()))]
+  #[case("ROLLBACK TO sp1;", SavepointQuery::RollbackTo("sp1".to_string()))]
+  #[case("ROLLBACK TRANSACTION TO SAVEPOINT sp1;", SavepointQuery::RollbackTo("sp1".to_string()))]
+  fn test_savepoint_query(
+    #[case] query: &str,
+    #[case] expected: SavepointQuery,
+  ) {
+    assert!(is_savepoint_query(query));
+    assert_eq!(SavepointQuery::new(query), Ok(expected));
+  }
+
+  #[rstest]
+  #[case("SAVEPOINT;")]
+  #[case("SAVEPOINT a b;")]
+  #[case("RELEASE TO sp1;")]
+  #[case("ROLLBACK TO;")]
+  #[case("ROLLBACK WORK TO sp1 sp2;")]
+  fn test_savepoint_query_error(#[case] query: &str) {
+    assert!(is_savepoint_query(query));
+    assert!(SavepointQuery::new(query).is_err());
+  }
+
+  #[rstest]
+  #[case("ROLLBACK;")]
+  #[case("ROLLBACK TRANSACTION;")]
+  #[case("SELECT * FROM savepoint;")]
+  fn test_is_not_savepoint_query(#[case] query: &str) {
+    assert!(!is_savepoint_query(query));
+  }
+}
@@ -0,0 +1,350 @@
+use sqlparser::ast::{
+  Statement,
+  Query,
+  SetExpr,
+  SelectItem,
+  TableFactor,
+  Expr,
+  OrderByExpr,
+  Join,
+  JoinOperator,
+  JoinConstraint,
+};
+
+use crate::error::{Result, NollaDBError};
+
+// SELECT 后面的每一项
+#[derive(Debug, PartialEq, Clone)]
+pub enum Projection {
+  // *，表示所有的 column
+  Wildcard,
+  // test.*，表示 test 这张表所有的 column
+  QualifiedWildcard(String),
+  // 要查询的表达式，以及输出时的列名
+  Expr(Expr, String),
+}
+
+// FROM 或者 JOIN 后面的表
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableReference {
+  pub table_name: String,
+  pub alias: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum JoinType {
+  Inner,
+  Left,
+  Right,
+  Cross,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum JoinCondition {
+  On(Expr),
+  Using(Vec<String>),
+  None,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct JoinQuery {
+  pub table: TableReference,
+  pub join_type: JoinType,
+  pub condition: JoinCondition,
+}
+
+#[derive(Debug)]
+pub struct SelectQuery {
+  pub table_name: String,
+  pub table_alias: Option<String>,
+  // FROM a, b 相当于 a CROSS JOIN b，所以也会放到 joins 中
+  pub joins: Vec<JoinQuery>,
+  pub projection: Vec<Projection>,
+  // WHERE 条件
+  pub selection: Option<Expr>,
+  pub group_by: Vec<Expr>,
+  pub having: Option<Expr>,
+  pub order_by: Vec<OrderByExpr>,
+  pub limit: Option<Expr>,
+  pub offset: Option<Expr>,
+}
+
+impl SelectQuery {
+  pub fn new(statement: &Statement) -> Result<SelectQuery> {
+    #[allow(unused_assignments)]
+    let mut option_table_name: Option<String> = None;
+    let mut table_alias: Option<String> = None;
+    let mut joins: Vec<JoinQuery> = vec![];
+    let mut projection: Vec<Projection> = vec![];
+    #[allow(unused_assignments)]
+    let mut selection: Option<Expr> = None;
+    let mut group_by: Vec<Expr> = vec![];
+    #[allow(unused_assignments)]
+    let mut having: Option<Expr> = None;
+    let mut query_order_by: Vec<OrderByExpr> = vec![];
+    #[allow(unused_assignments)]
+    let mut query_limit: Option<Expr> = None;
+    #[allow(unused_assignments)]
+    let mut query_offset: Option<Expr> = None;
+
+    match statement {
+      Statement::Query(query) => {
+        // &Query
+        let Query {
+          body,
+          order_by,
+          limit,
+          offset,
+          ..
+        } = &**query;
+
+        query_order_by = order_by.clone();
+        query_limit = limit.clone();
+        query_offset = offset.as_ref().map(|offset| offset.value.clone());
+
+        let select = match body {
+          SetExpr::Select(select) => select,
+          _ => return Err(NollaDBError::ToBeImplemented(
+            "Only simple SELECT statement is supported now".to_string()
+          )),
+        };
+
+        if select.from.is_empty() {
+          return Err(NollaDBError::ToBeImplemented(
+            "SELECT without FROM will be implemented soon".to_string()
+          ));
+        }
+        for (i, table_with_joins) in select.from.iter().enumerate() {
+          let table_reference = get_table_reference(&table_with_joins.relation)?;
+          if i == 0 {
+            option_table_name = Some(table_reference.table_name);
+            table_alias = table_reference.alias;
+          } else {
+            joins.push(JoinQuery {
+              table: table_reference,
+              join_type: JoinType::Cross,
+              condition: JoinCondition::None,
+            });
+          }
+
+          for join in &table_with_joins.joins {
+            joins.push(get_join_query(join)?);
+          }
+        }
+
+        selection = select.selection.clone();
+        group_by = select.group_by.clone();
+        having = select.having.clone();
+
+        // 处理 projection
+        for select_item in &select.projection {
+          match select_item {
+            SelectItem::Wildcard => projection.push(Projection::Wildcard),
+            SelectItem::QualifiedWildcard(name) => {
+              projection.push(Projection::QualifiedWildcard(name.to_string()));
+            },
+            SelectItem::UnnamedExpr(expr) => {
+              projection.push(Projection::Expr(expr.clone(), get_column_name(expr)));
+            },
+            // SELECT COUNT(*) AS total
+            SelectItem::ExprWithAlias { expr, alias } => {
+              projection.push(Projection::Expr(expr.clone(), alias.value.to_string()));
+            },
+          }
+        }
+      },
+      _ => return Err(NollaDBError::Internal("Parsing SELECT SQL query error".to_string())),
+    }
+
+    match option_table_name {
+      Some(table_name) => Ok(SelectQuery {
+        table_name,
+        table_alias,
+        joins,
+        projection,
+        selection,
+        group_by,
+        having,
+        order_by: query_order_by,
+        limit: query_limit,
+        offset: query_offset,
+      }),
+      _ => Err(NollaDBError::Internal("Parsing SELECT SQL query error".to_string())),
+    }
+  }
+
+  // FROM 以及 JOIN 中用到的所有表
+  pub fn get_table_names(&self) -> Vec<String> {
+    let mut table_names = vec![self.table_name.to_string()];
+    table_names.extend(self.joins.iter().map(|join| join.table.table_name.to_string()));
+    table_names
+  }
+}
+
+fn get_table_reference(table_factor: &TableFactor) -> Result<TableReference> {
+  match table_factor {
+    TableFactor::Table { name, alias, .. } => Ok(TableReference {
+      table_name: name.to_string(),
+      alias: alias.as_ref().map(|alias| alias.name.value.to_string()),
+    }),
+    _ => Err(NollaDBError::ToBeImplemented(
+      "SELECT from subquery will be implemented soon".to_string()
+    )),
+  }
+}
+
+fn get_join_query(join: &Join) -> Result<JoinQuery> {
+  let (join_type, join_constraint) = match &join.join_operator {
+    JoinOperator::Inner(join_constraint) => (JoinType::Inner, join_constraint),
+    JoinOperator::LeftOuter(join_constraint) => (JoinType::Left, join_constraint),
+    JoinOperator::RightOuter(join_constraint) => (JoinType::Right, join_constraint),
+    JoinOperator::CrossJoin => (JoinType::Cross, &JoinConstraint::None),
+    join_operator => return Err(NollaDBError::ToBeImplemented(
+      format!("JOIN operator '{:?}' will be implemented soon", join_operator)
+    )),
+  };
+
+  let condition = match join_constraint {
+    JoinConstraint::On(expr) => JoinCondition::On(expr.clone()),
+    JoinConstraint::Using(idents) => JoinCondition::Using(
+      idents.iter().map(|ident| ident.value.to_string()).collect()
+    ),
+    JoinConstraint::None => JoinCondition::None,
+    JoinConstraint::Natural => return Err(NollaDBError::ToBeImplemented(
+      "NATURAL JOIN will be implemented soon".to_string()
+    )),
+  };
+
+  Ok(JoinQuery {
+    table: get_table_reference(&join.relation)?,
+    join_type,
+    condition,
+  })
+}
+
+// 没有别名的话，输出时的列名
+// column 就是 column name，类似于 test.id 这种带表名的 column 只取 column name
+// 其他的表达式就是表达式本身
+fn get_column_name(expr: &Expr) -> String {
+  match expr {
+    Expr::Identifier(ident) => ident.value.to_string(),
+    Expr::CompoundIdentifier(idents) => match idents.last() {
+      Some(ident) => ident.value.to_string(),
+      None => expr.to_string(),
+    },
+    _ => expr.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+
+  #[rstest]
+  #[case("SELECT * FROM test;", "test", vec!["*"], 0, false)]
+  #[case("SELECT id, name FROM test;", "test", vec!["id", "name"], 0, false)]
+  #[case("SELECT test.name FROM test;", "test", vec!["name"], 0, false)]
+  #[case("SELECT id + 1, name AS user_name FROM test;", "test", vec!["id + 1", "user_name"], 0, false)]
+  #[case(
+    "SELECT name, COUNT(*) FROM test GROUP BY name HAVING COUNT(*) > 1;",
+    "test",
+    vec!["name", "COUNT(*)"],
+    1,
+    true,
+  )]
+  #[case(
+    "SELECT * FROM test ORDER BY id DESC, name LIMIT 10 OFFSET 5;",
+    "test",
+    vec!["*"],
+    0,
+    false,
+  )]
+  fn test_select_query(
+    #[case] query: &str,
+    #[case] expected_table_name: &str,
+    #[case] expected_projection: Vec<&str>,
+    #[case] expected_group_by_len: usize,
+    #[case] expected_has_having: bool,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let statement = ast.pop().unwrap();
+
+    match SelectQuery::new(&statement) {
+      Ok(select_query) => {
+        assert_eq!(select_query.table_name, expected_table_name);
+        assert_eq!(
+          select_query.projection
+            .iter()
+            .map(|projection| match projection {
+              Projection::Wildcard => "*".to_string(),
+              Projection::QualifiedWildcard(table_name) => format!("{}.*", table_name),
+              Projection::Expr(_, column_name) => column_name.to_string(),
+            })
+            .collect::<Vec<String>>(),
+          expected_projection
+        );
+        assert_eq!(select_query.group_by.len(), expected_group_by_len);
+        assert_eq!(select_query.having.is_some(), expected_has_having);
+      },
+      Err(error) => {
+        panic!("Error: {}", error)
+      },
+    }
+  }
+
+  #[rstest]
+  #[case(
+    "SELECT * FROM a JOIN b ON a.id = b.a_id;",
+    None,
+    vec![(TableReference { table_name: "b".to_string(), alias: None }, JoinType::Inner)],
+  )]
+  #[case(
+    "SELECT u.*, o.total FROM users AS u LEFT JOIN orders o USING (id) RIGHT JOIN items i ON i.id = o.id;",
+    Some("u"),
+    vec![
+      (TableReference { table_name: "orders".to_string(), alias: Some("o".to_string()) }, JoinType::Left),
+      (TableReference { table_name: "items".to_string(), alias: Some("i".to_string()) }, JoinType::Right),
+    ],
+  )]
+  #[case(
+    "SELECT * FROM a, b CROSS JOIN c;",
+    None,
+    vec![
+      (TableReference { table_name: "b".to_string(), alias: None }, JoinType::Cross),
+      (TableReference { table_name: "c".to_string(), alias: None }, JoinType::Cross),
+    ],
+  )]
+  fn test_select_query_with_joins(
+    #[case] query: &str,
+    #[case] expected_table_alias: Option<&str>,
+    #[case] expected_joins: Vec<(TableReference, JoinType)>,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let select_query = SelectQuery::new(&ast.pop().unwrap()).unwrap();
+
+    assert_eq!(select_query.table_alias.as_deref(), expected_table_alias);
+    assert_eq!(
+      select_query.joins
+        .into_iter()
+        .map(|join| (join.table, join.join_type))
+        .collect::<Vec<(TableReference, JoinType)>>(),
+      expected_joins
+    );
+  }
+
+  #[rstest]
+  #[case("SELECT * FROM a FULL OUTER JOIN b ON a.id = b.id;")]
+  #[case("SELECT * FROM a NATURAL JOIN b;")]
+  fn test_select_query_with_joins_error(#[case] query: &str) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+
+    assert!(SelectQuery::new(&ast.pop().unwrap()).is_err());
+  }
+}
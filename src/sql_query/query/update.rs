@@ -0,0 +1,127 @@
+use sqlparser::ast::{
+  Statement,
+  TableFactor,
+  Expr,
+};
+
+use crate::error::{Result, NollaDBError};
+
+#[derive(Debug)]
+pub struct UpdateQuery {
+  pub table_name: String,
+  // SET 后面的 column name 以及对应的表达式
+  pub assignments: Vec<(String, Expr)>,
+  // WHERE 条件
+  pub selection: Option<Expr>,
+}
+
+impl UpdateQuery {
+  pub fn new(statement: &Statement) -> Result<UpdateQuery> {
+    #[allow(unused_assignments)]
+    let mut option_table_name: Option<String> = None;
+    let mut table_assignments: Vec<(String, Expr)> = vec![];
+    #[allow(unused_assignments)]
+    let mut option_selection: Option<Expr> = None;
+
+    match statement {
+      Statement::Update {
+        table,
+        assignments,
+        selection,
+      } => {
+        if !table.joins.is_empty() {
+          return Err(NollaDBError::ToBeImplemented(
+            "UPDATE with JOIN will be implemented soon".to_string()
+          ));
+        }
+        match &table.relation {
+          TableFactor::Table { name, .. } => {
+            option_table_name = Some(name.to_string());
+          },
+          _ => return Err(NollaDBError::Internal("Parsing UPDATE SQL query error".to_string())),
+        }
+
+        for assignment in assignments {
+          // 类似于 test.name = 'xxx' 这种带表名的 column，只取 column name
+          let column_name = match assignment.id.last() {
+            Some(ident) => ident.value.to_string(),
+            None => return Err(NollaDBError::Internal("Parsing UPDATE SQL query error".to_string())),
+          };
+
+          // 检查 SET 中有没有重复的 column
+          if table_assignments
+              .iter()
+              .any(|(table_column_name, _)| *table_column_name == column_name) {
+            return Err(NollaDBError::Internal(
+              format!("Duplicate column name in SET: {}", column_name)
+            ));
+          }
+
+          table_assignments.push((column_name, assignment.value.clone()));
+        }
+
+        option_selection = selection.clone();
+      },
+      _ => return Err(NollaDBError::Internal("Parsing UPDATE SQL query error".to_string())),
+    }
+
+    match option_table_name {
+      Some(table_name) => Ok(UpdateQuery {
+        table_name,
+        assignments: table_assignments,
+        selection: option_selection,
+      }),
+      _ => Err(NollaDBError::Internal("Parsing UPDATE SQL query error".to_string())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+  use sqlparser::parser::Parser;
+  use sqlparser::dialect::SQLiteDialect;
+
+  #[rstest]
+  #[case("UPDATE test SET name = 'xxx' WHERE id = 1;", "test", vec!["name"], true)]
+  #[case("UPDATE test SET name = 'xxx', score = score + 1;", "test", vec!["name", "score"], false)]
+  fn test_update_query(
+    #[case] query: &str,
+    #[case] expected_table_name: &str,
+    #[case] expected_column_names: Vec<&str>,
+    #[case] expected_has_selection: bool,
+  ) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let statement = ast.pop().unwrap();
+
+    match UpdateQuery::new(&statement) {
+      Ok(update_query) => {
+        assert_eq!(update_query.table_name, expected_table_name);
+        assert_eq!(
+          update_query.assignments
+            .iter()
+            .map(|(column_name, _)| column_name.as_str())
+            .collect::<Vec<&str>>(),
+          expected_column_names
+        );
+        assert_eq!(update_query.selection.is_some(), expected_has_selection);
+      },
+      Err(error) => {
+        panic!("Error: {}", error)
+      },
+    }
+  }
+
+  #[rstest]
+  #[case("UPDATE test SET name = 'xxx', name = 'yyy';")]
+  fn test_update_query_error(#[case] query: &str) {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    let statement = ast.pop().unwrap();
+
+    assert!(UpdateQuery::new(&statement).is_err());
+  }
+}
@@ -0,0 +1,51 @@
+use prettytable::{
+  Table as PrintTable,
+  Row as PrintRow,
+  Cell as PrintCell,
+};
+
+use crate::error::{Result, NollaDBError};
+use crate::table::row::value::Value;
+
+// 查询返回的结果集
+// column_names 是输出的表头，rows 里面的每一行和表头一一对应
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResultSet {
+  pub column_names: Vec<String>,
+  pub rows: Vec<Vec<Value>>,
+}
+
+impl ResultSet {
+  pub fn new(column_names: Vec<String>, rows: Vec<Vec<Value>>) -> Self {
+    ResultSet {
+      column_names,
+      rows,
+    }
+  }
+
+  pub fn print_result_set(&self) -> Result<usize> {
+    let mut print_table = PrintTable::new();
+
+    // column name
+    // 输出为最顶部的 header
+    print_table.add_row(PrintRow::new(
+      self.column_names
+        .iter()
+        .map(|column_name| PrintCell::new(column_name))
+        .collect::<Vec<PrintCell>>(),
+    ));
+
+    for row in &self.rows {
+      print_table.add_row(PrintRow::new(
+        row
+          .iter()
+          .map(|value| PrintCell::new(&value.to_string()))
+          .collect::<Vec<PrintCell>>(),
+      ));
+    }
+
+    print_table
+      .print_tty(false)
+      .map_err(|error| NollaDBError::Internal(error.to_string()))
+  }
+}
@@ -0,0 +1,117 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bincode::{deserialize_from, serialize_into};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::error::{Result, NollaDBError};
+
+// 用来给临时文件命名，避免同一个进程中的临时文件重名
+static SPILL_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// 超过内存预算的时候写到临时文件中的数据，排序和 hash join 都会用到
+// 先一条一条写进去，第一次读的时候切换成读，之后按照写入的顺序读出来
+// 被 drop 的时候会删除临时文件
+pub struct SpillFile {
+  path: PathBuf,
+  writer: Option<BufWriter<File>>,
+  reader: Option<BufReader<File>>,
+  number_of_records: usize,
+}
+
+impl SpillFile {
+  pub fn new(name: &str) -> Result<Self> {
+    let path = std::env::temp_dir().join(format!(
+      "nolladb-{}-{}-{}.spill",
+      name,
+      process::id(),
+      SPILL_FILE_COUNTER.fetch_add(1, Ordering::SeqCst),
+    ));
+    let file = File::create(&path).map_err(|error| {
+      NollaDBError::Internal(format!("Can not create temporary file: {}", error))
+    })?;
+
+    Ok(SpillFile {
+      path,
+      writer: Some(BufWriter::new(file)),
+      reader: None,
+      number_of_records: 0,
+    })
+  }
+
+  pub fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+    let writer = match self.writer.as_mut() {
+      Some(writer) => writer,
+      None => return Err(NollaDBError::Internal("Can not write temporary file after reading".to_string())),
+    };
+    serialize_into(writer, record).map_err(|error| {
+      NollaDBError::Internal(format!("Can not write temporary file: {}", error))
+    })?;
+    self.number_of_records += 1;
+    Ok(())
+  }
+
+  // 按顺序读出下一条，读完之后返回 None
+  pub fn read<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+    if let Some(mut writer) = self.writer.take() {
+      writer.flush().map_err(|error| {
+        NollaDBError::Internal(format!("Can not write temporary file: {}", error))
+      })?;
+    }
+    if self.number_of_records == 0 { return Ok(None); }
+    if self.reader.is_none() {
+      let file = File::open(&self.path).map_err(|error| {
+        NollaDBError::Internal(format!("Can not open temporary file: {}", error))
+      })?;
+      self.reader = Some(BufReader::new(file));
+    }
+
+    let record = deserialize_from(self.reader.as_mut().unwrap()).map_err(|error| {
+      NollaDBError::Internal(format!("Can not read temporary file: {}", error))
+    })?;
+    self.number_of_records -= 1;
+    Ok(Some(record))
+  }
+}
+
+impl Drop for SpillFile {
+  fn drop(&mut self) {
+    self.writer = None;
+    self.reader = None;
+    let _ = fs::remove_file(&self.path);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::{assert_eq};
+  use crate::table::row::value::Value;
+
+  #[test]
+  fn test_spill_file() {
+    let records = vec![
+      (1, vec![Value::Integer(1), Value::Null]),
+      (2, vec![Value::Text("a".to_string())]),
+    ];
+    let mut spill_file = SpillFile::new("test").unwrap();
+    let path = spill_file.path.clone();
+    for record in &records {
+      spill_file.write(record).unwrap();
+    }
+
+    let mut read_records: Vec<(i64, Vec<Value>)> = vec![];
+    while let Some(record) = spill_file.read().unwrap() {
+      read_records.push(record);
+    }
+    assert_eq!(read_records, records);
+    assert!(spill_file.write(&records[0]).is_err());
+
+    drop(spill_file);
+    assert!(!path.exists());
+  }
+}
@@ -0,0 +1,504 @@
+use bincode::serialized_size;
+
+use crate::error::{Result, NollaDBError};
+use crate::storage::{PageId, PAGE_SIZE};
+use crate::storage::pager::{Pager, Page, Cell};
+
+// key 最长的长度，保证一页至少能放下几个 key，分裂之后每一半都能放进一页
+pub const MAX_KEY_SIZE: usize = PAGE_SIZE / 8;
+// 超过这个大小的 value 放到溢出页中
+const MAX_INLINE_VALUE_SIZE: usize = PAGE_SIZE / 8;
+// 一个溢出页中放多少字节，剩下的空间留给页的其他字段
+const OVERFLOW_DATA_SIZE: usize = PAGE_SIZE - 64;
+
+// 存放在 pager 中的一棵 B+ 树，key 和 value 都是字节数组，key 按照字节序排列
+// 根节点所在的页不会变，根节点分裂的时候把原来的内容搬到新的页中，这样别的地方记录的根节点一直有效
+// 删除的时候不合并不满的节点，只回收变空的节点
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BPlusTree {
+  pub root: PageId,
+}
+
+impl BPlusTree {
+  pub fn new(root: PageId) -> Self {
+    BPlusTree { root }
+  }
+
+  // 新建一棵空的树，根节点是一个空的叶子节点
+  pub fn create(pager: &mut Pager) -> Result<Self> {
+    let root = pager.allocate_page()?;
+    pager.write_page(root, &Page::Leaf { keys: vec![], cells: vec![] })?;
+    Ok(BPlusTree { root })
+  }
+
+  // key 已经存在的话替换 value，value 没有变化的话不会修改任何页
+  pub fn insert(&self, pager: &mut Pager, key: &[u8], value: &[u8]) -> Result<()> {
+    if key.len() > MAX_KEY_SIZE {
+      return Err(NollaDBError::Internal(
+        format!("Key is too large: {} bytes, at most {} bytes", key.len(), MAX_KEY_SIZE)
+      ));
+    }
+
+    if let Some((separator, right)) = insert_into(pager, self.root, key, value)? {
+      let left = pager.allocate_page()?;
+      let page = pager.read_page(self.root)?;
+      pager.write_page(left, &page)?;
+      pager.write_page(self.root, &Page::Internal { keys: vec![separator], children: vec![left, right] })?;
+    }
+    Ok(())
+  }
+
+  // 返回 key 是否存在
+  pub fn remove(&self, pager: &mut Pager, key: &[u8]) -> Result<bool> {
+    let (is_removed, _) = remove_from(pager, self.root, key)?;
+    if !is_removed { return Ok(false); }
+
+    // 根节点只剩下一个子节点的话，把子节点搬到根节点的页中，树的高度减一
+    loop {
+      match pager.read_page(self.root)? {
+        Page::Internal { children, .. } if children.len() == 1 => {
+          let page = pager.read_page(children[0])?;
+          pager.write_page(self.root, &page)?;
+          pager.free_page(children[0])?;
+        },
+        Page::Internal { children, .. } if children.is_empty() => {
+          pager.write_page(self.root, &Page::Leaf { keys: vec![], cells: vec![] })?;
+        },
+        _ => break,
+      }
+    }
+    Ok(true)
+  }
+
+  // key 对应的 value，key 不存在的话返回 None
+  pub fn get(&self, pager: &mut Pager, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut page_id = self.root;
+    loop {
+      match pager.read_page(page_id)? {
+        Page::Leaf { keys, cells } => {
+          return match keys.binary_search_by(|k| k.as_slice().cmp(key)) {
+            Ok(i) => Ok(Some(read_cell(pager, &cells[i])?)),
+            Err(_) => Ok(None),
+          };
+        },
+        Page::Internal { keys, children } => {
+          page_id = children[get_child_position(&keys, key)];
+        },
+        _ => return Err(get_corrupted_page_error(page_id)),
+      }
+    }
+  }
+
+  // 按照 key 从小到大的顺序访问每一个 key 和 value
+  pub fn for_each(
+    &self,
+    pager: &mut Pager,
+    f: &mut dyn FnMut(Vec<u8>, Vec<u8>) -> Result<()>,
+  ) -> Result<()> {
+    visit(pager, self.root, f)
+  }
+
+  // 从第一个大于等于 start 的 key 开始按照从小到大的顺序访问 key 和 value，f 返回 false 的时候停下来
+  // 只会读到停下来的地方，前面以及后面的叶子节点都不会读
+  pub fn scan(
+    &self,
+    pager: &mut Pager,
+    start: &[u8],
+    f: &mut dyn FnMut(Vec<u8>, Vec<u8>) -> Result<bool>,
+  ) -> Result<()> {
+    scan_from(pager, self.root, start, &mut |pager, key, cell| f(key, read_cell(pager, &cell)?))?;
+    Ok(())
+  }
+
+  // 和 scan 一样，但是只访问 key，不会读溢出页
+  pub fn scan_keys(
+    &self,
+    pager: &mut Pager,
+    start: &[u8],
+    f: &mut dyn FnMut(Vec<u8>) -> Result<bool>,
+  ) -> Result<()> {
+    scan_from(pager, self.root, start, &mut |_, key, _| f(key))?;
+    Ok(())
+  }
+
+  // 删除整棵树，所有的页都放回 free list
+  pub fn destroy(self, pager: &mut Pager) -> Result<()> {
+    free_tree(pager, self.root)
+  }
+}
+
+// 内部节点中 key 所在的子节点的位置
+fn get_child_position(keys: &[Vec<u8>], key: &[u8]) -> usize {
+  keys.partition_point(|k| k.as_slice() <= key)
+}
+
+// 插入之后这一页放不下的话分裂成两页，返回右边一页的第一个 key 以及右边一页
+fn insert_into(
+  pager: &mut Pager,
+  page_id: PageId,
+  key: &[u8],
+  value: &[u8],
+) -> Result<Option<(Vec<u8>, PageId)>> {
+  match pager.read_page(page_id)? {
+    Page::Leaf { mut keys, mut cells } => {
+      match keys.binary_search_by(|k| k.as_slice().cmp(key)) {
+        Ok(i) => {
+          if read_cell(pager, &cells[i])? == value { return Ok(None); }
+          let cell = write_cell(pager, value)?;
+          let old_cell = std::mem::replace(&mut cells[i], cell);
+          free_cell(pager, old_cell)?;
+        },
+        Err(i) => {
+          let cell = write_cell(pager, value)?;
+          keys.insert(i, key.to_vec());
+          cells.insert(i, cell);
+        },
+      }
+
+      let page = Page::Leaf { keys, cells };
+      if get_size(&page) <= PAGE_SIZE {
+        pager.write_page(page_id, &page)?;
+        return Ok(None);
+      }
+
+      let Page::Leaf { mut keys, mut cells } = page else { unreachable!() };
+      let sizes: Vec<usize> = keys
+        .iter()
+        .zip(&cells)
+        .map(|(key, cell)| get_size(key) + get_size(cell))
+        .collect();
+      let position = get_split_position(&sizes);
+      let right_keys = keys.split_off(position);
+      let right_cells = cells.split_off(position);
+      let separator = right_keys[0].clone();
+
+      let right = pager.allocate_page()?;
+      pager.write_page(right, &Page::Leaf { keys: right_keys, cells: right_cells })?;
+      pager.write_page(page_id, &Page::Leaf { keys, cells })?;
+      Ok(Some((separator, right)))
+    },
+    Page::Internal { mut keys, mut children } => {
+      let position = get_child_position(&keys, key);
+      let (separator, right) = match insert_into(pager, children[position], key, value)? {
+        Some(split) => split,
+        None => return Ok(None),
+      };
+      keys.insert(position, separator);
+      children.insert(position + 1, right);
+
+      let page = Page::Internal { keys, children };
+      if get_size(&page) <= PAGE_SIZE {
+        pager.write_page(page_id, &page)?;
+        return Ok(None);
+      }
+
+      // 中间的 key 移到父节点中，左右两页都不再保留这个 key
+      let Page::Internal { mut keys, mut children } = page else { unreachable!() };
+      let sizes: Vec<usize> = keys.iter().map(get_size).collect();
+      let position = get_split_position(&sizes);
+      let right_keys = keys.split_off(position + 1);
+      let separator = keys.pop().unwrap();
+      let right_children = children.split_off(position + 1);
+
+      let right = pager.allocate_page()?;
+      pager.write_page(right, &Page::Internal { keys: right_keys, children: right_children })?;
+      pager.write_page(page_id, &Page::Internal { keys, children })?;
+      Ok(Some((separator, right)))
+    },
+    _ => Err(get_corrupted_page_error(page_id)),
+  }
+}
+
+// 返回 key 是否存在，以及删除之后这一页是否变空了
+// 变空的子节点会被回收，并且从父节点中删除
+fn remove_from(pager: &mut Pager, page_id: PageId, key: &[u8]) -> Result<(bool, bool)> {
+  match pager.read_page(page_id)? {
+    Page::Leaf { mut keys, mut cells } => {
+      let i = match keys.binary_search_by(|k| k.as_slice().cmp(key)) {
+        Ok(i) => i,
+        Err(_) => return Ok((false, false)),
+      };
+      keys.remove(i);
+      free_cell(pager, cells.remove(i))?;
+
+      let is_empty = keys.is_empty();
+      pager.write_page(page_id, &Page::Leaf { keys, cells })?;
+      Ok((true, is_empty))
+    },
+    Page::Internal { mut keys, mut children } => {
+      let position = get_child_position(&keys, key);
+      let (is_removed, is_child_empty) = remove_from(pager, children[position], key)?;
+      if !is_child_empty { return Ok((is_removed, false)); }
+
+      pager.free_page(children.remove(position))?;
+      if !keys.is_empty() {
+        keys.remove(position.saturating_sub(1));
+      }
+
+      let is_empty = children.is_empty();
+      pager.write_page(page_id, &Page::Internal { keys, children })?;
+      Ok((is_removed, is_empty))
+    },
+    _ => Err(get_corrupted_page_error(page_id)),
+  }
+}
+
+fn visit(
+  pager: &mut Pager,
+  page_id: PageId,
+  f: &mut dyn FnMut(Vec<u8>, Vec<u8>) -> Result<()>,
+) -> Result<()> {
+  match pager.read_page(page_id)? {
+    Page::Leaf { keys, cells } => {
+      for (key, cell) in keys.into_iter().zip(cells) {
+        let value = read_cell(pager, &cell)?;
+        f(key, value)?;
+      }
+      Ok(())
+    },
+    Page::Internal { children, .. } => {
+      for child in children {
+        visit(pager, child, f)?;
+      }
+      Ok(())
+    },
+    _ => Err(get_corrupted_page_error(page_id)),
+  }
+}
+
+// 返回是否要继续访问后面的 key
+// 内部节点中 start 所在的子节点前面的子节点中的 key 都比 start 小，不需要读
+fn scan_from<F: FnMut(&mut Pager, Vec<u8>, Cell) -> Result<bool>>(
+  pager: &mut Pager,
+  page_id: PageId,
+  start: &[u8],
+  f: &mut F,
+) -> Result<bool> {
+  match pager.read_page(page_id)? {
+    Page::Leaf { keys, cells } => {
+      let position = keys.partition_point(|k| k.as_slice() < start);
+      for (key, cell) in keys.into_iter().zip(cells).skip(position) {
+        if !f(pager, key, cell)? { return Ok(false); }
+      }
+      Ok(true)
+    },
+    Page::Internal { keys, children } => {
+      let position = get_child_position(&keys, start);
+      for child in children.into_iter().skip(position) {
+        if !scan_from(pager, child, start, f)? { return Ok(false); }
+      }
+      Ok(true)
+    },
+    _ => Err(get_corrupted_page_error(page_id)),
+  }
+}
+
+fn free_tree(pager: &mut Pager, page_id: PageId) -> Result<()> {
+  match pager.read_page(page_id)? {
+    Page::Leaf { cells, .. } => {
+      for cell in cells {
+        free_cell(pager, cell)?;
+      }
+    },
+    Page::Internal { children, .. } => {
+      for child in children {
+        free_tree(pager, child)?;
+      }
+    },
+    _ => return Err(get_corrupted_page_error(page_id)),
+  }
+  pager.free_page(page_id)
+}
+
+// 大的 value 切成几段放到溢出页中，从最后一段开始写，这样每一页都知道下一页在哪里
+fn write_cell(pager: &mut Pager, value: &[u8]) -> Result<Cell> {
+  if value.len() <= MAX_INLINE_VALUE_SIZE {
+    return Ok(Cell::Inline(value.to_vec()));
+  }
+
+  let mut next = None;
+  for data in value.chunks(OVERFLOW_DATA_SIZE).rev() {
+    let page_id = pager.allocate_page()?;
+    pager.write_page(page_id, &Page::Overflow { next, data: data.to_vec() })?;
+    next = Some(page_id);
+  }
+  Ok(Cell::Overflow(next.unwrap()))
+}
+
+fn read_cell(pager: &mut Pager, cell: &Cell) -> Result<Vec<u8>> {
+  let mut next = match cell {
+    Cell::Inline(value) => return Ok(value.to_vec()),
+    Cell::Overflow(page_id) => Some(*page_id),
+  };
+
+  let mut value = vec![];
+  while let Some(page_id) = next {
+    match pager.read_page(page_id)? {
+      Page::Overflow { next: next_page_id, data } => {
+        value.extend(data);
+        next = next_page_id;
+      },
+      _ => return Err(get_corrupted_page_error(page_id)),
+    }
+  }
+  Ok(value)
+}
+
+fn free_cell(pager: &mut Pager, cell: Cell) -> Result<()> {
+  let mut next = match cell {
+    Cell::Inline(_) => return Ok(()),
+    Cell::Overflow(page_id) => Some(page_id),
+  };
+
+  while let Some(page_id) = next {
+    match pager.read_page(page_id)? {
+      Page::Overflow { next: next_page_id, .. } => {
+        pager.free_page(page_id)?;
+        next = next_page_id;
+      },
+      _ => return Err(get_corrupted_page_error(page_id)),
+    }
+  }
+  Ok(())
+}
+
+// 按照大小把一页分成两半，每一半至少有一个
+fn get_split_position(sizes: &[usize]) -> usize {
+  let half = sizes.iter().sum::<usize>() / 2;
+  let mut size = 0;
+  let position = sizes
+    .iter()
+    .position(|s| {
+      size += s;
+      size >= half
+    })
+    .unwrap_or(0);
+  position.clamp(1, sizes.len() - 1)
+}
+
+fn get_size<T: serde::Serialize>(value: &T) -> usize {
+  serialized_size(value).unwrap_or(u64::MAX) as usize
+}
+
+fn get_corrupted_page_error(page_id: PageId) -> NollaDBError {
+  NollaDBError::Internal(format!("Page {} is corrupted", page_id))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use pretty_assertions::{assert_eq};
+
+  // 比较长的 key，内部节点也会分裂
+  fn get_key(i: u32) -> Vec<u8> {
+    let mut key = i.to_be_bytes().to_vec();
+    key.resize(200, 0);
+    key
+  }
+
+  // 每隔几个 key 放一个需要溢出页的 value
+  fn get_value(i: u32) -> Vec<u8> {
+    let length = if i.is_multiple_of(7) { PAGE_SIZE * 2 + i as usize } else { i as usize % 100 };
+    vec![i as u8; length]
+  }
+
+  fn get_entries(tree: &BPlusTree, pager: &mut Pager) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut entries = vec![];
+    tree.for_each(pager, &mut |key, value| {
+      entries.push((key, value));
+      Ok(())
+    }).unwrap();
+    entries
+  }
+
+  #[test]
+  fn test_b_plus_tree() {
+    let path = std::env::temp_dir().join(format!("nolladb-b-plus-tree-{}.db", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    let mut pager = Pager::open(path, 8).unwrap();
+    let tree = BPlusTree::create(&mut pager).unwrap();
+
+    // 倒序插入，每一页都会分裂好几次
+    for i in (0..500).rev() {
+      tree.insert(&mut pager, &get_key(i), &get_value(i)).unwrap();
+    }
+    // 树有三层
+    match pager.read_page(tree.root).unwrap() {
+      Page::Internal { children, .. } => {
+        assert!(matches!(pager.read_page(children[0]).unwrap(), Page::Internal { .. }));
+      },
+      page => panic!("Root should be an internal node: {:?}", page),
+    }
+    assert_eq!(
+      get_entries(&tree, &mut pager),
+      (0..500).map(|i| (get_key(i), get_value(i))).collect::<Vec<_>>()
+    );
+
+    // 替换 value，以及删除一半的 key
+    tree.insert(&mut pager, &get_key(1), &get_value(7)).unwrap();
+    assert_eq!(get_entries(&tree, &mut pager)[1], (get_key(1), get_value(7)));
+    assert_eq!(tree.get(&mut pager, &get_key(1)).unwrap(), Some(get_value(7)));
+    assert_eq!(tree.get(&mut pager, &get_key(499)).unwrap(), Some(get_value(499)));
+    assert_eq!(tree.get(&mut pager, &get_key(500)).unwrap(), None);
+
+    // 从中间开始访问，停下来之后不会再访问后面的 key
+    let mut keys = vec![];
+    tree.scan(&mut pager, &get_key(250)[..4], &mut |key, value| {
+      assert_eq!(value, get_value(u32::from_be_bytes(key[..4].try_into().unwrap())));
+      keys.push(key);
+      Ok(keys.len() < 3)
+    }).unwrap();
+    assert_eq!(keys, vec![get_key(250), get_key(251), get_key(252)]);
+    let mut keys = vec![];
+    tree.scan_keys(&mut pager, &get_key(498), &mut |key| {
+      keys.push(key);
+      Ok(true)
+    }).unwrap();
+    assert_eq!(keys, vec![get_key(498), get_key(499)]);
+    for i in (0..500).filter(|i| i % 2 == 0) {
+      assert!(tree.remove(&mut pager, &get_key(i)).unwrap());
+    }
+    assert!(!tree.remove(&mut pager, &get_key(0)).unwrap());
+    assert_eq!(get_entries(&tree, &mut pager).len(), 250);
+
+    // 全部删除之后根节点变回空的叶子节点，所有的页都被回收，重新插入不需要新的页
+    for i in (0..500).filter(|i| i % 2 == 1) {
+      assert!(tree.remove(&mut pager, &get_key(i)).unwrap());
+    }
+    assert_eq!(pager.read_page(tree.root).unwrap(), Page::Leaf { keys: vec![], cells: vec![] });
+    pager.flush().unwrap();
+    let file_size = fs::metadata(path).unwrap().len();
+    for i in (0..500).rev() {
+      tree.insert(&mut pager, &get_key(i), &get_value(i)).unwrap();
+    }
+    pager.flush().unwrap();
+    assert_eq!(fs::metadata(path).unwrap().len(), file_size);
+
+    // 整棵树删除之后也一样
+    tree.destroy(&mut pager).unwrap();
+    let tree = BPlusTree::create(&mut pager).unwrap();
+    for i in (0..500).rev() {
+      tree.insert(&mut pager, &get_key(i), &get_value(i)).unwrap();
+    }
+    pager.flush().unwrap();
+    assert_eq!(fs::metadata(path).unwrap().len(), file_size);
+
+    fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn test_b_plus_tree_key_too_large() {
+    let path = std::env::temp_dir().join(format!("nolladb-b-plus-tree-key-{}.db", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    let mut pager = Pager::open(path, 8).unwrap();
+    let tree = BPlusTree::create(&mut pager).unwrap();
+
+    assert!(tree.insert(&mut pager, &vec![0; MAX_KEY_SIZE], b"").is_ok());
+    assert!(tree.insert(&mut pager, &vec![0; MAX_KEY_SIZE + 1], b"").is_err());
+
+    fs::remove_file(path).unwrap();
+  }
+}
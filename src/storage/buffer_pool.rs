@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+#[cfg(test)]
+use std::cell::Cell;
+
+use crate::error::{Result, NollaDBError};
+use crate::storage::{PageId, PAGE_SIZE};
+use crate::storage::journal::Journal;
+
+#[cfg(test)]
+thread_local! {
+  // 测试用，还可以写回多少页，用完之后写回都会失败，用来模拟写到一半崩溃
+  pub static PAGE_WRITES_BEFORE_CRASH: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// buffer pool 中缓存的一页
+struct Frame {
+  data: Vec<u8>,
+  // 被修改过，还没有写回文件
+  is_dirty: bool,
+  // 最近一次被访问的时间，用来找到最久没有被访问的页
+  last_used: u64,
+}
+
+// 文件中的页先读到 buffer pool 中再使用，最多缓存 capacity 页
+// 缓存满了之后按照 LRU 换出，被换出的页被修改过的话先写回文件
+// flush 时只把被修改过的页写回文件
+// 覆盖文件中的页之前先记录到回滚日志中，flush 完成之前崩溃的话文件可以恢复到上次 flush 之后的样子
+pub struct BufferPool {
+  file: File,
+  journal: Journal,
+  capacity: usize,
+  frames: HashMap<PageId, Frame>,
+  clock: u64,
+}
+
+impl BufferPool {
+  pub fn new(file: File, journal: Journal, capacity: usize) -> Self {
+    BufferPool {
+      file,
+      journal,
+      capacity: capacity.max(1),
+      frames: HashMap::new(),
+      clock: 0,
+    }
+  }
+
+  pub fn read_page(&mut self, page_id: PageId) -> Result<&[u8]> {
+    self.clock += 1;
+    if !self.frames.contains_key(&page_id) {
+      self.evict_page()?;
+      let mut data = vec![0; PAGE_SIZE];
+      self.file
+        .seek(SeekFrom::Start(get_offset(page_id)))
+        .and_then(|_| self.file.read_exact(&mut data))
+        .map_err(|error| NollaDBError::Internal(
+          format!("Can not read page {}: {}", page_id, error)
+        ))?;
+      self.frames.insert(page_id, Frame { data, is_dirty: false, last_used: self.clock });
+    }
+
+    let frame = self.frames.get_mut(&page_id).unwrap();
+    frame.last_used = self.clock;
+    Ok(&frame.data)
+  }
+
+  // 只写到 buffer pool 中，flush 或者被换出的时候才会写回文件
+  pub fn write_page(&mut self, page_id: PageId, mut data: Vec<u8>) -> Result<()> {
+    self.clock += 1;
+    data.resize(PAGE_SIZE, 0);
+    if !self.frames.contains_key(&page_id) {
+      self.evict_page()?;
+    }
+    self.frames.insert(page_id, Frame { data, is_dirty: true, last_used: self.clock });
+    Ok(())
+  }
+
+  // 把所有被修改过的页写回文件，返回写回的页数
+  pub fn flush(&mut self) -> Result<usize> {
+    let mut page_ids: Vec<PageId> = self.frames
+      .iter()
+      .filter(|(_, frame)| frame.is_dirty)
+      .map(|(page_id, _)| *page_id)
+      .collect();
+    page_ids.sort_unstable();
+
+    // 一次把所有要覆盖的页记录到日志中，只需要写一次磁盘
+    self.journal.record(&mut self.file, &page_ids)?;
+    for page_id in &page_ids {
+      self.write_back(*page_id)?;
+    }
+    let file_length = self.file
+      .sync_all()
+      .and_then(|_| self.file.metadata())
+      .map_err(|error| NollaDBError::Internal(format!("Can not sync file: {}", error)))?
+      .len();
+    self.journal.commit(file_length)?;
+    Ok(page_ids.len())
+  }
+
+  // 缓存满了的话换出最久没有被访问的页
+  fn evict_page(&mut self) -> Result<()> {
+    if self.frames.len() < self.capacity { return Ok(()); }
+
+    let page_id = match self.frames.iter().min_by_key(|(_, frame)| frame.last_used) {
+      Some((page_id, _)) => *page_id,
+      None => return Ok(()),
+    };
+    self.write_back(page_id)?;
+    self.frames.remove(&page_id);
+    Ok(())
+  }
+
+  fn write_back(&mut self, page_id: PageId) -> Result<()> {
+    if !self.frames[&page_id].is_dirty { return Ok(()); }
+
+    #[cfg(test)]
+    {
+      let is_crashed = PAGE_WRITES_BEFORE_CRASH.with(|page_writes| match page_writes.get() {
+        Some(0) => true,
+        Some(n) => {
+          page_writes.set(Some(n - 1));
+          false
+        },
+        None => false,
+      });
+      if is_crashed {
+        return Err(NollaDBError::Internal(format!("Crashed before writing page {}", page_id)));
+      }
+    }
+
+    self.journal.record(&mut self.file, &[page_id])?;
+    let frame = self.frames.get_mut(&page_id).unwrap();
+    self.file
+      .seek(SeekFrom::Start(get_offset(page_id)))
+      .and_then(|_| self.file.write_all(&frame.data))
+      .map_err(|error| NollaDBError::Internal(
+        format!("Can not write page {}: {}", page_id, error)
+      ))?;
+    frame.is_dirty = false;
+    Ok(())
+  }
+}
+
+pub fn get_offset(page_id: PageId) -> u64 {
+  page_id as u64 * PAGE_SIZE as u64
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs::{self, OpenOptions};
+  use pretty_assertions::{assert_eq};
+
+  #[test]
+  fn test_buffer_pool() {
+    let path = std::env::temp_dir().join(format!("nolladb-buffer-pool-{}.db", std::process::id()));
+    let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+    let mut buffer_pool = BufferPool::new(file, Journal::new(path.to_str().unwrap(), 0), 2);
+
+    for page_id in 0..4 {
+      buffer_pool.write_page(page_id, vec![page_id as u8; 8]).unwrap();
+    }
+    // 最多缓存两页，先写的两页已经被换出并写回文件了，剩下的两页在 flush 时写回
+    assert_eq!(buffer_pool.frames.len(), 2);
+    assert_eq!(buffer_pool.flush().unwrap(), 2);
+    assert_eq!(buffer_pool.flush().unwrap(), 0);
+    assert_eq!(fs::metadata(&path).unwrap().len(), 4 * PAGE_SIZE as u64);
+
+    // 只有被修改过的页会写回
+    assert_eq!(&buffer_pool.read_page(0).unwrap()[..8], &[0; 8]);
+    assert_eq!(&buffer_pool.read_page(1).unwrap()[..8], &[1; 8]);
+    buffer_pool.write_page(1, vec![9; 8]).unwrap();
+    assert_eq!(buffer_pool.flush().unwrap(), 1);
+    assert_eq!(&buffer_pool.read_page(3).unwrap()[..8], &[3; 8]);
+    assert_eq!(&buffer_pool.read_page(1).unwrap()[..8], &[9; 8]);
+
+    fs::remove_file(&path).unwrap();
+  }
+}
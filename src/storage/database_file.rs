@@ -0,0 +1,650 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, NollaDBError};
+use crate::database::Database;
+use crate::table::Table;
+use crate::table::table_rows::TableRows;
+use crate::table::row::value::Value;
+use crate::table::column::Column;
+use crate::table::column::index::Index;
+use crate::table::composite_index::CompositeIndex;
+use crate::storage::{PageId, DEFAULT_BUFFER_POOL_SIZE};
+use crate::storage::pager::{Pager, SharedPager, is_paged_file};
+use crate::storage::b_plus_tree::BPlusTree;
+use crate::storage::stored_table::StoredTable;
+use crate::storage::key::{encode_row_id, encode_index_entry};
+
+// catalog 中一张表的信息，key 是表名
+// 每张表的数据是一棵 B+ 树，key 是 row id，value 是按照 table_columns 的顺序排列的一行
+// 每个索引也是一棵 B+ 树，key 是索引的值加上 row id
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+struct TableEntry {
+  // 只有表结构的表，没有数据，索引也是空的
+  table: Table,
+  rows_root: PageId,
+  // PRIMARY KEY、UNIQUE 以及 CREATE INDEX 建立的单列索引，key 是 column name
+  column_index_roots: BTreeMap<String, PageId>,
+  // 多列索引，key 是索引名
+  composite_index_roots: BTreeMap<String, PageId>,
+}
+
+impl TableEntry {
+  fn get_roots(&self) -> Vec<PageId> {
+    std::iter::once(self.rows_root)
+      .chain(self.column_index_roots.values().cloned())
+      .chain(self.composite_index_roots.values().cloned())
+      .collect()
+  }
+
+  // 文件中按照这个 entry 保存的表，clone 出来的表共用同一个 pager
+  fn get_stored_table(&self, pager: &SharedPager) -> StoredTable {
+    StoredTable::new(
+      pager.clone(),
+      self.rows_root,
+      self.table.table_columns
+        .iter()
+        .map(|table_column| table_column.column_name.to_string())
+        .collect(),
+      self.column_index_roots.clone(),
+      self.composite_index_roots.clone(),
+    )
+  }
+}
+
+// 保存一张表时要写的一个索引，column_positions 是索引的 column 在一行中的位置
+// 单列索引不写入 NULL，多列索引中有 NULL 的行也要写入
+struct IndexTree {
+  tree: BPlusTree,
+  column_positions: Vec<usize>,
+  skips_null: bool,
+}
+
+impl IndexTree {
+  fn get_values(&self, row_values: &Option<Vec<Value>>) -> Option<Vec<Value>> {
+    let row_values = row_values.as_ref()?;
+    let values: Vec<Value> = self.column_positions
+      .iter()
+      .map(|i| row_values[*i].clone())
+      .collect();
+    match self.skips_null && values[0].is_null() {
+      true => None,
+      false => Some(values),
+    }
+  }
+
+  // 一行的值有变化时，只修改索引中值有变化的 key
+  fn update(
+    &self,
+    pager: &mut Pager,
+    row_id: i64,
+    old_row_values: &Option<Vec<Value>>,
+    row_values: &Option<Vec<Value>>,
+  ) -> Result<()> {
+    let old_values = self.get_values(old_row_values);
+    let values = self.get_values(row_values);
+    if old_values == values { return Ok(()); }
+    if let Some(values) = old_values {
+      self.tree.remove(pager, &encode_index_entry(&values, row_id).0)?;
+    }
+    if let Some(values) = values {
+      let (key, value) = encode_index_entry(&values, row_id);
+      self.tree.insert(pager, &key, &value)?;
+    }
+    Ok(())
+  }
+}
+
+// 保存一张表时要写的所有索引
+// PRIMARY KEY、UNIQUE 以及 CREATE INDEX 建立的单列索引，以及所有的多列索引
+struct IndexTrees {
+  // 文件中已经有的索引，只需要修改被修改过的行
+  existing: Vec<IndexTree>,
+  // 文件中还没有的索引，要用整张表建立
+  created: Vec<IndexTree>,
+  column_index_roots: BTreeMap<String, PageId>,
+  composite_index_roots: BTreeMap<String, PageId>,
+}
+
+impl IndexTrees {
+  fn new(pager: &SharedPager, table: &Table, stored_table: Option<&StoredTable>) -> Result<Self> {
+    let mut index_trees = IndexTrees {
+      existing: vec![],
+      created: vec![],
+      column_index_roots: BTreeMap::new(),
+      composite_index_roots: BTreeMap::new(),
+    };
+
+    for table_column in &table.table_columns {
+      if !table.has_usable_index(table_column) { continue; }
+      let column_name = &table_column.column_name;
+      let root = stored_table.and_then(|stored_table| stored_table.column_index_roots.get(column_name));
+      let index_tree = index_trees.add(pager, table, root, &[column_name.to_string()], true)?;
+      index_trees.column_index_roots.insert(column_name.to_string(), index_tree);
+    }
+
+    let mut index_names: Vec<&String> = table.composite_indexes.keys().collect();
+    index_names.sort();
+    for index_name in index_names {
+      let root = stored_table.and_then(|stored_table| stored_table.composite_index_roots.get(index_name));
+      let column_names = &table.composite_indexes[index_name].column_names;
+      let index_tree = index_trees.add(pager, table, root, column_names, false)?;
+      index_trees.composite_index_roots.insert(index_name.to_string(), index_tree);
+    }
+
+    Ok(index_trees)
+  }
+
+  // 文件中已经有的索引用原来的树，否则新建一棵，返回树的根
+  fn add(
+    &mut self,
+    pager: &SharedPager,
+    table: &Table,
+    root: Option<&PageId>,
+    column_names: &[String],
+    skips_null: bool,
+  ) -> Result<PageId> {
+    let tree = match root {
+      Some(root) => BPlusTree::new(*root),
+      None => BPlusTree::create(&mut pager.lock())?,
+    };
+    let index_tree = IndexTree {
+      tree,
+      column_positions: table.get_column_positions(column_names)?,
+      skips_null,
+    };
+    match root {
+      Some(_) => self.existing.push(index_tree),
+      None => self.created.push(index_tree),
+    }
+    Ok(tree.root)
+  }
+
+  fn contains_root(&self, root: &PageId) -> bool {
+    self.column_index_roots.values().chain(self.composite_index_roots.values()).any(|r| r == root)
+  }
+}
+
+// 打开分页的文件，只读出 catalog 中每张表的表结构
+// 表中的行以及索引留在文件中，查询的时候通过 buffer pool 按需读出用到的页，见 StoredTable
+pub fn read_database(path: &str) -> Result<Database> {
+  let pager = SharedPager::new(Pager::open(path, DEFAULT_BUFFER_POOL_SIZE)?);
+  let table_entries = read_catalog(&mut pager.lock())?;
+  let mut database = Database::new(path.to_string());
+  for (table_name, table_entry) in table_entries {
+    let stored_table = table_entry.get_stored_table(&pager);
+    let mut table = table_entry.table;
+    table.set_stored_table(stored_table);
+    database.tables.insert(table_name, table);
+  }
+  database.pager = Some(pager);
+  Ok(database)
+}
+
+// 把 database 写到分页的文件中，返回写回文件的页数
+// 保存到 database 自己的文件时，文件中已经有的表只写上次保存之后修改过的行，见 StoredTable::changed_row_ids
+// 其他的表，以及保存到别的文件时，整张表分批读出来写到新的树中
+// 不再用到的树占用的页放回 free list，之后会被重新使用
+// 以前整个 bincode 序列化的文件会被替换成分页的文件
+pub fn save_database(path: &str, database: &mut Database) -> Result<usize> {
+  if Path::new(path).exists() && !is_paged_file(path) {
+    fs::remove_file(path)
+      .map_err(|error| NollaDBError::Internal(format!("Can not replace {}: {}", path, error)))?;
+  }
+
+  let is_own_file = path == database.database_name;
+  let pager = match (is_own_file, &database.pager) {
+    (true, Some(pager)) => pager.clone(),
+    _ => SharedPager::new(Pager::open(path, DEFAULT_BUFFER_POOL_SIZE)?),
+  };
+  let catalog = BPlusTree::new(pager.lock().get_catalog_root());
+  let table_entries = read_catalog(&mut pager.lock())?;
+
+  // 表还在用的树留着，其他的树都是被删除的表以及索引，先把它们的页放回 free list
+  let used_roots: HashSet<PageId> = database.tables
+    .values()
+    .filter_map(|table| table.stored_table.as_ref())
+    .filter(|stored_table| stored_table.pager == pager)
+    .flat_map(StoredTable::get_roots)
+    .collect();
+  let mut table_names: Vec<&String> = table_entries.keys().collect();
+  table_names.sort();
+  for table_name in table_names {
+    let mut pager = pager.lock();
+    for root in table_entries[table_name].get_roots() {
+      if !used_roots.contains(&root) {
+        BPlusTree::new(root).destroy(&mut pager)?;
+      }
+    }
+    if !database.tables.contains_key(table_name) {
+      catalog.remove(&mut pager, table_name.as_bytes())?;
+    }
+  }
+
+  let mut table_names: Vec<String> = database.tables.keys().cloned().collect();
+  table_names.sort();
+  let mut saved_table_entries = vec![];
+  for table_name in table_names {
+    let table = &database.tables[&table_name];
+    let table_entry = match &table.stored_table {
+      Some(stored_table) if stored_table.pager == pager => save_changed_rows(&pager, table, stored_table)?,
+      _ => save_table(&pager, table)?,
+    };
+    if table_entries.get(&table_name) != Some(&table_entry) {
+      catalog.insert(&mut pager.lock(), table_name.as_bytes(), &encode(&table_entry)?)?;
+    }
+    saved_table_entries.push((table_name, table_entry));
+  }
+
+  let number_of_pages = {
+    let mut pager = pager.lock();
+    if let (true, Some(wal)) = (is_own_file, &database.wal) {
+      pager.set_wal_generation(wal.generation)?;
+    }
+    pager.flush()?
+  };
+
+  // 保存到自己的文件之后表中所有的行都在文件中了，之后查询的时候再从文件中读
+  // 别的文件和这个 database 无关
+  if is_own_file {
+    for (table_name, table_entry) in saved_table_entries {
+      let stored_table = table_entry.get_stored_table(&pager);
+      if let Some(table) = database.tables.get_mut(&table_name) {
+        table.set_stored_table(stored_table);
+      }
+    }
+    database.pager = Some(pager);
+  }
+  Ok(number_of_pages)
+}
+
+// 文件中已经有哪一代以及之前的 WAL 中的提交，以前整个 bincode 序列化的文件以及不存在的文件是 0
+pub fn read_wal_generation(path: &str) -> Result<u64> {
+  if !is_paged_file(path) { return Ok(0); }
+  Ok(Pager::open(path, 1)?.get_wal_generation())
+}
+
+fn read_catalog(pager: &mut Pager) -> Result<HashMap<String, TableEntry>> {
+  let catalog = BPlusTree::new(pager.get_catalog_root());
+  let mut table_entries = HashMap::new();
+  catalog.for_each(pager, &mut |key, value| {
+    let table_name = String::from_utf8(key)
+      .map_err(|_| NollaDBError::Internal("Can not decode table name from the database file".to_string()))?;
+    table_entries.insert(table_name, decode(&value)?);
+    Ok(())
+  })?;
+  Ok(table_entries)
+}
+
+// 整张表写到新的树中
+fn save_table(pager: &SharedPager, table: &Table) -> Result<TableEntry> {
+  let rows_tree = BPlusTree::create(&mut pager.lock())?;
+  let index_trees = IndexTrees::new(pager, table, None)?;
+  write_rows(pager, table, Some(rows_tree), &index_trees.created)?;
+
+  Ok(TableEntry {
+    table: get_table_schema(table),
+    rows_root: rows_tree.root,
+    column_index_roots: index_trees.column_index_roots,
+    composite_index_roots: index_trees.composite_index_roots,
+  })
+}
+
+// 文件中已经有这张表，只写上次保存之后修改过的行，索引中也只修改值有变化的 key
+// 表结构变化之后文件中的行和 table_columns 对不上，整张表重新写到一棵新的树中，已经有的索引不受影响
+// 文件中还没有的索引，比如保存之后新建的索引，用整张表建立
+fn save_changed_rows(pager: &SharedPager, table: &Table, stored_table: &StoredTable) -> Result<TableEntry> {
+  let index_trees = IndexTrees::new(pager, table, Some(stored_table))?;
+  let has_same_columns = stored_table.has_same_columns(&table.table_columns);
+  let rows_tree = BPlusTree::new(stored_table.rows_root);
+  for row_id in &stored_table.changed_row_ids {
+    let old_row_values = stored_table.get_row_values(*row_id, &table.table_columns)?;
+    let row_values = table.read_row(row_id)?;
+    if old_row_values == row_values { continue; }
+
+    let mut pager = pager.lock();
+    if has_same_columns {
+      let key = encode_row_id(*row_id);
+      match &row_values {
+        Some(row_values) => rows_tree.insert(&mut pager, &key, &encode(row_values)?)?,
+        None => { rows_tree.remove(&mut pager, &key)?; },
+      }
+    }
+    for index_tree in &index_trees.existing {
+      index_tree.update(&mut pager, *row_id, &old_row_values, &row_values)?;
+    }
+  }
+
+  // 新建的索引和新的行一起写，原来的那棵树删掉之后就读不到文件中的行了
+  let rows_tree = match has_same_columns {
+    true => {
+      write_rows(pager, table, None, &index_trees.created)?;
+      rows_tree
+    },
+    false => {
+      let new_rows_tree = BPlusTree::create(&mut pager.lock())?;
+      write_rows(pager, table, Some(new_rows_tree), &index_trees.created)?;
+      rows_tree.destroy(&mut pager.lock())?;
+      new_rows_tree
+    },
+  };
+
+  // 不能再用的索引，比如 DROP INDEX 之后只剩下 NOT NULL 约束的 column
+  let mut pager = pager.lock();
+  for root in stored_table.column_index_roots.values().chain(stored_table.composite_index_roots.values()) {
+    if !index_trees.contains_root(root) {
+      BPlusTree::new(*root).destroy(&mut pager)?;
+    }
+  }
+
+  Ok(TableEntry {
+    table: get_table_schema(table),
+    rows_root: rows_tree.root,
+    column_index_roots: index_trees.column_index_roots,
+    composite_index_roots: index_trees.composite_index_roots,
+  })
+}
+
+// 把表中所有的行写到 rows_tree 以及 index_trees 中
+// 文件中的行会被分批读出来，读的时候不能拿着 pager 的锁，所以每一行写的时候再拿
+fn write_rows(pager: &SharedPager, table: &Table, rows_tree: Option<BPlusTree>, index_trees: &[IndexTree]) -> Result<()> {
+  if rows_tree.is_none() && index_trees.is_empty() { return Ok(()); }
+  table.for_each_row(&mut |row_id, row_values| {
+    let mut pager = pager.lock();
+    if let Some(rows_tree) = rows_tree {
+      rows_tree.insert(&mut pager, &encode_row_id(row_id), &encode(&row_values)?)?;
+    }
+    let row_values = Some(row_values);
+    for index_tree in index_trees {
+      if let Some(values) = index_tree.get_values(&row_values) {
+        let (key, value) = encode_index_entry(&values, row_id);
+        index_tree.tree.insert(&mut pager, &key, &value)?;
+      }
+    }
+    Ok(())
+  })
+}
+
+// 去掉表中的数据以及索引的内容，只留下表结构
+// 每次保存都会用到，不能先复制整张表再去掉数据
+pub fn get_table_schema(table: &Table) -> Table {
+  Table {
+    primary_key: table.primary_key.to_string(),
+    table_name: table.table_name.to_string(),
+    indexes: table.indexes.clone(),
+    unique_indexes: table.unique_indexes.clone(),
+    composite_indexes: table.composite_indexes
+      .iter()
+      .map(|(index_name, composite_index)| (index_name.to_string(), CompositeIndex::new(
+        composite_index.column_names.clone(),
+        composite_index.is_unique,
+        composite_index.is_primary_key,
+        composite_index.is_constraint,
+      )))
+      .collect(),
+    most_recent_row_id: table.most_recent_row_id,
+    table_rows: TableRows::default(),
+    table_columns: table.table_columns
+      .iter()
+      .map(|table_column| Column {
+        column_name: table_column.column_name.to_string(),
+        column_datatype: table_column.column_datatype.clone(),
+        is_primary_key: table_column.is_primary_key,
+        is_unique_constraint: table_column.is_unique_constraint,
+        is_not_null_constraint: table_column.is_not_null_constraint,
+        is_indexed: table_column.is_indexed,
+        index: Index::new(&table_column.column_datatype),
+        default_value: table_column.default_value.clone(),
+      })
+      .collect(),
+    row_changes: vec![],
+    row_versions: HashMap::new(),
+    last_commit_ts: 0,
+    stored_table: None,
+  }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+  serialize(value)
+    .map_err(|error| NollaDBError::Internal(format!("Can not encode the database file: {}", error)))
+}
+
+pub fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+  deserialize(bytes)
+    .map_err(|error| NollaDBError::Internal(format!("Can not decode the database file: {}", error)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::{assert_eq};
+  use crate::sql_query::handle_sql_query;
+  use crate::database::database_manager::DatabaseManager;
+  use crate::table::ScannedRow;
+  use crate::sql_query::get_sql_ast;
+  use crate::sql_query::result_set::ResultSet;
+  use crate::sql_query::query::select::SelectQuery;
+  use crate::sql_query::planner::execute_select_query;
+
+  fn get_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("nolladb-{}-{}.db", name, std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let _ = fs::remove_file(&path);
+    path
+  }
+
+  // 文件中的表只有用到的时候才会读出来，所以比较表结构以及读出来的所有行
+  // 提交的时间戳不会被保存
+  fn get_tables(database: &Database) -> HashMap<String, (Table, Vec<ScannedRow>)> {
+    database.tables
+      .iter()
+      .map(|(table_name, table)| (
+        table_name.to_string(),
+        (get_table_schema(table), table.scan_rows(None, usize::MAX).unwrap()),
+      ))
+      .collect()
+  }
+
+  // 读到内存中的行数，文件中没有修改过的行不会被读到内存中
+  fn get_number_of_loaded_rows(table: &Table) -> usize {
+    table.table_rows.read()[&table.table_columns[0].column_name].get_row_ids().len()
+  }
+
+  fn create_database(path: &str) -> Database {
+    let mut database = Database::new(path.to_string());
+    for query in [
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        email TEXT UNIQUE,
+        active BOOLEAN,
+        score REAL,
+        UNIQUE (name, score)
+      );",
+      "CREATE INDEX idx_active ON test (active);",
+      "CREATE TABLE other (id INTEGER PRIMARY KEY, note TEXT);",
+      "INSERT INTO other (note) VALUES ('x');",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    // 有 NULL，也有需要放到溢出页中的长字符串
+    let values: Vec<String> = (0..300)
+      .map(|i| {
+        let email = if i % 10 == 0 { "NULL".to_string() } else { format!("'{}{}@x.com'", "a".repeat(i % 3 * 400), i) };
+        format!("('n{}', {}, {}, {})", i, email, i % 2 == 0, i as f32 / 4.0)
+      })
+      .collect();
+    handle_sql_query(
+      &format!("INSERT INTO test (name, email, active, score) VALUES {};", values.join(", ")),
+      &mut database,
+    ).unwrap();
+    database
+  }
+
+  #[test]
+  fn test_save_and_read_database() {
+    let path = get_path("database-file");
+    let mut database = create_database(&path);
+    assert!(save_database(&path, &mut database).unwrap() > 10);
+    assert_eq!(get_tables(&read_database(&path).unwrap()), get_tables(&database));
+
+    // 没有修改的话不会写任何页，修改一行只会写很少的页
+    assert_eq!(save_database(&path, &mut database).unwrap(), 0);
+    handle_sql_query("UPDATE test SET score = 100.0 WHERE id = 150;", &mut database).unwrap();
+    assert!(save_database(&path, &mut database).unwrap() <= 6);
+    assert_eq!(get_tables(&read_database(&path).unwrap()), get_tables(&database));
+
+    // 删除的表和索引占用的页会被重新使用，文件不会变大
+    let file_size = fs::metadata(&path).unwrap().len();
+    for query in [
+      "DROP INDEX idx_active;",
+      "ALTER TABLE other ADD COLUMN score INTEGER;",
+      "DELETE FROM test WHERE id > 100;",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    save_database(&path, &mut database).unwrap();
+    assert_eq!(get_tables(&read_database(&path).unwrap()), get_tables(&database));
+    handle_sql_query("DROP TABLE test;", &mut database).unwrap();
+    save_database(&path, &mut database).unwrap();
+    let mut database = create_database(&path);
+    save_database(&path, &mut database).unwrap();
+    assert_eq!(get_tables(&read_database(&path).unwrap()), get_tables(&database));
+    assert_eq!(fs::metadata(&path).unwrap().len(), file_size);
+
+    fs::remove_file(&path).unwrap();
+  }
+
+  // 保存到自己的文件时只写提交过修改的行，索引中的 key 也跟着修改
+  // 保存到别的文件不会清空这些标记，之后保存到自己的文件时还会写这些行
+  #[test]
+  fn test_save_unsaved_rows() {
+    let path = get_path("unsaved-rows");
+    let other_path = get_path("unsaved-rows-other");
+    let mut database = create_database(&path);
+    let number_of_pages = save_database(&path, &mut database).unwrap();
+    // 打开文件时不会读出任何一行
+    let mut database = read_database(&path).unwrap();
+    assert!(database.tables.values().all(|table| get_number_of_loaded_rows(table) == 0));
+    assert!(database.tables.values().all(|table| table.stored_table.as_ref().unwrap().changed_row_ids.is_empty()));
+    assert_eq!(database.tables["test"].get_row(&150).unwrap()["score"], Value::Real(149.0 / 4.0));
+    assert_eq!(get_number_of_loaded_rows(&database.tables["test"]), 0);
+
+    for query in [
+      "UPDATE test SET email = NULL, active = NULL WHERE id = 2;",
+      "UPDATE test SET email = 'new@x.com', score = 0.5 WHERE id = 1;",
+      "UPDATE test SET name = 'renamed' WHERE id = 3;",
+      "DELETE FROM test WHERE id > 250;",
+      "INSERT INTO test (name, email, active, score) VALUES ('new', NULL, true, 1.0);",
+      "UPDATE other SET note = 'y';",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    let get_number_of_changed_rows = |database: &Database| {
+      database.tables["test"].stored_table.as_ref().unwrap().changed_row_ids.len()
+    };
+    // 删除的行不在内存中，只有修改过的三行以及新插入的一行
+    assert_eq!(get_number_of_changed_rows(&database), 54);
+    assert_eq!(get_number_of_loaded_rows(&database.tables["test"]), 4);
+    save_database(&other_path, &mut database).unwrap();
+    assert_eq!(get_number_of_changed_rows(&database), 54);
+    assert_eq!(get_tables(&read_database(&other_path).unwrap()), get_tables(&database));
+
+    // 只会写修改过的行以及索引中对应的 key 所在的页
+    assert!(save_database(&path, &mut database).unwrap() < number_of_pages / 3);
+    assert!(database.tables.values().all(|table| get_number_of_loaded_rows(table) == 0));
+    assert_eq!(get_number_of_changed_rows(&database), 0);
+    assert_eq!(get_tables(&read_database(&path).unwrap()), get_tables(&database));
+
+    // 修改过表结构的表，文件中的行会按照新的表结构重新写一遍，新建的索引也要在原来的行被删掉之前建立
+    for query in [
+      "ALTER TABLE other ADD COLUMN score INTEGER;",
+      "CREATE INDEX test_name ON test (name);",
+      "ALTER TABLE test ADD COLUMN extra INTEGER;",
+    ] {
+      handle_sql_query(query, &mut database).unwrap();
+    }
+    let other = &database.tables["other"];
+    assert!(!other.stored_table.as_ref().unwrap().has_same_columns(&other.table_columns));
+    save_database(&path, &mut database).unwrap();
+    assert_eq!(get_tables(&read_database(&path).unwrap()), get_tables(&database));
+    assert_eq!(
+      select(&database, "SELECT id FROM test WHERE name = 'renamed';").unwrap().rows,
+      vec![vec![Value::Integer(3)]]
+    );
+
+    for file in [&path, &other_path] {
+      fs::remove_file(file).unwrap();
+    }
+  }
+
+  fn select(database: &Database, query: &str) -> Result<ResultSet> {
+    let select_query = SelectQuery::new(&get_sql_ast(query)?)?;
+    execute_select_query(database, database.get_snapshot(), select_query)
+  }
+
+  // 打开之后查询直接读文件中的页，结果和所有的行都在内存中时一样
+  // 修改过的行从内存中读，唯一约束也会检查文件中的行
+  #[test]
+  fn test_query_stored_tables() {
+    let path = get_path("stored-tables");
+    let mut database = create_database(&path);
+    save_database(&path, &mut database).unwrap();
+    let mut database = create_database(&path);
+    let mut stored_database = read_database(&path).unwrap();
+
+    for query in [
+      "UPDATE test SET score = 0.25, active = NULL WHERE id IN (3, 200);",
+      "DELETE FROM test WHERE id BETWEEN 100 AND 120;",
+      "INSERT INTO test (name, email, active, score) VALUES ('n0', 'dup@x.com', true, 1.0);",
+      "CREATE INDEX idx_score ON test (score);",
+      "ALTER TABLE test RENAME COLUMN active TO is_active;",
+      "INSERT INTO other (note) VALUES ('y');",
+      // 和文件中的行冲突
+      "INSERT INTO test (name, email) VALUES ('x', '3@x.com');",
+      "UPDATE test SET name = 'n41', score = 41.0 / 4.0 WHERE id = 1;",
+    ] {
+      assert_eq!(handle_sql_query(query, &mut stored_database), handle_sql_query(query, &mut database));
+    }
+    for query in [
+      "SELECT * FROM test;",
+      "SELECT id, name FROM test WHERE id = 150 OR email = '5@x.com';",
+      "SELECT id FROM test WHERE id > 280 OR id < 5;",
+      "SELECT id FROM test WHERE score BETWEEN 1.0 AND 2.0;",
+      "SELECT id FROM test WHERE is_active = false AND id < 30;",
+      "SELECT id FROM test WHERE name = 'n40';",
+      "SELECT id, score FROM test ORDER BY score DESC LIMIT 10;",
+      "SELECT id FROM test ORDER BY email LIMIT 20;",
+      "SELECT t.id, o.note FROM other o JOIN test t ON t.id = o.id;",
+      "SELECT COUNT(*), SUM(score) FROM test GROUP BY is_active;",
+    ] {
+      assert_eq!(select(&stored_database, query), select(&database, query));
+    }
+    assert_eq!(get_tables(&stored_database), get_tables(&database));
+    // 只有修改过的行在内存中，修改失败的语句读出来的行也留在内存中
+    assert_eq!(get_number_of_loaded_rows(&stored_database.tables["test"]), 4);
+
+    fs::remove_file(&path).unwrap();
+  }
+
+  // 以前整个 bincode 序列化的文件可以读，保存之后变成分页的格式
+  #[test]
+  fn test_read_legacy_database_file() {
+    let path = get_path("legacy-database-file");
+    let database = create_database(&path);
+    DatabaseManager::save(path.to_string(), &database).unwrap();
+    assert!(!is_paged_file(&path));
+
+    let mut legacy_database = Database::read(path.to_string()).unwrap();
+    assert_eq!(get_tables(&legacy_database), get_tables(&database));
+    Database::save(path.to_string(), &mut legacy_database).unwrap();
+    assert!(is_paged_file(&path));
+    assert_eq!(get_tables(&Database::read(path.to_string()).unwrap()), get_tables(&database));
+
+    fs::remove_file(&path).unwrap();
+  }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+
+use crate::error::{Result, NollaDBError};
+use crate::database::wal::get_checksum;
+use crate::storage::{PageId, PAGE_SIZE};
+use crate::storage::buffer_pool::get_offset;
+
+// 回滚日志 DATABASE_NAME.db-journal，保证一次 flush 写进文件的页要么全部都在，要么和没有写一样
+// 文件中已经有的一页第一次被覆盖之前，先把原来的内容追加到日志中并写到磁盘上
+// flush 完成并且文件写到磁盘上之后删除日志，这时这次 flush 才算完成
+// 打开文件时日志还在的话说明上次写到一半崩溃了，用日志中原来的内容恢复文件
+// 日志开头是 8 字节的文件原来的长度以及 4 字节的校验和
+// 之后每一页是 4 字节的校验和，4 字节的 page id，然后是这一页原来的内容
+pub struct Journal {
+  path: String,
+  file: Option<File>,
+  // 上次 flush 之后文件的长度，之后新加的页不需要记录
+  file_length: u64,
+  // 已经记录过的页
+  page_ids: HashSet<PageId>,
+}
+
+impl Journal {
+  pub fn new(path: &str, file_length: u64) -> Self {
+    Journal {
+      path: get_journal_path(path),
+      file: None,
+      file_length,
+      page_ids: HashSet::new(),
+    }
+  }
+
+  // 覆盖这些页之前调用，第一次被覆盖的页把 database_file 中原来的内容记录下来
+  pub fn record(&mut self, database_file: &mut File, page_ids: &[PageId]) -> Result<()> {
+    let page_ids: Vec<PageId> = page_ids
+      .iter()
+      .filter(|page_id| get_offset(**page_id) < self.file_length && !self.page_ids.contains(*page_id))
+      .cloned()
+      .collect();
+    if page_ids.is_empty() { return Ok(()); }
+
+    let mut records = vec![];
+    if self.file.is_none() {
+      let header = self.file_length.to_le_bytes();
+      records.extend(header);
+      records.extend(get_checksum(&header).to_le_bytes());
+    }
+    for page_id in &page_ids {
+      let mut record = page_id.to_le_bytes().to_vec();
+      record.resize(4 + PAGE_SIZE, 0);
+      database_file
+        .seek(SeekFrom::Start(get_offset(*page_id)))
+        .and_then(|_| database_file.read_exact(&mut record[4..]))
+        .map_err(|error| NollaDBError::Internal(format!("Can not read page {}: {}", page_id, error)))?;
+      records.extend(get_checksum(&record).to_le_bytes());
+      records.extend(record);
+    }
+
+    if self.file.is_none() {
+      let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&self.path)
+        .map_err(|error| NollaDBError::Internal(format!("Can not open {}: {}", self.path, error)))?;
+      self.file = Some(file);
+    }
+    let file = self.file.as_mut().unwrap();
+    file
+      .write_all(&records)
+      .and_then(|_| file.sync_data())
+      .map_err(|error| NollaDBError::Internal(format!("Can not write {}: {}", self.path, error)))?;
+    self.page_ids.extend(page_ids);
+    Ok(())
+  }
+
+  // database 文件已经写到磁盘上之后调用，删除日志，下次 flush 重新开始记录
+  pub fn commit(&mut self, file_length: u64) -> Result<()> {
+    if self.file.take().is_some() {
+      fs::remove_file(&self.path)
+        .map_err(|error| NollaDBError::Internal(format!("Can not remove {}: {}", self.path, error)))?;
+    }
+    self.file_length = file_length;
+    self.page_ids.clear();
+    Ok(())
+  }
+}
+
+// 打开 database 文件之前调用，日志还在的话把记录的页写回去，文件恢复到原来的长度，然后删除日志
+// 校验和对不上的页是还没有写到磁盘上的记录，这些页还没有被覆盖过
+pub fn rollback_journal(path: &str) -> Result<()> {
+  let journal_path = get_journal_path(path);
+  let bytes = match fs::read(&journal_path) {
+    Ok(bytes) => bytes,
+    Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+    Err(error) => return Err(NollaDBError::Internal(format!("Can not read {}: {}", journal_path, error))),
+  };
+
+  let header = bytes
+    .get(..12)
+    .filter(|header| get_checksum(&header[..8]).to_le_bytes() == header[8..]);
+  if let Some(header) = header {
+    let file_length = u64::from_le_bytes(header[..8].try_into().unwrap());
+    let mut file = OpenOptions::new()
+      .write(true)
+      .open(path)
+      .map_err(|error| NollaDBError::Internal(format!("Can not open {}: {}", path, error)))?;
+    let mut i = 12;
+    while let Some(record) = bytes.get(i..i + 8 + PAGE_SIZE) {
+      if get_checksum(&record[4..]).to_le_bytes() != record[..4] { break; }
+      let page_id = PageId::from_le_bytes(record[4..8].try_into().unwrap());
+      file
+        .seek(SeekFrom::Start(get_offset(page_id)))
+        .and_then(|_| file.write_all(&record[8..]))
+        .map_err(|error| NollaDBError::Internal(format!("Can not write page {}: {}", page_id, error)))?;
+      i += 8 + PAGE_SIZE;
+    }
+    file
+      .set_len(file_length)
+      .and_then(|_| file.sync_all())
+      .map_err(|error| NollaDBError::Internal(format!("Can not roll back {}: {}", path, error)))?;
+  }
+
+  fs::remove_file(&journal_path)
+    .map_err(|error| NollaDBError::Internal(format!("Can not remove {}: {}", journal_path, error)))
+}
+
+fn get_journal_path(path: &str) -> String {
+  format!("{}-journal", path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::Path;
+  use pretty_assertions::{assert_eq};
+  use crate::storage::buffer_pool::PAGE_WRITES_BEFORE_CRASH;
+  use crate::storage::pager::{Pager, Page};
+
+  // 换出被修改过的页以及 flush 的时候写到一半崩溃
+  // 重新打开时文件恢复到上次 flush 之后的样子，新加的页也会被去掉，日志被删除
+  #[test]
+  fn test_rollback_journal() {
+    let path = std::env::temp_dir().join(format!("nolladb-journal-{}.db", std::process::id()));
+    let path = path.to_str().unwrap();
+    let journal_path = get_journal_path(path);
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(&journal_path);
+
+    let mut pager = Pager::open(path, 2).unwrap();
+    for _ in 0..4 {
+      let page_id = pager.allocate_page().unwrap();
+      pager.write_page(page_id, &Page::Overflow { next: None, data: vec![page_id as u8] }).unwrap();
+    }
+    pager.flush().unwrap();
+    let bytes = fs::read(path).unwrap();
+    assert!(!Path::new(&journal_path).exists());
+
+    // 只能缓存两页，每写一页都会换出一页
+    PAGE_WRITES_BEFORE_CRASH.with(|page_writes| page_writes.set(Some(5)));
+    let result = (1..10)
+      .try_for_each(|i| {
+        let page_id = if i < 6 { i } else { pager.allocate_page()? };
+        pager.write_page(page_id, &Page::Overflow { next: None, data: vec![0xff] })
+      })
+      .and_then(|_| pager.flush().map(|_| ()));
+    PAGE_WRITES_BEFORE_CRASH.with(|page_writes| page_writes.set(None));
+    assert!(result.is_err());
+    drop(pager);
+    assert!(Path::new(&journal_path).exists());
+    assert!(fs::read(path).unwrap() != bytes);
+
+    let mut pager = Pager::open(path, 2).unwrap();
+    assert_eq!(fs::read(path).unwrap(), bytes);
+    assert!(!Path::new(&journal_path).exists());
+    assert_eq!(pager.read_page(2).unwrap(), Page::Overflow { next: None, data: vec![2] });
+    assert_eq!(pager.allocate_page().unwrap(), 6);
+
+    fs::remove_file(path).unwrap();
+  }
+}
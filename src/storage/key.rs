@@ -0,0 +1,210 @@
+use crate::error::{Result, NollaDBError};
+use crate::table::row::value::Value;
+use crate::table::column::index::RealKey;
+use crate::storage::b_plus_tree::MAX_KEY_SIZE;
+
+// B+ 树中的 key 按照字节序排列，这里把 row id 以及 value 编码成顺序不变的字节数组
+// 1. 整数把符号位取反之后按照大端序排列
+// 2. 浮点数和 RealKey 一样按照 total_cmp 的顺序排列，-0.0 和 0.0 以及所有的 NaN 编码成一样的
+// 3. 字符串中的 0x00 转义成 0x00 0xFF，最后以 0x00 0x00 结尾，这样前缀相同的字符串短的在前面
+// 4. 每个 value 前面有一个类型标记，NULL 排在最前面
+const NULL_TAG: u8 = 0;
+const BOOL_TAG: u8 = 1;
+const INTEGER_TAG: u8 = 2;
+const REAL_TAG: u8 = 3;
+const TEXT_TAG: u8 = 4;
+
+const ROW_ID_SIZE: usize = 8;
+
+pub fn encode_row_id(row_id: i64) -> Vec<u8> {
+  ((row_id as u64) ^ (1 << 63)).to_be_bytes().to_vec()
+}
+
+pub fn decode_row_id(bytes: &[u8]) -> Result<i64> {
+  let bytes: [u8; ROW_ID_SIZE] = bytes
+    .try_into()
+    .map_err(|_| get_decode_error("row id"))?;
+  Ok((u64::from_be_bytes(bytes) ^ (1 << 63)) as i64)
+}
+
+pub fn encode_values(values: &[Value]) -> Vec<u8> {
+  let mut bytes = vec![];
+  for value in values {
+    match value {
+      Value::Null => bytes.push(NULL_TAG),
+      Value::Bool(v) => bytes.extend([BOOL_TAG, *v as u8]),
+      Value::Integer(v) => {
+        bytes.push(INTEGER_TAG);
+        bytes.extend(((*v as u32) ^ (1 << 31)).to_be_bytes());
+      },
+      Value::Real(v) => {
+        // 负数所有的位取反，正数只把符号位取反
+        let bits = RealKey::new(*v).get().to_bits();
+        let bits = if bits >> 31 == 1 { !bits } else { bits ^ (1 << 31) };
+        bytes.push(REAL_TAG);
+        bytes.extend(bits.to_be_bytes());
+      },
+      Value::Text(v) => {
+        bytes.push(TEXT_TAG);
+        for byte in v.as_bytes() {
+          bytes.push(*byte);
+          if *byte == 0 { bytes.push(0xFF); }
+        }
+        bytes.extend([0, 0]);
+      },
+    }
+  }
+  bytes
+}
+
+// 查询的时候直接比较编码，不需要解码，只在测试中检查编码可以还原
+#[cfg(test)]
+pub fn decode_values(bytes: &[u8]) -> Result<Vec<Value>> {
+  let mut values = vec![];
+  let mut i = 0;
+  while i < bytes.len() {
+    let tag = bytes[i];
+    i += 1;
+    let value = match tag {
+      NULL_TAG => Value::Null,
+      BOOL_TAG => {
+        let v = *bytes.get(i).ok_or_else(|| get_decode_error("bool"))?;
+        i += 1;
+        Value::Bool(v == 1)
+      },
+      INTEGER_TAG => {
+        let v = read_u32(bytes, i, "integer")?;
+        i += 4;
+        Value::Integer((v ^ (1 << 31)) as i32)
+      },
+      REAL_TAG => {
+        let bits = read_u32(bytes, i, "real")?;
+        i += 4;
+        let bits = if bits >> 31 == 1 { bits ^ (1 << 31) } else { !bits };
+        Value::Real(f32::from_bits(bits))
+      },
+      TEXT_TAG => {
+        let mut text = vec![];
+        loop {
+          match (bytes.get(i), bytes.get(i + 1)) {
+            (Some(0), Some(0)) => break,
+            (Some(0), Some(0xFF)) => {
+              text.push(0);
+              i += 2;
+            },
+            (Some(byte), _) if *byte != 0 => {
+              text.push(*byte);
+              i += 1;
+            },
+            _ => return Err(get_decode_error("text")),
+          }
+        }
+        i += 2;
+        Value::Text(String::from_utf8(text).map_err(|_| get_decode_error("text"))?)
+      },
+      _ => return Err(get_decode_error("value")),
+    };
+    values.push(value);
+  }
+  Ok(values)
+}
+
+// 索引中的一项，key 是 values 加上 row id，value 不需要存放任何东西
+// values 太长的话 key 中只放前面一部分，完整的 values 放到 value 中
+pub fn encode_index_entry(values: &[Value], row_id: i64) -> (Vec<u8>, Vec<u8>) {
+  let values = encode_values(values);
+  let mut key = truncate_index_values(&values).to_vec();
+  key.extend(encode_row_id(row_id));
+  match key.len() - ROW_ID_SIZE < values.len() {
+    true => (key, values),
+    false => (key, vec![]),
+  }
+}
+
+#[cfg(test)]
+pub fn decode_index_entry(key: &[u8], value: &[u8]) -> Result<(Vec<Value>, i64)> {
+  let (values, row_id) = split_index_entry(key, value)?;
+  Ok((decode_values(values)?, row_id))
+}
+
+// 拿到索引中一项完整的 values 编码以及 row id，values 不需要解码就可以按照字节序比较
+pub fn split_index_entry<'a>(key: &'a [u8], value: &'a [u8]) -> Result<(&'a [u8], i64)> {
+  if key.len() < ROW_ID_SIZE {
+    return Err(get_decode_error("index entry"));
+  }
+  let (values, row_id) = key.split_at(key.len() - ROW_ID_SIZE);
+  let values = if value.is_empty() { values } else { value };
+  Ok((values, decode_row_id(row_id)?))
+}
+
+// values 的编码在索引的 key 中的部分，太长的话只放前面一部分
+pub fn truncate_index_values(values: &[u8]) -> &[u8] {
+  &values[..values.len().min(MAX_KEY_SIZE - ROW_ID_SIZE)]
+}
+
+#[cfg(test)]
+fn read_u32(bytes: &[u8], i: usize, name: &str) -> Result<u32> {
+  let bytes: [u8; 4] = bytes
+    .get(i..i + 4)
+    .and_then(|bytes| bytes.try_into().ok())
+    .ok_or_else(|| get_decode_error(name))?;
+  Ok(u32::from_be_bytes(bytes))
+}
+
+fn get_decode_error(name: &str) -> NollaDBError {
+  NollaDBError::Internal(format!("Can not decode {} from the database file", name))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  #[rstest]
+  #[case(vec![Value::Null], vec![Value::Integer(i32::MIN)])]
+  #[case(vec![Value::Integer(-1)], vec![Value::Integer(0)])]
+  #[case(vec![Value::Integer(1)], vec![Value::Integer(256)])]
+  #[case(vec![Value::Real(f32::NEG_INFINITY)], vec![Value::Real(-1.5)])]
+  #[case(vec![Value::Real(-0.5)], vec![Value::Real(0.0)])]
+  #[case(vec![Value::Real(0.5)], vec![Value::Real(f32::NAN)])]
+  #[case(vec![Value::Real(-1.5)], vec![Value::Real(-0.0)])]
+  #[case(vec![Value::Bool(false)], vec![Value::Bool(true)])]
+  #[case(vec![Value::Text("a".to_string())], vec![Value::Text("a\0".to_string())])]
+  #[case(vec![Value::Text("a\0".to_string())], vec![Value::Text("ab".to_string())])]
+  #[case(vec![Value::Text("a".to_string()), Value::Integer(9)], vec![Value::Text("ab".to_string()), Value::Integer(1)])]
+  #[case(vec![Value::Integer(1), Value::Null], vec![Value::Integer(1), Value::Text("".to_string())])]
+  fn test_encode_values(#[case] smaller: Vec<Value>, #[case] larger: Vec<Value>) {
+    assert!(encode_values(&smaller) < encode_values(&larger));
+    assert_eq!(decode_values(&encode_values(&smaller)), Ok(smaller));
+    assert_eq!(decode_values(&encode_values(&larger)).unwrap().len(), larger.len());
+  }
+
+  // 和 RealKey 一样，-0.0 和 0.0 是同一个值，NaN 等于 NaN
+  #[rstest]
+  #[case(-0.0, 0.0)]
+  #[case(f32::NAN, -f32::NAN)]
+  fn test_encode_same_real(#[case] a: f32, #[case] b: f32) {
+    assert_eq!(encode_values(&[Value::Real(a)]), encode_values(&[Value::Real(b)]));
+  }
+
+  #[rstest]
+  #[case(i64::MIN, -1)]
+  #[case(-1, 0)]
+  #[case(1, 256)]
+  #[case(256, i64::MAX)]
+  fn test_encode_row_id(#[case] smaller: i64, #[case] larger: i64) {
+    assert!(encode_row_id(smaller) < encode_row_id(larger));
+    assert_eq!(decode_row_id(&encode_row_id(smaller)), Ok(smaller));
+    assert_eq!(decode_row_id(&encode_row_id(larger)), Ok(larger));
+  }
+
+  #[rstest]
+  #[case(vec![Value::Text("a".to_string()), Value::Null], 3)]
+  #[case(vec![Value::Text("a".repeat(MAX_KEY_SIZE))], 4)]
+  fn test_encode_index_entry(#[case] values: Vec<Value>, #[case] row_id: i64) {
+    let (key, value) = encode_index_entry(&values, row_id);
+    assert!(key.len() <= MAX_KEY_SIZE);
+    assert_eq!(decode_index_entry(&key, &value), Ok((values, row_id)));
+  }
+}
@@ -0,0 +1,15 @@
+pub mod buffer_pool;
+pub mod journal;
+pub mod pager;
+pub mod b_plus_tree;
+pub mod key;
+pub mod database_file;
+pub mod stored_table;
+
+// 文件按照固定大小的页读写，单位是字节
+pub const PAGE_SIZE: usize = 4096;
+// buffer pool 中最多缓存的页数，超过之后把最久没有用过的页换出
+pub const DEFAULT_BUFFER_POOL_SIZE: usize = 256;
+
+// 页在文件中的编号，第 0 页是文件头
+pub type PageId = u32;
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, NollaDBError};
+use crate::storage::{PageId, PAGE_SIZE};
+use crate::storage::buffer_pool::BufferPool;
+use crate::storage::journal::{Journal, rollback_journal};
+
+// 文件开头的 magic，用来区分分页的文件和以前整个 bincode 序列化的文件
+pub const MAGIC: [u8; 8] = *b"NOLLADB\x01";
+
+// 第 0 页，记录整个文件的信息
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+struct Header {
+  magic: [u8; 8],
+  page_size: u32,
+  // 文件中一共有多少页，新的页从这里分配
+  number_of_pages: u32,
+  // 被释放的页组成的链表，分配页的时候先从这里拿
+  free_list_head: Option<PageId>,
+  // 所有表的信息都在这棵 B+ 树中
+  catalog_root: PageId,
+  // 这一代以及之前的 WAL 中的提交都已经在文件中了，见 Wal::generation
+  wal_generation: u64,
+}
+
+// B+ 树中 key 对应的 value，太大的 value 放到溢出页中
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub enum Cell {
+  Inline(Vec<u8>),
+  Overflow(PageId),
+}
+
+// 除了第 0 页以外，每一页都是下面的一种
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub enum Page {
+  // B+ 树的叶子节点，keys 从小到大排列，和 cells 一一对应
+  Leaf { keys: Vec<Vec<u8>>, cells: Vec<Cell> },
+  // B+ 树的内部节点，children[i] 中的 key 都小于 keys[i]，children[i + 1] 中的 key 都大于等于 keys[i]
+  Internal { keys: Vec<Vec<u8>>, children: Vec<PageId> },
+  // 一个放不进叶子节点的 value 被切成几段，按顺序连起来
+  Overflow { next: Option<PageId>, data: Vec<u8> },
+  // free list 中的页
+  Free { next: Option<PageId> },
+}
+
+// 管理一个分页的文件，所有的页都通过 buffer pool 读写
+pub struct Pager {
+  buffer_pool: BufferPool,
+  header: Header,
+}
+
+impl Pager {
+  // 文件不存在或者是空的话创建一个新的文件，只有一棵空的 catalog 树
+  // 上次写到一半崩溃的话先用回滚日志恢复文件
+  pub fn open(path: &str, buffer_pool_size: usize) -> Result<Self> {
+    rollback_journal(path)?;
+    let file = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(false)
+      .open(path)
+      .map_err(|error| NollaDBError::Internal(format!("Can not open {}: {}", path, error)))?;
+    let length = file
+      .metadata()
+      .map_err(|error| NollaDBError::Internal(format!("Can not open {}: {}", path, error)))?
+      .len();
+    let mut buffer_pool = BufferPool::new(file, Journal::new(path, length), buffer_pool_size);
+
+    if length == 0 {
+      let mut pager = Pager {
+        buffer_pool,
+        header: Header {
+          magic: MAGIC,
+          page_size: PAGE_SIZE as u32,
+          number_of_pages: 2,
+          free_list_head: None,
+          catalog_root: 1,
+          wal_generation: 0,
+        },
+      };
+      pager.write_header()?;
+      pager.write_page(1, &Page::Leaf { keys: vec![], cells: vec![] })?;
+      return Ok(pager);
+    }
+
+    let header: Header = deserialize(buffer_pool.read_page(0)?)
+      .map_err(|error| NollaDBError::Internal(format!("Can not read header of {}: {}", path, error)))?;
+    if header.magic != MAGIC || header.page_size != PAGE_SIZE as u32 {
+      return Err(NollaDBError::Internal(format!("{} is not a paged database file", path)));
+    }
+    Ok(Pager { buffer_pool, header })
+  }
+
+  pub fn get_catalog_root(&self) -> PageId {
+    self.header.catalog_root
+  }
+
+  pub fn get_wal_generation(&self) -> u64 {
+    self.header.wal_generation
+  }
+
+  pub fn set_wal_generation(&mut self, wal_generation: u64) -> Result<()> {
+    if self.header.wal_generation == wal_generation { return Ok(()); }
+    self.header.wal_generation = wal_generation;
+    self.write_header()
+  }
+
+  pub fn read_page(&mut self, page_id: PageId) -> Result<Page> {
+    deserialize(self.buffer_pool.read_page(page_id)?)
+      .map_err(|error| NollaDBError::Internal(format!("Can not decode page {}: {}", page_id, error)))
+  }
+
+  pub fn write_page(&mut self, page_id: PageId, page: &Page) -> Result<()> {
+    let data = serialize(page)
+      .map_err(|error| NollaDBError::Internal(format!("Can not encode page {}: {}", page_id, error)))?;
+    if data.len() > PAGE_SIZE {
+      return Err(NollaDBError::Internal(
+        format!("Page {} is too large: {} bytes", page_id, data.len())
+      ));
+    }
+    self.buffer_pool.write_page(page_id, data)
+  }
+
+  // 先从 free list 中拿，free list 是空的话在文件末尾加一页
+  pub fn allocate_page(&mut self) -> Result<PageId> {
+    let page_id = match self.header.free_list_head {
+      Some(page_id) => {
+        match self.read_page(page_id)? {
+          Page::Free { next } => self.header.free_list_head = next,
+          _ => return Err(NollaDBError::Internal(
+            format!("Page {} in the free list is not free", page_id)
+          )),
+        }
+        page_id
+      },
+      None => {
+        self.header.number_of_pages += 1;
+        self.header.number_of_pages - 1
+      },
+    };
+    self.write_header()?;
+    Ok(page_id)
+  }
+
+  // 被释放的页放到 free list 的开头，之后分配页的时候会被重新使用
+  pub fn free_page(&mut self, page_id: PageId) -> Result<()> {
+    self.write_page(page_id, &Page::Free { next: self.header.free_list_head })?;
+    self.header.free_list_head = Some(page_id);
+    self.write_header()
+  }
+
+  // 把所有被修改过的页写回文件，返回写回的页数
+  pub fn flush(&mut self) -> Result<usize> {
+    self.buffer_pool.flush()
+  }
+
+  fn write_header(&mut self) -> Result<()> {
+    let data = serialize(&self.header)
+      .map_err(|error| NollaDBError::Internal(format!("Can not encode header: {}", error)))?;
+    self.buffer_pool.write_page(0, data)
+  }
+}
+
+// 一个文件的 pager 被这个 database 中所有的表共用，查询的时候通过它按需读页
+// 读一页也会修改 buffer pool 中页的顺序，所以用 Mutex，clone 只会复制指针
+#[derive(Clone)]
+pub struct SharedPager(Arc<Mutex<Pager>>);
+
+impl SharedPager {
+  pub fn new(pager: Pager) -> Self {
+    SharedPager(Arc::new(Mutex::new(pager)))
+  }
+
+  // 拿锁的线程 panic 之后 buffer pool 可能只改了一半，和 TableRows 一样直接 panic
+  pub fn lock(&self) -> MutexGuard<'_, Pager> {
+    self.0.lock().expect("Pager lock is poisoned")
+  }
+}
+
+// 是不是同一个文件的 pager
+impl PartialEq for SharedPager {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.0, &other.0)
+  }
+}
+
+impl fmt::Debug for SharedPager {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("SharedPager")
+  }
+}
+
+// 文件是否是分页的格式，不存在的文件不是
+pub fn is_paged_file(path: &str) -> bool {
+  if !Path::new(path).exists() { return false; }
+
+  let mut magic = [0; 8];
+  File::open(path)
+    .and_then(|mut file| file.read_exact(&mut magic))
+    .is_ok_and(|_| magic == MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use pretty_assertions::{assert_eq};
+
+  #[test]
+  fn test_pager_free_list() {
+    let path = std::env::temp_dir().join(format!("nolladb-pager-{}.db", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+
+    let mut pager = Pager::open(path, 4).unwrap();
+    let page_ids: Vec<PageId> = (0..3).map(|_| pager.allocate_page().unwrap()).collect();
+    assert_eq!(page_ids, vec![2, 3, 4]);
+    for page_id in &page_ids {
+      pager.write_page(*page_id, &Page::Overflow { next: None, data: vec![*page_id as u8] }).unwrap();
+    }
+
+    // 释放的页被重新使用，文件不会变大
+    pager.free_page(3).unwrap();
+    pager.free_page(2).unwrap();
+    assert_eq!(pager.allocate_page().unwrap(), 2);
+    pager.flush().unwrap();
+    assert!(is_paged_file(path));
+
+    let mut pager = Pager::open(path, 4).unwrap();
+    assert_eq!(pager.allocate_page().unwrap(), 3);
+    assert_eq!(pager.allocate_page().unwrap(), 5);
+    assert_eq!(pager.read_page(4).unwrap(), Page::Overflow { next: None, data: vec![4] });
+    assert_eq!(pager.read_page(1).unwrap(), Page::Leaf { keys: vec![], cells: vec![] });
+
+    fs::remove_file(path).unwrap();
+  }
+}
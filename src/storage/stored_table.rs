@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Bound, RangeBounds};
+
+use crate::error::Result;
+use crate::table::ScannedRow;
+use crate::table::column::Column;
+use crate::table::row::value::Value;
+use crate::storage::PageId;
+use crate::storage::pager::SharedPager;
+use crate::storage::b_plus_tree::BPlusTree;
+use crate::storage::key::{
+  encode_row_id,
+  decode_row_id,
+  encode_values,
+  split_index_entry,
+  truncate_index_values,
+};
+use crate::storage::database_file::decode;
+
+// 已经保存在 database 文件中的一张表，查询的时候通过 buffer pool 按需读出用到的页
+// 上次保存之后插入、修改以及删除过的行放在 Table::table_rows 中，这些行在文件中的值已经没有用了
+// 只有保存到自己的文件时才会修改文件中的页，这时没有进行中的事务，所以 clone 出来的表都可以放心地读
+#[derive(PartialEq, Debug, Clone)]
+pub struct StoredTable {
+  pub pager: SharedPager,
+  pub rows_root: PageId,
+  // 文件中一行的每个值是哪个 column 的，按照保存时 table_columns 的顺序排列
+  // RENAME COLUMN 之后改成新的名字，DROP COLUMN 之后是 None
+  // ADD COLUMN 新加的 column 不在这里，文件中的行在这一列上是 DEFAULT 值
+  pub column_names: Vec<Option<String>>,
+  // 文件中的单列索引，key 是 column name，内存中这些 column 的索引只有 table_rows 中的行
+  pub column_index_roots: BTreeMap<String, PageId>,
+  // 文件中的多列索引，key 是索引名，内存中的索引也只有 table_rows 中的行
+  pub composite_index_roots: BTreeMap<String, PageId>,
+  // 上次保存之后插入、修改以及删除过的行
+  pub changed_row_ids: BTreeSet<i64>,
+}
+
+impl StoredTable {
+  pub fn new(
+    pager: SharedPager,
+    rows_root: PageId,
+    column_names: Vec<String>,
+    column_index_roots: BTreeMap<String, PageId>,
+    composite_index_roots: BTreeMap<String, PageId>,
+  ) -> Self {
+    StoredTable {
+      pager,
+      rows_root,
+      column_names: column_names.into_iter().map(Some).collect(),
+      column_index_roots,
+      composite_index_roots,
+      changed_row_ids: BTreeSet::new(),
+    }
+  }
+
+  // 这张表在文件中用到的所有树
+  pub fn get_roots(&self) -> Vec<PageId> {
+    std::iter::once(self.rows_root)
+      .chain(self.column_index_roots.values().cloned())
+      .chain(self.composite_index_roots.values().cloned())
+      .collect()
+  }
+
+  // 文件中的行是不是和 table_columns 一一对应
+  pub fn has_same_columns(&self, table_columns: &[Column]) -> bool {
+    self.column_names.len() == table_columns.len() &&
+    self.column_names
+      .iter()
+      .zip(table_columns)
+      .all(|(column_name, table_column)| column_name.as_ref() == Some(&table_column.column_name))
+  }
+
+  // 文件中 row id 对应的一行，按照 table_columns 的顺序排列，文件中没有这一行的话返回 None
+  // 不管这一行之后有没有被修改过
+  pub fn get_row_values(&self, row_id: i64, table_columns: &[Column]) -> Result<Option<Vec<Value>>> {
+    let value = BPlusTree::new(self.rows_root).get(&mut self.pager.lock(), &encode_row_id(row_id))?;
+    value
+      .map(|value| self.decode_row_values(&value, table_columns))
+      .transpose()
+  }
+
+  // 按照 row id 从小到大的顺序读出 row id 大于 after 的最多 limit 行，跳过修改过的行
+  pub fn scan_rows(
+    &self,
+    after: Option<i64>,
+    limit: usize,
+    table_columns: &[Column],
+  ) -> Result<Vec<ScannedRow>> {
+    let start = match after.map(|after| after.checked_add(1)) {
+      Some(Some(row_id)) => encode_row_id(row_id),
+      Some(None) => return Ok(vec![]),
+      None => vec![],
+    };
+    let mut rows = vec![];
+    if limit == 0 { return Ok(rows); }
+    BPlusTree::new(self.rows_root).scan(&mut self.pager.lock(), &start, &mut |key, value| {
+      let row_id = decode_row_id(&key)?;
+      if !self.changed_row_ids.contains(&row_id) {
+        rows.push((row_id, self.decode_row_values(&value, table_columns)?));
+      }
+      Ok(rows.len() < limit)
+    })?;
+    Ok(rows)
+  }
+
+  // 文件中没有被修改过的行的 row id，按照从小到大的顺序排列
+  pub fn get_row_ids(&self) -> Result<Vec<i64>> {
+    let mut row_ids = vec![];
+    BPlusTree::new(self.rows_root).scan_keys(&mut self.pager.lock(), &[], &mut |key| {
+      let row_id = decode_row_id(&key)?;
+      if !self.changed_row_ids.contains(&row_id) {
+        row_ids.push(row_id);
+      }
+      Ok(true)
+    })?;
+    Ok(row_ids)
+  }
+
+  // 在文件中的索引里找到前几列的值等于 values 的行，跳过修改过的行
+  pub fn get_index_row_ids(&self, root: PageId, values: &[Value]) -> Result<Vec<i64>> {
+    let prefix = encode_values(values);
+    let key_prefix = truncate_index_values(&prefix);
+    let mut row_ids = vec![];
+    self.scan_index(root, key_prefix, &mut |key, values, row_id| {
+      if !key.starts_with(key_prefix) { return Ok(false); }
+      if values.starts_with(&prefix) {
+        row_ids.push(row_id);
+      }
+      Ok(true)
+    })?;
+    Ok(row_ids)
+  }
+
+  // 在文件中的单列索引里找到值在 lower 和 upper 之间的行，跳过修改过的行
+  // values 的编码和值的顺序一样，所以直接比较编码
+  pub fn get_index_row_ids_in_range(
+    &self,
+    root: PageId,
+    lower: Bound<&Value>,
+    upper: Bound<&Value>,
+  ) -> Result<Vec<i64>> {
+    let lower = lower.map(|value| encode_values(std::slice::from_ref(value)));
+    let upper = upper.map(|value| encode_values(std::slice::from_ref(value)));
+    let start = match &lower {
+      Bound::Included(lower) | Bound::Excluded(lower) => truncate_index_values(lower).to_vec(),
+      Bound::Unbounded => vec![],
+    };
+    let mut row_ids = vec![];
+    self.scan_index(root, &start, &mut |key, values, row_id| {
+      // key 中的 values 已经比 upper 大的话，后面的都比 upper 大
+      if let Bound::Included(upper) | Bound::Excluded(upper) = &upper {
+        if key > upper.as_slice() { return Ok(false); }
+      }
+      let range = (lower.as_ref().map(Vec::as_slice), upper.as_ref().map(Vec::as_slice));
+      if RangeBounds::<[u8]>::contains(&range, values) {
+        row_ids.push(row_id);
+      }
+      Ok(true)
+    })?;
+    Ok(row_ids)
+  }
+
+  // 文件中的索引里每一项的 values 编码以及 row id，按照索引的顺序排列，跳过修改过的行
+  pub fn get_index_entries(&self, root: PageId) -> Result<Vec<(Vec<u8>, i64)>> {
+    let mut entries = vec![];
+    self.scan_index(root, &[], &mut |_, values, row_id| {
+      entries.push((values.to_vec(), row_id));
+      Ok(true)
+    })?;
+    Ok(entries)
+  }
+
+  // 从 start 开始按照顺序访问索引中没有被修改过的行
+  // f 拿到 key 中的 values 部分、完整的 values 编码以及 row id，返回 false 的时候停下来
+  fn scan_index<F: FnMut(&[u8], &[u8], i64) -> Result<bool>>(
+    &self,
+    root: PageId,
+    start: &[u8],
+    f: &mut F,
+  ) -> Result<()> {
+    BPlusTree::new(root).scan(&mut self.pager.lock(), start, &mut |key, value| {
+      let (values, row_id) = split_index_entry(&key, &value)?;
+      if self.changed_row_ids.contains(&row_id) { return Ok(true); }
+      f(truncate_index_values(values), values, row_id)
+    })
+  }
+
+  // 把文件中的一行换成 table_columns 的顺序，文件中没有的 column 用 DEFAULT 值
+  fn decode_row_values(&self, value: &[u8], table_columns: &[Column]) -> Result<Vec<Value>> {
+    let stored_values: Vec<Value> = decode(value)?;
+    Ok(
+      table_columns
+        .iter()
+        .map(|table_column| {
+          self.column_names
+            .iter()
+            .position(|column_name| column_name.as_ref() == Some(&table_column.column_name))
+            .and_then(|i| stored_values.get(i).cloned())
+            .unwrap_or_else(|| table_column.default_value.clone().unwrap_or(Value::Null))
+        })
+        .collect()
+    )
+  }
+}
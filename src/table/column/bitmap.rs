@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+// 每个 word 存放 64 个 row id
+const WORD_BITS: i64 = 64;
+
+// 存放一组 row id 的位图，Bool 的索引中 true 和 false 各有一个
+// row id 可能是负数也可能很稀疏，所以只保存至少有一位是 1 的 word
+// key 是 row id / 64，value 中的第 row id % 64 位表示这个 row id 是否存在
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+pub struct Bitmap {
+  words: BTreeMap<i64, u64>,
+}
+
+impl Bitmap {
+  pub fn insert(&mut self, row_id: i64) {
+    let (word_index, mask) = get_position(row_id);
+    *self.words.entry(word_index).or_default() |= mask;
+  }
+
+  pub fn remove(&mut self, row_id: i64) {
+    let (word_index, mask) = get_position(row_id);
+    if let Some(word) = self.words.get_mut(&word_index) {
+      *word &= !mask;
+      if *word == 0 {
+        self.words.remove(&word_index);
+      }
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.words.is_empty()
+  }
+
+  // 按照从小到大的顺序拿到所有的 row id
+  pub fn get_row_ids(&self) -> Vec<i64> {
+    let mut row_ids = vec![];
+    for (word_index, word) in &self.words {
+      let mut word = *word;
+      while word != 0 {
+        let bit = i64::from(word.trailing_zeros());
+        row_ids.push(word_index * WORD_BITS + bit);
+        word &= word - 1;
+      }
+    }
+    row_ids
+  }
+}
+
+fn get_position(row_id: i64) -> (i64, u64) {
+  (row_id.div_euclid(WORD_BITS), 1 << row_id.rem_euclid(WORD_BITS))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  #[rstest]
+  #[case(vec![3, 1, 2], vec![], vec![1, 2, 3])]
+  #[case(vec![-65, 0, 63, 64, 100000], vec![], vec![-65, 0, 63, 64, 100000])]
+  #[case(vec![-1, 1, 2, 70], vec![2, 70, 5], vec![-1, 1])]
+  #[case(vec![7, 7], vec![7], vec![])]
+  fn test_bitmap(
+    #[case] inserted_row_ids: Vec<i64>,
+    #[case] removed_row_ids: Vec<i64>,
+    #[case] expected: Vec<i64>,
+  ) {
+    let mut bitmap = Bitmap::default();
+    for row_id in inserted_row_ids {
+      bitmap.insert(row_id);
+    }
+    for row_id in removed_row_ids {
+      bitmap.remove(row_id);
+    }
+
+    assert_eq!(bitmap.get_row_ids(), expected);
+    assert_eq!(bitmap.is_empty(), expected.is_empty());
+    // 空的 word 会被删掉
+    assert_eq!(bitmap.words.values().filter(|word| **word == 0).count(), 0);
+  }
+}
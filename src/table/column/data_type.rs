@@ -22,7 +22,7 @@ impl DataType {
       "none" => DataType::None,
       _ => {
         eprintln!("Invalid datatype: {}", command);
-        return DataType::Invalid;
+        DataType::Invalid
       },
     }
   }
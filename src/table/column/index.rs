@@ -1,12 +1,324 @@
-use std::collections::{BTreeMap};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
 
 use serde::{Deserialize, Serialize};
 
+use crate::table::row::value::Value;
+use crate::table::column::data_type::DataType;
+use crate::table::column::bitmap::Bitmap;
+
+// 每个 value 对应一组 row id，非 UNIQUE 的 column 中多行可以有同一个 value
+// Bool 只有两个 value，每个 value 下的 row id 很多，所以用位图存放
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub enum Index {
-  Integer(BTreeMap<i32, i64>),
-  Text(BTreeMap<String, i64>),
-  // Bool(BTreeMap<bool, i64>),
-  // Real(BTreeMap<f32, i64>),
+  Integer(BTreeMap<i32, BTreeSet<i64>>),
+  Text(BTreeMap<String, BTreeSet<i64>>),
+  Bool(BTreeMap<bool, Bitmap>),
+  Real(BTreeMap<RealKey, BTreeSet<i64>>),
   None,
 }
+
+// Real 索引的 key，f32 本身只有偏序，不能直接作为 BTreeMap 的 key
+// 1. -0.0 和 0.0 是同一个 key
+// 2. NaN 只有一个 key，NaN 等于 NaN，并且比所有的数（包括 inf）都大
+// 所以 UNIQUE 的 column 中只能有一个 NaN，ORDER BY 时 NaN 排在所有的数后面
+// 和 NaN 比较的结果是未知的，索引找到的行最后还要检查一遍 WHERE 条件，所以 NaN 不会被 WHERE 选中
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct RealKey(f32);
+
+impl RealKey {
+  pub fn new(value: f32) -> Self {
+    if value.is_nan() {
+      RealKey(f32::NAN.abs())
+    } else if value == 0.0 {
+      RealKey(0.0)
+    } else {
+      RealKey(value)
+    }
+  }
+
+  pub fn get(&self) -> f32 {
+    self.0
+  }
+}
+
+impl Ord for RealKey {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.0.total_cmp(&other.0)
+  }
+}
+
+impl PartialOrd for RealKey {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl PartialEq for RealKey {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for RealKey {}
+
+// 索引中一个 value 下的所有 row id
+trait RowIdSet: Default {
+  fn insert_row_id(&mut self, row_id: i64);
+  fn remove_row_id(&mut self, row_id: i64);
+  fn is_empty(&self) -> bool;
+  // 按照 row id 从小到大排列
+  fn get_row_ids(&self) -> Vec<i64>;
+}
+
+impl RowIdSet for BTreeSet<i64> {
+  fn insert_row_id(&mut self, row_id: i64) { self.insert(row_id); }
+  fn remove_row_id(&mut self, row_id: i64) { self.remove(&row_id); }
+  fn is_empty(&self) -> bool { BTreeSet::is_empty(self) }
+  fn get_row_ids(&self) -> Vec<i64> { self.iter().cloned().collect() }
+}
+
+impl RowIdSet for Bitmap {
+  fn insert_row_id(&mut self, row_id: i64) { self.insert(row_id); }
+  fn remove_row_id(&mut self, row_id: i64) { self.remove(row_id); }
+  fn is_empty(&self) -> bool { Bitmap::is_empty(self) }
+  fn get_row_ids(&self) -> Vec<i64> { Bitmap::get_row_ids(self) }
+}
+
+impl Index {
+  pub fn new(data_type: &DataType) -> Self {
+    match data_type {
+      DataType::Integer => Index::Integer(BTreeMap::new()),
+      DataType::Text => Index::Text(BTreeMap::new()),
+      DataType::Bool => Index::Bool(BTreeMap::new()),
+      DataType::Real => Index::Real(BTreeMap::new()),
+      DataType::None => Index::None,
+      DataType::Invalid => Index::None,
+    }
+  }
+
+  // 在索引中查找 value 对应的所有 row id，按照 row id 从小到大排列
+  pub fn get_row_ids_of_value(&self, value: &Value) -> Vec<i64> {
+    match self {
+      Index::Integer(tree) => get_value_row_ids(tree, get_integer_key(value)),
+      Index::Text(tree) => get_value_row_ids(tree, get_text_key(value)),
+      Index::Bool(tree) => get_value_row_ids(tree, get_bool_key(value)),
+      Index::Real(tree) => get_value_row_ids(tree, get_real_key(value)),
+      Index::None => vec![],
+    }
+  }
+
+  // 按照索引中 value 的顺序拿到所有的 row id
+  // value 一样的行不管是升序还是降序都按照 row id 从小到大排列，和排序的结果一样是稳定的
+  pub fn get_row_ids(&self, asc: bool) -> Vec<i64> {
+    match self {
+      Index::Integer(tree) => get_ordered_row_ids(tree, asc),
+      Index::Text(tree) => get_ordered_row_ids(tree, asc),
+      Index::Bool(tree) => get_ordered_row_ids(tree, asc),
+      Index::Real(tree) => get_ordered_row_ids(tree, asc),
+      Index::None => vec![],
+    }
+  }
+
+  // 按照索引中 value 从小到大的顺序拿到 lower 和 upper 之间的所有 row id
+  // 边界的类型和索引的类型不一样的话返回 None
+  pub fn get_row_ids_in_range(&self, lower: Bound<&Value>, upper: Bound<&Value>) -> Option<Vec<i64>> {
+    match self {
+      Index::Integer(tree) => Some(get_range_row_ids(
+        tree,
+        map_bound(lower, get_integer_key)?,
+        map_bound(upper, get_integer_key)?,
+      )),
+      Index::Text(tree) => Some(get_range_row_ids(
+        tree,
+        map_bound(lower, get_text_key)?,
+        map_bound(upper, get_text_key)?,
+      )),
+      Index::Bool(tree) => Some(get_range_row_ids(
+        tree,
+        map_bound(lower, get_bool_key)?,
+        map_bound(upper, get_bool_key)?,
+      )),
+      Index::Real(tree) => Some(get_range_row_ids(
+        tree,
+        map_bound(lower, get_real_key)?,
+        map_bound(upper, get_real_key)?,
+      )),
+      Index::None => None,
+    }
+  }
+
+  // NULL 以及类型和索引不一样的值不会写入索引
+  pub fn insert_value(&mut self, value: &Value, row_id: i64) {
+    match self {
+      Index::Integer(tree) => insert_row_id(tree, get_integer_key(value), row_id),
+      Index::Text(tree) => insert_row_id(tree, get_text_key(value), row_id),
+      Index::Bool(tree) => insert_row_id(tree, get_bool_key(value), row_id),
+      Index::Real(tree) => insert_row_id(tree, get_real_key(value), row_id),
+      Index::None => (),
+    }
+  }
+
+  // 只删除 value 下的这个 row id，不会把其他行的索引删掉
+  pub fn remove_value(&mut self, value: &Value, row_id: i64) {
+    match self {
+      Index::Integer(tree) => remove_row_id(tree, get_integer_key(value), row_id),
+      Index::Text(tree) => remove_row_id(tree, get_text_key(value), row_id),
+      Index::Bool(tree) => remove_row_id(tree, get_bool_key(value), row_id),
+      Index::Real(tree) => remove_row_id(tree, get_real_key(value), row_id),
+      Index::None => (),
+    }
+  }
+}
+
+fn get_integer_key(value: &Value) -> Option<i32> {
+  match value { Value::Integer(v) => Some(*v), _ => None }
+}
+
+fn get_text_key(value: &Value) -> Option<String> {
+  match value { Value::Text(v) => Some(v.to_string()), _ => None }
+}
+
+fn get_bool_key(value: &Value) -> Option<bool> {
+  match value { Value::Bool(v) => Some(*v), _ => None }
+}
+
+fn get_real_key(value: &Value) -> Option<RealKey> {
+  match value { Value::Real(v) => Some(RealKey::new(*v)), _ => None }
+}
+
+fn get_value_row_ids<K: Ord, S: RowIdSet>(tree: &BTreeMap<K, S>, key: Option<K>) -> Vec<i64> {
+  key
+    .and_then(|key| tree.get(&key))
+    .map(|row_ids| row_ids.get_row_ids())
+    .unwrap_or_default()
+}
+
+fn insert_row_id<K: Ord, S: RowIdSet>(tree: &mut BTreeMap<K, S>, key: Option<K>, row_id: i64) {
+  if let Some(key) = key {
+    tree.entry(key).or_default().insert_row_id(row_id);
+  }
+}
+
+// value 下一个 row id 都没有的话把 value 也删掉
+fn remove_row_id<K: Ord, S: RowIdSet>(tree: &mut BTreeMap<K, S>, key: Option<K>, row_id: i64) {
+  let key = match key {
+    Some(key) => key,
+    None => return,
+  };
+  if let Some(row_ids) = tree.get_mut(&key) {
+    row_ids.remove_row_id(row_id);
+    if row_ids.is_empty() {
+      tree.remove(&key);
+    }
+  }
+}
+
+fn get_ordered_row_ids<K: Ord, S: RowIdSet>(tree: &BTreeMap<K, S>, asc: bool) -> Vec<i64> {
+  match asc {
+    true => tree.values().flat_map(|row_ids| row_ids.get_row_ids()).collect(),
+    false => tree.values().rev().flat_map(|row_ids| row_ids.get_row_ids()).collect(),
+  }
+}
+
+fn map_bound<K, F>(bound: Bound<&Value>, f: F) -> Option<Bound<K>>
+where
+  F: Fn(&Value) -> Option<K>,
+{
+  match bound {
+    Bound::Included(value) => Some(Bound::Included(f(value)?)),
+    Bound::Excluded(value) => Some(Bound::Excluded(f(value)?)),
+    Bound::Unbounded => Some(Bound::Unbounded),
+  }
+}
+
+// lower 比 upper 大的时候 BTreeMap::range 会 panic，这种情况直接返回空
+fn get_range_row_ids<K: Ord, S: RowIdSet>(tree: &BTreeMap<K, S>, lower: Bound<K>, upper: Bound<K>) -> Vec<i64> {
+  if let (
+    Bound::Included(l) | Bound::Excluded(l),
+    Bound::Included(u) | Bound::Excluded(u),
+  ) = (&lower, &upper) {
+    let is_empty = match (&lower, &upper) {
+      (Bound::Included(_), Bound::Included(_)) => l > u,
+      _ => l >= u,
+    };
+    if is_empty { return vec![]; }
+  }
+
+  tree
+    .range((lower, upper))
+    .flat_map(|(_, row_ids)| row_ids.get_row_ids())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  #[rstest]
+  #[case(Bound::Excluded(Value::Integer(20)), Bound::Unbounded, Some(vec![3, 4]))]
+  #[case(Bound::Included(Value::Integer(20)), Bound::Included(Value::Integer(30)), Some(vec![2, 3]))]
+  #[case(Bound::Unbounded, Bound::Excluded(Value::Integer(20)), Some(vec![1]))]
+  #[case(Bound::Included(Value::Integer(15)), Bound::Excluded(Value::Integer(15)), Some(vec![]))]
+  #[case(Bound::Excluded(Value::Integer(40)), Bound::Included(Value::Integer(10)), Some(vec![]))]
+  #[case(Bound::Included(Value::Text("a".to_string())), Bound::Unbounded, None)]
+  fn test_get_row_ids_in_range(
+    #[case] lower: Bound<Value>,
+    #[case] upper: Bound<Value>,
+    #[case] expected: Option<Vec<i64>>,
+  ) {
+    // 第 n 行的 value 是 n * 10
+    let mut index = Index::Integer(BTreeMap::new());
+    for row_id in 1..=4 {
+      index.insert_value(&Value::Integer(row_id as i32 * 10), row_id);
+    }
+
+    assert_eq!(index.get_row_ids_in_range(lower.as_ref(), upper.as_ref()), expected);
+  }
+
+  #[rstest]
+  #[case(Value::Text("open".to_string()), vec![1, 3, 4])]
+  #[case(Value::Text("closed".to_string()), vec![2])]
+  #[case(Value::Text("draft".to_string()), vec![])]
+  #[case(Value::Integer(1), vec![])]
+  fn test_get_row_ids_of_value(
+    #[case] value: Value,
+    #[case] expected: Vec<i64>,
+  ) {
+    let mut index = Index::Text(BTreeMap::new());
+    for (row_id, status) in [(1, "open"), (2, "closed"), (3, "open"), (4, "open")] {
+      index.insert_value(&Value::Text(status.to_string()), row_id);
+    }
+
+    assert_eq!(index.get_row_ids_of_value(&value), expected);
+  }
+
+  #[rstest]
+  #[case(true, vec![2, 5, 1, 3, 4])]
+  #[case(false, vec![1, 3, 4, 2, 5])]
+  fn test_get_row_ids_with_duplicate_values(
+    #[case] asc: bool,
+    #[case] expected: Vec<i64>,
+  ) {
+    let mut index = Index::Integer(BTreeMap::new());
+    for (row_id, user_id) in [(1, 20), (2, 10), (3, 20), (4, 20), (5, 10)] {
+      index.insert_value(&Value::Integer(user_id), row_id);
+    }
+    assert_eq!(index.get_row_ids(asc), expected);
+    assert_eq!(
+      index.get_row_ids_in_range(Bound::Included(&Value::Integer(20)), Bound::Unbounded),
+      Some(vec![1, 3, 4]),
+    );
+
+    // 删掉一行不会影响同一个 value 下的其他行
+    index.remove_value(&Value::Integer(20), 3);
+    assert_eq!(index.get_row_ids_of_value(&Value::Integer(20)), vec![1, 4]);
+    index.remove_value(&Value::Integer(10), 2);
+    index.remove_value(&Value::Integer(10), 5);
+    assert_eq!(index.get_row_ids_of_value(&Value::Integer(10)), vec![]);
+    assert_eq!(index, Index::Integer(BTreeMap::from([(20, BTreeSet::from([1, 4]))])));
+  }
+}
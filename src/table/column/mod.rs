@@ -1,10 +1,11 @@
 pub mod index;
+pub mod bitmap;
 pub mod data_type;
 
-use std::collections::{BTreeMap};
-
 use serde::{Deserialize, Serialize};
 
+use crate::table::row::value::Value;
+
 use index::Index;
 use data_type::DataType;
 
@@ -17,6 +18,8 @@ pub struct Column {
   pub is_not_null_constraint: bool,
   pub is_indexed: bool,
   pub index: Index,
+  // DEFAULT 值，INSERT 时没有给这一列赋值就用这个值
+  pub default_value: Option<Value>,
 }
 
 impl Column {
@@ -26,16 +29,10 @@ impl Column {
     is_primary_key: bool,
     is_unique_constraint: bool,
     is_not_null_constraint: bool,
+    default_value: Option<Value>,
   ) -> Self {
     let cd = DataType::new(column_datatype);
-    let index = match cd {
-      DataType::Integer => Index::Integer(BTreeMap::new()),
-      DataType::Text => Index::Text(BTreeMap::new()),
-      DataType::Bool => Index::None,
-      DataType::Real => Index::None,
-      DataType::None => Index::None,
-      DataType::Invalid => Index::None,
-    };
+    let index = Index::new(&cd);
 
     Column {
       column_name,
@@ -45,6 +42,7 @@ impl Column {
       is_not_null_constraint,
       is_indexed: is_primary_key,
       index,
+      default_value,
     }
   }
 
@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::ops::Bound;
+
+use serde::{Deserialize, Serialize};
+
+use crate::table::row::value::Value;
+use crate::table::column::index::RealKey;
+
+// 多列索引中每一列的 key，NULL 排在最前面
+// 同一列的 key 类型都是一样的，所以不同类型之间的顺序没有意义
+#[derive(Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum IndexKey {
+  Null,
+  Bool(bool),
+  Integer(i32),
+  Real(RealKey),
+  Text(String),
+}
+
+impl IndexKey {
+  pub fn new(value: &Value) -> Self {
+    match value {
+      Value::Null => IndexKey::Null,
+      Value::Bool(v) => IndexKey::Bool(*v),
+      Value::Integer(v) => IndexKey::Integer(*v),
+      Value::Real(v) => IndexKey::Real(RealKey::new(*v)),
+      Value::Text(v) => IndexKey::Text(v.to_string()),
+    }
+  }
+}
+
+// 建立在多个 column 上的索引，key 是这几列的值按照 column 的顺序组成的元组
+// 元组按照字典序排列，所以前几列相等的行在索引中是连续的，可以用前缀查找
+// PRIMARY KEY (a, b) 以及 UNIQUE (a, b) 约束也是用这个索引检查的
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct CompositeIndex {
+  pub column_names: Vec<String>,
+  pub is_unique: bool,
+  pub is_primary_key: bool,
+  // 由表的 PRIMARY KEY 或者 UNIQUE 约束建立的索引，不能用 DROP INDEX 删除
+  pub is_constraint: bool,
+  entries: BTreeMap<Vec<IndexKey>, BTreeSet<i64>>,
+}
+
+impl CompositeIndex {
+  pub fn new(
+    column_names: Vec<String>,
+    is_unique: bool,
+    is_primary_key: bool,
+    is_constraint: bool,
+  ) -> Self {
+    CompositeIndex {
+      column_names,
+      is_unique,
+      is_primary_key,
+      is_constraint,
+      entries: BTreeMap::new(),
+    }
+  }
+
+  // values 是这一行在 column_names 中每一列的值
+  pub fn insert_values(&mut self, values: &[Value], row_id: i64) {
+    self.entries
+      .entry(get_index_keys(values))
+      .or_default()
+      .insert(row_id);
+  }
+
+  pub fn remove_values(&mut self, values: &[Value], row_id: i64) {
+    let keys = get_index_keys(values);
+    if let Some(row_ids) = self.entries.get_mut(&keys) {
+      row_ids.remove(&row_id);
+      if row_ids.is_empty() {
+        self.entries.remove(&keys);
+      }
+    }
+  }
+
+  // 去掉索引中所有的行
+  pub fn clear(&mut self) {
+    self.entries.clear();
+  }
+
+  // UNIQUE 约束下 values 是否和 ignored_row_ids 以外的行重复
+  // 和 SQL 一样，有任意一列是 NULL 的话不会和其他行重复
+  pub fn is_duplicated(&self, values: &[Value], ignored_row_ids: &HashSet<i64>) -> bool {
+    if values.iter().any(|value| value.is_null()) { return false; }
+    match self.entries.get(&get_index_keys(values)) {
+      Some(row_ids) => row_ids.iter().any(|row_id| !ignored_row_ids.contains(row_id)),
+      None => false,
+    }
+  }
+
+  // 找到前几列的值等于 values 的所有行，按照 row id 从小到大排列
+  pub fn get_row_ids_with_prefix(&self, values: &[Value]) -> Vec<i64> {
+    let prefix = get_index_keys(values);
+    let mut row_ids: Vec<i64> = self.entries
+      .range::<Vec<IndexKey>, _>((Bound::Included(&prefix), Bound::Unbounded))
+      .take_while(|(keys, _)| keys.starts_with(&prefix))
+      .flat_map(|(_, row_ids)| row_ids.iter().cloned())
+      .collect();
+    row_ids.sort_unstable();
+    row_ids
+  }
+}
+
+fn get_index_keys(values: &[Value]) -> Vec<IndexKey> {
+  values.iter().map(IndexKey::new).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  #[rstest]
+  #[case(vec![Value::Integer(1)], vec![1, 2, 5])]
+  #[case(vec![Value::Integer(1), Value::Text("b".to_string())], vec![2, 5])]
+  #[case(vec![Value::Integer(2), Value::Text("a".to_string())], vec![3])]
+  #[case(vec![Value::Integer(3)], vec![])]
+  #[case(vec![Value::Null], vec![4])]
+  #[case(vec![], vec![1, 2, 3, 4, 5])]
+  fn test_get_row_ids_with_prefix(
+    #[case] prefix: Vec<Value>,
+    #[case] expected: Vec<i64>,
+  ) {
+    let mut index = CompositeIndex::new(vec!["user_id".to_string(), "tag".to_string()], false, false, false);
+    for (row_id, user_id, tag) in [
+      (1, Value::Integer(1), "a"),
+      (2, Value::Integer(1), "b"),
+      (3, Value::Integer(2), "a"),
+      (4, Value::Null, "a"),
+      (5, Value::Integer(1), "b"),
+    ] {
+      index.insert_values(&[user_id, Value::Text(tag.to_string())], row_id);
+    }
+
+    assert_eq!(index.get_row_ids_with_prefix(&prefix), expected);
+  }
+
+  #[rstest]
+  #[case(vec![Value::Integer(1), Value::Integer(2)], vec![], true)]
+  #[case(vec![Value::Integer(1), Value::Integer(2)], vec![1], false)]
+  #[case(vec![Value::Integer(2), Value::Integer(1)], vec![], false)]
+  #[case(vec![Value::Integer(1), Value::Null], vec![], false)]
+  fn test_is_duplicated(
+    #[case] values: Vec<Value>,
+    #[case] ignored_row_ids: Vec<i64>,
+    #[case] expected: bool,
+  ) {
+    let mut index = CompositeIndex::new(vec!["a".to_string(), "b".to_string()], true, true, true);
+    index.insert_values(&[Value::Integer(1), Value::Integer(2)], 1);
+    index.insert_values(&[Value::Integer(1), Value::Null], 2);
+    index.insert_values(&[Value::Integer(3), Value::Integer(4)], 3);
+    index.remove_values(&[Value::Integer(3), Value::Integer(4)], 3);
+
+    let ignored_row_ids: HashSet<i64> = ignored_row_ids.into_iter().collect();
+    assert_eq!(index.is_duplicated(&values, &ignored_row_ids), expected);
+    assert_eq!(index.entries.len(), 2);
+  }
+}
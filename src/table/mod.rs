@@ -1,11 +1,14 @@
-mod row;
-mod column;
+pub mod row;
+pub mod column;
+pub mod composite_index;
+pub mod row_version;
+pub mod table_rows;
 
-use std::collections::{HashMap, BTreeMap};
-use std::rc::Rc;
-use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
 
 use serde::{Deserialize, Serialize};
+use sqlparser::ast::Expr;
 use prettytable::{
   Table as PrintTable,
   Row as PrintRow,
@@ -15,22 +18,78 @@ use prettytable::{
 use crate::sql_query::query::create::{
   CreateQuery,
   SchemaOfSQLColumn,
+  SchemaOfSQLConstraint,
+};
+use crate::sql_query::expression::{
+  RowValues,
+  is_row_matched,
+  evaluate_expression,
 };
 use crate::error::{Result, NollaDBError};
+use crate::storage::PageId;
+use crate::storage::stored_table::StoredTable;
+use crate::storage::key::encode_values;
 
 use row::Row;
+use row::value::Value;
 use column::Column;
 use column::data_type::DataType;
 use column::index::Index;
+use composite_index::CompositeIndex;
+use row_version::{RowVersion, get_visible_row_values};
+use table_rows::TableRows;
+
+// 按照 row id 的顺序访问所有的行时，一次从文件中读出的行数
+const ROW_BATCH_SIZE: usize = 1024;
+
+// UPDATE 时一行在多列索引中的 row id、旧值以及新值
+type CompositeIndexChange = (i64, Vec<Value>, Vec<Value>);
+// 提交时一行的 row id 以及这一行的值，None 表示这一行被删除了
+pub type CommittedRow = (i64, Option<Vec<Value>>);
+// 快照中看不到最新版本的行，以及这些行在快照中的值，None 表示这一行在快照中不存在
+pub type InvisibleRows = HashMap<i64, Option<Vec<Value>>>;
+// 按照 row id 的顺序读出来的一行，值按照 table_columns 的顺序排列
+pub type ScannedRow = (i64, Vec<Value>);
+
+// 对表中一行数据的修改，回滚的时候用来撤销
+// 一行的值按照 table_columns 的顺序保存
+#[derive(PartialEq, Debug, Clone)]
+pub enum RowChange {
+  // 插入的 row id 以及插入之前的 most_recent_row_id
+  Insert(i64, i64),
+  // 被更新的 row id 以及这一行更新之前的值
+  Update(i64, Vec<Value>),
+  // 被删除的 row id 以及这一行删除之前的值
+  Delete(i64, Vec<Value>),
+}
 
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct Table {
   pub primary_key: String,
   pub table_name: String,
+  // CREATE INDEX 创建的索引，key 是索引名，value 是建立索引的 column name
   pub indexes: HashMap<String, String>,
+  // 其中是 UNIQUE 的索引名
+  pub unique_indexes: HashSet<String>,
+  // 建立在多个 column 上的索引，key 是索引名
+  // CREATE INDEX 创建的多列索引以及表的 PRIMARY KEY (a, b) / UNIQUE (a, b) 约束建立的索引都在这里
+  pub composite_indexes: HashMap<String, CompositeIndex>,
   pub most_recent_row_id: i64,
-  pub table_rows: Rc<RefCell<HashMap<String, Row>>>,
+  pub table_rows: TableRows,
   pub table_columns: Vec<Column>,
+  // 还没有被收集到数据库 undo log 中的修改，按照修改的顺序排列
+  #[serde(skip)]
+  pub row_changes: Vec<RowChange>,
+  // 被修改过的行的版本链，key 是 row id，已经没有快照需要的版本会被回收
+  #[serde(skip)]
+  pub row_versions: HashMap<i64, Vec<RowVersion>>,
+  // 最近一次提交的对这张表的修改的时间戳
+  #[serde(skip)]
+  pub last_commit_ts: u64,
+  // 已经保存在 database 文件中的部分，None 表示所有的行都在 table_rows 中
+  // 不是 None 的话 table_rows 中只有上次保存之后修改过的行，其他的行查询的时候再从文件中读
+  #[serde(skip)]
+  pub stored_table: Option<StoredTable>,
 }
 
 impl Table {
@@ -42,14 +101,23 @@ impl Table {
     let CreateQuery {
       table_name,
       table_metadata_columns,
+      table_constraints,
+      ..
     } = create_query;
 
     let indexes = HashMap::new();
+    let unique_indexes = HashSet::new();
+    // 表中还没有数据，直接建立空的多列索引
+    let composite_indexes = table_constraints
+      .into_iter()
+      .map(|SchemaOfSQLConstraint { constraint_name, column_names, is_primary_key }| {
+        (constraint_name, CompositeIndex::new(column_names, true, is_primary_key, true))
+      })
+      .collect();
     let most_recent_row_id = 0;
 
-    // table rows 是由 RefCell 指针管理的 HashMap
-    let table_rows: Rc<RefCell<HashMap<String, Row>>>
-      = Rc::new(RefCell::new(HashMap::new()));
+    // table rows 是由 Arc<RwLock> 管理的 HashMap
+    let table_rows = TableRows::default();
     // table columns 是 Column 元素组成的数组
     let mut table_columns: Vec<Column> = vec![];
 
@@ -60,6 +128,7 @@ impl Table {
         is_primary_key,
         is_unique_constraint,
         is_not_null_constraint,
+        default_value,
       } = &table_metadata_column;
 
       // 如果是 PRIMARY KEY，说明需要列名就是 PRIMARY KEY
@@ -68,51 +137,13 @@ impl Table {
       }
 
       // 构建 table rows
-      match DataType::new(column_datatype.to_string()) {
-        DataType::Integer => table_rows
-          .clone()
-          // 获取一个可变引用，配合 RefCell 使用
-          .borrow_mut()
-          .insert(
-            column_name.to_string(),
-            Row::Integer(BTreeMap::new())
-          ),
-        DataType::Text => table_rows
-          .clone()
-          .borrow_mut()
-          .insert(
-            column_name.to_string(),
-            Row::Text(BTreeMap::new())
-          ),
-        DataType::Bool => table_rows
-          .clone()
-          .borrow_mut()
-          .insert(
-            column_name.to_string(),
-            Row::Bool(BTreeMap::new())
-          ),
-        DataType::Real => table_rows
-          .clone()
-          .borrow_mut()
-          .insert(
-            column_name.to_string(),
-            Row::Real(BTreeMap::new())
-          ),
-        DataType::None => table_rows
-          .clone()
-          .borrow_mut()
-          .insert(
-            column_name.to_string(),
-            Row::None
-          ),
-        DataType::Invalid => table_rows
-          .clone()
-          .borrow_mut()
-          .insert(
-            column_name.to_string(),
-            Row::None
-          ),
-      };
+      table_rows
+        // 拿到写锁之后才能修改
+        .write()
+        .insert(
+          column_name.to_string(),
+          Row::new(&DataType::new(column_datatype.to_string()))
+        );
 
       // 构建 table columns
       table_columns.push(Column::new(
@@ -121,6 +152,7 @@ impl Table {
         *is_primary_key,
         *is_unique_constraint,
         *is_not_null_constraint,
+        default_value.clone(),
       ));
     }
 
@@ -128,9 +160,24 @@ impl Table {
       primary_key,
       table_name,
       indexes,
+      unique_indexes,
+      composite_indexes,
       most_recent_row_id,
       table_rows,
       table_columns,
+      row_changes: vec![],
+      row_versions: HashMap::new(),
+      last_commit_ts: 0,
+      stored_table: None,
+    }
+  }
+
+  // 复制一份完全独立的表，table_rows 也会被复制
+  // derive 的 clone 只会复制 Arc 指针，两张表会共享同一份数据
+  pub fn deep_clone(&self) -> Self {
+    Table {
+      table_rows: self.table_rows.deep_clone(),
+      ..self.clone()
     }
   }
 
@@ -141,225 +188,1305 @@ impl Table {
       .any(|table_column| table_column.column_name == column_name)
   }
 
-  #[allow(dead_code)]
-  pub fn get_column(&mut self, column_name: String) -> Result<&Column> {
-    for table_column in self.table_columns.iter() {
-      if table_column.column_name == column_name {
-        return Ok(table_column);
+  pub fn get_column(&self, column_name: String) -> Result<&Column> {
+    for table_column in self.table_columns.iter() {
+      if table_column.column_name == column_name {
+        return Ok(table_column);
+      }
+    }
+
+    Err(NollaDBError::General(String::from("Column not found")))
+  }
+
+  pub fn get_column_mut(&mut self, column_name: String) -> Result<&mut Column> {
+    // TODO: 待优化
+    for table_column in self.table_columns.iter_mut() {
+      if table_column.column_name == column_name {
+        return Ok(table_column);
+      }
+    }
+
+    Err(NollaDBError::General(String::from("Column not found")))
+  }
+
+  // 有 UNIQUE 约束或者有 UNIQUE 索引的 column 不能有重复的值
+  pub fn is_unique_column(&self, table_column: &Column) -> bool {
+    table_column.is_unique_constraint ||
+    self.unique_indexes
+      .iter()
+      .any(|index_name| self.indexes.get(index_name) == Some(&table_column.column_name))
+  }
+
+  // PRIMARY KEY、UNIQUE 以及 CREATE INDEX 建立过索引的 column 在查询中可以用索引
+  // 索引中一个 value 对应一组 row id，所以 value 有重复的 column 也可以用
+  pub fn has_usable_index(&self, table_column: &Column) -> bool {
+    table_column.index != Index::None &&
+    (table_column.is_indexed || self.is_unique_column(table_column))
+  }
+
+  pub fn has_index(&self, index_name: &str) -> bool {
+    self.indexes.contains_key(index_name) || self.composite_indexes.contains_key(index_name)
+  }
+
+  // CREATE [UNIQUE] INDEX
+  // 用表中已经存在的行重新建立这一列的索引，UNIQUE 索引要求已经存在的值没有重复
+  // 之后 INSERT / UPDATE / DELETE 时会和其他列的索引一样被维护
+  pub fn create_index(&mut self, index_name: &str, column_name: &str, is_unique: bool) -> Result<()> {
+    if self.has_index(index_name) {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not create index, because index '{}' already exists",
+          index_name
+        )
+      ));
+    }
+    let table_column = self.get_column(column_name.to_string()).map_err(|_| {
+      NollaDBError::Internal(
+        format!(
+          "Can not create index, because column '{}' does not exist",
+          column_name
+        )
+      )
+    })?;
+    let mut index = Index::new(&table_column.column_datatype);
+    if index == Index::None {
+      return Err(NollaDBError::ToBeImplemented(
+        format!(
+          "Index on {} column will be implemented soon",
+          table_column.column_datatype
+        )
+      ));
+    }
+
+    let i = self.get_column_positions(&[column_name.to_string()])?[0];
+    self.for_each_row(&mut |row_id, row_values| {
+      let value = &row_values[i];
+      if value.is_null() { return Ok(()); }
+      if is_unique && !index.get_row_ids_of_value(value).is_empty() {
+        return Err(NollaDBError::General(
+          format!(
+            "Can not create unique index '{}', because value {} is duplicated in column '{}'",
+            index_name, value, column_name
+          )
+        ));
+      }
+      index.insert_value(value, row_id);
+      Ok(())
+    })?;
+
+    // 内存中的索引已经有所有的行了，文件中这一列原来的索引不再使用，保存的时候重新建立
+    if let Some(stored_table) = self.stored_table.as_mut() {
+      stored_table.column_index_roots.remove(column_name);
+    }
+    let table_column = self.get_column_mut(column_name.to_string())?;
+    table_column.index = index;
+    table_column.is_indexed = true;
+    self.indexes.insert(index_name.to_string(), column_name.to_string());
+    if is_unique {
+      self.unique_indexes.insert(index_name.to_string());
+    }
+
+    Ok(())
+  }
+
+  // CREATE [UNIQUE] INDEX 建立在多个 column 上的索引
+  // 和单列索引一样用表中已经存在的行建立，UNIQUE 索引要求已经存在的值没有重复
+  pub fn create_composite_index(&mut self, index_name: &str, column_names: &[String], is_unique: bool) -> Result<()> {
+    if self.has_index(index_name) {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not create index, because index '{}' already exists",
+          index_name
+        )
+      ));
+    }
+    for (i, column_name) in column_names.iter().enumerate() {
+      if !self.has_column(column_name.to_string()) {
+        return Err(NollaDBError::Internal(
+          format!(
+            "Can not create index, because column '{}' does not exist",
+            column_name
+          )
+        ));
+      }
+      if column_names[..i].contains(column_name) {
+        return Err(NollaDBError::Internal(
+          format!(
+            "Can not create index, because column '{}' is duplicated",
+            column_name
+          )
+        ));
+      }
+    }
+
+    let mut composite_index = CompositeIndex::new(column_names.to_vec(), is_unique, false, false);
+    let column_positions = self.get_column_positions(column_names)?;
+    self.for_each_row(&mut |row_id, row_values| {
+      let values: Vec<Value> = column_positions.iter().map(|i| row_values[*i].clone()).collect();
+      if is_unique && composite_index.is_duplicated(&values, &HashSet::new()) {
+        return Err(NollaDBError::General(
+          format!(
+            "Can not create unique index '{}', because value ({}) is duplicated in columns ({})",
+            index_name, join_values(&values), column_names.join(", ")
+          )
+        ));
+      }
+      composite_index.insert_values(&values, row_id);
+      Ok(())
+    })?;
+
+    self.composite_indexes.insert(index_name.to_string(), composite_index);
+
+    Ok(())
+  }
+
+  // DROP INDEX
+  // column 上的索引数据还要用来检查 PRIMARY KEY 和 UNIQUE 约束，所以只删除索引名
+  // PRIMARY KEY (a, b) 和 UNIQUE (a, b) 约束建立的多列索引不能删除
+  pub fn drop_index(&mut self, index_name: &str) -> Result<()> {
+    if let Some(composite_index) = self.composite_indexes.get(index_name) {
+      if composite_index.is_constraint {
+        return Err(NollaDBError::Internal(
+          format!(
+            "Can not drop index '{}', because it is used by a PRIMARY KEY or UNIQUE constraint",
+            index_name
+          )
+        ));
+      }
+      self.composite_indexes.remove(index_name);
+      if let Some(stored_table) = self.stored_table.as_mut() {
+        stored_table.composite_index_roots.remove(index_name);
+      }
+      return Ok(());
+    }
+
+    let column_name = match self.indexes.remove(index_name) {
+      Some(column_name) => column_name,
+      None => return Err(NollaDBError::Internal(
+        format!(
+          "Can not drop index, because index '{}' does not exist",
+          index_name
+        )
+      )),
+    };
+    self.unique_indexes.remove(index_name);
+
+    let is_indexed = self.indexes.values().any(|indexed_column_name| *indexed_column_name == column_name);
+    let table_column = self.get_column_mut(column_name.to_string())?;
+    table_column.is_indexed = table_column.is_primary_key || is_indexed;
+
+    // 查询中不能再用这一列的索引的话，文件中的索引也不需要了
+    let is_usable = self.has_usable_index(self.get_column(column_name.to_string())?);
+    if let (false, Some(stored_table)) = (is_usable, self.stored_table.as_mut()) {
+      stored_table.column_index_roots.remove(&column_name);
+    }
+
+    Ok(())
+  }
+
+  // 检查 InsertQuery 中的唯一性约束
+  // NULL 不参与唯一性约束的检查
+  pub fn check_unique_constraint(
+    &self,
+    table_column_names: &[String],
+    table_column_value: &[Value],
+  ) -> Result<()> {
+    for (i, table_column_name) in table_column_names.iter().enumerate() {
+      let table_column = self.get_column(table_column_name.to_string())?;
+      let Column { index, column_name, column_datatype, .. } = &table_column;
+
+      // 找到下一个具备唯一性约束的 column 为止
+      if !self.is_unique_column(table_column) { continue; }
+
+      let column_value = table_column_value[i].cast(column_datatype)?;
+      if column_value.is_null() { continue; }
+
+      if let Index::None = index {
+        return Err(
+          NollaDBError::General(
+            format!(
+              "Error: cannot find index in column {} ",
+              *column_name
+            )
+          )
+        );
+      }
+      if !self.get_row_ids_of_value(table_column, &column_value)?.is_empty() {
+        return Err(
+          NollaDBError::General(
+            format!(
+              "Error: column {} has a unique constraint violation,
+              value {} already exists for column {}",
+              *column_name, column_value, *column_name
+            )
+          )
+        );
+      }
+    }
+
+    Ok(())
+  }
+
+  // 插入一行数据，返回这一行的 row id
+  // 约束检查失败的话不会写入任何数据
+  pub fn insert_row(
+    &mut self,
+    table_column_names: &[String],
+    table_column_value: &[Value],
+  ) -> Result<i64> {
+    // 1. 按照表中 column 的顺序，拿到这一行每一列的值
+    // 没有赋值的 column 优先使用 DEFAULT 值，没有 DEFAULT 值就是 NULL
+    let mut row_values: Vec<Value> = vec![];
+    for table_column in &self.table_columns {
+      let value = match table_column_names
+        .iter()
+        .position(|table_column_name| *table_column_name == table_column.column_name) {
+        Some(i) => table_column_value[i].cast(&table_column.column_datatype)?,
+        None => table_column.default_value.clone().unwrap_or(Value::Null),
+      };
+      row_values.push(value);
+    }
+
+    // 2. 确定 row id
+    // 如果 PRIMARY KEY 是 Integer 并且有值，row id 就是这个值
+    // 否则 row id 自增，Integer 类型的 PRIMARY KEY 也会被赋值成这个 row id
+    let mut new_row_id = self.most_recent_row_id + i64::from(1);
+    if let Some(i) = self
+      .table_columns
+      .iter()
+      .position(|table_column| table_column.column_name == self.primary_key) {
+      match &row_values[i] {
+        Value::Integer(value) => new_row_id = i64::from(*value),
+        Value::Null if self.table_columns[i].column_datatype == DataType::Integer => {
+          row_values[i] = Value::Integer(new_row_id as i32);
+        },
+        _ => (),
+      }
+    }
+
+    // 3. 检查 NOT NULL 约束
+    for (table_column, value) in self.table_columns.iter().zip(&row_values) {
+      if value.is_null() && table_column.is_not_null_constraint {
+        return Err(NollaDBError::NotNullConstraint(
+          self.table_name.to_string(),
+          table_column.column_name.to_string(),
+        ));
+      }
+    }
+
+    // 4. 检查多列索引的唯一性约束
+    let mut composite_values: Vec<(String, Vec<Value>)> = vec![];
+    for (index_name, composite_index) in &self.composite_indexes {
+      let values = composite_index.column_names
+        .iter()
+        .map(|column_name| {
+          let i = self.table_columns
+            .iter()
+            .position(|table_column| table_column.column_name == *column_name)
+            .unwrap();
+          row_values[i].clone()
+        })
+        .collect::<Vec<Value>>();
+      if composite_index.is_unique && self.is_composite_duplicated(index_name, &values, &HashSet::new())? {
+        return Err(get_composite_unique_error(composite_index, &values));
+      }
+      composite_values.push((index_name.to_string(), values));
+    }
+
+    // 5. 以上检查完毕，更新 row 和 index
+    self.row_changes.push(RowChange::Insert(new_row_id, self.most_recent_row_id));
+    if let Some(stored_table) = self.stored_table.as_mut() {
+      stored_table.changed_row_ids.insert(new_row_id);
+    }
+    let table_rows_clone = self.table_rows.clone();
+    let mut table_rows_data =
+      table_rows_clone
+        .write();
+
+    for (table_column, value) in self.table_columns.iter_mut().zip(&row_values) {
+      table_rows_data
+        .get_mut(&table_column.column_name)
+        .unwrap()
+        .set_value(new_row_id, value)?;
+      table_column.get_index_mut().insert_value(value, new_row_id);
+    }
+    for (index_name, values) in composite_values {
+      self.composite_indexes
+        .get_mut(&index_name)
+        .unwrap()
+        .insert_values(&values, new_row_id);
+    }
+
+    // 手动指定的 PRIMARY KEY 可能比之前的 row id 小，这里取最大的那个
+    self.most_recent_row_id = self.most_recent_row_id.max(new_row_id);
+
+    Ok(new_row_id)
+  }
+
+  // 插入 INSERT 中 VALUES 的每一行，返回插入的行数
+  // 只要有一行检查失败，就把这条语句已经插入的行全部撤销
+  // 这样表中的数据和执行之前一样
+  pub fn insert_rows(
+    &mut self,
+    table_column_names: &[String],
+    table_column_values: &[Vec<Value>],
+  ) -> Result<usize> {
+    let number_of_row_changes = self.row_changes.len();
+    let mut inserted_row_ids: Vec<i64> = vec![];
+
+    for table_column_value in table_column_values {
+      let result = self
+        .check_unique_constraint(table_column_names, table_column_value)
+        .map_err(|error| NollaDBError::Internal(
+          format!(
+            "Unique key constraint violation: {}",
+            error
+          )
+        ))
+        .and_then(|_| self.insert_row(table_column_names, table_column_value));
+
+      match result {
+        Ok(row_id) => inserted_row_ids.push(row_id),
+        Err(error) => {
+          self.rollback_row_changes(number_of_row_changes);
+          return Err(error);
+        },
+      }
+    }
+
+    Ok(inserted_row_ids.len())
+  }
+
+  // 检查 UPDATE 之后的唯一性约束
+  // 被更新的行原来的值都会被替换掉，所以只需要检查
+  // 1. 新的值之间有没有重复
+  // 2. 新的值有没有和没有被更新的行的值重复
+  pub fn check_unique_constraint_for_update(
+    &self,
+    table_column_name: &str,
+    new_values: &[(i64, Value)],
+  ) -> Result<()> {
+    let table_column = self.get_column(table_column_name.to_string())?;
+    if !self.is_unique_column(table_column) { return Ok(()); }
+
+    let updated_row_ids: HashSet<i64> = new_values
+      .iter()
+      .map(|(row_id, _)| *row_id)
+      .collect();
+    // 用一个临时的索引记录已经检查过的新值，和 column 的索引一样判断两个值是否相等
+    // 比如 NaN 等于 NaN，0.0 等于 -0.0
+    let mut checked_values = Index::new(&table_column.column_datatype);
+
+    for (row_id, value) in new_values {
+      if value.is_null() { continue; }
+
+      let is_duplicated = !checked_values.get_row_ids_of_value(value).is_empty() ||
+        self.get_row_ids_of_value(table_column, value)?
+          .iter()
+          .any(|row_id| !updated_row_ids.contains(row_id));
+      if is_duplicated {
+        return Err(
+          NollaDBError::General(
+            format!(
+              "Error: column {} has a unique constraint violation,
+              value {} already exists for column {}",
+              table_column_name, value, table_column_name
+            )
+          )
+        );
+      }
+
+      checked_values.insert_value(value, *row_id);
+    }
+
+    Ok(())
+  }
+
+  // 对 row_ids 中的每一行执行 SET，返回被更新的行数
+  // 先对所有的行求值并且检查约束，全部通过之后才真正写入
+  // 这样约束检查失败的时候表中的数据不会被改动
+  pub fn update_rows(
+    &mut self,
+    row_ids: &[i64],
+    assignments: &[(String, Expr)],
+  ) -> Result<usize> {
+    // 1. 检查要更新的 column
+    for (column_name, _) in assignments {
+      if !self.has_column(column_name.to_string()) {
+        return Err(NollaDBError::Internal(
+          format!(
+            "Can not update, because column '{}' does not exist",
+            column_name
+          )
+        ));
+      }
+      // row id 和 PRIMARY KEY 是绑定在一起的，所以不允许更新 PRIMARY KEY
+      if *column_name == self.primary_key {
+        return Err(NollaDBError::Internal(
+          format!(
+            "Can not update, because column '{}' is the PRIMARY KEY",
+            column_name
+          )
+        ));
+      }
+    }
+
+    // 2. 用更新之前的值对 SET 中的表达式求值，得到每一列的新值
+    // 被更新的行先从文件中读到 table_rows 中，之后只在 table_rows 中修改
+    for row_id in row_ids {
+      self.load_row(*row_id)?;
+    }
+    let mut columns_new_values: Vec<Vec<(i64, Value)>> = vec![vec![]; assignments.len()];
+    for row_id in row_ids {
+      let row = self.get_row(row_id)?;
+      for (i, (column_name, expr)) in assignments.iter().enumerate() {
+        let table_column = self.get_column(column_name.to_string())?;
+        let value = evaluate_expression(expr, &row)?.cast(&table_column.column_datatype)?;
+
+        // 3. 检查 NOT NULL 约束
+        if value.is_null() && table_column.is_not_null_constraint {
+          return Err(NollaDBError::NotNullConstraint(
+            self.table_name.to_string(),
+            column_name.to_string(),
+          ));
+        }
+
+        columns_new_values[i].push((*row_id, value));
+      }
+    }
+
+    // 4. 检查唯一性约束
+    for (i, (column_name, _)) in assignments.iter().enumerate() {
+      if let Err(error) =
+        self.check_unique_constraint_for_update(column_name, &columns_new_values[i]) {
+        return Err(NollaDBError::Internal(
+          format!(
+            "Unique key constraint violation: {}",
+            error
+          )
+        ));
+      }
+    }
+
+    // 5. 检查多列索引的唯一性约束
+    // 先算出被更新的行在多列索引中的新值，再检查新值之间以及和没有被更新的行之间有没有重复
+    let updated_row_ids: HashSet<i64> = row_ids.iter().cloned().collect();
+    let mut composite_changes: Vec<(String, Vec<CompositeIndexChange>)> = vec![];
+    {
+      let table_rows_data = self.table_rows.read();
+      for (index_name, composite_index) in &self.composite_indexes {
+        let column_names = &composite_index.column_names;
+        if !assignments.iter().any(|(column_name, _)| column_names.contains(column_name)) {
+          continue;
+        }
+
+        let mut checked_values = CompositeIndex::new(column_names.clone(), true, false, false);
+        let mut changes = vec![];
+        for (j, row_id) in row_ids.iter().enumerate() {
+          let old_values = get_column_values(&table_rows_data, row_id, column_names);
+          let new_values = column_names
+            .iter()
+            .zip(&old_values)
+            .map(|(column_name, old_value)| {
+              match assignments.iter().position(|(assigned_column_name, _)| assigned_column_name == column_name) {
+                Some(i) => columns_new_values[i][j].1.clone(),
+                None => old_value.clone(),
+              }
+            })
+            .collect::<Vec<Value>>();
+
+          if composite_index.is_unique && (
+            checked_values.is_duplicated(&new_values, &HashSet::new()) ||
+            self.is_composite_duplicated(index_name, &new_values, &updated_row_ids)?
+          ) {
+            return Err(get_composite_unique_error(composite_index, &new_values));
+          }
+          checked_values.insert_values(&new_values, *row_id);
+          changes.push((*row_id, old_values, new_values));
+        }
+        composite_changes.push((index_name.to_string(), changes));
+      }
+    }
+
+    // 6. 以上检查完毕，记录每一行更新之前的值，然后更新 row 和 index
+    for row_id in row_ids {
+      let row_values = self.get_row_values(row_id);
+      self.row_changes.push(RowChange::Update(*row_id, row_values));
+    }
+    let table_rows_clone = self.table_rows.clone();
+    let mut table_rows_data =
+      table_rows_clone
+        .write();
+
+    for (i, (column_name, _)) in assignments.iter().enumerate() {
+      let table_certain_column_data =
+        table_rows_data
+          .get_mut(column_name)
+          .unwrap();
+      let table_certain_column_index =
+        self
+          .get_column_mut(column_name.to_string())
+          .unwrap()
+          .get_index_mut();
+
+      // 先把旧的值从 index 中全部删掉再插入新的值
+      // 避免两行交换值的时候把对方的 index 删掉
+      for (row_id, _) in &columns_new_values[i] {
+        let old_value = table_certain_column_data.get_value(row_id);
+        table_certain_column_index.remove_value(&old_value, *row_id);
+      }
+      for (row_id, value) in &columns_new_values[i] {
+        table_certain_column_data.set_value(*row_id, value)?;
+        table_certain_column_index.insert_value(value, *row_id);
+      }
+    }
+    for (index_name, changes) in composite_changes {
+      let composite_index = self.composite_indexes.get_mut(&index_name).unwrap();
+      for (row_id, old_values, _) in &changes {
+        composite_index.remove_values(old_values, *row_id);
+      }
+      for (row_id, _, new_values) in &changes {
+        composite_index.insert_values(new_values, *row_id);
+      }
+    }
+
+    Ok(row_ids.len())
+  }
+
+  // 把 row_ids 中的每一行从所有的 column 以及 index 中删除，返回被删除的行数
+  pub fn delete_rows(&mut self, row_ids: &[i64]) -> Result<usize> {
+    for row_id in row_ids {
+      self.load_row(*row_id)?;
+      let row_values = self.get_row_values(row_id);
+      self.row_changes.push(RowChange::Delete(*row_id, row_values));
+      self.remove_row(row_id);
+    }
+
+    Ok(row_ids.len())
+  }
+
+  // 撤销 row_changes 中前 number_of_row_changes 个之后的修改
+  pub fn rollback_row_changes(&mut self, number_of_row_changes: usize) {
+    let row_changes = self.row_changes.split_off(number_of_row_changes);
+    self.undo_row_changes(row_changes);
+  }
+
+  // 按照和修改相反的顺序撤销 row_changes，撤销本身不会再被记录
+  pub fn undo_row_changes(&mut self, row_changes: Vec<RowChange>) {
+    for row_change in row_changes.into_iter().rev() {
+      match row_change {
+        RowChange::Insert(row_id, most_recent_row_id) => {
+          self.remove_row(&row_id);
+          // 别的会话可能在这之后又插入了新的行，这时不能把 most_recent_row_id 改小
+          if self.most_recent_row_id == row_id {
+            self.most_recent_row_id = most_recent_row_id;
+          }
+        },
+        RowChange::Update(row_id, row_values) => {
+          self.remove_row(&row_id);
+          self.restore_row(row_id, &row_values);
+        },
+        RowChange::Delete(row_id, row_values) => {
+          self.restore_row(row_id, &row_values);
+        },
+      }
+    }
+  }
+
+  // 把一条语句对这张表的修改加到每一行的版本链中
+  // 这一行最新的修改是别的会话还没有提交的，或者是在快照之后才提交的，就是写冲突
+  // 有冲突的话这条语句会被回滚，所以这里还是先把版本记录下来，回滚的时候再一起删掉
+  pub fn add_row_versions(
+    &mut self,
+    session_id: usize,
+    snapshot_ts: u64,
+    row_changes: &[RowChange],
+  ) -> Result<()> {
+    let mut conflicted_row_id = None;
+    for row_change in row_changes {
+      let (row_id, row_values) = match row_change {
+        RowChange::Insert(row_id, _) => (*row_id, None),
+        RowChange::Update(row_id, row_values) | RowChange::Delete(row_id, row_values) => {
+          (*row_id, Some(row_values.clone()))
+        },
+      };
+      let row_versions = self.row_versions.entry(row_id).or_default();
+      if row_versions.last().is_some_and(|row_version| !row_version.is_visible(session_id, snapshot_ts)) {
+        conflicted_row_id.get_or_insert(row_id);
+      }
+      row_versions.push(RowVersion { session_id, commit_ts: None, row_values });
+    }
+
+    match conflicted_row_id {
+      Some(row_id) => Err(get_write_conflict_error(&self.table_name, row_id)),
+      None => Ok(()),
+    }
+  }
+
+  // UPDATE 和 DELETE 修改之前先检查写冲突
+  // 快照中能看到的行在表中可能已经被别的会话删掉了，不能在这样的行上修改
+  pub fn check_write_conflicts(
+    &self,
+    session_id: usize,
+    snapshot_ts: u64,
+    row_ids: &[i64],
+  ) -> Result<()> {
+    for row_id in row_ids {
+      let is_conflicted = self.row_versions
+        .get(row_id)
+        .and_then(|row_versions| row_versions.last())
+        .is_some_and(|row_version| !row_version.is_visible(session_id, snapshot_ts));
+      if is_conflicted {
+        return Err(get_write_conflict_error(&self.table_name, *row_id));
+      }
+    }
+
+    Ok(())
+  }
+
+  // 回滚的时候删掉 row_changes 加到版本链中的版本，和 undo_row_changes 一起使用
+  // 冲突检查保证了一行中还没有提交的版本都是同一个会话的，所以直接删掉最新的版本就可以
+  pub fn remove_row_versions(&mut self, row_changes: &[RowChange]) {
+    for row_change in row_changes.iter().rev() {
+      let row_id = match row_change {
+        RowChange::Insert(row_id, _) | RowChange::Update(row_id, _) | RowChange::Delete(row_id, _) => row_id,
+      };
+      if let Some(row_versions) = self.row_versions.get_mut(row_id) {
+        row_versions.pop();
+        if row_versions.is_empty() {
+          self.row_versions.remove(row_id);
+        }
+      }
+    }
+  }
+
+  // 提交 session_id 对这张表的所有修改
+  pub fn commit_row_versions(&mut self, session_id: usize, commit_ts: u64) {
+    for row_versions in self.row_versions.values_mut() {
+      for row_version in row_versions.iter_mut().rev() {
+        if row_version.commit_ts.is_some() { break; }
+        if row_version.session_id == session_id {
+          row_version.commit_ts = Some(commit_ts);
+        }
+      }
+    }
+    self.last_commit_ts = commit_ts;
+  }
+
+  // 回收旧的版本
+  // 在 min_snapshot_ts 之前提交的修改对所有快照都是可见的，这次修改以及更早的版本都不会再被用到
+  pub fn remove_old_row_versions(&mut self, min_snapshot_ts: u64) {
+    self.row_versions.retain(|_, row_versions| {
+      if let Some(i) = row_versions
+        .iter()
+        .rposition(|row_version| row_version.commit_ts.is_some_and(|commit_ts| commit_ts <= min_snapshot_ts)) {
+        row_versions.drain(..=i);
+      }
+      !row_versions.is_empty()
+    });
+  }
+
+  // session_id 在 snapshot_ts 时的快照中看不到最新版本的行
+  // 只需要沿着这些行的版本链往回找，不需要复制整张表，读的时候这些行用快照中的值
+  pub fn get_invisible_rows(&self, session_id: usize, snapshot_ts: u64) -> Result<InvisibleRows> {
+    self.row_versions
+      .iter()
+      .filter(|(_, row_versions)| {
+        row_versions
+          .last()
+          .is_some_and(|row_version| !row_version.is_visible(session_id, snapshot_ts))
+      })
+      .map(|(row_id, row_versions)| {
+        let row_values = self.read_row(row_id)?;
+        Ok((*row_id, get_visible_row_values(row_values, row_versions, session_id, snapshot_ts)))
+      })
+      .collect()
+  }
+
+  // 提交时这些行的值，写到 WAL 中
+  pub fn get_committed_rows(&self, row_ids: &BTreeSet<i64>) -> Result<Vec<CommittedRow>> {
+    row_ids
+      .iter()
+      .map(|row_id| Ok((*row_id, self.read_row(row_id)?)))
+      .collect()
+  }
+
+  // 重放 WAL 时把这些行改成提交时的值
+  pub fn redo_committed_rows(&mut self, committed_rows: &[CommittedRow]) -> Result<()> {
+    for (row_id, row_values) in committed_rows {
+      self.load_row(*row_id)?;
+      if self.has_row(row_id) {
+        self.remove_row(row_id);
+      }
+      if let Some(row_values) = row_values {
+        self.restore_row(*row_id, row_values);
+      }
+    }
+    Ok(())
+  }
+
+  // 按照 table_columns 的顺序拿到一行的值，表中没有这一行的话返回 None
+  // 文件中没有被修改过的行从文件中读，其他的行在 table_rows 中
+  pub fn read_row(&self, row_id: &i64) -> Result<Option<Vec<Value>>> {
+    match &self.stored_table {
+      Some(stored_table) if !stored_table.changed_row_ids.contains(row_id) => {
+        stored_table.get_row_values(*row_id, &self.table_columns)
+      },
+      _ => Ok(self.has_row(row_id).then(|| self.get_row_values(row_id))),
+    }
+  }
+
+  // 修改一行之前先把它从文件中读到 table_rows 以及内存中的索引中，之后这一行只从 table_rows 中读
+  fn load_row(&mut self, row_id: i64) -> Result<()> {
+    let row_values = match &self.stored_table {
+      Some(stored_table) if !stored_table.changed_row_ids.contains(&row_id) => {
+        stored_table.get_row_values(row_id, &self.table_columns)?
+      },
+      _ => return Ok(()),
+    };
+    if let Some(stored_table) = self.stored_table.as_mut() {
+      stored_table.changed_row_ids.insert(row_id);
+    }
+    if let Some(row_values) = row_values {
+      self.restore_row(row_id, &row_values);
+    }
+    Ok(())
+  }
+
+  // 表中的行都已经保存在文件中了，清空 table_rows 以及内存中的索引，之后查询的时候再从文件中读
+  // 复制出来的表还在用原来的 table_rows，所以换成新的而不是清空原来的
+  pub fn set_stored_table(&mut self, stored_table: StoredTable) {
+    self.table_rows = TableRows::new(
+      self.table_columns
+        .iter()
+        .map(|table_column| (table_column.column_name.to_string(), Row::new(&table_column.column_datatype)))
+        .collect()
+    );
+    for table_column in self.table_columns.iter_mut() {
+      table_column.index = Index::new(&table_column.column_datatype);
+    }
+    for composite_index in self.composite_indexes.values_mut() {
+      composite_index.clear();
+    }
+    self.stored_table = Some(stored_table);
+  }
+
+  // 表中是否有 row id 对应的这一行
+  fn has_row(&self, row_id: &i64) -> bool {
+    let table_rows_clone = self.table_rows.clone();
+    let table_rows_data =
+      table_rows_clone
+        .read();
+
+    self.table_columns
+      .first()
+      .and_then(|table_column| table_rows_data.get(&table_column.column_name))
+      .is_some_and(|column_data| column_data.has_row_id(row_id))
+  }
+
+  // 按照 table_columns 的顺序拿到一行的值
+  fn get_row_values(&self, row_id: &i64) -> Vec<Value> {
+    let column_names = self.table_columns
+      .iter()
+      .map(|table_column| table_column.column_name.to_string())
+      .collect::<Vec<String>>();
+    get_column_values(&self.table_rows.read(), row_id, &column_names)
+  }
+
+  // 把一行从所有的 column 以及 index 中删除
+  fn remove_row(&mut self, row_id: &i64) {
+    let table_rows_clone = self.table_rows.clone();
+    let mut table_rows_data =
+      table_rows_clone
+        .write();
+
+    // 先删除多列索引中的数据，column 中的数据删掉之后就拿不到这一行的值了
+    for composite_index in self.composite_indexes.values_mut() {
+      let values = get_column_values(&table_rows_data, row_id, &composite_index.column_names);
+      composite_index.remove_values(&values, *row_id);
+    }
+
+    for table_column in self.table_columns.iter_mut() {
+      let value = table_rows_data
+        .get_mut(&table_column.column_name)
+        .unwrap()
+        .remove_value(row_id);
+      table_column.get_index_mut().remove_value(&value, *row_id);
+    }
+  }
+
+  // 把 get_row_values 拿到的一行重新写回所有的 column 以及 index
+  // 这一行原来就在表中，所以不需要再检查约束
+  fn restore_row(&mut self, row_id: i64, row_values: &[Value]) {
+    let table_rows_clone = self.table_rows.clone();
+    let mut table_rows_data =
+      table_rows_clone
+        .write();
+
+    for (table_column, value) in self.table_columns.iter_mut().zip(row_values) {
+      table_rows_data
+        .get_mut(&table_column.column_name)
+        .unwrap()
+        .set_value(row_id, value)
+        .expect("Restored value should match the column data type");
+      table_column.get_index_mut().insert_value(value, row_id);
+    }
+    for composite_index in self.composite_indexes.values_mut() {
+      let values = get_column_values(&table_rows_data, &row_id, &composite_index.column_names);
+      composite_index.insert_values(&values, row_id);
+    }
+  }
+
+  // table_rows 中所有的 row id，每一列的 row id 都是对齐的，所以取第一列的就可以
+  fn get_loaded_row_ids(&self) -> Vec<i64> {
+    let table_rows_clone = self.table_rows.clone();
+    let table_rows_data =
+      table_rows_clone
+        .read();
+
+    match self.table_columns.first() {
+      Some(table_column) => table_rows_data
+        .get(&table_column.column_name)
+        .map_or(vec![], |column_data| column_data.get_row_ids()),
+      None => vec![],
+    }
+  }
+
+  // column_names 中每一列在 table_columns 中的位置，也就是在一行中的位置
+  pub fn get_column_positions(&self, column_names: &[String]) -> Result<Vec<usize>> {
+    column_names
+      .iter()
+      .map(|column_name| {
+        self.table_columns
+          .iter()
+          .position(|table_column| table_column.column_name == *column_name)
+          .ok_or_else(|| NollaDBError::Internal(
+            format!(
+              "Column '{}' does not exist in table '{}'",
+              column_name,
+              self.table_name
+            )
+          ))
+      })
+      .collect()
+  }
+
+  // 文件中 column 的索引
+  fn get_stored_column_index(&self, column_name: &str) -> Option<(&StoredTable, PageId)> {
+    let stored_table = self.stored_table.as_ref()?;
+    Some((stored_table, *stored_table.column_index_roots.get(column_name)?))
+  }
+
+  // 文件中的多列索引
+  fn get_stored_composite_index(&self, index_name: &str) -> Option<(&StoredTable, PageId)> {
+    let stored_table = self.stored_table.as_ref()?;
+    Some((stored_table, *stored_table.composite_index_roots.get(index_name)?))
+  }
+
+  // 多列索引的 UNIQUE 约束下 values 是否和 ignored_row_ids 以外的行重复，也要检查文件中没有被修改过的行
+  fn is_composite_duplicated(&self, index_name: &str, values: &[Value], ignored_row_ids: &HashSet<i64>) -> Result<bool> {
+    if self.composite_indexes[index_name].is_duplicated(values, ignored_row_ids) {
+      return Ok(true);
+    }
+    if values.iter().any(|value| value.is_null()) { return Ok(false); }
+    match self.get_stored_composite_index(index_name) {
+      Some((stored_table, root)) => Ok(
+        stored_table
+          .get_index_row_ids(root, values)?
+          .iter()
+          .any(|row_id| !ignored_row_ids.contains(row_id))
+      ),
+      None => Ok(false),
+    }
+  }
+
+  // 文件中有 column 的索引时，按照索引的顺序拿到所有的 row id
+  // 文件中没有被修改过的行和 table_rows 中的行一起按照 value 的编码排序，value 一样的行按照 row id 从小到大排列
+  fn get_stored_index_order(&self, stored_table: &StoredTable, root: PageId, column_name: &str, asc: bool) -> Result<Vec<i64>> {
+    let mut index_entries = stored_table.get_index_entries(root)?;
+    let table_rows_data = self.table_rows.read();
+    let column_data = &table_rows_data[column_name];
+    for row_id in column_data.get_row_ids() {
+      let value = column_data.get_value(&row_id);
+      if !value.is_null() {
+        index_entries.push((encode_values(&[value]), row_id));
+      }
+    }
+    index_entries.sort_unstable_by(|(values, row_id), (other_values, other_row_id)| {
+      let ordering = match asc {
+        true => values.cmp(other_values),
+        false => other_values.cmp(values),
+      };
+      ordering.then(row_id.cmp(other_row_id))
+    });
+    Ok(index_entries.into_iter().map(|(_, row_id)| row_id).collect())
+  }
+
+  // ALTER TABLE ADD COLUMN
+  // 表中已经存在的行用 DEFAULT 值回填，没有 DEFAULT 值就回填 NULL
+  pub fn add_column(&mut self, schema_of_sql_column: &SchemaOfSQLColumn) -> Result<()> {
+    let SchemaOfSQLColumn {
+      column_name,
+      column_datatype,
+      is_primary_key,
+      is_unique_constraint,
+      is_not_null_constraint,
+      default_value,
+    } = schema_of_sql_column;
+
+    if self.has_column(column_name.to_string()) {
+      return Err(NollaDBError::Internal(
+        format!("Duplicate column name: {}", column_name)
+      ));
+    }
+    // 和 SQLite 一样，不能新增 PRIMARY KEY 或者 UNIQUE 的 column
+    if *is_primary_key || *is_unique_constraint {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not add column '{}', because it is a PRIMARY KEY or UNIQUE column",
+          column_name
+        )
+      ));
+    }
+
+    let data_type = DataType::new(column_datatype.to_string());
+    if data_type == DataType::Invalid {
+      return Err(NollaDBError::Internal(
+        format!("Invalid datatype for column '{}'", column_name)
+      ));
+    }
+
+    let value = default_value.clone().unwrap_or(Value::Null);
+    if value.is_null() && *is_not_null_constraint {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not add NOT NULL column '{}' with default value NULL",
+          column_name
+        )
+      ));
+    }
+
+    // 先在新的 column 中回填数据，全部成功之后再加到表中
+    // 文件中的行没有这一列，读的时候用 DEFAULT 值，所以只需要回填 table_rows 中的行
+    let mut table_column = Column::new(
+      column_name.to_string(),
+      column_datatype.to_string(),
+      *is_primary_key,
+      *is_unique_constraint,
+      *is_not_null_constraint,
+      default_value.clone(),
+    );
+    let mut table_column_data = Row::new(&data_type);
+    for row_id in self.get_loaded_row_ids() {
+      table_column_data.set_value(row_id, &value)?;
+      table_column.get_index_mut().insert_value(&value, row_id);
+    }
+
+    self.table_rows
+      .write()
+      .insert(column_name.to_string(), table_column_data);
+    self.table_columns.push(table_column);
+
+    Ok(())
+  }
+
+  // ALTER TABLE DROP COLUMN
+  pub fn drop_column(&mut self, column_name: &str) -> Result<()> {
+    if !self.has_column(column_name.to_string()) {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not drop column, because column '{}' does not exist",
+          column_name
+        )
+      ));
+    }
+    if column_name == self.primary_key {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not drop column '{}', because it is the PRIMARY KEY",
+          column_name
+        )
+      ));
+    }
+    if let Some((index_name, _)) = self.composite_indexes
+        .iter()
+        .find(|(_, composite_index)| {
+          composite_index.is_constraint && composite_index.column_names.iter().any(|name| name == column_name)
+        }) {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not drop column '{}', because it is used by constraint '{}'",
+          column_name,
+          index_name
+        )
+      ));
+    }
+    if self.table_columns.len() == 1 {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not drop column '{}', because it is the only column in table '{}'",
+          column_name,
+          self.table_name
+        )
+      ));
+    }
+
+    self.table_rows
+      .write()
+      .remove(column_name);
+    self.table_columns.retain(|table_column| table_column.column_name != column_name);
+    // 删除建立在这一列上的索引
+    self.indexes.retain(|_, indexed_column_name| indexed_column_name != column_name);
+    self.unique_indexes.retain(|index_name| self.indexes.contains_key(index_name));
+    self.composite_indexes.retain(|_, composite_index| {
+      !composite_index.column_names.iter().any(|name| name == column_name)
+    });
+    if let Some(stored_table) = self.stored_table.as_mut() {
+      for stored_column_name in stored_table.column_names.iter_mut() {
+        if stored_column_name.as_deref() == Some(column_name) {
+          *stored_column_name = None;
+        }
       }
+      stored_table.column_index_roots.remove(column_name);
+      stored_table.composite_index_roots.retain(|index_name, _| self.composite_indexes.contains_key(index_name));
     }
 
-    Err(NollaDBError::General(String::from("Column not found")))
+    Ok(())
   }
 
-  pub fn get_column_mut(&mut self, column_name: String) -> Result<&mut Column> {
-    // TODO: 待优化
-    for table_column in self.table_columns.iter_mut() {
-      if table_column.column_name == column_name {
-        return Ok(table_column);
-      }
+  // ALTER TABLE RENAME COLUMN
+  pub fn rename_column(&mut self, old_column_name: &str, new_column_name: &str) -> Result<()> {
+    if !self.has_column(old_column_name.to_string()) {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not rename column, because column '{}' does not exist",
+          old_column_name
+        )
+      ));
+    }
+    if self.has_column(new_column_name.to_string()) {
+      return Err(NollaDBError::Internal(
+        format!(
+          "Can not rename column, because column '{}' already exists",
+          new_column_name
+        )
+      ));
     }
 
-    Err(NollaDBError::General(String::from("Column not found")))
-  }
-
-  // 检查 InsertQuery 中的唯一性约束
-  pub fn check_unique_constraint(
-    &mut self,
-    table_column_names: &Vec<String>,
-    table_column_value: &Vec<String>,
-  ) -> Result<()> {
-    for (i, table_column_name) in table_column_names.iter().enumerate() {
-      let table_column = self.get_column_mut(table_column_name.to_string()).unwrap();
-      let Column { index, column_name, .. } = &table_column;
+    let mut table_rows_data = self.table_rows.write();
+    if let Some(table_column_data) = table_rows_data.remove(old_column_name) {
+      table_rows_data.insert(new_column_name.to_string(), table_column_data);
+    }
+    drop(table_rows_data);
 
-      // 找到下一个具备唯一性约束的 column 为止
-      // 或者
-      // 如果当前插入的 column name 和表中的 column name 不一致也继续找
-      if !table_column.is_unique_constraint ||
-         *table_column_name != *column_name { continue; }
-
-      let column_value = &table_column_value[i];
-      match index {
-        Index::Integer(tree) => {
-          if tree.contains_key(&column_value.parse::<i32>().unwrap()) {
-            return Err(
-              NollaDBError::General(
-                format!(
-                  "Error: column {} has a unique constraint violation,
-                  value {} already exists for column {}",
-                  *column_name, column_value, *column_name
-                )
-              )
-            );
-          }
-        },
-        Index::Text(tree) => {
-          if tree.contains_key(column_value) {
-            return Err(
-              NollaDBError::General(
-                format!(
-                  "Error: column {} has a unique constraint violation,
-                  value {} already exists for column {}",
-                  *column_name, column_value, *column_name
-                )
-              )
-            );
-          }
-        },
-        Index::None => {
-          return Err(
-            NollaDBError::General(
-              format!(
-                "Error: cannot find index in column {} ",
-                *column_name
-              )
-            )
-          );
-        },
-      };
+    self.get_column_mut(old_column_name.to_string())?.column_name = new_column_name.to_string();
+    if self.primary_key == old_column_name {
+      self.primary_key = new_column_name.to_string();
+    }
+    for indexed_column_name in self.indexes.values_mut() {
+      if indexed_column_name == old_column_name {
+        *indexed_column_name = new_column_name.to_string();
+      }
+    }
+    for composite_index in self.composite_indexes.values_mut() {
+      for indexed_column_name in composite_index.column_names.iter_mut() {
+        if indexed_column_name == old_column_name {
+          *indexed_column_name = new_column_name.to_string();
+        }
+      }
+    }
+    if let Some(stored_table) = self.stored_table.as_mut() {
+      for stored_column_name in stored_table.column_names.iter_mut() {
+        if stored_column_name.as_deref() == Some(old_column_name) {
+          *stored_column_name = Some(new_column_name.to_string());
+        }
+      }
+      if let Some(root) = stored_table.column_index_roots.remove(old_column_name) {
+        stored_table.column_index_roots.insert(new_column_name.to_string(), root);
+      }
     }
 
     Ok(())
   }
 
-  pub fn insert_row(
-    &mut self,
-    table_column_names: &Vec<String>,
-    table_column_value: &Vec<String>,
-  ) {
-    let mut new_row_id = self.most_recent_row_id + i64::from(1);
+  // 拿到表中所有的 row id，按照从小到大的顺序排列
+  pub fn get_row_ids(&self) -> Result<Vec<i64>> {
+    let mut row_ids = match &self.stored_table {
+      Some(stored_table) => stored_table.get_row_ids()?,
+      None => vec![],
+    };
+    row_ids.extend(self.get_loaded_row_ids());
+    row_ids.sort_unstable();
+    Ok(row_ids)
+  }
 
-    let table_rows_clone = Rc::clone(&self.table_rows);
-    let mut table_rows_data =
-      table_rows_clone
-        .as_ref()
-        .borrow_mut();
+  // 拿到 row id 对应的一整行数据，用于表达式求值
+  pub fn get_row(&self, row_id: &i64) -> Result<RowValues> {
+    let row_values = self.read_row(row_id)?;
+    Ok(
+      self.table_columns
+        .iter()
+        .enumerate()
+        .map(|(i, table_column)| {
+          let value = row_values.as_ref().map_or(Value::Null, |row_values| row_values[i].clone());
+          (table_column.column_name.to_string(), value)
+        })
+        .collect()
+    )
+  }
 
-    if self.primary_key != "-1" {
+  // 按照 row id 从小到大的顺序拿到 row id 大于 after 的最多 limit 行，每一行按照 table_columns 的顺序排列
+  // 文件中的行只会读出用到的页
+  pub fn scan_rows(&self, after: Option<i64>, limit: usize) -> Result<Vec<ScannedRow>> {
+    let mut rows = match &self.stored_table {
+      Some(stored_table) => stored_table.scan_rows(after, limit, &self.table_columns)?,
+      None => vec![],
+    };
+    let loaded_row_ids = self.table_columns
+      .first()
+      .map_or(vec![], |table_column| self.table_rows.read()[&table_column.column_name].get_row_ids_after(after, limit));
+    rows.extend(loaded_row_ids.into_iter().map(|row_id| (row_id, self.get_row_values(&row_id))));
+    rows.sort_unstable_by_key(|(row_id, _)| *row_id);
+    rows.truncate(limit);
+    Ok(rows)
+  }
 
-      // 定义了某一列的名字为 PRIMARY KEY
-      let mut table_certain_column_data =
-        table_rows_data
-          .get_mut(&self.primary_key)
-          .unwrap();
+  // 按照 row id 从小到大的顺序访问表中所有的行，文件中的行一次读出一批
+  pub fn for_each_row(&self, f: &mut dyn FnMut(i64, Vec<Value>) -> Result<()>) -> Result<()> {
+    let mut after = None;
+    loop {
+      let rows = self.scan_rows(after, ROW_BATCH_SIZE)?;
+      let is_finished = rows.len() < ROW_BATCH_SIZE;
+      for (row_id, row_values) in rows {
+        after = Some(row_id);
+        f(row_id, row_values)?;
+      }
+      if is_finished { return Ok(()); }
+    }
+  }
 
-      match
-       table_column_names
-        .iter()
-        .any(|table_column_name| table_column_name == &self.primary_key) {
-          // 如果要 INSERT 的列的列名包含 PRIMARY KEY
-          // 就只更新 row id
-          true => {
-
-            if let Row::Integer(_) = &mut table_certain_column_data {
-              for (i, table_column_name) in table_column_names.iter().enumerate() {
-                // 循环并找到这一列
-                if table_column_name != &self.primary_key { continue; }
-                let value = &table_column_value[i];
-                // 更新 row id
-                new_row_id = value.parse::<i64>().unwrap();
-              }
-            }
-
-          },
-          // 如果要 INSERT 的列的列名不包含 PRIMARY KEY
-          // 直接插入这一列的数据，不用更新 row id
-          false => {
-
-            if let Row::Integer(row_tree) = &mut table_certain_column_data {
-              let key = new_row_id.clone();
-              let value = new_row_id as i32;
-
-              row_tree.insert(key, value);
-
-              let table_certain_column_index =
-                self
-                  .get_column_mut(self.primary_key.to_string())
-                  .unwrap()
-                  .get_index_mut();
-              if let Index::Integer(column_tree) = table_certain_column_index {
-                column_tree.insert(value, key);
-              }
-            }
+  // 找到满足 WHERE 条件的所有 row id
+  // 没有 WHERE 条件的话就是表中所有的 row id
+  pub fn select_row_ids(&self, selection: &Option<Expr>) -> Result<Vec<i64>> {
+    self.filter_row_ids(self.get_row_ids()?, selection)
+  }
+
+  // 从 row_ids 中找到满足 WHERE 条件的 row id
+  pub fn filter_row_ids(&self, row_ids: Vec<i64>, selection: &Option<Expr>) -> Result<Vec<i64>> {
+    let expr = match selection {
+      Some(expr) => expr,
+      None => return Ok(row_ids),
+    };
 
-          },
+    let mut matched_row_ids: Vec<i64> = vec![];
+    for row_id in row_ids {
+      if is_row_matched(expr, &self.get_row(&row_id)?)? {
+        matched_row_ids.push(row_id);
       }
     }
 
-    // 检查 INSERT statement 中是否有表中没有的 column
-    // 如果是，就对该 column 补 "Null"
-    // 使得 row 的长度能对齐
-    let mut j: usize = 0;
-    let column_names_vec = self
-        .table_columns
-        .iter()
-        .map(|table_column| table_column.column_name.to_string())
-        .collect::<Vec<String>>();
+    Ok(matched_row_ids)
+  }
 
-    for i in 0..column_names_vec.len() {
-      let key = &column_names_vec[i];
-      let mut value = String::from("Null");
-
-      match &table_column_names.get(j) {
-        Some(table_column_name) => {
-          if key == &table_column_name.to_string() {
-            value = table_column_value[j].to_string();
-            j += 1;
-          } else {
-            // 如果在原来的表中对应 column 名字不匹配
-            // 如果是 PRIMARY KEY 则跳过
-            if key == &self.primary_key { continue; }
-          }
-        },
-        _ => {
-          // 找到末尾
-          // 如果是 PRIMARY KEY 则跳过
-          if key == &self.primary_key { continue; }
-        },
-      }
+  // column 的索引中值等于 value 的行，按照 row id 从小到大排列
+  // 文件中有这一列的索引的话，内存中的索引只有 table_rows 中的行，还要加上文件中没有被修改过的行
+  pub fn get_row_ids_of_value(&self, table_column: &Column, value: &Value) -> Result<Vec<i64>> {
+    let mut row_ids = table_column.index.get_row_ids_of_value(value);
+    if let Some((stored_table, root)) = self.get_stored_column_index(&table_column.column_name) {
+      row_ids.extend(stored_table.get_index_row_ids(root, std::slice::from_ref(value))?);
+      row_ids.sort_unstable();
+    }
+    Ok(row_ids)
+  }
 
-      // 拿到这个 key 对应的 column data
-      let mut table_key_corresponding_column_data =
-        table_rows_data
-          .get_mut(key)
-          .unwrap();
+  // column 的索引中值在 lower 和 upper 之间的行，按照 row id 从小到大排列
+  // 边界的类型和索引的类型不一样的话返回 None
+  pub fn get_row_ids_in_range(
+    &self,
+    table_column: &Column,
+    lower: Bound<&Value>,
+    upper: Bound<&Value>,
+  ) -> Result<Option<Vec<i64>>> {
+    let mut row_ids = match table_column.index.get_row_ids_in_range(lower, upper) {
+      Some(row_ids) => row_ids,
+      None => return Ok(None),
+    };
+    if let Some((stored_table, root)) = self.get_stored_column_index(&table_column.column_name) {
+      row_ids.extend(stored_table.get_index_row_ids_in_range(root, lower, upper)?);
+    }
+    row_ids.sort_unstable();
+    Ok(Some(row_ids))
+  }
 
-      // 拿到这个 key 对应的 column index
-      let table_key_corresponding_column_index =
-        self
-          .get_column_mut(key.to_string())
-          .unwrap()
-          .get_index_mut();
+  // 多列索引中前几列的值等于 values 的行，按照 row id 从小到大排列
+  pub fn get_row_ids_with_prefix(&self, index_name: &str, values: &[Value]) -> Result<Vec<i64>> {
+    let mut row_ids = self.composite_indexes
+      .get(index_name)
+      .map(|composite_index| composite_index.get_row_ids_with_prefix(values))
+      .unwrap_or_default();
+    if let Some((stored_table, root)) = self.get_stored_composite_index(index_name) {
+      row_ids.extend(stored_table.get_index_row_ids(root, values)?);
+      row_ids.sort_unstable();
+    }
+    Ok(row_ids)
+  }
 
-      // 更新
-      let key = new_row_id.clone();
-      match &mut table_key_corresponding_column_data {
-        Row::Integer(row_tree) => {
-          let value = value.parse::<i32>().unwrap();
-          row_tree.insert(key, value);
-          if let Index::Integer(column_tree) = table_key_corresponding_column_index {
-            column_tree.insert(value, key);
-          }
-        },
-        Row::Bool(row_tree) => {
-          let value = value.parse::<bool>().unwrap();
-          row_tree.insert(key, value);
-        },
-        Row::Text(row_tree) => {
-          row_tree.insert(key, value.to_string());
-          if let Index::Text(column_tree) = table_key_corresponding_column_index {
-            column_tree.insert(value.to_string(), key);
-          }
-        },
-        Row::Real(row_tree) => {
-          let value = value.parse::<f32>().unwrap();
-          row_tree.insert(key, value);
-        },
-        Row::None => panic!("None column data found"),
-      }
+  // 按照 column 的索引对 row_ids 排序，索引中的 value 本身就是有序的
+  // 不能用索引的 column 返回 None，需要对每一行求值之后再排序
+  // NULL 不在索引中，按照 row id 的顺序放在最前面或者最后面
+  pub fn sort_row_ids_by_index(
+    &self,
+    row_ids: &[i64],
+    column_name: &str,
+    asc: bool,
+    nulls_first: bool,
+  ) -> Result<Option<Vec<i64>>> {
+    let table_column = match self.get_column(column_name.to_string()) {
+      Ok(table_column) if self.has_usable_index(table_column) => table_column,
+      _ => return Ok(None),
+    };
+
+    let matched_row_ids: HashSet<i64> = row_ids.iter().cloned().collect();
+    let index_row_ids = match self.get_stored_column_index(column_name) {
+      Some((stored_table, root)) => self.get_stored_index_order(stored_table, root, column_name, asc)?,
+      None => table_column.index.get_row_ids(asc),
+    };
+    let sorted_row_ids: Vec<i64> = index_row_ids
+      .into_iter()
+      .filter(|row_id| matched_row_ids.contains(row_id))
+      .collect();
+
+    let indexed_row_ids: HashSet<i64> = sorted_row_ids.iter().cloned().collect();
+    let null_row_ids = row_ids
+      .iter()
+      .filter(|row_id| !indexed_row_ids.contains(row_id))
+      .cloned();
+
+    match nulls_first {
+      true => Ok(Some(null_row_ids.chain(sorted_row_ids).collect())),
+      false => Ok(Some(sorted_row_ids.into_iter().chain(null_row_ids).collect())),
     }
-    self.most_recent_row_id = new_row_id;
+  }
+
+  // 按照 column_names 的顺序，把 row_ids 中每一行对应的值取出来
+  pub fn select_rows(
+    &self,
+    row_ids: &[i64],
+    column_names: &[String],
+  ) -> Result<Vec<Vec<Value>>> {
+    let column_positions = self.get_column_positions(column_names)?;
+    row_ids
+      .iter()
+      .map(|row_id| {
+        let row_values = self.read_row(row_id)?;
+        Ok(
+          column_positions
+            .iter()
+            .map(|i| row_values.as_ref().map_or(Value::Null, |row_values| row_values[*i].clone()))
+            .collect()
+        )
+      })
+      .collect()
   }
 
   pub fn print_column_of_schema(&self) -> Result<usize> {
@@ -378,7 +1505,6 @@ impl Table {
         column_name,
         column_datatype,
         is_primary_key,
-        is_unique_constraint,
         is_not_null_constraint,
         is_indexed,
         ..
@@ -388,13 +1514,47 @@ impl Table {
         column_name,
         column_datatype,
         is_primary_key,
-        is_unique_constraint,
+        self.is_unique_column(table_column),
         is_not_null_constraint,
         is_indexed,
       ]);
     }
 
-    Ok(print_table.printstd())
+    let number_of_lines = print_table
+      .print_tty(false)
+      .map_err(|error| NollaDBError::Internal(error.to_string()))?;
+    if self.indexes.is_empty() && self.composite_indexes.is_empty() {
+      return Ok(number_of_lines);
+    }
+
+    // CREATE INDEX 创建的索引以及多列索引，按照索引名排序输出
+    let mut print_index_table = PrintTable::new();
+    print_index_table.add_row(row![
+      "Index Name",
+      "Column Name",
+      "IS UNIQUE",
+    ]);
+    let mut index_rows = self.indexes
+      .iter()
+      .map(|(index_name, column_name)| {
+        (index_name, column_name.to_string(), self.unique_indexes.contains(index_name))
+      })
+      .chain(self.composite_indexes.iter().map(|(index_name, composite_index)| {
+        (index_name, composite_index.column_names.join(", "), composite_index.is_unique)
+      }))
+      .collect::<Vec<(&String, String, bool)>>();
+    index_rows.sort();
+    for (index_name, column_names, is_unique) in index_rows {
+      print_index_table.add_row(row![
+        index_name,
+        column_names,
+        is_unique,
+      ]);
+    }
+
+    Ok(number_of_lines + print_index_table
+      .print_tty(false)
+      .map_err(|error| NollaDBError::Internal(error.to_string()))?)
   }
 
   pub fn print_table_data(&self) -> Result<usize> {
@@ -411,65 +1571,83 @@ impl Table {
     let print_table_rows_header = PrintRow::new(
       column_names_vec
         .iter()
-        .map(|column_name| PrintCell::new(&column_name))
+        .map(|column_name| PrintCell::new(column_name))
         .collect::<Vec<PrintCell>>(),
     );
 
-    let table_rows_clone = Rc::clone(&self.table_rows);
-    let table_rows_data =
-      table_rows_clone
-        .as_ref()
-        .borrow();
-    let table_first_column_data =
-      table_rows_data
-        .get(&self.table_columns.first().unwrap().column_name)
-        .unwrap();
-
-    let number_of_element_in_column
-      = table_first_column_data.get_number_of_element_in_column();
-
-    let mut print_table_rows: Vec<PrintRow>
-      = vec![
-        PrintRow::new(vec![]);
-        number_of_element_in_column
-      ];
-
-    // 拿到每个 column_name 对应下的数据，并进行输出
-    for column_name in &column_names_vec {
-      let table_certain_column_data =
-        table_rows_data
-          .get(column_name)
-          .expect("Can not find any rows with the given column name");
-      let values_of_table_certain_column_data =
-        table_certain_column_data.get_serialized_column_data();
-
-      for i in 0..number_of_element_in_column {
-        let mut cell_instance = "";
-        if let Some(cell) =
-          &values_of_table_certain_column_data.get(i) {
-          cell_instance = cell;
-        }
-        print_table_rows[i].add_cell(PrintCell::new(cell_instance));
-      }
-    }
+    // 拿到每一行按照 column 顺序排列的数据，并进行输出
+    let mut print_table_rows: Vec<PrintRow> = vec![];
+    self.for_each_row(&mut |_, row_values| {
+      print_table_rows.push(PrintRow::new(
+        row_values
+          .iter()
+          .map(|value| PrintCell::new(&value.to_string()))
+          .collect::<Vec<PrintCell>>(),
+      ));
+      Ok(())
+    })?;
 
     print_table.add_row(print_table_rows_header);
     for row in print_table_rows {
       print_table.add_row(row);
     }
 
-    Ok(print_table.printstd())
+    print_table
+      .print_tty(false)
+      .map_err(|error| NollaDBError::Internal(error.to_string()))
   }
 }
 
+// 按照 column_names 的顺序拿到一行中这几列的值
+fn get_column_values(table_rows_data: &HashMap<String, Row>, row_id: &i64, column_names: &[String]) -> Vec<Value> {
+  column_names
+    .iter()
+    .map(|column_name| table_rows_data[column_name].get_value(row_id))
+    .collect()
+}
+
+fn get_write_conflict_error(table_name: &str, row_id: i64) -> NollaDBError {
+  NollaDBError::SerializationFailure(
+    format!(
+      "row {} of table '{}' has been changed by another transaction",
+      row_id, table_name
+    )
+  )
+}
+
+fn join_values(values: &[Value]) -> String {
+  values
+    .iter()
+    .map(|value| value.to_string())
+    .collect::<Vec<String>>()
+    .join(", ")
+}
+
+fn get_composite_unique_error(composite_index: &CompositeIndex, values: &[Value]) -> NollaDBError {
+  NollaDBError::Internal(
+    format!(
+      "Unique key constraint violation: columns ({}) have a unique constraint violation,
+      value ({}) already exists for columns ({})",
+      composite_index.column_names.join(", "),
+      join_values(values),
+      composite_index.column_names.join(", "),
+    )
+  )
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use std::result::Result;
+  use std::collections::BTreeMap;
   use rstest::rstest;
   use pretty_assertions::{assert_eq};
   use sqlparser::parser::Parser;
   use sqlparser::dialect::SQLiteDialect;
+  use crate::sql_query::query::select::SelectQuery;
+  use crate::sql_query::query::update::UpdateQuery;
+  use crate::sql_query::query::delete::DeleteQuery;
+  use crate::sql_query::query::alter::{AlterQuery, AlterOperation};
 
   #[rstest]
   #[case(DataType::Integer, "Integer")]
@@ -512,7 +1690,7 @@ mod tests {
     if let Some(table_column) =
       table.table_columns
         .iter()
-        .filter(|tc| tc.column_name == "id".to_string())
+        .filter(|tc| tc.column_name == "id")
         .collect::<Vec<&Column>>()
         .first() {
       assert_eq!(table.table_columns.len(), expected_table_columns_len);
@@ -541,9 +1719,544 @@ mod tests {
     assert_eq!(table.print_column_of_schema(), Ok(print_lines_number));
   }
 
-  fn create_new_table(query: &str) -> Result<Table, ()> {
+  #[rstest]
+  #[case(
+    "CREATE TABLE test (
+      id INTEGER PRIMARY KEY,
+      name TEXT,
+      score REAL
+    );",
+    vec!["name", "id"],
+    vec![
+      vec![Value::Text("a".to_string()), Value::Integer(1)],
+      vec![Value::Text("b".to_string()), Value::Integer(2)],
+    ],
+  )]
+  fn test_select_rows(
+    #[case] query: &str,
+    #[case] column_names: Vec<&str>,
+    #[case] expected: Vec<Vec<Value>>,
+  ) {
+    let mut table = create_new_table(query).unwrap();
+    let insert_column_names = vec!["name".to_string(), "score".to_string()];
+    table.insert_row(&insert_column_names, &[Value::Text("a".to_string()), Value::Real(1.5)]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("b".to_string()), Value::Real(2.5)]).unwrap();
+
+    let column_names = column_names
+      .iter()
+      .map(|column_name| column_name.to_string())
+      .collect::<Vec<String>>();
+
+    assert_eq!(table.get_row_ids().unwrap(), vec![1, 2]);
+    assert_eq!(table.select_rows(&table.get_row_ids().unwrap(), &column_names), Ok(expected));
+  }
+
+  #[rstest]
+  #[case("SELECT * FROM test;", vec![1, 2, 3])]
+  #[case("SELECT * FROM test WHERE id = 2;", vec![2])]
+  #[case("SELECT * FROM test WHERE score > 1.5 AND name <> 'c';", vec![2])]
+  #[case("SELECT * FROM test WHERE id >= 2 OR score < 1;", vec![2, 3])]
+  fn test_select_row_ids(
+    #[case] select_query: &str,
+    #[case] expected: Vec<i64>,
+  ) {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT,
+        score REAL
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["name".to_string(), "score".to_string()];
+    table.insert_row(&insert_column_names, &[Value::Text("a".to_string()), Value::Real(1.5)]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("b".to_string()), Value::Real(2.5)]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("c".to_string()), Value::Real(3.5)]).unwrap();
+
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, select_query).unwrap();
+    let select_query = SelectQuery::new(&ast.pop().unwrap()).unwrap();
+
+    assert_eq!(table.select_row_ids(&select_query.selection), Ok(expected));
+  }
+
+  #[rstest]
+  #[case(
+    "UPDATE test SET email = email || '.cn', score = score + 1 WHERE id >= 2;",
+    Ok(2),
+    vec![
+      vec![Value::Text("a@x.com".to_string()), Value::Real(1.5)],
+      vec![Value::Text("b@x.com.cn".to_string()), Value::Real(3.5)],
+      vec![Value::Text("c@x.com.cn".to_string()), Value::Real(4.5)],
+    ],
+  )]
+  #[case(
+    "UPDATE test SET email = 'a@x.com' WHERE id = 2;",
+    Err(()),
+    vec![
+      vec![Value::Text("a@x.com".to_string()), Value::Real(1.5)],
+      vec![Value::Text("b@x.com".to_string()), Value::Real(2.5)],
+      vec![Value::Text("c@x.com".to_string()), Value::Real(3.5)],
+    ],
+  )]
+  #[case(
+    "UPDATE test SET email = 'same@x.com' WHERE id >= 2;",
+    Err(()),
+    vec![
+      vec![Value::Text("a@x.com".to_string()), Value::Real(1.5)],
+      vec![Value::Text("b@x.com".to_string()), Value::Real(2.5)],
+      vec![Value::Text("c@x.com".to_string()), Value::Real(3.5)],
+    ],
+  )]
+  #[case(
+    "UPDATE test SET email = NULL WHERE id <= 2;",
+    Ok(2),
+    vec![
+      vec![Value::Null, Value::Real(1.5)],
+      vec![Value::Null, Value::Real(2.5)],
+      vec![Value::Text("c@x.com".to_string()), Value::Real(3.5)],
+    ],
+  )]
+  #[case(
+    "UPDATE test SET email = 'd@x.com', score = NULL WHERE id = 3;",
+    Err(()),
+    vec![
+      vec![Value::Text("a@x.com".to_string()), Value::Real(1.5)],
+      vec![Value::Text("b@x.com".to_string()), Value::Real(2.5)],
+      vec![Value::Text("c@x.com".to_string()), Value::Real(3.5)],
+    ],
+  )]
+  fn test_update_rows(
+    #[case] update_query: &str,
+    #[case] expected_result: Result<usize, ()>,
+    #[case] expected_rows: Vec<Vec<Value>>,
+  ) {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        email TEXT UNIQUE,
+        score REAL NOT NULL
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["email".to_string(), "score".to_string()];
+    table.insert_row(&insert_column_names, &[Value::Text("a@x.com".to_string()), Value::Real(1.5)]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("b@x.com".to_string()), Value::Real(2.5)]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("c@x.com".to_string()), Value::Real(3.5)]).unwrap();
+
+    let update_query = parse_update_query(update_query);
+    let row_ids = table.select_row_ids(&update_query.selection).unwrap();
+    let result = table.update_rows(&row_ids, &update_query.assignments);
+
+    assert_eq!(result.map_err(|_| ()), expected_result);
+    assert_eq!(
+      table.select_rows(
+        &table.get_row_ids().unwrap(),
+        &["email".to_string(), "score".to_string()]
+      ),
+      Ok(expected_rows)
+    );
+  }
+
+  #[rstest]
+  fn test_update_rows_index() {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        email TEXT UNIQUE
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["email".to_string()];
+    table.insert_row(&insert_column_names, &[Value::Text("a@x.com".to_string())]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("b@x.com".to_string())]).unwrap();
+
+    let update_query = parse_update_query("UPDATE test SET email = 'c@x.com' WHERE id = 1;");
+    assert_eq!(table.update_rows(&[1], &update_query.assignments), Ok(1));
+
+    // 旧的值从 index 中删掉了，新的值指向了被更新的行
+    let index = &table.get_column("email".to_string()).unwrap().index;
+    assert_eq!(index.get_row_ids_of_value(&Value::Text("a@x.com".to_string())), vec![]);
+    assert_eq!(index.get_row_ids_of_value(&Value::Text("c@x.com".to_string())), vec![1]);
+
+    // 被释放出来的值可以被其他行使用
+    let update_query = parse_update_query("UPDATE test SET email = 'a@x.com' WHERE id = 2;");
+    assert_eq!(table.update_rows(&[2], &update_query.assignments), Ok(1));
+    let index = &table.get_column("email".to_string()).unwrap().index;
+    assert_eq!(index.get_row_ids_of_value(&Value::Text("a@x.com".to_string())), vec![2]);
+    assert_eq!(index.get_row_ids_of_value(&Value::Text("b@x.com".to_string())), vec![]);
+  }
+
+  #[rstest]
+  fn test_create_and_drop_index() {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT,
+        email TEXT,
+        score REAL
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["name".to_string(), "email".to_string()];
+    for (name, email) in [("a", "a@x.com"), ("a", "b@x.com"), ("b", "c@x.com")] {
+      table.insert_row(
+        &insert_column_names,
+        &[Value::Text(name.to_string()), Value::Text(email.to_string())],
+      ).unwrap();
+    }
+
+    // 已经存在的值有重复的话不能建立 UNIQUE 索引
+    assert!(table.create_index("idx_name", "name", true).is_err());
+    assert!(table.indexes.is_empty());
+    assert!(table.create_index("idx_not_exist", "not_exist", false).is_err());
+
+    table.create_index("idx_name", "name", false).unwrap();
+    table.create_index("idx_email", "email", true).unwrap();
+    assert!(table.create_index("idx_email", "name", false).is_err());
+    let email_column = table.get_column("email".to_string()).unwrap();
+    assert!(email_column.is_indexed);
+    assert!(table.is_unique_column(email_column));
+    assert!(table.has_usable_index(email_column));
+    assert!(table.has_usable_index(table.get_column("name".to_string()).unwrap()));
+    assert_eq!(email_column.index.get_row_ids(true), vec![1, 2, 3]);
+    let name_index = &table.get_column("name".to_string()).unwrap().index;
+    assert_eq!(name_index.get_row_ids_of_value(&Value::Text("a".to_string())), vec![1, 2]);
+
+    // 之后的 INSERT / UPDATE / DELETE 会检查 UNIQUE 索引，并且维护索引
+    assert!(table.insert_rows(&insert_column_names, &[vec![Value::Text("c".to_string()), Value::Text("a@x.com".to_string())]]).is_err());
+    let update_query = parse_update_query("UPDATE test SET email = 'd@x.com' WHERE id = 1;");
+    assert_eq!(table.update_rows(&[1], &update_query.assignments), Ok(1));
+    table.delete_rows(&[2]).unwrap();
+    let index = &table.get_column("email".to_string()).unwrap().index;
+    assert_eq!(index.get_row_ids_of_value(&Value::Text("a@x.com".to_string())), vec![]);
+    assert_eq!(index.get_row_ids_of_value(&Value::Text("b@x.com".to_string())), vec![]);
+    assert_eq!(index.get_row_ids_of_value(&Value::Text("d@x.com".to_string())), vec![1]);
+
+    // DROP INDEX 之后不再有 UNIQUE 约束
+    table.drop_index("idx_email").unwrap();
+    assert!(table.drop_index("idx_email").is_err());
+    let email_column = table.get_column("email".to_string()).unwrap();
+    assert!(!email_column.is_indexed);
+    assert!(!table.is_unique_column(email_column));
+    assert_eq!(table.indexes.keys().collect::<Vec<&String>>(), vec!["idx_name"]);
+    assert!(table.unique_indexes.is_empty());
+    assert!(table.insert_rows(&insert_column_names, &[vec![Value::Text("c".to_string()), Value::Text("d@x.com".to_string())]]).is_ok());
+  }
+
+  #[rstest]
+  #[case("DELETE FROM test WHERE id = 2;", 1, vec![1, 3])]
+  #[case("DELETE FROM test WHERE email <> 'b@x.com';", 2, vec![2])]
+  #[case("DELETE FROM test WHERE id > 5;", 0, vec![1, 2, 3])]
+  #[case("DELETE FROM test;", 3, vec![])]
+  fn test_delete_rows(
+    #[case] delete_query: &str,
+    #[case] expected_number_of_deleted_rows: usize,
+    #[case] expected_row_ids: Vec<i64>,
+  ) {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        email TEXT UNIQUE
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["email".to_string()];
+    table.insert_row(&insert_column_names, &[Value::Text("a@x.com".to_string())]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("b@x.com".to_string())]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("c@x.com".to_string())]).unwrap();
+
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, delete_query).unwrap();
+    let delete_query = DeleteQuery::new(&ast.pop().unwrap()).unwrap();
+    let row_ids = table.select_row_ids(&delete_query.selection).unwrap();
+
+    assert_eq!(table.delete_rows(&row_ids).unwrap(), expected_number_of_deleted_rows);
+    assert_eq!(table.get_row_ids().unwrap(), expected_row_ids);
+
+    // 被删除的行在每个 index 中都不存在了
+    for row_id in row_ids {
+      let id_index = &table.get_column("id".to_string()).unwrap().index;
+      assert_eq!(id_index.get_row_ids_of_value(&Value::Integer(row_id as i32)), vec![]);
+    }
+    let email_index = &table.get_column("email".to_string()).unwrap().index;
+    for row_id in &expected_row_ids {
+      let email = table.get_row(row_id).unwrap().get("email").unwrap().clone();
+      assert_eq!(email_index.get_row_ids_of_value(&email), vec![*row_id]);
+    }
+  }
+
+  #[rstest]
+  fn test_insert_row_with_null() {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        email TEXT UNIQUE,
+        age INTEGER,
+        name TEXT NOT NULL
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["email".to_string(), "name".to_string()];
+
+    // 没有赋值的 column 以及显式的 NULL 都会被存成 NULL
+    table.insert_row(&insert_column_names, &[Value::Null, Value::Text("a".to_string())]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Null, Value::Text("b".to_string())]).unwrap();
+    assert_eq!(
+      table.select_rows(&table.get_row_ids().unwrap(), &["id".to_string(), "email".to_string(), "age".to_string()]),
+      Ok(vec![
+        vec![Value::Integer(1), Value::Null, Value::Null],
+        vec![Value::Integer(2), Value::Null, Value::Null],
+      ])
+    );
+
+    // NULL 不会写到索引中，所以多个 NULL 不违反唯一性约束
+    let email_column = table.get_column("email".to_string()).unwrap();
+    assert_eq!(email_column.index, Index::Text(BTreeMap::new()));
+    assert!(table.check_unique_constraint(&insert_column_names, &[Value::Null, Value::Text("c".to_string())]).is_ok());
+
+    // NOT NULL 的 column 不能是 NULL，并且不会写入任何数据
+    assert!(table.insert_row(&insert_column_names, &[Value::Text("c@x.com".to_string()), Value::Null]).is_err());
+    assert!(table.insert_row(&["email".to_string()], &[Value::Text("c@x.com".to_string())]).is_err());
+    assert_eq!(table.get_row_ids().unwrap(), vec![1, 2]);
+
+    // WHERE 中和 NULL 比较的结果是未知的，只有 IS NULL 可以匹配到
+    assert_eq!(table.select_row_ids(&parse_selection("age = NULL")), Ok(vec![]));
+    assert_eq!(table.select_row_ids(&parse_selection("age IS NULL")), Ok(vec![1, 2]));
+  }
+
+  #[rstest]
+  fn test_insert_rows() {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        email TEXT UNIQUE,
+        name TEXT NOT NULL
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["email".to_string(), "name".to_string()];
+    table.insert_row(&insert_column_names, &[Value::Text("a@x.com".to_string()), Value::Text("a".to_string())]).unwrap();
+
+    // 第 3 行违反 NOT NULL 约束，前 2 行也不会被插入
+    assert_eq!(
+      table.insert_rows(&insert_column_names, &[
+        vec![Value::Text("b@x.com".to_string()), Value::Text("b".to_string())],
+        vec![Value::Text("c@x.com".to_string()), Value::Text("c".to_string())],
+        vec![Value::Text("d@x.com".to_string()), Value::Null],
+      ]),
+      Err(NollaDBError::NotNullConstraint("test".to_string(), "name".to_string()))
+    );
+    // 第 2 行和第 1 行的 email 重复
+    assert!(
+      table.insert_rows(&insert_column_names, &[
+        vec![Value::Text("b@x.com".to_string()), Value::Text("b".to_string())],
+        vec![Value::Text("b@x.com".to_string()), Value::Text("c".to_string())],
+      ]).is_err()
+    );
+    assert_eq!(table.get_row_ids().unwrap(), vec![1]);
+    assert_eq!(table.most_recent_row_id, 1);
+    assert_eq!(table.get_column("email".to_string()).unwrap().index.get_row_ids_of_value(&Value::Text("b@x.com".to_string())), vec![]);
+
+    assert_eq!(
+      table.insert_rows(&insert_column_names, &[
+        vec![Value::Text("b@x.com".to_string()), Value::Text("b".to_string())],
+        vec![Value::Text("c@x.com".to_string()), Value::Text("c".to_string())],
+      ]),
+      Ok(2)
+    );
+    assert_eq!(table.get_row_ids().unwrap(), vec![1, 2, 3]);
+  }
+
+  #[rstest]
+  #[case("email", true, true, Some(vec![2, 1, 3]))]
+  #[case("email", false, true, Some(vec![2, 3, 1]))]
+  #[case("email", true, false, Some(vec![1, 3, 2]))]
+  #[case("id", false, false, Some(vec![3, 2, 1]))]
+  #[case("name", true, true, None)]
+  fn test_sort_row_ids_by_index(
+    #[case] column_name: &str,
+    #[case] asc: bool,
+    #[case] nulls_first: bool,
+    #[case] expected: Option<Vec<i64>>,
+  ) {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        email TEXT UNIQUE,
+        name TEXT
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["email".to_string(), "name".to_string()];
+    table.insert_row(&insert_column_names, &[Value::Text("b@x.com".to_string()), Value::Text("a".to_string())]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Null, Value::Text("a".to_string())]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("c@x.com".to_string()), Value::Text("b".to_string())]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("a@x.com".to_string()), Value::Text("b".to_string())]).unwrap();
+
+    // 第 4 行不满足 WHERE 条件，不会出现在结果中
+    assert_eq!(
+      table.sort_row_ids_by_index(&[1, 2, 3], column_name, asc, nulls_first).unwrap(),
+      expected
+    );
+  }
+
+  #[rstest]
+  fn test_bool_and_real_index() {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        is_active BOOLEAN,
+        score REAL UNIQUE
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["is_active".to_string(), "score".to_string()];
+    for (is_active, score) in [(true, 2.5), (false, f32::NAN), (true, -0.0), (false, f32::NEG_INFINITY)] {
+      table.insert_rows(&insert_column_names, &[vec![Value::Bool(is_active), Value::Real(score)]]).unwrap();
+    }
+
+    // 0.0 和 -0.0 是同一个值，NaN 等于 NaN
+    for score in [0.0, f32::NAN] {
+      assert!(table.insert_rows(&insert_column_names, &[vec![Value::Bool(true), Value::Real(score)]]).is_err());
+    }
+    let update_query = parse_update_query("UPDATE test SET score = 0.0 WHERE id = 4;");
+    assert!(table.update_rows(&[4], &update_query.assignments).is_err());
+    assert!(table.check_unique_constraint_for_update("score", &[(1, Value::Real(f32::NAN)), (2, Value::Real(f32::NAN))]).is_err());
+
+    // NaN 排在所有的数后面
+    assert_eq!(table.sort_row_ids_by_index(&[1, 2, 3, 4], "score", true, false).unwrap(), Some(vec![4, 3, 1, 2]));
+    assert_eq!(table.sort_row_ids_by_index(&[1, 2, 3, 4], "score", false, false).unwrap(), Some(vec![2, 1, 3, 4]));
+
+    // Bool 的列用位图索引，可以建立普通索引
+    assert!(table.create_index("idx_is_active", "is_active", true).is_err());
+    table.create_index("idx_is_active", "is_active", false).unwrap();
+    let index = &table.get_column("is_active".to_string()).unwrap().index;
+    assert_eq!(index.get_row_ids_of_value(&Value::Bool(true)), vec![1, 3]);
+    assert_eq!(index.get_row_ids(false), vec![1, 3, 2, 4]);
+    table.delete_rows(&[1]).unwrap();
+    let index = &table.get_column("is_active".to_string()).unwrap().index;
+    assert_eq!(index.get_row_ids_of_value(&Value::Bool(true)), vec![3]);
+  }
+
+  #[rstest]
+  fn test_composite_index() {
+    let mut table = create_new_table(
+      "CREATE TABLE link (
+        user_id INTEGER,
+        group_id INTEGER,
+        role TEXT,
+        PRIMARY KEY (user_id, group_id)
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["user_id".to_string(), "group_id".to_string()];
+    let get_values = |user_id: i32, group_id: i32| vec![Value::Integer(user_id), Value::Integer(group_id)];
+    assert_eq!(table.insert_rows(&insert_column_names, &[get_values(1, 10), get_values(1, 20), get_values(2, 10)]), Ok(3));
+
+    // 一条 INSERT 中有一行重复的话整条语句都不会生效
+    assert!(table.insert_rows(&insert_column_names, &[get_values(1, 10)]).is_err());
+    assert!(table.insert_rows(&insert_column_names, &[get_values(3, 30), get_values(1, 20)]).is_err());
+    assert_eq!(table.insert_rows(&insert_column_names, &[get_values(3, 30)]), Ok(1));
+    assert!(table.insert_rows(&insert_column_names, &[vec![Value::Integer(4), Value::Null]]).is_err());
+
+    // UPDATE 之后的新值不能和其他行重复，新值之间也不能重复
+    let update_query = parse_update_query("UPDATE link SET group_id = 20 WHERE user_id = 1;");
+    assert!(table.update_rows(&[1], &update_query.assignments).is_err());
+    let update_query = parse_update_query("UPDATE link SET group_id = 30;");
+    assert!(table.update_rows(&[1, 2], &update_query.assignments).is_err());
+    let update_query = parse_update_query("UPDATE link SET group_id = group_id + 10;");
+    assert_eq!(table.update_rows(&[1, 2, 3], &update_query.assignments), Ok(3));
+    let composite_index = &table.composite_indexes["link_pkey"];
+    assert_eq!(composite_index.get_row_ids_with_prefix(&get_values(1, 10)), vec![]);
+    assert_eq!(composite_index.get_row_ids_with_prefix(&[Value::Integer(1)]), vec![1, 2]);
+    assert_eq!(composite_index.get_row_ids_with_prefix(&get_values(2, 20)), vec![3]);
+
+    // 删除之后可以再插入同样的值
+    table.delete_rows(&[3]).unwrap();
+    assert_eq!(table.insert_rows(&insert_column_names, &[get_values(2, 20)]), Ok(1));
+
+    // 约束建立的索引不能删除，其中的 column 也不能删除
+    assert!(table.drop_index("link_pkey").is_err());
+    assert!(table.drop_column("user_id").is_err());
+    table.rename_column("group_id", "team_id").unwrap();
+    assert_eq!(table.composite_indexes["link_pkey"].column_names, vec!["user_id", "team_id"]);
+
+    // CREATE INDEX 建立的多列索引会随着 column 一起删除
+    let role_column_names = vec!["role".to_string(), "user_id".to_string()];
+    assert!(table.create_composite_index("idx_role", &role_column_names, false).is_ok());
+    assert!(table.create_composite_index("idx_role", &role_column_names, false).is_err());
+    assert!(table.create_composite_index("idx_unique_role", &role_column_names, true).is_ok());
+    assert_eq!(table.composite_indexes["idx_role"].get_row_ids_with_prefix(&[Value::Null, Value::Integer(1)]), vec![1, 2]);
+    table.drop_column("role").unwrap();
+    assert_eq!(table.composite_indexes.keys().collect::<Vec<&String>>(), vec!["link_pkey"]);
+  }
+
+  #[rstest]
+  fn test_alter_table_columns() {
+    let mut table = create_new_table(
+      "CREATE TABLE test (
+        id INTEGER PRIMARY KEY,
+        name TEXT
+      );"
+    ).unwrap();
+    let insert_column_names = vec!["name".to_string()];
+    table.insert_row(&insert_column_names, &[Value::Text("a".to_string())]).unwrap();
+    table.insert_row(&insert_column_names, &[Value::Text("b".to_string())]).unwrap();
+    table.indexes.insert("idx_name".to_string(), "name".to_string());
+
+    // ADD COLUMN 用 DEFAULT 值回填已经存在的行
+    table.add_column(&parse_column("score REAL DEFAULT 1.5")).unwrap();
+    assert_eq!(
+      table.select_rows(&table.get_row_ids().unwrap(), &["score".to_string()]),
+      Ok(vec![vec![Value::Real(1.5)], vec![Value::Real(1.5)]])
+    );
+    // 之后 INSERT 时没有赋值的话也会用 DEFAULT 值
+    table.insert_row(&insert_column_names, &[Value::Text("c".to_string())]).unwrap();
+    assert_eq!(table.get_row(&3).unwrap().get("score"), Some(&Value::Real(1.5)));
+
+    // 没有 DEFAULT 值的话回填 NULL
+    table.add_column(&parse_column("age INTEGER")).unwrap();
+    assert_eq!(table.get_row(&1).unwrap().get("age"), Some(&Value::Null));
+
+    assert!(table.add_column(&parse_column("score INTEGER")).is_err());
+    assert!(table.add_column(&parse_column("email TEXT UNIQUE")).is_err());
+    assert!(table.add_column(&parse_column("email TEXT NOT NULL")).is_err());
+
+    // RENAME COLUMN 同时更新 primary_key 和 indexes
+    table.rename_column("id", "user_id").unwrap();
+    table.rename_column("name", "user_name").unwrap();
+    assert_eq!(table.primary_key, "user_id");
+    assert_eq!(table.indexes.get("idx_name"), Some(&"user_name".to_string()));
+    assert_eq!(table.get_row(&1).unwrap().get("user_name"), Some(&Value::Text("a".to_string())));
+    assert!(table.rename_column("user_name", "score").is_err());
+    assert!(table.rename_column("not_exist", "xxx").is_err());
+
+    // DROP COLUMN 同时删除建立在这一列上的索引
+    table.drop_column("user_name").unwrap();
+    assert!(!table.has_column("user_name".to_string()));
+    assert!(table.indexes.is_empty());
+    assert!(table.drop_column("user_id").is_err());
+    assert!(table.drop_column("not_exist").is_err());
+    assert_eq!(table.get_row_ids().unwrap(), vec![1, 2, 3]);
+  }
+
+  fn parse_column(column: &str) -> SchemaOfSQLColumn {
+    let dialect = SQLiteDialect {};
+    let query = format!("ALTER TABLE test ADD COLUMN {};", column);
+    let mut ast = Parser::parse_sql(&dialect, &query).unwrap();
+    match AlterQuery::new(&ast.pop().unwrap()).unwrap().operation {
+      AlterOperation::AddColumn(schema_of_sql_column) => schema_of_sql_column,
+      _ => panic!("Expected ADD COLUMN"),
+    }
+  }
+
+  fn parse_selection(selection: &str) -> Option<Expr> {
     let dialect = SQLiteDialect {};
+    let query = format!("SELECT * FROM test WHERE {};", selection);
     let mut ast = Parser::parse_sql(&dialect, &query).unwrap();
+    SelectQuery::new(&ast.pop().unwrap()).unwrap().selection
+  }
+
+  fn parse_update_query(query: &str) -> UpdateQuery {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
+    UpdateQuery::new(&ast.pop().unwrap()).unwrap()
+  }
+
+  fn create_new_table(query: &str) -> Result<Table, ()> {
+    let dialect = SQLiteDialect {};
+    let mut ast = Parser::parse_sql(&dialect, query).unwrap();
     let create_query = CreateQuery::new(&ast.pop().unwrap()).unwrap();
     let table = Table::new(create_query);
 
@@ -1,13 +1,23 @@
+pub mod value;
+
 use std::collections::{BTreeMap};
+use std::ops::Bound;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, PartialEq, Debug)]
+use crate::error::{Result, NollaDBError};
+use crate::table::column::data_type::DataType;
+
+use value::Value;
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+// BTreeMap 的 value 是 Option，None 表示这一行在这一列上的值是 NULL
+// 这样 NULL 的行也会占一个 row id，每一列的 row id 都能对齐
 pub enum Row {
-  Integer(BTreeMap<i64, i32>),
-  Bool(BTreeMap<i64, bool>),
-  Text(BTreeMap<i64, String>),
-  Real(BTreeMap<i64, f32>),
+  Integer(BTreeMap<i64, Option<i32>>),
+  Bool(BTreeMap<i64, Option<bool>>),
+  Text(BTreeMap<i64, Option<String>>),
+  Real(BTreeMap<i64, Option<f32>>),
   None,
 }
 
@@ -16,22 +26,93 @@ pub enum Row {
 // Column 有一个 Index，这个 Index 也是由 BTreeMap 管理
 // 这个 Index 里的 BTreeMap 存的 key/value 跟 Row 里面的 key/value 刚好相反
 impl Row {
-  pub fn get_number_of_element_in_column(&self) -> usize {
+  // 根据 column 的 DataType 创建一个空的 Row
+  pub fn new(data_type: &DataType) -> Self {
+    match data_type {
+      DataType::Integer => Row::Integer(BTreeMap::new()),
+      DataType::Text => Row::Text(BTreeMap::new()),
+      DataType::Bool => Row::Bool(BTreeMap::new()),
+      DataType::Real => Row::Real(BTreeMap::new()),
+      DataType::None => Row::None,
+      DataType::Invalid => Row::None,
+    }
+  }
+
+  // 拿到这一列所有的 row id，BTreeMap 保证了 row id 是有序的
+  pub fn get_row_ids(&self) -> Vec<i64> {
     match self {
-      Row::Integer(tree) => tree.len(),
-      Row::Bool(tree) => tree.len(),
-      Row::Text(tree) => tree.len(),
-      Row::Real(tree) => tree.len(),
+      Row::Integer(tree) => tree.keys().cloned().collect(),
+      Row::Bool(tree) => tree.keys().cloned().collect(),
+      Row::Text(tree) => tree.keys().cloned().collect(),
+      Row::Real(tree) => tree.keys().cloned().collect(),
       Row::None => panic!("Found None Type in columns"),
     }
   }
 
-  pub fn get_serialized_column_data(&self) -> Vec<String> {
+  // 拿到 row id 大于 after 的最多 limit 个 row id，按照从小到大的顺序排列
+  pub fn get_row_ids_after(&self, after: Option<i64>, limit: usize) -> Vec<i64> {
+    let range = (after.map_or(Bound::Unbounded, Bound::Excluded), Bound::Unbounded);
+    match self {
+      Row::Integer(tree) => tree.range(range).map(|(row_id, _)| *row_id).take(limit).collect(),
+      Row::Bool(tree) => tree.range(range).map(|(row_id, _)| *row_id).take(limit).collect(),
+      Row::Text(tree) => tree.range(range).map(|(row_id, _)| *row_id).take(limit).collect(),
+      Row::Real(tree) => tree.range(range).map(|(row_id, _)| *row_id).take(limit).collect(),
+      Row::None => panic!("Found None Type in columns"),
+    }
+  }
+
+  pub fn has_row_id(&self, row_id: &i64) -> bool {
+    match self {
+      Row::Integer(tree) => tree.contains_key(row_id),
+      Row::Bool(tree) => tree.contains_key(row_id),
+      Row::Text(tree) => tree.contains_key(row_id),
+      Row::Real(tree) => tree.contains_key(row_id),
+      Row::None => panic!("Found None Type in columns"),
+    }
+  }
+
+  // 根据 row id 拿到这一列对应的值，找不到就是 Null
+  pub fn get_value(&self, row_id: &i64) -> Value {
+    match self {
+      Row::Integer(tree) => tree.get(row_id).cloned().flatten().map_or(Value::Null, Value::Integer),
+      Row::Bool(tree) => tree.get(row_id).cloned().flatten().map_or(Value::Null, Value::Bool),
+      Row::Text(tree) => tree.get(row_id).cloned().flatten().map_or(Value::Null, Value::Text),
+      Row::Real(tree) => tree.get(row_id).cloned().flatten().map_or(Value::Null, Value::Real),
+      Row::None => panic!("Found None Type in columns"),
+    }
+  }
+
+  // 把 row id 对应的值更新成 value，value 的类型需要和这一列的类型一致
+  // NULL 可以存到任意类型的列中
+  pub fn set_value(&mut self, row_id: i64, value: &Value) -> Result<()> {
+    match (self, value) {
+      (Row::Integer(tree), Value::Integer(v)) => { tree.insert(row_id, Some(*v)); },
+      (Row::Bool(tree), Value::Bool(v)) => { tree.insert(row_id, Some(*v)); },
+      (Row::Text(tree), Value::Text(v)) => { tree.insert(row_id, Some(v.to_string())); },
+      (Row::Real(tree), Value::Real(v)) => { tree.insert(row_id, Some(*v)); },
+      (Row::Integer(tree), Value::Null) => { tree.insert(row_id, None); },
+      (Row::Bool(tree), Value::Null) => { tree.insert(row_id, None); },
+      (Row::Text(tree), Value::Null) => { tree.insert(row_id, None); },
+      (Row::Real(tree), Value::Null) => { tree.insert(row_id, None); },
+      _ => return Err(NollaDBError::Internal(
+        format!(
+          "Can not store {} value '{}' in this column",
+          value.get_data_type(),
+          value
+        )
+      )),
+    }
+
+    Ok(())
+  }
+
+  // 删除 row id 对应的值，返回被删除的值
+  pub fn remove_value(&mut self, row_id: &i64) -> Value {
     match self {
-      Row::Integer(tree) => tree.iter().map(|(_key, value)| value.to_string()).collect(),
-      Row::Bool(tree) => tree.iter().map(|(_key, value)| value.to_string()).collect(),
-      Row::Text(tree) => tree.iter().map(|(_key, value)| value.to_string()).collect(),
-      Row::Real(tree) => tree.iter().map(|(_key, value)| value.to_string()).collect(),
+      Row::Integer(tree) => tree.remove(row_id).flatten().map_or(Value::Null, Value::Integer),
+      Row::Bool(tree) => tree.remove(row_id).flatten().map_or(Value::Null, Value::Bool),
+      Row::Text(tree) => tree.remove(row_id).flatten().map_or(Value::Null, Value::Text),
+      Row::Real(tree) => tree.remove(row_id).flatten().map_or(Value::Null, Value::Real),
       Row::None => panic!("Found None Type in columns"),
     }
   }
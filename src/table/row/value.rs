@@ -0,0 +1,91 @@
+use std::fmt;
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, NollaDBError};
+use crate::table::column::data_type::DataType;
+
+// Value 表示 table 中某一行某一列上具体的值
+// 和 Row 里面 BTreeMap 存放的 value 类型一一对应
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub enum Value {
+  Integer(i32),
+  Text(String),
+  Bool(bool),
+  Real(f32),
+  Null,
+}
+
+impl Value {
+  pub fn get_data_type(&self) -> DataType {
+    match self {
+      Value::Integer(_) => DataType::Integer,
+      Value::Text(_) => DataType::Text,
+      Value::Bool(_) => DataType::Bool,
+      Value::Real(_) => DataType::Real,
+      Value::Null => DataType::None,
+    }
+  }
+
+  pub fn is_null(&self) -> bool {
+    *self == Value::Null
+  }
+
+  // 把值转换成 column 的 DataType，用于写入 Row 之前
+  // Integer 可以转成 Real，其他类型必须一致
+  pub fn cast(&self, data_type: &DataType) -> Result<Value> {
+    match (self, data_type) {
+      (Value::Null, _) => Ok(Value::Null),
+      (Value::Integer(_), DataType::Integer) |
+      (Value::Text(_), DataType::Text) |
+      (Value::Bool(_), DataType::Bool) |
+      (Value::Real(_), DataType::Real) => Ok(self.clone()),
+      (Value::Integer(value), DataType::Real) => Ok(Value::Real(*value as f32)),
+      _ => Err(NollaDBError::General(
+        format!(
+          "Can not convert {} value '{}' into {}",
+          self.get_data_type(),
+          self,
+          data_type
+        )
+      )),
+    }
+  }
+
+  // 按照 DataType 比较两个值
+  // Integer 和 Real 之间统一按照 Real 来比较
+  // 任意一边是 Null 的话结果是未知的，返回 None
+  pub fn compare(&self, other: &Value) -> Result<Option<Ordering>> {
+    match (self, other) {
+      (Value::Null, _) | (_, Value::Null) => Ok(None),
+      (Value::Integer(a), Value::Integer(b)) => Ok(Some(a.cmp(b))),
+      (Value::Integer(a), Value::Real(b)) => Ok(f64::from(*a).partial_cmp(&f64::from(*b))),
+      (Value::Real(a), Value::Integer(b)) => Ok(f64::from(*a).partial_cmp(&f64::from(*b))),
+      (Value::Real(a), Value::Real(b)) => Ok(a.partial_cmp(b)),
+      (Value::Text(a), Value::Text(b)) => Ok(Some(a.cmp(b))),
+      (Value::Bool(a), Value::Bool(b)) => Ok(Some(a.cmp(b))),
+      _ => Err(NollaDBError::General(
+        format!(
+          "Can not compare {} value '{}' with {} value '{}'",
+          self.get_data_type(),
+          self,
+          other.get_data_type(),
+          other
+        )
+      )),
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Integer(value) => write!(f, "{}", value),
+      Value::Text(value) => write!(f, "{}", value),
+      Value::Bool(value) => write!(f, "{}", value),
+      Value::Real(value) => write!(f, "{}", value),
+      Value::Null => f.write_str("NULL"),
+    }
+  }
+}
@@ -0,0 +1,72 @@
+use crate::table::row::value::Value;
+
+// 一行数据的一个历史版本
+// 修改是直接写到表中的，表中永远是最新的版本（可能还没有提交）
+// 每次修改之前把这一行原来的值记录下来，按照从旧到新的顺序组成这一行的版本链
+// 其他会话沿着版本链往回找，就能拿到自己的快照中这一行的样子
+#[derive(PartialEq, Debug, Clone)]
+pub struct RowVersion {
+  // 做这次修改的会话
+  pub session_id: usize,
+  // 这次修改提交时的时间戳，还没有提交的话是 None
+  pub commit_ts: Option<u64>,
+  // 这次修改之前这一行的值，按照 table_columns 的顺序排列，None 表示这一行之前不存在
+  pub row_values: Option<Vec<Value>>,
+}
+
+impl RowVersion {
+  // 这次修改对 session_id 在 snapshot_ts 时的快照是否可见
+  // 自己做的修改，以及在快照之前提交的修改是可见的
+  pub fn is_visible(&self, session_id: usize, snapshot_ts: u64) -> bool {
+    self.session_id == session_id ||
+      self.commit_ts.is_some_and(|commit_ts| commit_ts <= snapshot_ts)
+  }
+}
+
+// 从最新的版本往回找，拿到快照中这一行的值
+// 第一个可见的修改之后的值就是快照中的值，所有修改都不可见的话就是最早的那个版本
+pub fn get_visible_row_values(
+  row_values: Option<Vec<Value>>,
+  row_versions: &[RowVersion],
+  session_id: usize,
+  snapshot_ts: u64,
+) -> Option<Vec<Value>> {
+  let mut row_values = row_values;
+  for row_version in row_versions.iter().rev() {
+    if row_version.is_visible(session_id, snapshot_ts) { break; }
+    row_values = row_version.row_values.clone();
+  }
+  row_values
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rstest::rstest;
+  use pretty_assertions::{assert_eq};
+
+  // 会话 1 在时间戳 1 插入了这一行，会话 2 在时间戳 3 把它更新成 b，会话 3 正在把它删除
+  #[rstest]
+  #[case(4, 0, None)]
+  #[case(4, 2, Some("a"))]
+  #[case(4, 3, Some("b"))]
+  #[case(1, 0, Some("a"))]
+  #[case(2, 2, Some("b"))]
+  #[case(3, 0, None)]
+  fn test_get_visible_row_values(
+    #[case] session_id: usize,
+    #[case] snapshot_ts: u64,
+    #[case] expected: Option<&str>,
+  ) {
+    let row_versions = vec![
+      RowVersion { session_id: 1, commit_ts: Some(1), row_values: None },
+      RowVersion { session_id: 2, commit_ts: Some(3), row_values: Some(vec![Value::Text("a".to_string())]) },
+      RowVersion { session_id: 3, commit_ts: None, row_values: Some(vec![Value::Text("b".to_string())]) },
+    ];
+
+    assert_eq!(
+      get_visible_row_values(None, &row_versions, session_id, snapshot_ts),
+      expected.map(|value| vec![Value::Text(value.to_string())])
+    );
+  }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::table::row::Row;
+
+// 表中每一列的数据，key 是 column name
+// 用 Arc<RwLock> 管理，这样 Table 以及 Database 可以在多个线程之间共享
+// 和原来的 Rc<RefCell> 一样，clone 只会复制指针，需要完全独立的一份时用 deep_clone
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct TableRows(Arc<RwLock<HashMap<String, Row>>>);
+
+impl TableRows {
+  pub fn new(table_rows_data: HashMap<String, Row>) -> Self {
+    TableRows(Arc::new(RwLock::new(table_rows_data)))
+  }
+
+  // 拿锁的线程 panic 之后数据可能只改了一半，和 RefCell 一样直接 panic
+  pub fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Row>> {
+    self.0.read().expect("Table rows lock is poisoned")
+  }
+
+  pub fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, Row>> {
+    self.0.write().expect("Table rows lock is poisoned")
+  }
+
+  pub fn deep_clone(&self) -> Self {
+    TableRows::new(self.read().clone())
+  }
+}
+
+// 同一个指针的话不需要比较，也避免对同一把锁拿两次读锁
+impl PartialEq for TableRows {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.0, &other.0) || *self.read() == *other.read()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::thread;
+  use pretty_assertions::{assert_eq};
+  use crate::table::column::data_type::DataType;
+  use crate::table::row::value::Value;
+
+  #[test]
+  fn test_table_rows() {
+    let table_rows = TableRows::new(HashMap::from([
+      ("id".to_string(), Row::new(&DataType::Integer)),
+    ]));
+    let shared_table_rows = table_rows.clone();
+    let copied_table_rows = table_rows.deep_clone();
+
+    // clone 出来的在别的线程中写入，原来的也能看到，deep_clone 出来的不受影响
+    thread::spawn(move || {
+      shared_table_rows.write().get_mut("id").unwrap().set_value(1, &Value::Integer(1)).unwrap();
+    }).join().unwrap();
+
+    assert_eq!(table_rows.read()["id"].get_value(&1), Value::Integer(1));
+    assert_eq!(copied_table_rows.read()["id"].get_value(&1), Value::Null);
+    assert!(table_rows != copied_table_rows);
+    assert!(table_rows == table_rows.clone());
+  }
+}